@@ -1,5 +1,5 @@
 import { useEffect, useRef, useState, useCallback, useMemo } from 'react';
-import { RustWebSocket, WsMessage, ReplayStatus } from './websocket';
+import { RustWebSocket, WsMessage, ReplayStatus, ConnectionStatus, VwapUpdate, AnchoredVwapUpdate, DevelopingValueArea, MarketStateUpdate, DepthSnapshot, LiquidityEvent, Footprint, ImpulseLegEvent, LvnUpdate, Divergence, LargeTrade, Sweep } from './websocket';
 import { BubbleRenderer } from './BubbleRenderer';
 import { StatsPage } from './StatsPage';
 import { ReplayControls } from './ReplayControls';
@@ -100,6 +100,9 @@ interface SessionStats {
   deltaFlips: SignalStats;
   absorptions: SignalStats;
   stackedImbalances: SignalStats;
+  divergences: SignalStats;
+  largeTrades: SignalStats;
+  sweeps: SignalStats;
   confluences: SignalStats;
   currentPrice: number;
   sessionHigh: number;
@@ -238,13 +241,26 @@ function App() {
   const [absorptionZones, setAbsorptionZones] = useState<AbsorptionZone[]>([]); // Passed to BubbleRenderer for canvas rendering
   const [showAbsorptionBadge, setShowAbsorptionBadge] = useState<AbsorptionAlert | null>(null);
   const [stackedImbalances, setStackedImbalances] = useState<StackedImbalance[]>([]);
+  const [divergences, setDivergences] = useState<Divergence[]>([]);
+  const [largeTrades, setLargeTrades] = useState<LargeTrade[]>([]);
+  const [sweeps, setSweeps] = useState<Sweep[]>([]);
   const [showStackedBadge, setShowStackedBadge] = useState<StackedImbalance | null>(null);
   const [_confluenceEvents, setConfluenceEvents] = useState<ConfluenceEvent[]>([]); // eslint-disable-line @typescript-eslint/no-unused-vars
   const [showConfluenceBadge, setShowConfluenceBadge] = useState<ConfluenceEvent | null>(null);
   const [sessionStats, setSessionStats] = useState<SessionStats | null>(null);
+  const [vwap, setVwap] = useState<VwapUpdate | null>(null);
+  const [anchoredVwaps, setAnchoredVwaps] = useState<AnchoredVwapUpdate | null>(null);
+  const [valueAreaHistory, setValueAreaHistory] = useState<DevelopingValueArea[]>([]);
+  const [footprints, setFootprints] = useState<Footprint[]>([]);
+  const [marketStates, setMarketStates] = useState<Record<string, MarketStateUpdate>>({});
+  const [depthHistory, setDepthHistory] = useState<DepthSnapshot[]>([]);
+  const [liquidityEvents, setLiquidityEvents] = useState<LiquidityEvent[]>([]);
+  const [impulseLegs, setImpulseLegs] = useState<ImpulseLegEvent[]>([]);
+  const [lvns, setLvns] = useState<LvnUpdate[]>([]);
   const [currentView, setCurrentView] = useState<'chart' | 'stats' | 'history'>('chart');
   const [serverMode, setServerMode] = useState<string>('live');
   const [replayStatus, setReplayStatus] = useState<ReplayStatus | null>(null);
+  const [feedStatus, setFeedStatus] = useState<ConnectionStatus | null>(null);
   const [connectedSymbols, setConnectedSymbols] = useState<string[]>([]);
   const [selectedSymbol, setSelectedSymbol] = useState<string>('all');
   const [showSettings, setShowSettings] = useState(false);
@@ -263,6 +279,11 @@ function App() {
     if (selectedSymbol === 'all') return bubbles;
     return bubbles.filter((b) => b.symbol === selectedSymbol);
   }, [bubbles, selectedSymbol]);
+
+  // Market state badge - the selected symbol's, or the first symbol's under ALL
+  const marketState = selectedSymbol === 'all'
+    ? marketStates[connectedSymbols[0]] ?? Object.values(marketStates)[0]
+    : marketStates[selectedSymbol];
   const lastRawCvdRef = useRef<number>(0);  // Track last raw CVD from server
   const prevAdjustedCvdRef = useRef<number>(0); // Track previous adjusted CVD for zero-cross
 
@@ -483,6 +504,30 @@ function App() {
           }
           break;
 
+        case 'Divergence':
+          console.log(
+            `🔀 ${message.kind.toUpperCase()} ${message.direction.toUpperCase()} DIVERGENCE: price ${message.prevSwingPrice.toFixed(2)} → ${message.swingPrice.toFixed(2)}, CVD ${message.prevSwingCvd} → ${message.swingCvd}`
+          );
+          setDivergences((prev) => [...prev, message]);
+          break;
+
+        case 'LargeTrade':
+          // Tier 1 prints are frequent - log from tier 3 (100+ by default)
+          if (message.tier >= 3) {
+            console.log(
+              `🐋 LARGE ${message.kind.toUpperCase()} [${message.side.toUpperCase()}]: ${message.size} @ ${message.price.toFixed(2)} (${message.fills} fills, ${message.tierMin}+)`
+            );
+          }
+          setLargeTrades((prev) => [...prev, message]);
+          break;
+
+        case 'Sweep':
+          console.log(
+            `🧹 SWEEP [${message.side.toUpperCase()}]: ${message.size} across ${message.levels} levels ${message.startPrice.toFixed(2)} → ${message.endPrice.toFixed(2)} (${message.fills} fills)`
+          );
+          setSweeps((prev) => [...prev, message]);
+          break;
+
         case 'Confluence':
           console.log(
             `🎯 CONFLUENCE [${message.score >= 3 ? 'HIGH' : 'MEDIUM'}]: ${message.signals.join(' + ')} → ${message.direction.toUpperCase()}`
@@ -523,6 +568,9 @@ function App() {
             deltaFlips: message.deltaFlips,
             absorptions: message.absorptions,
             stackedImbalances: message.stackedImbalances,
+            divergences: message.divergences,
+            largeTrades: message.largeTrades,
+            sweeps: message.sweeps,
             confluences: message.confluences,
             currentPrice: message.currentPrice,
             sessionHigh: message.sessionHigh,
@@ -531,6 +579,52 @@ function App() {
           });
           break;
 
+        case 'Vwap':
+          setVwap(message);
+          break;
+
+        case 'AnchoredVwaps':
+          setAnchoredVwaps(message);
+          break;
+
+        case 'DevelopingValueArea':
+          // Keep the last 60 moves for the value migration trail
+          setValueAreaHistory(prev => [...prev.slice(-59), message]);
+          break;
+
+        case 'MarketState':
+          console.log(`🧭 ${message.symbol} ${message.previous ?? 'none'} → ${message.state} (${message.reasons.join(', ')})`);
+          setMarketStates(prev => ({ ...prev, [message.symbol]: message }));
+          break;
+
+        case 'ImpulseLeg':
+          console.log(`🚀 ${message.symbol} impulse ${message.direction} ${message.startPrice} → ${message.endPrice} (score ${message.score}/5)`);
+          setImpulseLegs(prev => [...prev.slice(-9), message]);
+          break;
+
+        case 'Lvns':
+          // LVNs of the last 10 legs
+          setLvns(prev => [...prev.slice(-9), message]);
+          break;
+
+        case 'Footprint':
+          // Updates replace the open bar; keep the last 30 bars
+          setFootprints(prev => {
+            const rest = prev.filter(f => !(f.symbol === message.symbol && f.timestamp === message.timestamp));
+            return [...rest.slice(-29), message];
+          });
+          break;
+
+        case 'Depth':
+          // Keep the last 120 snapshots (~2 minutes) for the liquidity heatmap
+          setDepthHistory(prev => [...prev.slice(-119), message]);
+          break;
+
+        case 'Liquidity':
+          console.log(`💧 ${message.kind.toUpperCase()} ${message.side} @ ${message.price}: ${message.size}`);
+          setLiquidityEvents(prev => [...prev.slice(-49), message]);
+          break;
+
         case 'Connected':
           console.log('📡 Connected to symbols:', message.symbols, 'mode:', message.mode);
           setServerMode(message.mode);
@@ -548,6 +642,16 @@ function App() {
           });
           break;
 
+        case 'ConnectionStatus':
+          setFeedStatus({
+            status: message.status,
+            attempt: message.attempt,
+            gapStart: message.gapStart,
+            gapEnd: message.gapEnd,
+            message: message.message,
+          });
+          break;
+
         case 'Error':
           console.error('Backend error:', message.message);
           setError(message.message);
@@ -578,6 +682,10 @@ function App() {
     wsRef.current?.setReplaySpeed(speed);
   }, []);
 
+  const handleReplayStep = useCallback((unit: 'window' | 'second' | 'next_signal') => {
+    wsRef.current?.replayStep(unit);
+  }, []);
+
   const handleMinSizeChange = useCallback((size: number) => {
     wsRef.current?.setMinSize(size);
     setMinSize(size);
@@ -827,6 +935,9 @@ function App() {
         setZeroCrosses((prev) => prev.filter((c) => now - c.timestamp < maxAge));
         setAbsorptionAlerts((prev) => prev.filter((a) => now - a.timestamp < maxAge));
         setStackedImbalances((prev) => prev.filter((s) => now - s.timestamp < maxAge));
+        setDivergences((prev) => prev.filter((d) => now - d.timestamp < maxAge));
+        setLargeTrades((prev) => prev.filter((t) => now - t.timestamp < maxAge));
+        setSweeps((prev) => prev.filter((s) => now - s.timestamp < maxAge));
         setConfluenceEvents((prev) => prev.filter((c) => now - c.timestamp < maxAge));
       }
 
@@ -866,6 +977,27 @@ function App() {
         }))
       );
 
+      setDivergences((prev) =>
+        prev.map((divergence) => ({
+          ...divergence,
+          x: divergence.x - movement,
+        }))
+      );
+
+      setLargeTrades((prev) =>
+        prev.map((large) => ({
+          ...large,
+          x: large.x - movement,
+        }))
+      );
+
+      setSweeps((prev) =>
+        prev.map((sweep) => ({
+          ...sweep,
+          x: sweep.x - movement,
+        }))
+      );
+
       setConfluenceEvents((prev) =>
         prev.map((conf) => ({
           ...conf,
@@ -907,6 +1039,18 @@ function App() {
               ))}
             </div>
           )}
+          {isConnected && marketState && (
+            <div
+              className={`market-state-badge ${marketState.state}`}
+              title={`${marketState.symbol}: ${marketState.reasons.join(', ')}`}
+            >
+              {marketState.state === 'balance'
+                ? '⇄ BALANCE'
+                : marketState.state === 'bullish_imbalance'
+                  ? '↗ IMBALANCE'
+                  : '↘ IMBALANCE'}
+            </div>
+          )}
         </div>
 
         <div className="header-center">
@@ -956,12 +1100,24 @@ function App() {
             <span className="status-dot"></span>
             {isConnected ? serverMode.toUpperCase() : 'OFFLINE'}
           </div>
+          {isConnected && feedStatus && feedStatus.status !== 'connected' && (
+            <div
+              className={`feed-status ${feedStatus.status}`}
+              title={feedStatus.message ?? 'Live feed interrupted - data may be missing'}
+            >
+              {feedStatus.status === 'connecting' && `⟳ FEED CONNECTING (${feedStatus.attempt})`}
+              {feedStatus.status === 'reconnecting' && `⚠ FEED RECONNECTING (${feedStatus.attempt})`}
+              {feedStatus.status === 'backfilling' && '⟳ BACKFILLING GAP'}
+              {feedStatus.status === 'degraded' && '⚠ FEED DEGRADED'}
+            </div>
+          )}
           {isConnected && serverMode === 'replay' && replayStatus && (
             <ReplayControls
               status={replayStatus}
               onPause={handleReplayPause}
               onResume={handleReplayResume}
               onSpeedChange={handleReplaySpeedChange}
+              onStep={handleReplayStep}
             />
           )}
           {isPaused && (
@@ -1259,6 +1415,17 @@ function App() {
               volumeProfile={volumeProfile}
               absorptionZones={absorptionZones}
               stackedImbalances={stackedImbalances}
+              divergences={divergences}
+              largeTrades={largeTrades}
+              sweeps={sweeps}
+              vwap={vwap}
+              anchoredVwaps={anchoredVwaps}
+              valueAreaHistory={valueAreaHistory}
+              footprints={footprints}
+              depthHistory={depthHistory}
+              liquidityEvents={liquidityEvents}
+              impulseLegs={impulseLegs}
+              lvns={lvns}
             />
 
             {/* Bubble Info Tooltip */}
@@ -1293,6 +1460,18 @@ function App() {
                     {new Date(selectedBubble.timestamp).toLocaleTimeString()}
                   </span>
                 </div>
+                <button
+                  className="tooltip-action"
+                  onClick={(e) => {
+                    e.stopPropagation();
+                    const symbol = selectedSymbol === 'all' ? undefined : selectedSymbol;
+                    wsRef.current?.addVwapAnchor(selectedBubble.timestamp, symbol);
+                    setSelectedBubble(null);
+                    setClickPosition(null);
+                  }}
+                >
+                  ⚓ Anchor VWAP here
+                </button>
                 <div className="tooltip-footer">Click to close</div>
               </div>
             )}
@@ -1306,6 +1485,12 @@ function App() {
             {sessionStats ? (
               <div className="stats-grid">
                 <div className="stats-overview">
+                  <div className="overview-card">
+                    <span className="overview-label">Trading Date</span>
+                    <span className="overview-value">
+                      {sessionStats.tradingDate} ({sessionStats.holiday ?? sessionStats.segment.toUpperCase()})
+                    </span>
+                  </div>
                   <div className="overview-card">
                     <span className="overview-label">Session Start</span>
                     <span className="overview-value">
@@ -1344,6 +1529,21 @@ function App() {
                     bullish={sessionStats.stackedImbalances.bullishCount}
                     bearish={sessionStats.stackedImbalances.bearishCount}
                   />
+                  <DirectionChart
+                    title="Divergences"
+                    bullish={sessionStats.divergences.bullishCount}
+                    bearish={sessionStats.divergences.bearishCount}
+                  />
+                  <DirectionChart
+                    title="Large Trades"
+                    bullish={sessionStats.largeTrades.bullishCount}
+                    bearish={sessionStats.largeTrades.bearishCount}
+                  />
+                  <DirectionChart
+                    title="Sweeps"
+                    bullish={sessionStats.sweeps.bullishCount}
+                    bearish={sessionStats.sweeps.bearishCount}
+                  />
                   <DirectionChart
                     title="Confluences"
                     bullish={sessionStats.confluences.bullishCount}
@@ -1454,6 +1654,108 @@ function App() {
                     </div>
                   </div>
 
+                  {/* Divergences */}
+                  <div className="signal-card">
+                    <h3>Divergences</h3>
+                    <div className="signal-counts">
+                      <span className="count total">{sessionStats.divergences.count} total</span>
+                      <span className="count bullish">{sessionStats.divergences.bullishCount} bullish</span>
+                      <span className="count bearish">{sessionStats.divergences.bearishCount} bearish</span>
+                    </div>
+                    <div className="signal-metrics">
+                      <div className="metric">
+                        <span className="metric-label">Win Rate</span>
+                        <span className={`metric-value ${sessionStats.divergences.winRate >= 50 ? 'positive' : 'negative'}`}>
+                          {sessionStats.divergences.winRate.toFixed(1)}%
+                        </span>
+                      </div>
+                      <div className="metric">
+                        <span className="metric-label">Avg Move (1m)</span>
+                        <span className={`metric-value ${sessionStats.divergences.avgMove1m >= 0 ? 'positive' : 'negative'}`}>
+                          {sessionStats.divergences.avgMove1m >= 0 ? '+' : ''}{sessionStats.divergences.avgMove1m.toFixed(2)}
+                        </span>
+                      </div>
+                      <div className="metric">
+                        <span className="metric-label">Avg Move (5m)</span>
+                        <span className={`metric-value ${sessionStats.divergences.avgMove5m >= 0 ? 'positive' : 'negative'}`}>
+                          {sessionStats.divergences.avgMove5m >= 0 ? '+' : ''}{sessionStats.divergences.avgMove5m.toFixed(2)}
+                        </span>
+                      </div>
+                    </div>
+                    <div className="win-loss">
+                      <span className="wins">{sessionStats.divergences.wins} W</span>
+                      <span className="losses">{sessionStats.divergences.losses} L</span>
+                    </div>
+                  </div>
+
+                  {/* Large Trades */}
+                  <div className="signal-card">
+                    <h3>Large Trades</h3>
+                    <div className="signal-counts">
+                      <span className="count total">{sessionStats.largeTrades.count} total</span>
+                      <span className="count bullish">{sessionStats.largeTrades.bullishCount} bullish</span>
+                      <span className="count bearish">{sessionStats.largeTrades.bearishCount} bearish</span>
+                    </div>
+                    <div className="signal-metrics">
+                      <div className="metric">
+                        <span className="metric-label">Win Rate</span>
+                        <span className={`metric-value ${sessionStats.largeTrades.winRate >= 50 ? 'positive' : 'negative'}`}>
+                          {sessionStats.largeTrades.winRate.toFixed(1)}%
+                        </span>
+                      </div>
+                      <div className="metric">
+                        <span className="metric-label">Avg Move (1m)</span>
+                        <span className={`metric-value ${sessionStats.largeTrades.avgMove1m >= 0 ? 'positive' : 'negative'}`}>
+                          {sessionStats.largeTrades.avgMove1m >= 0 ? '+' : ''}{sessionStats.largeTrades.avgMove1m.toFixed(2)}
+                        </span>
+                      </div>
+                      <div className="metric">
+                        <span className="metric-label">Avg Move (5m)</span>
+                        <span className={`metric-value ${sessionStats.largeTrades.avgMove5m >= 0 ? 'positive' : 'negative'}`}>
+                          {sessionStats.largeTrades.avgMove5m >= 0 ? '+' : ''}{sessionStats.largeTrades.avgMove5m.toFixed(2)}
+                        </span>
+                      </div>
+                    </div>
+                    <div className="win-loss">
+                      <span className="wins">{sessionStats.largeTrades.wins} W</span>
+                      <span className="losses">{sessionStats.largeTrades.losses} L</span>
+                    </div>
+                  </div>
+
+                  {/* Sweeps */}
+                  <div className="signal-card">
+                    <h3>Sweeps</h3>
+                    <div className="signal-counts">
+                      <span className="count total">{sessionStats.sweeps.count} total</span>
+                      <span className="count bullish">{sessionStats.sweeps.bullishCount} bullish</span>
+                      <span className="count bearish">{sessionStats.sweeps.bearishCount} bearish</span>
+                    </div>
+                    <div className="signal-metrics">
+                      <div className="metric">
+                        <span className="metric-label">Win Rate</span>
+                        <span className={`metric-value ${sessionStats.sweeps.winRate >= 50 ? 'positive' : 'negative'}`}>
+                          {sessionStats.sweeps.winRate.toFixed(1)}%
+                        </span>
+                      </div>
+                      <div className="metric">
+                        <span className="metric-label">Avg Move (1m)</span>
+                        <span className={`metric-value ${sessionStats.sweeps.avgMove1m >= 0 ? 'positive' : 'negative'}`}>
+                          {sessionStats.sweeps.avgMove1m >= 0 ? '+' : ''}{sessionStats.sweeps.avgMove1m.toFixed(2)}
+                        </span>
+                      </div>
+                      <div className="metric">
+                        <span className="metric-label">Avg Move (5m)</span>
+                        <span className={`metric-value ${sessionStats.sweeps.avgMove5m >= 0 ? 'positive' : 'negative'}`}>
+                          {sessionStats.sweeps.avgMove5m >= 0 ? '+' : ''}{sessionStats.sweeps.avgMove5m.toFixed(2)}
+                        </span>
+                      </div>
+                    </div>
+                    <div className="win-loss">
+                      <span className="wins">{sessionStats.sweeps.wins} W</span>
+                      <span className="losses">{sessionStats.sweeps.losses} L</span>
+                    </div>
+                  </div>
+
                   {/* Confluences */}
                   <div className="signal-card confluence-card">
                     <h3>Confluences</h3>
@@ -1,4 +1,5 @@
 import { useEffect, RefObject } from 'react';
+import { VwapUpdate, AnchoredVwapUpdate, DevelopingValueArea, DepthSnapshot, LiquidityEvent, Footprint, ImpulseLegEvent, LvnUpdate, Divergence, LargeTrade, Sweep } from './websocket';
 
 interface Bubble {
   id: string;
@@ -66,6 +67,17 @@ interface BubbleRendererProps {
   volumeProfile: Map<number, VolumeProfileLevel>;
   absorptionZones?: AbsorptionZone[];
   stackedImbalances?: StackedImbalance[];
+  divergences?: Divergence[];
+  largeTrades?: LargeTrade[];
+  sweeps?: Sweep[];
+  vwap?: VwapUpdate | null;
+  anchoredVwaps?: AnchoredVwapUpdate | null;
+  valueAreaHistory?: DevelopingValueArea[];
+  footprints?: Footprint[];
+  depthHistory?: DepthSnapshot[];
+  liquidityEvents?: LiquidityEvent[];
+  impulseLegs?: ImpulseLegEvent[];
+  lvns?: LvnUpdate[];
 }
 
 // Colors matching trading aesthetic
@@ -111,7 +123,18 @@ export function BubbleRenderer({
   onClick,
   volumeProfile,
   absorptionZones = [],
-  stackedImbalances = []
+  stackedImbalances = [],
+  divergences = [],
+  largeTrades = [],
+  sweeps = [],
+  vwap = null,
+  anchoredVwaps = null,
+  valueAreaHistory = [],
+  footprints = [],
+  depthHistory = [],
+  liquidityEvents = [],
+  impulseLegs = [],
+  lvns = []
 }: BubbleRendererProps) {
   useEffect(() => {
     const canvas = canvasRef.current;
@@ -306,7 +329,34 @@ export function BubbleRenderer({
     // Draw stacked imbalances (vertical highlight zones)
     drawStackedImbalances(ctx, stackedImbalances, rect.width, mainChartHeight, priceMin, priceMax);
 
-  }, [bubbles, priceRange, canvasRef, cvdHistory, cvdRange, currentCVD, zeroCrosses, volumeProfile, absorptionZones, stackedImbalances]);
+    // Draw CVD/price divergences at the swing they were confirmed on
+    drawDivergences(ctx, divergences, rect.width, mainChartHeight, priceMin, priceMax);
+
+    // Draw individual large orders (single prints and sweeps) from tier 2 up
+    drawLargeTrades(ctx, largeTrades, rect.width, mainChartHeight, priceMin, priceMax);
+
+    // Draw multi-level sweeps as arrows through the levels they took
+    drawSweeps(ctx, sweeps, rect.width, mainChartHeight, priceMin, priceMax);
+
+    // Draw session VWAP and its deviation bands
+    drawVwap(ctx, vwap, rect.width, mainChartHeight, priceMin, priceMax);
+    drawAnchoredVwaps(ctx, anchoredVwaps, rect.width, mainChartHeight, priceMin, priceMax);
+
+    // Draw how the developing value area migrated this session
+    drawValueAreaMigration(ctx, valueAreaHistory, rect.width, mainChartHeight, priceMin, priceMax);
+
+    // Draw the latest footprint bar as a bid x ask ladder
+    drawFootprintLadder(ctx, footprints[footprints.length - 1], rect.width, mainChartHeight, priceMin, priceMax);
+
+    // Draw resting liquidity (order book heatmap) and liquidity events
+    drawDepthHeatmap(ctx, depthHistory, rect.width, mainChartHeight, priceMin, priceMax);
+    drawLiquidityEvents(ctx, liquidityEvents, rect.width, mainChartHeight, priceMin, priceMax);
+
+    // Draw confirmed impulse legs and the LVNs inside them
+    drawLvns(ctx, lvns, rect.width, mainChartHeight, priceMin, priceMax);
+    drawImpulseLegs(ctx, impulseLegs, mainChartHeight, priceMin, priceMax);
+
+  }, [bubbles, priceRange, canvasRef, cvdHistory, cvdRange, currentCVD, zeroCrosses, volumeProfile, absorptionZones, stackedImbalances, divergences, largeTrades, sweeps, vwap, anchoredVwaps, valueAreaHistory, footprints, depthHistory, liquidityEvents, impulseLegs, lvns]);
 
   return (
     <canvas
@@ -810,3 +860,474 @@ function drawStackedImbalances(
     ctx.fillText(`${imbalance.levelCount}L`, x, rectY - 5);
   });
 }
+
+/**
+ * Draw divergences as triangles beyond the swing price (above highs, below lows)
+ * labelled DIV (regular) or HDIV (hidden)
+ */
+function drawDivergences(
+  ctx: CanvasRenderingContext2D,
+  divergences: Divergence[],
+  width: number,
+  height: number,
+  priceMin: number,
+  priceMax: number
+) {
+  if (divergences.length === 0) return;
+
+  const priceSpan = priceMax - priceMin;
+  const profileWidth = 120;
+
+  divergences.forEach((divergence) => {
+    const x = divergence.x * width;
+    const y = height - ((divergence.swingPrice - priceMin) / priceSpan) * height;
+    if (x < profileWidth || x > width || y < 0 || y > height) return;
+
+    const bullish = divergence.direction === 'bullish';
+    const color = bullish ? COLORS.buy.stroke : COLORS.sell.stroke;
+    // Bullish points up from below the low, bearish down from above the high
+    const tipY = bullish ? y + 8 : y - 8;
+    const baseY = bullish ? tipY + 8 : tipY - 8;
+
+    ctx.fillStyle = color;
+    ctx.beginPath();
+    ctx.moveTo(x, tipY);
+    ctx.lineTo(x - 6, baseY);
+    ctx.lineTo(x + 6, baseY);
+    ctx.closePath();
+    ctx.fill();
+
+    ctx.font = 'bold 9px "JetBrains Mono", monospace';
+    ctx.textAlign = 'center';
+    ctx.fillText(divergence.kind === 'regular' ? 'DIV' : 'HDIV', x, bullish ? baseY + 10 : baseY - 4);
+  });
+}
+
+/**
+ * Draw large orders from tier 2 up as rings sized by tier with the contract
+ * count; sweeps also get a bar over the prices they took
+ */
+function drawLargeTrades(
+  ctx: CanvasRenderingContext2D,
+  trades: LargeTrade[],
+  width: number,
+  height: number,
+  priceMin: number,
+  priceMax: number
+) {
+  if (trades.length === 0) return;
+
+  const priceSpan = priceMax - priceMin;
+  const toY = (price: number) => height - ((price - priceMin) / priceSpan) * height;
+  const profileWidth = 120;
+
+  trades.forEach((trade) => {
+    if (trade.tier < 2) return;
+    const x = trade.x * width;
+    const y = toY(trade.price);
+    if (x < profileWidth || x > width || y < 0 || y > height) return;
+
+    const color = trade.side === 'buy' ? COLORS.buy.stroke : COLORS.sell.stroke;
+    const radius = 6 + trade.tier * 3;
+
+    if (trade.kind === 'sweep' && trade.priceHigh > trade.priceLow) {
+      ctx.fillStyle = trade.side === 'buy' ? COLORS.buy.glow : COLORS.sell.glow;
+      ctx.fillRect(x - 3, toY(trade.priceHigh), 6, toY(trade.priceLow) - toY(trade.priceHigh));
+    }
+
+    ctx.strokeStyle = color;
+    ctx.lineWidth = trade.tier >= 4 ? 3 : 2;
+    ctx.beginPath();
+    ctx.arc(x, y, radius, 0, Math.PI * 2);
+    ctx.stroke();
+
+    ctx.fillStyle = color;
+    ctx.font = 'bold 9px "JetBrains Mono", monospace';
+    ctx.textAlign = 'left';
+    ctx.fillText(`${trade.size}`, x + radius + 3, y + 3);
+  });
+  ctx.lineWidth = 1;
+}
+
+/**
+ * Draw sweeps as an arrow from the first fill to the last one, labelled with
+ * the levels taken
+ */
+function drawSweeps(
+  ctx: CanvasRenderingContext2D,
+  sweeps: Sweep[],
+  width: number,
+  height: number,
+  priceMin: number,
+  priceMax: number
+) {
+  if (sweeps.length === 0) return;
+
+  const priceSpan = priceMax - priceMin;
+  const toY = (price: number) => height - ((price - priceMin) / priceSpan) * height;
+  const profileWidth = 120;
+
+  sweeps.forEach((sweep) => {
+    const x = sweep.x * width;
+    const startY = toY(sweep.startPrice);
+    const endY = toY(sweep.endPrice);
+    if (x < profileWidth || x > width || Math.max(startY, endY) < 0 || Math.min(startY, endY) > height) return;
+
+    const color = sweep.side === 'buy' ? COLORS.buy.stroke : COLORS.sell.stroke;
+    const head = endY < startY ? 5 : -5;
+
+    ctx.strokeStyle = color;
+    ctx.lineWidth = 2;
+    ctx.beginPath();
+    ctx.moveTo(x, startY);
+    ctx.lineTo(x, endY);
+    ctx.moveTo(x - 4, endY + head);
+    ctx.lineTo(x, endY);
+    ctx.lineTo(x + 4, endY + head);
+    ctx.stroke();
+
+    ctx.fillStyle = color;
+    ctx.font = 'bold 9px "JetBrains Mono", monospace';
+    ctx.textAlign = 'right';
+    ctx.fillText(`${sweep.levels}L`, x - 6, endY + 3);
+  });
+  ctx.lineWidth = 1;
+}
+
+/**
+ * Draw session VWAP (solid) with 1σ/2σ/3σ bands (dashed, fading outward)
+ */
+function drawVwap(
+  ctx: CanvasRenderingContext2D,
+  vwap: VwapUpdate | null,
+  width: number,
+  height: number,
+  priceMin: number,
+  priceMax: number
+) {
+  if (!vwap) return;
+
+  const priceSpan = priceMax - priceMin;
+  const startX = 120; // After volume profile
+  const endX = width - 60;
+
+  const lines: { price: number; label: string; alpha: number; dashed: boolean }[] = [
+    { price: vwap.vwap, label: 'VWAP', alpha: 0.9, dashed: false },
+    { price: vwap.upper1, label: '+1σ', alpha: 0.6, dashed: true },
+    { price: vwap.lower1, label: '-1σ', alpha: 0.6, dashed: true },
+    { price: vwap.upper2, label: '+2σ', alpha: 0.45, dashed: true },
+    { price: vwap.lower2, label: '-2σ', alpha: 0.45, dashed: true },
+    { price: vwap.upper3, label: '+3σ', alpha: 0.3, dashed: true },
+    { price: vwap.lower3, label: '-3σ', alpha: 0.3, dashed: true },
+  ];
+
+  lines.forEach(({ price, label, alpha, dashed }) => {
+    const y = height - ((price - priceMin) / priceSpan) * height;
+    if (y < 0 || y > height) return;
+
+    const color = `rgba(64, 156, 255, ${alpha})`;
+    ctx.strokeStyle = color;
+    ctx.lineWidth = dashed ? 1 : 2;
+    ctx.setLineDash(dashed ? [6, 4] : []);
+    ctx.beginPath();
+    ctx.moveTo(startX, y);
+    ctx.lineTo(endX, y);
+    ctx.stroke();
+    ctx.setLineDash([]);
+
+    ctx.fillStyle = color;
+    ctx.font = 'bold 9px "JetBrains Mono", monospace';
+    ctx.textAlign = 'left';
+    ctx.fillText(`${label} ${price.toFixed(2)}`, endX + 4, y + 3);
+  });
+}
+
+// Anchored VWAP colors by anchor source
+const ANCHORED_VWAP_COLORS: Record<string, string> = {
+  manual: 'rgba(255, 255, 255, 0.8)',
+  impulse: 'rgba(255, 167, 38, 0.8)',
+  session_high: 'rgba(255, 82, 82, 0.7)',
+  session_low: 'rgba(0, 230, 118, 0.7)',
+};
+
+/**
+ * Draw anchored VWAPs (manual, impulse, session high/low) as dotted lines
+ */
+function drawAnchoredVwaps(
+  ctx: CanvasRenderingContext2D,
+  update: AnchoredVwapUpdate | null,
+  width: number,
+  height: number,
+  priceMin: number,
+  priceMax: number
+) {
+  if (!update) return;
+
+  const priceSpan = priceMax - priceMin;
+  const startX = 120; // After volume profile
+  const endX = width - 60;
+
+  update.anchors.forEach((anchor) => {
+    const y = height - ((anchor.vwap - priceMin) / priceSpan) * height;
+    if (y < 0 || y > height) return;
+
+    const color = ANCHORED_VWAP_COLORS[anchor.source] ?? 'rgba(158, 158, 158, 0.8)';
+    ctx.strokeStyle = color;
+    ctx.lineWidth = 1;
+    ctx.setLineDash([2, 3]);
+    ctx.beginPath();
+    ctx.moveTo(startX, y);
+    ctx.lineTo(endX, y);
+    ctx.stroke();
+    ctx.setLineDash([]);
+
+    const time = new Date(anchor.anchorTime).toLocaleTimeString([], { hour: '2-digit', minute: '2-digit' });
+    const label = anchor.source === 'manual' || anchor.source === 'impulse'
+      ? `AVWAP ${time}`
+      : anchor.source === 'session_high' ? 'AVWAP HOD' : 'AVWAP LOD';
+    ctx.fillStyle = color;
+    ctx.font = '9px "JetBrains Mono", monospace';
+    ctx.textAlign = 'right';
+    ctx.fillText(label, endX - 4, y - 3);
+  });
+}
+
+/**
+ * Draw the developing value area as a trail along the right edge: one VAH-VAL
+ * bar per move (oldest left) with the POC marked, newest brightest
+ */
+function drawValueAreaMigration(
+  ctx: CanvasRenderingContext2D,
+  history: DevelopingValueArea[],
+  width: number,
+  height: number,
+  priceMin: number,
+  priceMax: number
+) {
+  if (history.length === 0) return;
+
+  const priceSpan = priceMax - priceMin;
+  const toY = (price: number) => height - ((price - priceMin) / priceSpan) * height;
+  const trailWidth = 50;
+  const endX = width - 4;
+  const step = trailWidth / Math.max(history.length, 1);
+
+  history.forEach((va, i) => {
+    const x = endX - trailWidth + i * step;
+    const alpha = 0.2 + 0.6 * ((i + 1) / history.length);
+
+    ctx.fillStyle = `rgba(255, 193, 7, ${alpha * 0.3})`;
+    ctx.fillRect(x, toY(va.vah), Math.max(step - 1, 1), toY(va.val) - toY(va.vah));
+
+    ctx.fillStyle = `rgba(255, 193, 7, ${alpha})`;
+    ctx.fillRect(x, toY(va.poc) - 1, Math.max(step - 1, 1), 2);
+  });
+}
+
+/**
+ * Draw resting order book size as a heatmap left of the value area trail: one
+ * column per depth snapshot (oldest left), bids teal and asks red, brighter
+ * where more size rests
+ */
+function drawDepthHeatmap(
+  ctx: CanvasRenderingContext2D,
+  history: DepthSnapshot[],
+  width: number,
+  height: number,
+  priceMin: number,
+  priceMax: number
+) {
+  if (history.length === 0) return;
+
+  const priceSpan = priceMax - priceMin;
+  const toY = (price: number) => height - ((price - priceMin) / priceSpan) * height;
+  const heatmapWidth = 120;
+  const endX = width - 60;
+  const step = heatmapWidth / Math.max(history.length, 1);
+
+  const maxSize = Math.max(
+    1,
+    ...history.flatMap(snapshot => [...snapshot.bids, ...snapshot.asks].map(level => level.size))
+  );
+
+  history.forEach((snapshot, i) => {
+    const x = endX - heatmapWidth + i * step;
+    // Row height from the spacing of adjacent levels (one tick)
+    const levels = [...snapshot.bids].reverse().concat(snapshot.asks);
+    const tick = levels.length > 1 ? Math.abs(levels[1].price - levels[0].price) : 0;
+    const rowHeight = Math.max(tick > 0 ? (tick / priceSpan) * height : 2, 1);
+
+    const drawLevels = (side: DepthSnapshot['bids'], rgb: string) => {
+      side.forEach(level => {
+        if (level.price < priceMin || level.price > priceMax) return;
+        const intensity = Math.sqrt(level.size / maxSize);
+        ctx.fillStyle = `rgba(${rgb}, ${0.1 + 0.7 * intensity})`;
+        ctx.fillRect(x, toY(level.price) - rowHeight / 2, Math.max(step, 1), rowHeight);
+      });
+    };
+    drawLevels(snapshot.bids, '0, 188, 212');
+    drawLevels(snapshot.asks, '255, 82, 82');
+  });
+}
+
+/**
+ * Mark absorption (A), iceberg (I), pulled (P) and stacked (S) liquidity at its
+ * price on the heatmap's right edge, newest brightest
+ */
+function drawLiquidityEvents(
+  ctx: CanvasRenderingContext2D,
+  events: LiquidityEvent[],
+  width: number,
+  height: number,
+  priceMin: number,
+  priceMax: number
+) {
+  if (events.length === 0) return;
+
+  const priceSpan = priceMax - priceMin;
+  const toY = (price: number) => height - ((price - priceMin) / priceSpan) * height;
+  const x = width - 64;
+  const labels: Record<LiquidityEvent['kind'], string> = {
+    absorption: 'A',
+    iceberg: 'I',
+    pulled: 'P',
+    stacked: 'S',
+  };
+
+  ctx.font = 'bold 10px "JetBrains Mono", monospace';
+  ctx.textAlign = 'right';
+  events.forEach((event, i) => {
+    if (event.price < priceMin || event.price > priceMax) return;
+    const alpha = 0.3 + 0.7 * ((i + 1) / events.length);
+    const rgb = event.side === 'bid' ? '0, 188, 212' : '255, 82, 82';
+    ctx.fillStyle = `rgba(${rgb}, ${alpha})`;
+    ctx.fillText(`${labels[event.kind]} ${event.size}`, x, toY(event.price) + 3);
+  });
+}
+
+/**
+ * Draw impulse legs as arrows from start to end price just right of the volume
+ * profile (oldest left), newest brightest
+ */
+function drawImpulseLegs(
+  ctx: CanvasRenderingContext2D,
+  legs: ImpulseLegEvent[],
+  height: number,
+  priceMin: number,
+  priceMax: number
+) {
+  if (legs.length === 0) return;
+
+  const priceSpan = priceMax - priceMin;
+  const toY = (price: number) => height - ((price - priceMin) / priceSpan) * height;
+  const startX = 130; // After volume profile
+
+  ctx.lineWidth = 2;
+  ctx.font = 'bold 9px "JetBrains Mono", monospace';
+  ctx.textAlign = 'center';
+  legs.forEach((leg, i) => {
+    const x = startX + i * 12;
+    const alpha = 0.3 + 0.7 * ((i + 1) / legs.length);
+    const rgb = leg.direction === 'up' ? '0, 230, 118' : '255, 82, 82';
+    const y1 = toY(leg.startPrice);
+    const y2 = toY(leg.endPrice);
+    if (Math.max(y1, y2) < 0 || Math.min(y1, y2) > height) return;
+
+    ctx.strokeStyle = `rgba(${rgb}, ${alpha})`;
+    ctx.beginPath();
+    ctx.moveTo(x, y1);
+    ctx.lineTo(x, y2);
+    const head = leg.direction === 'up' ? 5 : -5;
+    ctx.moveTo(x - 4, y2 + head);
+    ctx.lineTo(x, y2);
+    ctx.lineTo(x + 4, y2 + head);
+    ctx.stroke();
+
+    ctx.fillStyle = `rgba(${rgb}, ${alpha})`;
+    ctx.fillText(`${leg.score}`, x, y2 - head - 2);
+  });
+  ctx.lineWidth = 1;
+}
+
+/**
+ * Draw the low volume nodes of recent impulse legs as dashed lines, newest brightest
+ */
+function drawLvns(
+  ctx: CanvasRenderingContext2D,
+  updates: LvnUpdate[],
+  width: number,
+  height: number,
+  priceMin: number,
+  priceMax: number
+) {
+  if (updates.length === 0) return;
+
+  const priceSpan = priceMax - priceMin;
+  const startX = 120; // After volume profile
+  const endX = width - 60;
+
+  ctx.lineWidth = 1;
+  ctx.font = '9px "JetBrains Mono", monospace';
+  ctx.textAlign = 'left';
+  updates.forEach((update, i) => {
+    const alpha = 0.2 + 0.5 * ((i + 1) / updates.length);
+    update.levels.forEach((level) => {
+      const y = height - ((level.price - priceMin) / priceSpan) * height;
+      if (y < 0 || y > height) return;
+
+      ctx.strokeStyle = `rgba(171, 71, 188, ${alpha})`;
+      ctx.setLineDash([4, 4]);
+      ctx.beginPath();
+      ctx.moveTo(startX, y);
+      ctx.lineTo(endX, y);
+      ctx.stroke();
+      ctx.setLineDash([]);
+
+      ctx.fillStyle = `rgba(171, 71, 188, ${alpha})`;
+      ctx.fillText(`LVN ${level.price.toFixed(2)}`, startX + 4, y - 3);
+    });
+  });
+}
+
+/**
+ * Draw one footprint bar as a sell x buy ladder left of the depth heatmap:
+ * imbalanced sides highlighted, the bar's POC boxed
+ */
+function drawFootprintLadder(
+  ctx: CanvasRenderingContext2D,
+  footprint: Footprint | undefined,
+  width: number,
+  height: number,
+  priceMin: number,
+  priceMax: number
+) {
+  if (!footprint || footprint.levels.length === 0) return;
+
+  const priceSpan = priceMax - priceMin;
+  const toY = (price: number) => height - ((price - priceMin) / priceSpan) * height;
+  const centerX = width - 220;
+  const cellWidth = 36;
+
+  ctx.font = '9px "JetBrains Mono", monospace';
+  footprint.levels.forEach(level => {
+    if (level.price < priceMin || level.price > priceMax) return;
+    const y = toY(level.price);
+
+    if (level.price === footprint.poc) {
+      ctx.strokeStyle = 'rgba(255, 193, 7, 0.8)';
+      ctx.strokeRect(centerX - cellWidth, y - 6, cellWidth * 2, 12);
+    }
+
+    ctx.textAlign = 'right';
+    ctx.fillStyle = level.sellImbalance ? COLORS.sell.stroke : COLORS.gridText;
+    ctx.fillText(String(level.sellVolume), centerX - 3, y + 3);
+
+    ctx.textAlign = 'left';
+    ctx.fillStyle = level.buyImbalance ? COLORS.buy.stroke : COLORS.gridText;
+    ctx.fillText(String(level.buyVolume), centerX + 3, y + 3);
+  });
+
+  ctx.textAlign = 'center';
+  ctx.fillStyle = footprint.delta >= 0 ? COLORS.buy.stroke : COLORS.sell.stroke;
+  ctx.fillText(`${footprint.timeframe} Δ${footprint.delta}`, centerX, toY(footprint.low) + 16);
+}
@@ -176,6 +176,9 @@ export function PnLSimulator({
                 <option value="delta_flip">Delta Flip</option>
                 <option value="absorption">Absorption</option>
                 <option value="stacked_imbalance">Stacked Imbalance</option>
+                <option value="divergence">Divergence</option>
+                <option value="large_trade">Large Trade</option>
+                <option value="sweep">Sweep</option>
               </select>
             </div>
             <button className="enter-trade-btn" onClick={handleManualTrade}>
@@ -14,9 +14,10 @@ interface ReplayControlsProps {
   onPause: () => void;
   onResume: () => void;
   onSpeedChange: (speed: number) => void;
+  onStep: (unit: 'window' | 'second' | 'next_signal') => void;
 }
 
-export function ReplayControls({ status, onPause, onResume, onSpeedChange }: ReplayControlsProps) {
+export function ReplayControls({ status, onPause, onResume, onSpeedChange, onStep }: ReplayControlsProps) {
   const handleSpeedChange = useCallback(
     (e: React.ChangeEvent<HTMLInputElement>) => {
       const speed = parseInt(e.target.value, 10);
@@ -53,11 +54,30 @@ export function ReplayControls({ status, onPause, onResume, onSpeedChange }: Rep
           {status.isPaused ? '▶' : '⏸'}
         </button>
 
+        <button className="step-btn" onClick={() => onStep('window')} title="Step one window">
+          ⏭
+        </button>
+        <button className="step-btn" onClick={() => onStep('second')} title="Step one second">
+          +1s
+        </button>
+        <button className="step-btn" onClick={() => onStep('next_signal')} title="Step to next signal">
+          ⚡
+        </button>
+
         {status.currentTime && (
           <span className="replay-time">{formatTime(status.currentTime)}</span>
         )}
       </div>
 
+      {status.replayProgress !== null && (
+        <div className="replay-progress">
+          <div
+            className="replay-progress-fill"
+            style={{ width: `${(status.replayProgress * 100).toFixed(1)}%` }}
+          />
+        </div>
+      )}
+
       <div className="replay-speed">
         <label>Speed</label>
         <input
@@ -33,7 +33,7 @@ const CHART_COLORS = {
   border: 'rgba(255, 255, 255, 0.08)',
 };
 
-const SIGNAL_TYPES = ['delta_flip', 'absorption', 'stacked_imbalance', 'confluence'];
+const SIGNAL_TYPES = ['delta_flip', 'absorption', 'stacked_imbalance', 'divergence', 'large_trade', 'sweep', 'confluence'];
 
 export function StatsCharts({ stats }: StatsChartsProps) {
   const canvasRef = useRef<HTMLCanvasElement>(null);
@@ -246,7 +246,7 @@ export function StatsPage({ onClose }: StatsPageProps) {
     return 'outcome-breakeven';
   };
 
-  const signalTypes = ['delta_flip', 'absorption', 'stacked_imbalance', 'confluence'];
+  const signalTypes = ['delta_flip', 'absorption', 'stacked_imbalance', 'divergence', 'large_trade', 'sweep', 'confluence'];
 
   return (
     <div className="stats-page">
@@ -0,0 +1,221 @@
+//! Aggressor Order Reconstruction
+//!
+//! CME reports one aggressive order as many prints - one per resting order it
+//! filled, across every price level it took - all with the same `ts_event`.
+//! Prints are grouped back into aggressor orders before the order-level
+//! detectors (large trades, sweeps) see them:
+//! - same `ts_event` (nanoseconds), same aggressor side
+//! - `sequence` unchanged or one past the previous print
+//! - an order ends at a print flagged `F_LAST` (last record of the match event)
+//!
+//! Sources without Databento fields (demo, stored bars) fall back to the same
+//! millisecond timestamp and side.
+
+use databento::dbn::{flags, TradeMsg};
+
+/// An order is a sweep when it takes more than this many price levels
+pub const DEFAULT_SWEEP_LEVELS: u32 = 2;
+
+/// Databento fields of a print that tie it to its aggressor order
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrintInfo {
+    /// Matching engine event time (nanoseconds)
+    pub ts_event_ns: u64,
+    pub sequence: u32,
+    pub flags: u8,
+}
+
+impl PrintInfo {
+    pub fn from_msg(msg: &TradeMsg) -> Self {
+        Self {
+            ts_event_ns: msg.hd.ts_event,
+            sequence: msg.sequence,
+            flags: msg.flags.raw(),
+        }
+    }
+
+    /// Last record of its match event
+    pub fn is_last(&self) -> bool {
+        self.flags & flags::LAST != 0
+    }
+}
+
+/// One aggressive order rebuilt from its prints
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggressorOrder {
+    /// Unix ms
+    pub timestamp: u64,
+    pub is_buy: bool,
+    pub size: u64,
+    /// Volume weighted over the fills
+    pub price: f64,
+    /// First and last fill - the sweep's direction through the book
+    pub first_price: f64,
+    pub last_price: f64,
+    pub price_low: f64,
+    pub price_high: f64,
+    /// Price levels taken (each change of fill price starts a level)
+    pub levels: u32,
+    pub fills: u32,
+}
+
+impl AggressorOrder {
+    /// Took more than `max_levels` price levels
+    pub fn is_sweep(&self, max_levels: u32) -> bool {
+        self.levels > max_levels
+    }
+
+    fn fill(&mut self, price: f64, size: u64) {
+        if price != self.last_price {
+            self.levels += 1;
+        }
+        self.price = (self.price * self.size as f64 + price * size as f64) / (self.size + size) as f64;
+        self.size += size;
+        self.last_price = price;
+        self.price_low = self.price_low.min(price);
+        self.price_high = self.price_high.max(price);
+        self.fills += 1;
+    }
+}
+
+#[derive(Debug)]
+struct OpenOrder {
+    order: AggressorOrder,
+    // Print fields of the latest fill (None for sources without them)
+    print: Option<PrintInfo>,
+}
+
+/// Groups prints into aggressor orders
+#[derive(Debug, Default)]
+pub struct OrderAggregator {
+    // Order still taking fills
+    open: Option<OpenOrder>,
+    completed: Vec<AggressorOrder>,
+}
+
+impl OrderAggregator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, price: f64, size: u64, is_buy: bool, timestamp: u64, print: Option<PrintInfo>) {
+        let continues = self.open.as_ref().is_some_and(|open| {
+            open.order.is_buy == is_buy
+                && match (open.print, print) {
+                    (Some(last), Some(print)) => {
+                        print.ts_event_ns == last.ts_event_ns
+                            && (print.sequence == last.sequence
+                                || print.sequence == last.sequence.wrapping_add(1))
+                    }
+                    _ => open.order.timestamp == timestamp,
+                }
+        });
+
+        match self.open.as_mut().filter(|_| continues) {
+            Some(open) => {
+                open.order.fill(price, size);
+                open.print = print;
+            }
+            None => {
+                self.close();
+                self.open = Some(OpenOrder {
+                    order: AggressorOrder {
+                        timestamp,
+                        is_buy,
+                        size,
+                        price,
+                        first_price: price,
+                        last_price: price,
+                        price_low: price,
+                        price_high: price,
+                        levels: 1,
+                        fills: 1,
+                    },
+                    print,
+                });
+            }
+        }
+
+        if print.is_some_and(|print| print.is_last()) {
+            self.close();
+        }
+    }
+
+    /// Orders completed by `now` - an order is complete at its `F_LAST` print,
+    /// once a print of another order arrives or once time has moved past it
+    pub fn take_orders(&mut self, now: u64) -> Vec<AggressorOrder> {
+        if self.open.as_ref().is_some_and(|open| open.order.timestamp < now) {
+            self.close();
+        }
+        std::mem::take(&mut self.completed)
+    }
+
+    fn close(&mut self) {
+        if let Some(open) = self.open.take() {
+            self.completed.push(open.order);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn print(ts_event_ns: u64, sequence: u32, last: bool) -> Option<PrintInfo> {
+        Some(PrintInfo {
+            ts_event_ns,
+            sequence,
+            flags: if last { flags::LAST } else { 0 },
+        })
+    }
+
+    #[test]
+    fn test_sweep_rebuilt_from_prints() {
+        let mut orders = OrderAggregator::new();
+        // A buyer lifting 100.00-100.50: 4 fills over 3 levels, ending at F_LAST
+        orders.add(100.0, 10, true, 1_000, print(1_000_000_001, 7, false));
+        orders.add(100.0, 5, true, 1_000, print(1_000_000_001, 7, false));
+        orders.add(100.25, 10, true, 1_000, print(1_000_000_001, 8, false));
+        orders.add(100.5, 15, true, 1_000, print(1_000_000_001, 9, true));
+        // Another buyer in the same millisecond is a new order
+        orders.add(100.5, 2, true, 1_000, print(1_000_000_002, 10, true));
+
+        let completed = orders.take_orders(1_000);
+        assert_eq!(completed.len(), 2);
+        assert_eq!(
+            completed[0],
+            AggressorOrder {
+                timestamp: 1_000,
+                is_buy: true,
+                size: 40,
+                price: (100.0 * 15.0 + 100.25 * 10.0 + 100.5 * 15.0) / 40.0,
+                first_price: 100.0,
+                last_price: 100.5,
+                price_low: 100.0,
+                price_high: 100.5,
+                levels: 3,
+                fills: 4,
+            }
+        );
+        assert!(completed[0].is_sweep(DEFAULT_SWEEP_LEVELS));
+        assert_eq!((completed[1].size, completed[1].levels), (2, 1));
+    }
+
+    #[test]
+    fn test_prints_without_databento_fields_group_by_millisecond() {
+        let mut orders = OrderAggregator::new();
+        orders.add(100.0, 20, false, 1_000, None);
+        orders.add(99.75, 20, false, 1_000, None);
+        // Still open at its own timestamp
+        assert!(orders.take_orders(1_000).is_empty());
+
+        // The other side closes it
+        orders.add(99.75, 5, true, 1_000, None);
+        let completed = orders.take_orders(1_000);
+        assert_eq!(completed.len(), 1);
+        assert_eq!((completed[0].size, completed[0].levels, completed[0].fills), (40, 2, 2));
+
+        // Time moving on closes the last one
+        assert_eq!(orders.take_orders(1_001).len(), 1);
+    }
+}
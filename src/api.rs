@@ -7,7 +7,7 @@ use axum::{
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 
-use crate::supabase::{SessionRow, SignalQuery, SignalRow};
+use crate::store::{SessionRow, SignalQuery, SignalRow};
 use crate::types::AppState;
 
 /// Response for signals list
@@ -48,10 +48,10 @@ pub async fn get_signals(
     State(state): State<Arc<AppState>>,
     Query(params): Query<SignalsQueryParams>,
 ) -> impl IntoResponse {
-    let Some(ref supabase) = state.supabase else {
+    let Some(ref store) = state.store else {
         return (
             StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({"error": "Supabase not configured"})),
+            Json(serde_json::json!({"error": "Signal store not configured"})),
         );
     };
 
@@ -67,8 +67,8 @@ pub async fn get_signals(
 
     // Get signals and count in parallel
     let (signals_result, count_result) = tokio::join!(
-        supabase.query_signals(&query),
-        supabase.count_signals(&query)
+        store.query_signals(&query),
+        store.count_signals(&query)
     );
 
     match (signals_result, count_result) {
@@ -88,16 +88,16 @@ pub async fn get_sessions(
     State(state): State<Arc<AppState>>,
     Query(params): Query<SessionsQueryParams>,
 ) -> impl IntoResponse {
-    let Some(ref supabase) = state.supabase else {
+    let Some(ref store) = state.store else {
         return (
             StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({"error": "Supabase not configured"})),
+            Json(serde_json::json!({"error": "Signal store not configured"})),
         );
     };
 
     let limit = params.limit.unwrap_or(20);
 
-    match supabase.query_sessions(limit).await {
+    match store.query_sessions(limit).await {
         Ok(sessions) => (
             StatusCode::OK,
             Json(serde_json::json!(SessionsResponse { sessions })),
@@ -111,14 +111,14 @@ pub async fn get_sessions(
 
 /// GET /api/stats - Aggregate stats
 pub async fn get_stats(State(state): State<Arc<AppState>>) -> impl IntoResponse {
-    let Some(ref supabase) = state.supabase else {
+    let Some(ref store) = state.store else {
         return (
             StatusCode::SERVICE_UNAVAILABLE,
-            Json(serde_json::json!({"error": "Supabase not configured"})),
+            Json(serde_json::json!({"error": "Signal store not configured"})),
         );
     };
 
-    match supabase.get_aggregate_stats().await {
+    match store.get_aggregate_stats().await {
         Ok(stats) => (StatusCode::OK, Json(serde_json::json!(stats))),
         Err(e) => (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -144,11 +144,11 @@ pub async fn export_signals(
     State(state): State<Arc<AppState>>,
     Query(params): Query<ExportQueryParams>,
 ) -> impl IntoResponse {
-    let Some(ref supabase) = state.supabase else {
+    let Some(ref store) = state.store else {
         return (
             StatusCode::SERVICE_UNAVAILABLE,
             [(header::CONTENT_TYPE, "application/json")],
-            r#"{"error": "Supabase not configured"}"#.to_string(),
+            r#"{"error": "Signal store not configured"}"#.to_string(),
         );
     };
 
@@ -162,7 +162,7 @@ pub async fn export_signals(
         end_date: params.end_date.clone(),
     };
 
-    let signals = match supabase.query_signals(&query).await {
+    let signals = match store.query_signals(&query).await {
         Ok(s) => s,
         Err(e) => {
             return (
@@ -0,0 +1,578 @@
+//! Order Book
+//!
+//! Resting liquidity from Databento MBP-10 (top ten levels on every update) or
+//! MBO (every order, aggregated to price levels), kept per instrument by
+//! ProcessingState. A size change at a price is split into fills - explained by
+//! the book's own trade records there - and cancels, which drives the passive
+//! liquidity events:
+//! - absorption: more traded into a resting price than it ever displayed, and it still stands
+//! - iceberg: a resting price refilled again and again after being traded into
+//! - pulled: a large resting size cancelled
+//! - stacked: a large size added at a price
+//!
+//! The top ten levels per side are streamed as a `Depth` snapshot for the heatmap.
+
+use clap::ValueEnum;
+use databento::dbn::{MboMsg, Mbp10Msg, Schema, UNDEF_PRICE};
+use std::collections::{BTreeMap, HashMap};
+use std::ffi::c_char;
+
+use crate::instruments::InstrumentSpec;
+use crate::types::{DepthLevel, LiquidityEvent};
+
+/// Levels per side in a depth snapshot (MBP-10 depth)
+pub const DEPTH_LEVELS: usize = 10;
+
+/// Pulled/stacked: size of at least this many typical displayed levels
+const LARGE_LEVEL_MULTIPLE: u64 = 4;
+
+/// Smallest size (contracts) that counts as large, however thin the book
+const MIN_LARGE_SIZE: u64 = 20;
+
+/// Absorption: traded into a price at least this many times its peak displayed size
+const ABSORPTION_MULTIPLE: u64 = 2;
+
+/// Iceberg: refills of a price after it was traded into
+const ICEBERG_MIN_REFILLS: u32 = 3;
+
+/// Activity at prices that haven't changed for this long is dropped
+const ACTIVITY_TTL_MS: u64 = 5 * 60 * 1000;
+
+/// Order book feed to subscribe to next to trades
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum BookSchema {
+    /// Market by price, top ten levels
+    Mbp10,
+    /// Market by order (full depth, replayed from a snapshot on subscribe)
+    Mbo,
+}
+
+impl BookSchema {
+    pub fn schema(&self) -> Schema {
+        match self {
+            Self::Mbp10 => Schema::Mbp10,
+            Self::Mbo => Schema::Mbo,
+        }
+    }
+}
+
+/// Databento book action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookAction {
+    Add,
+    Cancel,
+    Modify,
+    Trade,
+    Fill,
+    Clear,
+    None,
+}
+
+impl BookAction {
+    fn from_dbn(action: c_char) -> Self {
+        match action as u8 {
+            b'A' => Self::Add,
+            b'C' => Self::Cancel,
+            b'M' => Self::Modify,
+            b'T' => Self::Trade,
+            b'F' => Self::Fill,
+            b'R' => Self::Clear,
+            _ => Self::None,
+        }
+    }
+}
+
+/// Book side - for trades, the aggressor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BookSide {
+    Bid,
+    Ask,
+}
+
+impl BookSide {
+    fn from_dbn(side: c_char) -> Option<Self> {
+        match side as u8 {
+            b'B' => Some(Self::Bid),
+            b'A' => Some(Self::Ask),
+            _ => None,
+        }
+    }
+
+    fn opposite(&self) -> Self {
+        match self {
+            Self::Bid => Self::Ask,
+            Self::Ask => Self::Bid,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Bid => "bid",
+            Self::Ask => "ask",
+        }
+    }
+}
+
+/// One book record
+#[derive(Debug, Clone, PartialEq)]
+pub enum BookEvent {
+    /// MBP-10: the event and the top ten levels per side after it
+    Depth {
+        action: BookAction,
+        side: Option<BookSide>,
+        price: f64,
+        size: u32,
+        bids: Vec<DepthLevel>,
+        asks: Vec<DepthLevel>,
+    },
+    /// MBO: one order event
+    Order {
+        action: BookAction,
+        side: Option<BookSide>,
+        price: f64,
+        size: u32,
+        order_id: u64,
+    },
+    /// Book cleared (start of a snapshot or a reconnect)
+    Clear,
+}
+
+/// A book record with its event time
+#[derive(Debug, Clone, PartialEq)]
+pub struct BookUpdate {
+    /// Unix ms
+    pub timestamp: u64,
+    pub event: BookEvent,
+}
+
+/// Convert a Databento MBP-10 record
+pub fn book_update_from_mbp10(msg: &Mbp10Msg) -> BookUpdate {
+    let action = BookAction::from_dbn(msg.action);
+    let event = if action == BookAction::Clear {
+        BookEvent::Clear
+    } else {
+        let level = |px: i64, size: u32, count: u32| {
+            (px != UNDEF_PRICE && size > 0).then(|| DepthLevel {
+                price: px as f64 / 1e9,
+                size,
+                count,
+            })
+        };
+        BookEvent::Depth {
+            action,
+            side: BookSide::from_dbn(msg.side),
+            price: msg.price as f64 / 1e9,
+            size: msg.size,
+            bids: msg.levels.iter().filter_map(|l| level(l.bid_px, l.bid_sz, l.bid_ct)).collect(),
+            asks: msg.levels.iter().filter_map(|l| level(l.ask_px, l.ask_sz, l.ask_ct)).collect(),
+        }
+    };
+    BookUpdate {
+        timestamp: msg.hd.ts_event / 1_000_000,
+        event,
+    }
+}
+
+/// Convert a Databento MBO record
+pub fn book_update_from_mbo(msg: &MboMsg) -> BookUpdate {
+    let action = BookAction::from_dbn(msg.action);
+    let event = if action == BookAction::Clear {
+        BookEvent::Clear
+    } else {
+        BookEvent::Order {
+            action,
+            side: BookSide::from_dbn(msg.side),
+            price: msg.price as f64 / 1e9,
+            size: msg.size,
+            order_id: msg.order_id,
+        }
+    };
+    BookUpdate {
+        timestamp: msg.hd.ts_event / 1_000_000,
+        event,
+    }
+}
+
+/// Size and order count at a price
+type Levels = BTreeMap<i64, (u32, u32)>;
+
+/// Fill/refill history of one resting price
+#[derive(Debug, Clone, Default)]
+struct LevelActivity {
+    // Traded at the price but not yet seen as a size decrease
+    unmatched_trades: u64,
+    // Total traded into the price
+    traded: u64,
+    peak_size: u32,
+    refills: u32,
+    // A fill reduced the size since it last grew
+    depleted: bool,
+    last_update: u64,
+    absorption_sent: bool,
+    iceberg_sent: bool,
+    stacked_sent: bool,
+}
+
+/// Per-instrument book state and liquidity event detection
+#[derive(Debug, Clone)]
+pub struct OrderBook {
+    instrument: InstrumentSpec,
+    bids: Levels,
+    asks: Levels,
+    // MBO only: order_id -> (side, price key, size)
+    orders: HashMap<u64, (BookSide, i64, u32)>,
+    activity: HashMap<(BookSide, i64), LevelActivity>,
+    events: Vec<LiquidityEvent>,
+    changed: bool,
+}
+
+impl OrderBook {
+    pub fn new(instrument: &InstrumentSpec) -> Self {
+        Self {
+            instrument: instrument.clone(),
+            bids: Levels::new(),
+            asks: Levels::new(),
+            orders: HashMap::new(),
+            activity: HashMap::new(),
+            events: Vec::new(),
+            changed: false,
+        }
+    }
+
+    /// Apply a book record
+    pub fn apply(&mut self, update: &BookUpdate) {
+        let now = update.timestamp;
+        match &update.event {
+            BookEvent::Clear => self.clear(),
+            BookEvent::Depth { action, side, price, size, bids, asks } => {
+                let key = self.instrument.price_key(*price);
+                if *action == BookAction::Trade {
+                    self.on_trade(*side, key, *size, now);
+                }
+
+                let before = side.map(|side| self.size_at(side, key));
+                self.bids = self.levels_from(bids);
+                self.asks = self.levels_from(asks);
+                self.changed = true;
+
+                if let (Some(side), Some(before)) = (side, before) {
+                    if matches!(action, BookAction::Add | BookAction::Cancel | BookAction::Modify) {
+                        let after = self.size_at(*side, key);
+                        self.on_level_change(*side, key, before, after, now);
+                    }
+                }
+            }
+            BookEvent::Order { action, side, price, size, order_id } => {
+                let key = self.instrument.price_key(*price);
+                match action {
+                    BookAction::Trade => self.on_trade(*side, key, *size, now),
+                    BookAction::Add | BookAction::Modify => {
+                        // Unknown modified orders (e.g. from before the snapshot) are added
+                        let side = self.orders.get(order_id).map(|(side, _, _)| *side).or(*side);
+                        if let Some(side) = side {
+                            self.set_order(*order_id, side, key, *size, now);
+                        }
+                    }
+                    BookAction::Cancel => {
+                        // `size` is the cancelled quantity - partial cancels leave the rest
+                        if let Some(&(side, key, resting)) = self.orders.get(order_id) {
+                            self.set_order(*order_id, side, key, resting.saturating_sub(*size), now);
+                        }
+                    }
+                    // Fills don't change the book - the cancel that follows does
+                    BookAction::Fill | BookAction::Clear | BookAction::None => {}
+                }
+            }
+        }
+    }
+
+    /// Top levels per side (bids best first, asks best first)
+    pub fn depth(&self, levels: usize) -> (Vec<DepthLevel>, Vec<DepthLevel>) {
+        let level = |(key, (size, count)): (&i64, &(u32, u32))| DepthLevel {
+            price: self.instrument.key_price(*key),
+            size: *size,
+            count: *count,
+        };
+        (
+            self.bids.iter().rev().take(levels).map(level).collect(),
+            self.asks.iter().take(levels).map(level).collect(),
+        )
+    }
+
+    /// Did the book change since the last call?
+    pub fn take_changed(&mut self) -> bool {
+        std::mem::take(&mut self.changed)
+    }
+
+    /// Liquidity events detected since the last call (symbol left empty)
+    pub fn take_events(&mut self) -> Vec<LiquidityEvent> {
+        std::mem::take(&mut self.events)
+    }
+
+    /// Forget fill/refill history at prices that went quiet
+    pub fn prune(&mut self, now: u64) {
+        self.activity
+            .retain(|_, activity| now.saturating_sub(activity.last_update) < ACTIVITY_TTL_MS);
+    }
+
+    fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.orders.clear();
+        self.activity.clear();
+        self.changed = true;
+    }
+
+    fn levels_from(&self, levels: &[DepthLevel]) -> Levels {
+        levels
+            .iter()
+            .map(|l| (self.instrument.price_key(l.price), (l.size, l.count)))
+            .collect()
+    }
+
+    fn side_levels(&mut self, side: BookSide) -> &mut Levels {
+        match side {
+            BookSide::Bid => &mut self.bids,
+            BookSide::Ask => &mut self.asks,
+        }
+    }
+
+    fn size_at(&self, side: BookSide, key: i64) -> u32 {
+        let levels = match side {
+            BookSide::Bid => &self.bids,
+            BookSide::Ask => &self.asks,
+        };
+        levels.get(&key).map(|(size, _)| *size).unwrap_or(0)
+    }
+
+    /// Move/resize an order (size 0 removes it), one level change per price touched
+    fn set_order(&mut self, order_id: u64, side: BookSide, key: i64, size: u32, now: u64) {
+        let old = self.orders.remove(&order_id);
+        if let Some((old_side, old_key, old_size)) = old.filter(|(s, k, _)| (*s, *k) != (side, key)) {
+            self.change_level(old_side, old_key, -(old_size as i64), -1, now);
+        }
+        let (resting, orders) = match old.filter(|(s, k, _)| (*s, *k) == (side, key)) {
+            Some((_, _, old_size)) => (old_size, 1),
+            None => (0, 0),
+        };
+        if size > 0 {
+            self.orders.insert(order_id, (side, key, size));
+        }
+        let count_change = (size > 0) as i32 - orders;
+        self.change_level(side, key, size as i64 - resting as i64, count_change, now);
+    }
+
+    fn change_level(&mut self, side: BookSide, key: i64, size_change: i64, count_change: i32, now: u64) {
+        if size_change == 0 && count_change == 0 {
+            return;
+        }
+        let before = self.size_at(side, key);
+        let after = (before as i64 + size_change).max(0) as u32;
+        let levels = self.side_levels(side);
+        let level = levels.entry(key).or_default();
+        level.0 = after;
+        level.1 = (level.1 as i32 + count_change).max(0) as u32;
+        if after == 0 {
+            levels.remove(&key);
+        }
+        self.changed = true;
+        self.on_level_change(side, key, before, after, now);
+    }
+
+    /// Median displayed size of the top levels (both sides) - robust to the outliers we flag
+    fn typical_level_size(&self) -> u64 {
+        let mut sizes: Vec<u32> = self
+            .bids
+            .values()
+            .rev()
+            .take(DEPTH_LEVELS)
+            .chain(self.asks.values().take(DEPTH_LEVELS))
+            .map(|(size, _)| *size)
+            .collect();
+        if sizes.is_empty() {
+            return 0;
+        }
+        sizes.sort_unstable();
+        sizes[sizes.len() / 2] as u64
+    }
+
+    fn large_size(&self) -> u64 {
+        (self.typical_level_size() * LARGE_LEVEL_MULTIPLE).max(MIN_LARGE_SIZE)
+    }
+
+    /// A trade hits the resting side opposite the aggressor
+    fn on_trade(&mut self, aggressor: Option<BookSide>, key: i64, size: u32, now: u64) {
+        let Some(aggressor) = aggressor else {
+            return;
+        };
+        let activity = self.activity.entry((aggressor.opposite(), key)).or_default();
+        activity.unmatched_trades += size as u64;
+        activity.traded += size as u64;
+        activity.last_update = now;
+    }
+
+    fn on_level_change(&mut self, side: BookSide, key: i64, before: u32, after: u32, now: u64) {
+        if before == after {
+            return;
+        }
+        let large_size = self.large_size();
+        let price = self.instrument.key_price(key);
+        let activity = self.activity.entry((side, key)).or_default();
+        activity.last_update = now;
+
+        let mut events = Vec::new();
+        let event = |kind: &str, size: u64, refills: u32| LiquidityEvent {
+            symbol: String::new(),
+            timestamp: now,
+            kind: kind.to_string(),
+            side: side.as_str().to_string(),
+            price,
+            size,
+            resting_size: after,
+            refills,
+        };
+
+        if after < before {
+            // Decreases explained by trades at the price are fills, the rest was cancelled
+            let decrease = (before - after) as u64;
+            let filled = decrease.min(activity.unmatched_trades);
+            activity.unmatched_trades -= filled;
+            if filled > 0 {
+                activity.depleted = true;
+            }
+            let cancelled = decrease - filled;
+            if cancelled >= large_size {
+                events.push(event("pulled", cancelled, activity.refills));
+                activity.stacked_sent = false;
+            }
+        } else {
+            if activity.depleted {
+                activity.refills += 1;
+                activity.depleted = false;
+            }
+            if after as u64 >= large_size && !activity.stacked_sent {
+                activity.stacked_sent = true;
+                events.push(event("stacked", after as u64, activity.refills));
+            }
+        }
+        activity.peak_size = activity.peak_size.max(after);
+
+        // The price is still defended after taking more than it ever showed
+        let peak = activity.peak_size as u64;
+        if after > 0
+            && !activity.absorption_sent
+            && peak > 0
+            && activity.traded >= peak * ABSORPTION_MULTIPLE
+            && activity.traded >= MIN_LARGE_SIZE
+        {
+            activity.absorption_sent = true;
+            events.push(event("absorption", activity.traded, activity.refills));
+        }
+        if after > 0
+            && !activity.iceberg_sent
+            && activity.refills >= ICEBERG_MIN_REFILLS
+            && activity.traded > peak
+        {
+            activity.iceberg_sent = true;
+            events.push(event("iceberg", activity.traded, activity.refills));
+        }
+
+        self.events.extend(events);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn level(price: f64, size: u32) -> DepthLevel {
+        DepthLevel { price, size, count: 1 }
+    }
+
+    fn depth(action: BookAction, side: BookSide, price: f64, size: u32, bids: &[(f64, u32)], asks: &[(f64, u32)]) -> BookUpdate {
+        BookUpdate {
+            timestamp: 1_000,
+            event: BookEvent::Depth {
+                action,
+                side: Some(side),
+                price,
+                size,
+                bids: bids.iter().map(|&(p, s)| level(p, s)).collect(),
+                asks: asks.iter().map(|&(p, s)| level(p, s)).collect(),
+            },
+        }
+    }
+
+    fn order(action: BookAction, side: BookSide, price: f64, size: u32, order_id: u64) -> BookUpdate {
+        BookUpdate {
+            timestamp: 1_000,
+            event: BookEvent::Order {
+                action,
+                side: Some(side),
+                price,
+                size,
+                order_id,
+            },
+        }
+    }
+
+    fn kinds(book: &mut OrderBook) -> Vec<String> {
+        book.take_events().into_iter().map(|e| e.kind).collect()
+    }
+
+    #[test]
+    fn test_mbp_iceberg_and_absorption() {
+        let mut book = OrderBook::new(&InstrumentSpec::default());
+        let bids = [(100.0, 10), (99.75, 10)];
+        book.apply(&depth(BookAction::Add, BookSide::Ask, 100.25, 10, &bids, &[(100.25, 10)]));
+
+        // Buyers lift the 10 shown at 100.25, and it's refilled each time
+        for _ in 0..3 {
+            book.apply(&depth(BookAction::Trade, BookSide::Bid, 100.25, 10, &bids, &[(100.25, 10)]));
+            book.apply(&depth(BookAction::Cancel, BookSide::Ask, 100.25, 10, &bids, &[(100.5, 10)]));
+            book.apply(&depth(BookAction::Add, BookSide::Ask, 100.25, 10, &bids, &[(100.25, 10), (100.5, 10)]));
+        }
+
+        let events = book.take_events();
+        let kinds: Vec<&str> = events.iter().map(|e| e.kind.as_str()).collect();
+        assert_eq!(kinds, vec!["absorption", "iceberg"]);
+        assert_eq!(events[1].side, "ask");
+        assert_eq!(events[1].price, 100.25);
+        assert_eq!(events[1].size, 30);
+        assert_eq!(events[1].refills, 3);
+    }
+
+    #[test]
+    fn test_mbo_pulled_and_stacked() {
+        let mut book = OrderBook::new(&InstrumentSpec::default());
+        book.apply(&order(BookAction::Add, BookSide::Bid, 100.0, 5, 1));
+        book.apply(&order(BookAction::Add, BookSide::Ask, 100.25, 5, 2));
+        assert!(kinds(&mut book).is_empty());
+
+        book.apply(&order(BookAction::Add, BookSide::Bid, 99.75, 50, 3));
+        assert_eq!(kinds(&mut book), vec!["stacked"]);
+
+        // A fill then its cancel isn't a pull; cancelling the rest without trades is
+        book.apply(&order(BookAction::Trade, BookSide::Ask, 99.75, 10, 0));
+        book.apply(&order(BookAction::Cancel, BookSide::Bid, 99.75, 10, 3));
+        assert!(kinds(&mut book).is_empty());
+        book.apply(&order(BookAction::Cancel, BookSide::Bid, 99.75, 40, 3));
+        assert_eq!(kinds(&mut book), vec!["pulled"]);
+
+        let (bids, asks) = book.depth(DEPTH_LEVELS);
+        assert_eq!(bids, vec![level(100.0, 5)]);
+        assert_eq!(asks, vec![level(100.25, 5)]);
+    }
+
+    #[test]
+    fn test_clear_resets_book() {
+        let mut book = OrderBook::new(&InstrumentSpec::default());
+        book.apply(&order(BookAction::Add, BookSide::Bid, 100.0, 5, 1));
+        assert!(book.take_changed());
+        assert!(!book.take_changed());
+
+        book.apply(&BookUpdate { timestamp: 2_000, event: BookEvent::Clear });
+        assert!(book.take_changed());
+        assert_eq!(book.depth(DEPTH_LEVELS), (Vec::new(), Vec::new()));
+    }
+}
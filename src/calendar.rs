@@ -0,0 +1,351 @@
+//! CME Trading Calendar
+//!
+//! Maps any timestamp to its CME trading date (the Globex session that opens
+//! at 18:00 ET belongs to the next day), its RTH/ETH segment and the exchange
+//! schedule for that day - holidays and early closes. Times are America/New_York
+//! wall-clock, so RTH follows DST. Holiday rules follow the CME equity index
+//! schedule (NQ, ES and their micros).
+
+use chrono::{DateTime, Datelike, Days, NaiveDate, NaiveTime, TimeZone, Weekday};
+use chrono_tz::America::New_York;
+
+use crate::instruments::SessionTimes;
+
+/// Globex halt on exchange holidays (13:00 ET) - the session rolls into the next trading date
+const HOLIDAY_HALT: NaiveTime = NaiveTime::from_hms_opt(13, 0, 0).unwrap();
+
+/// Globex and RTH halt on early-close days (13:15 ET)
+const EARLY_CLOSE: NaiveTime = NaiveTime::from_hms_opt(13, 15, 0).unwrap();
+
+/// Part of the trading day a timestamp falls in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    /// Regular trading hours
+    Rth,
+    /// Globex outside RTH (overnight, pre-market, post-close)
+    Eth,
+    /// No trading - daily maintenance break, weekend or holiday halt
+    Closed,
+}
+
+impl Segment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rth => "rth",
+            Self::Eth => "eth",
+            Self::Closed => "closed",
+        }
+    }
+}
+
+/// Exchange schedule for an ET calendar date
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DaySchedule {
+    Regular,
+    /// Halts at 13:15 ET (day after Thanksgiving, Christmas Eve, July 3)
+    EarlyClose,
+    /// No RTH - Globex halts at 13:00 ET and the session belongs to the next trading date
+    Holiday(&'static str),
+    Weekend,
+}
+
+impl DaySchedule {
+    /// Is this a trading date (a day with an RTH session)?
+    pub fn is_trading_day(&self) -> bool {
+        matches!(self, Self::Regular | Self::EarlyClose)
+    }
+}
+
+/// Where a timestamp falls in the CME calendar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionInfo {
+    /// CME trading date (rolls at the Globex open)
+    pub trading_date: NaiveDate,
+    pub segment: Segment,
+    /// Schedule of the trading date
+    pub schedule: DaySchedule,
+    /// Holiday on the ET calendar date (an abbreviated session that rolls forward)
+    pub holiday: Option<&'static str>,
+}
+
+/// Trading calendar for one instrument's session times
+#[derive(Debug, Clone)]
+pub struct SessionCalendar {
+    rth_start: NaiveTime,
+    rth_end: NaiveTime,
+    eth_start: NaiveTime,
+    eth_end: NaiveTime,
+}
+
+impl SessionCalendar {
+    pub fn new(session: &SessionTimes) -> Self {
+        Self {
+            rth_start: session.rth_start_time(),
+            rth_end: session.rth_end_time(),
+            eth_start: session.eth_start_time(),
+            eth_end: session.eth_end_time(),
+        }
+    }
+
+    /// Trading date, segment and schedule for a timestamp (Unix ms)
+    pub fn classify(&self, timestamp_ms: u64) -> SessionInfo {
+        let (date, time) = et_date_time(timestamp_ms);
+        let trading_date = self.trading_date_at(date, time);
+        let calendar_schedule = schedule(date);
+
+        SessionInfo {
+            trading_date,
+            segment: self.segment_at(date, time, calendar_schedule),
+            schedule: schedule(trading_date),
+            holiday: match calendar_schedule {
+                DaySchedule::Holiday(name) => Some(name),
+                _ => None,
+            },
+        }
+    }
+
+    /// CME trading date of a timestamp (Unix ms)
+    pub fn trading_date(&self, timestamp_ms: u64) -> NaiveDate {
+        let (date, time) = et_date_time(timestamp_ms);
+        self.trading_date_at(date, time)
+    }
+
+    /// Is a timestamp (Unix ms) inside RTH?
+    pub fn is_rth(&self, timestamp_ms: u64) -> bool {
+        let (date, time) = et_date_time(timestamp_ms);
+        self.segment_at(date, time, schedule(date)) == Segment::Rth
+    }
+
+    /// RTH open and close (Unix ms) for a trading date - None if it has no RTH
+    pub fn rth_bounds(&self, trading_date: NaiveDate) -> Option<(u64, u64)> {
+        let close = match schedule(trading_date) {
+            DaySchedule::Regular => self.rth_end,
+            DaySchedule::EarlyClose => self.rth_end.min(EARLY_CLOSE),
+            _ => return None,
+        };
+        Some((et_to_ms(trading_date, self.rth_start)?, et_to_ms(trading_date, close)?))
+    }
+
+    /// Previous trading date (skips weekends and holidays)
+    pub fn previous_trading_date(&self, trading_date: NaiveDate) -> NaiveDate {
+        let mut date = trading_date - Days::new(1);
+        while !schedule(date).is_trading_day() {
+            date = date - Days::new(1);
+        }
+        date
+    }
+
+    fn trading_date_at(&self, date: NaiveDate, time: NaiveTime) -> NaiveDate {
+        let mut trading_date = if time >= self.eth_start {
+            date + Days::new(1)
+        } else {
+            date
+        };
+        while !schedule(trading_date).is_trading_day() {
+            trading_date = trading_date + Days::new(1);
+        }
+        trading_date
+    }
+
+    fn segment_at(&self, date: NaiveDate, time: NaiveTime, schedule: DaySchedule) -> Segment {
+        let weekend = match date.weekday() {
+            Weekday::Sat => true,
+            Weekday::Fri => time >= self.eth_end,
+            Weekday::Sun => time < self.eth_start,
+            _ => false,
+        };
+        let halted = match schedule {
+            DaySchedule::Holiday(_) => time >= HOLIDAY_HALT && time < self.eth_start,
+            DaySchedule::EarlyClose => time >= EARLY_CLOSE && time < self.eth_start,
+            _ => false,
+        };
+        let maintenance = time >= self.eth_end && time < self.eth_start;
+
+        if weekend || halted || maintenance {
+            Segment::Closed
+        } else if schedule.is_trading_day() && time >= self.rth_start && time < self.rth_end {
+            Segment::Rth
+        } else {
+            Segment::Eth
+        }
+    }
+}
+
+/// Exchange schedule for an ET calendar date
+pub fn schedule(date: NaiveDate) -> DaySchedule {
+    if matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+        return DaySchedule::Weekend;
+    }
+    if let Some(name) = holiday(date) {
+        return DaySchedule::Holiday(name);
+    }
+
+    let year = date.year();
+    let day_after_thanksgiving = nth_weekday(year, 11, Weekday::Thu, 4) + Days::new(1);
+    let early_close = date == day_after_thanksgiving
+        || (date.month() == 12 && date.day() == 24)
+        || (date.month() == 7 && date.day() == 3);
+    if early_close {
+        DaySchedule::EarlyClose
+    } else {
+        DaySchedule::Regular
+    }
+}
+
+/// CME equity index holiday on a weekday, if any
+fn holiday(date: NaiveDate) -> Option<&'static str> {
+    let year = date.year();
+    let fixed = |month, day| observed(NaiveDate::from_ymd_opt(year, month, day).unwrap());
+
+    let holidays = match date.month() {
+        // New Year's Day falling on a Saturday isn't observed
+        1 => vec![
+            (new_years_day(year), "New Year's Day"),
+            (Some(nth_weekday(year, 1, Weekday::Mon, 3)), "Martin Luther King Jr. Day"),
+        ],
+        2 => vec![(Some(nth_weekday(year, 2, Weekday::Mon, 3)), "Presidents' Day")],
+        3 | 4 => vec![(Some(easter(year) - Days::new(2)), "Good Friday")],
+        5 => vec![(Some(last_weekday(year, 5, Weekday::Mon)), "Memorial Day")],
+        6 if year >= 2022 => vec![(fixed(6, 19), "Juneteenth")],
+        7 => vec![(fixed(7, 4), "Independence Day")],
+        9 => vec![(Some(nth_weekday(year, 9, Weekday::Mon, 1)), "Labor Day")],
+        11 => vec![(Some(nth_weekday(year, 11, Weekday::Thu, 4)), "Thanksgiving Day")],
+        12 => vec![(fixed(12, 25), "Christmas Day")],
+        _ => vec![],
+    };
+
+    holidays
+        .into_iter()
+        .find(|(day, _)| *day == Some(date))
+        .map(|(_, name)| name)
+}
+
+fn new_years_day(year: i32) -> Option<NaiveDate> {
+    let day = NaiveDate::from_ymd_opt(year, 1, 1)?;
+    match day.weekday() {
+        Weekday::Sat => None,
+        _ => observed(day),
+    }
+}
+
+/// Weekday a fixed-date holiday is observed on (Saturday -> Friday, Sunday -> Monday)
+fn observed(day: NaiveDate) -> Option<NaiveDate> {
+    match day.weekday() {
+        Weekday::Sat => Some(day - Days::new(1)),
+        Weekday::Sun => Some(day + Days::new(1)),
+        _ => Some(day),
+    }
+}
+
+/// `n`th weekday of a month (1-based)
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: u8) -> NaiveDate {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, n).expect("valid weekday of month")
+}
+
+/// Last weekday of a month
+fn last_weekday(year: i32, month: u32, weekday: Weekday) -> NaiveDate {
+    let mut day = nth_weekday(year, month, weekday, 4);
+    while let Some(next) = day.checked_add_days(Days::new(7)).filter(|d| d.month() == month) {
+        day = next;
+    }
+    day
+}
+
+/// Easter Sunday (Anonymous Gregorian algorithm)
+fn easter(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).expect("valid Easter date")
+}
+
+/// ET calendar date and wall-clock time of a timestamp (Unix ms)
+fn et_date_time(timestamp_ms: u64) -> (NaiveDate, NaiveTime) {
+    let et = DateTime::from_timestamp_millis(timestamp_ms as i64)
+        .unwrap_or_default()
+        .with_timezone(&New_York)
+        .naive_local();
+    (et.date(), et.time())
+}
+
+/// Unix ms of an ET wall-clock time (earliest if ambiguous)
+fn et_to_ms(date: NaiveDate, time: NaiveTime) -> Option<u64> {
+    New_York
+        .from_local_datetime(&date.and_time(time))
+        .earliest()
+        .map(|dt| dt.timestamp_millis() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruments::InstrumentSpec;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn et_ms(d: NaiveDate, h: u32, m: u32) -> u64 {
+        et_to_ms(d, NaiveTime::from_hms_opt(h, m, 0).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_trading_date_and_segments() {
+        let calendar = SessionCalendar::new(&InstrumentSpec::default().session);
+
+        // Wednesday 2025-06-11 (EDT): 18:00 ET rolls into Thursday's trading date
+        let wed = date(2025, 6, 11);
+        assert_eq!(calendar.trading_date(et_ms(wed, 17, 59)), wed);
+        assert_eq!(calendar.trading_date(et_ms(wed, 18, 0)), date(2025, 6, 12));
+
+        // RTH opens at 09:30 ET in summer (13:30 UTC) and winter (14:30 UTC)
+        assert!(calendar.is_rth(et_ms(wed, 9, 30)));
+        assert_eq!(et_ms(wed, 9, 30) % 86_400_000, 13 * 3_600_000 + 30 * 60_000);
+        assert!(!calendar.is_rth(et_ms(wed, 9, 29)));
+        assert_eq!(calendar.classify(et_ms(wed, 17, 30)).segment, Segment::Closed);
+        assert_eq!(calendar.classify(et_ms(wed, 20, 0)).segment, Segment::Eth);
+
+        // Friday evening and Sunday's open both belong to Monday
+        let sunday_open = calendar.classify(et_ms(date(2025, 6, 15), 18, 0));
+        assert_eq!(sunday_open.trading_date, date(2025, 6, 16));
+        assert_eq!(sunday_open.segment, Segment::Eth);
+        assert_eq!(calendar.classify(et_ms(date(2025, 6, 13), 17, 30)).segment, Segment::Closed);
+    }
+
+    #[test]
+    fn test_holidays_and_early_closes() {
+        let calendar = SessionCalendar::new(&InstrumentSpec::default().session);
+
+        assert_eq!(schedule(date(2025, 11, 27)), DaySchedule::Holiday("Thanksgiving Day"));
+        assert_eq!(schedule(date(2025, 11, 28)), DaySchedule::EarlyClose);
+        assert_eq!(schedule(date(2025, 4, 18)), DaySchedule::Holiday("Good Friday"));
+        assert_eq!(schedule(date(2025, 5, 26)), DaySchedule::Holiday("Memorial Day"));
+        assert_eq!(schedule(date(2026, 7, 3)), DaySchedule::Holiday("Independence Day"));
+        assert_eq!(schedule(date(2025, 12, 24)), DaySchedule::EarlyClose);
+
+        // Thanksgiving's abbreviated session rolls into Friday's trading date
+        let thanksgiving = calendar.classify(et_ms(date(2025, 11, 27), 10, 0));
+        assert_eq!(thanksgiving.trading_date, date(2025, 11, 28));
+        assert_eq!(thanksgiving.segment, Segment::Eth);
+        assert_eq!(thanksgiving.holiday, Some("Thanksgiving Day"));
+        assert_eq!(calendar.classify(et_ms(date(2025, 11, 27), 14, 0)).segment, Segment::Closed);
+
+        // Early close ends RTH at 13:15 ET
+        let friday = date(2025, 11, 28);
+        assert!(calendar.is_rth(et_ms(friday, 13, 0)));
+        assert!(!calendar.is_rth(et_ms(friday, 13, 15)));
+        assert_eq!(calendar.rth_bounds(friday), Some((et_ms(friday, 9, 30), et_ms(friday, 13, 15))));
+        assert_eq!(calendar.previous_trading_date(friday), date(2025, 11, 26));
+    }
+}
@@ -0,0 +1,169 @@
+//! Time Sources
+//!
+//! ProcessingState stamps bubbles, signals, cooldowns and outcome windows with
+//! "now". Live mode uses wall-clock time; replays and the pipeline backtester
+//! use event time (the trade timestamps) so signals are identical at any speed.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveTime, TimeZone};
+use chrono_tz::America::New_York;
+
+/// Source of the current time (Unix milliseconds) for ProcessingState
+pub trait Clock: Send + Sync {
+    /// Current time in milliseconds since the Unix epoch
+    fn now_ms(&self) -> u64;
+
+    /// Observe a trade timestamp - called for every trade added to the buffer
+    fn observe(&mut self, _event_ts: u64) {}
+}
+
+/// Wall-clock time (live and demo modes)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64
+    }
+}
+
+/// Event time - advances with the timestamps of observed trades (never goes backwards)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct EventClock {
+    now: u64,
+}
+
+impl EventClock {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Clock for EventClock {
+    fn now_ms(&self) -> u64 {
+        self.now
+    }
+
+    fn observe(&mut self, event_ts: u64) {
+        self.now = self.now.max(event_ts);
+    }
+}
+
+/// Cuts an event-time trade stream into fixed aggregation windows
+///
+/// Replays call `advance` with each trade timestamp before adding the trade;
+/// when it returns true the buffered window is complete and should be flushed.
+#[derive(Debug, Clone)]
+pub struct EventWindow {
+    size_ms: u64,
+    window_end: Option<u64>,
+}
+
+impl EventWindow {
+    pub fn new(size_ms: u64) -> Self {
+        Self {
+            size_ms: size_ms.max(1),
+            window_end: None,
+        }
+    }
+
+    /// Returns true if `event_ts` falls outside the current window (flush before adding it)
+    pub fn advance(&mut self, event_ts: u64) -> bool {
+        match self.window_end {
+            None => {
+                self.window_end = Some(event_ts + self.size_ms);
+                false
+            }
+            Some(end) if event_ts >= end => {
+                self.window_end = Some(event_ts + self.size_ms);
+                true
+            }
+            Some(_) => false,
+        }
+    }
+}
+
+/// Resolve an ET clock time ("10:15" or "10:15:30") to Unix ms on the ET date of `reference_ms`
+pub fn et_clock_time_to_ms(reference_ms: u64, time: &str) -> Result<u64> {
+    let clock_time = NaiveTime::parse_from_str(time, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(time, "%H:%M"))
+        .with_context(|| format!("Expected HH:MM or HH:MM:SS, got '{}'", time))?;
+
+    let reference = DateTime::from_timestamp_millis(reference_ms as i64)
+        .context("Reference timestamp out of range")?
+        .with_timezone(&New_York);
+
+    let target = New_York
+        .from_local_datetime(&reference.date_naive().and_time(clock_time))
+        .earliest()
+        .context("ET time skipped by DST")?;
+    Ok(target.timestamp_millis() as u64)
+}
+
+/// Most recent ET occurrence of a clock time at or before `ts_ms` (session anchors)
+pub fn et_anchor_ms(ts_ms: u64, anchor: NaiveTime) -> u64 {
+    let Some(now) = DateTime::from_timestamp_millis(ts_ms as i64) else {
+        return ts_ms;
+    };
+    let date = now.with_timezone(&New_York).date_naive();
+    let at = |date: chrono::NaiveDate| {
+        New_York
+            .from_local_datetime(&date.and_time(anchor))
+            .earliest()
+            .map(|dt| dt.timestamp_millis() as u64)
+            .unwrap_or(ts_ms)
+    };
+
+    let today = at(date);
+    if today <= ts_ms {
+        today
+    } else {
+        date.pred_opt().map(at).unwrap_or(ts_ms)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_clock_never_goes_backwards() {
+        let mut clock = EventClock::new();
+        assert_eq!(clock.now_ms(), 0);
+        clock.observe(5_000);
+        clock.observe(4_000);
+        assert_eq!(clock.now_ms(), 5_000);
+    }
+
+    #[test]
+    fn test_event_window_boundaries() {
+        let mut window = EventWindow::new(1000);
+        assert!(!window.advance(10_000)); // Opens window [10000, 11000)
+        assert!(!window.advance(10_999));
+        assert!(window.advance(11_000)); // Crossed - flush, new window [11000, 12000)
+        assert!(!window.advance(11_500));
+        assert!(window.advance(20_000));
+    }
+
+    #[test]
+    fn test_et_clock_time_to_ms() {
+        // 2025-11-27 14:30:00 UTC = 09:30 ET
+        let reference = 1_764_253_800_000;
+        assert_eq!(et_clock_time_to_ms(reference, "10:15").unwrap(), reference + 45 * 60_000);
+        assert_eq!(et_clock_time_to_ms(reference, "09:30:30").unwrap(), reference + 30_000);
+        assert!(et_clock_time_to_ms(reference, "noon").is_err());
+    }
+
+    #[test]
+    fn test_et_anchor_ms() {
+        // 2025-11-27 14:30:00 UTC = 09:30 ET
+        let rth_open = 1_764_253_800_000;
+        let anchor = NaiveTime::from_hms_opt(9, 30, 0).unwrap();
+        assert_eq!(et_anchor_ms(rth_open, anchor), rth_open);
+        assert_eq!(et_anchor_ms(rth_open + 60_000, anchor), rth_open);
+        assert_eq!(et_anchor_ms(rth_open - 60_000, anchor), rth_open - 24 * 3_600_000);
+    }
+}
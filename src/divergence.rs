@@ -0,0 +1,317 @@
+//! CVD/Price Divergence
+//!
+//! Price and CVD are sampled into short bars. A swing high (low) is a bar whose
+//! high (low) beats `swing_bars` bars on each side, so it's confirmed once those
+//! later bars close; its CVD is the CVD extreme over the same bars. Each swing
+//! is compared with the previous swing of its kind when that one is recent enough:
+//! - regular bearish: price higher high, CVD lower high
+//! - regular bullish: price lower low, CVD higher low
+//! - hidden bearish: price lower high, CVD higher high
+//! - hidden bullish: price higher low, CVD lower low
+
+use std::collections::VecDeque;
+
+/// Divergence detector settings shared by the live server and the backtester
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DivergenceSettings {
+    /// Bar length price and CVD are sampled into (seconds, at least 1)
+    pub bar_secs: u64,
+    /// Bars on each side a swing high/low has to beat (at least 1 - with none,
+    /// every bar would be both a swing high and a swing low)
+    pub swing_bars: usize,
+    /// Max bars between the two swings compared
+    pub lookback_bars: usize,
+}
+
+impl Default for DivergenceSettings {
+    fn default() -> Self {
+        Self {
+            bar_secs: 15,
+            swing_bars: 3,
+            lookback_bars: 40,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DivergenceKind {
+    Regular,
+    Hidden,
+}
+
+impl DivergenceKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Regular => "regular",
+            Self::Hidden => "hidden",
+        }
+    }
+}
+
+/// A confirmed price or CVD swing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Swing {
+    /// Start of the swing bar (Unix ms)
+    pub time: u64,
+    pub price: f64,
+    pub cvd: i64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Divergence {
+    pub kind: DivergenceKind,
+    pub direction: &'static str, // "bullish" (swing lows) or "bearish" (swing highs)
+    pub swing: Swing,
+    pub previous: Swing,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bar {
+    start: u64,
+    high: f64,
+    low: f64,
+    cvd_high: i64,
+    cvd_low: i64,
+}
+
+/// Finds price/CVD swings from trades and the divergences between them
+#[derive(Debug)]
+pub struct DivergenceDetector {
+    settings: DivergenceSettings,
+    // Open bar last, plus the 2 * swing_bars closed bars a swing is judged on
+    bars: VecDeque<Bar>,
+    last_high: Option<Swing>,
+    last_low: Option<Swing>,
+    confirmed: Vec<Divergence>,
+}
+
+impl DivergenceDetector {
+    /// A zero bar length or swing bar count is taken as 1
+    pub fn new(settings: DivergenceSettings) -> Self {
+        Self {
+            settings: DivergenceSettings {
+                bar_secs: settings.bar_secs.max(1),
+                swing_bars: settings.swing_bars.max(1),
+                ..settings
+            },
+            bars: VecDeque::new(),
+            last_high: None,
+            last_low: None,
+            confirmed: Vec::new(),
+        }
+    }
+
+    /// Add a trade with the CVD after it
+    pub fn add_trade(&mut self, price: f64, cvd: i64, timestamp: u64) {
+        let bar_ms = self.settings.bar_secs * 1000;
+        let start = timestamp - timestamp % bar_ms;
+
+        match self.bars.back_mut() {
+            Some(bar) if bar.start == start => {
+                bar.high = bar.high.max(price);
+                bar.low = bar.low.min(price);
+                bar.cvd_high = bar.cvd_high.max(cvd);
+                bar.cvd_low = bar.cvd_low.min(cvd);
+            }
+            // Late trades (out of order feeds) are dropped
+            Some(bar) if bar.start > start => {}
+            _ => {
+                if !self.bars.is_empty() {
+                    self.close_bar();
+                }
+                self.bars.push_back(Bar {
+                    start,
+                    high: price,
+                    low: price,
+                    cvd_high: cvd,
+                    cvd_low: cvd,
+                });
+            }
+        }
+    }
+
+    /// Divergences confirmed since the last call
+    pub fn take_divergences(&mut self) -> Vec<Divergence> {
+        std::mem::take(&mut self.confirmed)
+    }
+
+    /// The open bar just closed - judge the bar `swing_bars` back
+    fn close_bar(&mut self) {
+        let swing_bars = self.settings.swing_bars;
+        let window = 2 * swing_bars + 1;
+        while self.bars.len() > window {
+            self.bars.pop_front();
+        }
+        if self.bars.len() < window {
+            return;
+        }
+
+        let pivot = self.bars[swing_bars];
+        let (before, after) = (
+            self.bars.range(..swing_bars),
+            self.bars.range(swing_bars + 1..),
+        );
+        // The first of equal highs/lows is the swing
+        let is_high = before.clone().all(|bar| pivot.high > bar.high)
+            && after.clone().all(|bar| pivot.high >= bar.high);
+        let is_low = before.clone().all(|bar| pivot.low < bar.low)
+            && after.clone().all(|bar| pivot.low <= bar.low);
+
+        if is_high {
+            let swing = Swing {
+                time: pivot.start,
+                price: pivot.high,
+                cvd: self.bars.iter().map(|bar| bar.cvd_high).max().unwrap_or_default(),
+            };
+            if let Some(previous) = self.last_high.filter(|prev| self.in_lookback(prev, &swing)) {
+                let kind = if swing.price > previous.price && swing.cvd < previous.cvd {
+                    Some(DivergenceKind::Regular)
+                } else if swing.price < previous.price && swing.cvd > previous.cvd {
+                    Some(DivergenceKind::Hidden)
+                } else {
+                    None
+                };
+                self.confirmed.extend(kind.map(|kind| Divergence {
+                    kind,
+                    direction: "bearish",
+                    swing,
+                    previous,
+                }));
+            }
+            self.last_high = Some(swing);
+        }
+
+        if is_low {
+            let swing = Swing {
+                time: pivot.start,
+                price: pivot.low,
+                cvd: self.bars.iter().map(|bar| bar.cvd_low).min().unwrap_or_default(),
+            };
+            if let Some(previous) = self.last_low.filter(|prev| self.in_lookback(prev, &swing)) {
+                let kind = if swing.price < previous.price && swing.cvd > previous.cvd {
+                    Some(DivergenceKind::Regular)
+                } else if swing.price > previous.price && swing.cvd < previous.cvd {
+                    Some(DivergenceKind::Hidden)
+                } else {
+                    None
+                };
+                self.confirmed.extend(kind.map(|kind| Divergence {
+                    kind,
+                    direction: "bullish",
+                    swing,
+                    previous,
+                }));
+            }
+            self.last_low = Some(swing);
+        }
+    }
+
+    fn in_lookback(&self, previous: &Swing, swing: &Swing) -> bool {
+        let bar_ms = self.settings.bar_secs * 1000;
+        swing.time - previous.time <= self.settings.lookback_bars as u64 * bar_ms
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BAR_MS: u64 = 15_000;
+
+    /// One trade per bar at `(price, cvd)`, then a trade that closes the last bar
+    fn detect(bars: &[(f64, i64)]) -> Vec<Divergence> {
+        let mut detector = DivergenceDetector::new(DivergenceSettings {
+            swing_bars: 2,
+            ..Default::default()
+        });
+        for (i, &(price, cvd)) in bars.iter().enumerate() {
+            detector.add_trade(price, cvd, i as u64 * BAR_MS);
+        }
+        let (price, cvd) = bars[bars.len() - 1];
+        detector.add_trade(price, cvd, bars.len() as u64 * BAR_MS);
+        detector.take_divergences()
+    }
+
+    #[test]
+    fn test_regular_bearish_on_higher_high_with_lower_cvd() {
+        let divergences = detect(&[
+            (100.0, 0),
+            (101.0, 50),
+            (103.0, 200), // swing high
+            (101.0, 100),
+            (100.0, 80),
+            (102.0, 120),
+            (104.0, 150), // higher high, lower CVD
+            (102.0, 90),
+            (101.0, 60),
+        ]);
+
+        assert_eq!(
+            divergences,
+            vec![Divergence {
+                kind: DivergenceKind::Regular,
+                direction: "bearish",
+                swing: Swing { time: 6 * BAR_MS, price: 104.0, cvd: 150 },
+                previous: Swing { time: 2 * BAR_MS, price: 103.0, cvd: 200 },
+            }]
+        );
+    }
+
+    #[test]
+    fn test_hidden_bullish_on_higher_low_with_lower_cvd() {
+        let divergences = detect(&[
+            (105.0, 0),
+            (104.0, -50),
+            (100.0, -100), // swing low
+            (103.0, -60),
+            (104.0, -40),
+            (102.0, -90),
+            (101.0, -150), // higher low, lower CVD
+            (103.0, -120),
+            (104.0, -100),
+        ]);
+
+        assert_eq!(divergences.len(), 1);
+        assert_eq!(divergences[0].kind, DivergenceKind::Hidden);
+        assert_eq!(divergences[0].direction, "bullish");
+        assert_eq!(divergences[0].swing.cvd, -150);
+    }
+
+    #[test]
+    fn test_no_divergence_when_cvd_confirms() {
+        let divergences = detect(&[
+            (100.0, 0),
+            (101.0, 50),
+            (103.0, 200),
+            (101.0, 100),
+            (100.0, 80),
+            (102.0, 220),
+            (104.0, 300), // higher high, higher CVD
+            (102.0, 250),
+            (101.0, 240),
+        ]);
+
+        assert!(divergences.is_empty());
+    }
+
+    #[test]
+    fn test_zero_bar_length_and_swing_bars_clamp_to_one() {
+        let mut detector = DivergenceDetector::new(DivergenceSettings {
+            bar_secs: 0,
+            swing_bars: 0,
+            lookback_bars: 40,
+        });
+        assert_eq!((detector.settings.bar_secs, detector.settings.swing_bars), (1, 1));
+
+        // 1s bars, swings judged on one bar each side
+        let bars = [(100.0, 0), (103.0, 200), (101.0, 100), (104.0, 150), (102.0, 90), (102.0, 90)];
+        for (i, &(price, cvd)) in bars.iter().enumerate() {
+            detector.add_trade(price, cvd, i as u64 * 1000);
+        }
+
+        let divergences = detector.take_divergences();
+        assert_eq!(divergences.len(), 1);
+        assert_eq!((divergences[0].kind, divergences[0].direction), (DivergenceKind::Regular, "bearish"));
+        assert_eq!(divergences[0].swing.time, 3000);
+    }
+}
@@ -0,0 +1,356 @@
+//! Footprint Bars
+//!
+//! Per-bar bid x ask: OHLC plus buy/sell volume at every tick, delta, POC and
+//! diagonal imbalances (buyers at a price against sellers one tick below, and
+//! sellers against buyers one tick above). Bars close on time (`1m`, `30s`),
+//! trade count (`500t`) or price range in ticks (`10r`). The live
+//! ProcessingState streams them as `Footprint` messages and the pipeline
+//! exports them to Parquet.
+
+use anyhow::Context;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::instruments::{InstrumentSpec, ProfileSettings};
+use crate::types::{Footprint, FootprintLevel};
+use crate::volume_profile::VolumeProfile;
+
+/// When a footprint bar closes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FootprintTimeframe {
+    /// Fixed time buckets (ms), aligned to the epoch
+    Time(u64),
+    /// Every N trades
+    Ticks(u32),
+    /// When the bar's high-low range would exceed N ticks
+    Range(u32),
+}
+
+impl Default for FootprintTimeframe {
+    fn default() -> Self {
+        Self::Time(60_000)
+    }
+}
+
+impl FromStr for FootprintTimeframe {
+    type Err = anyhow::Error;
+
+    /// "30s", "1m", "5m", "1h", "500t" (trades) or "10r" (range in ticks)
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim().to_lowercase();
+        let split = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+        let (count, unit) = s.split_at(split);
+        let count: u64 = count
+            .parse()
+            .with_context(|| format!("Expected a timeframe like 1m, 500t or 10r, got '{}'", s))?;
+        if count == 0 {
+            anyhow::bail!("Footprint timeframe must be positive: '{}'", s);
+        }
+
+        match unit {
+            "s" => Ok(Self::Time(count * 1000)),
+            "m" => Ok(Self::Time(count * 60_000)),
+            "h" => Ok(Self::Time(count * 3_600_000)),
+            "t" => Ok(Self::Ticks(count as u32)),
+            "r" => Ok(Self::Range(count as u32)),
+            _ => anyhow::bail!("Unknown footprint timeframe unit '{}' (use s, m, h, t or r)", unit),
+        }
+    }
+}
+
+impl fmt::Display for FootprintTimeframe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Time(ms) if ms % 3_600_000 == 0 => write!(f, "{}h", ms / 3_600_000),
+            Self::Time(ms) if ms % 60_000 == 0 => write!(f, "{}m", ms / 60_000),
+            Self::Time(ms) => write!(f, "{}s", ms / 1000),
+            Self::Ticks(count) => write!(f, "{}t", count),
+            Self::Range(ticks) => write!(f, "{}r", ticks),
+        }
+    }
+}
+
+/// Footprint bar settings shared by the live server and the pipeline
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FootprintSettings {
+    pub timeframe: FootprintTimeframe,
+    /// Diagonal volume ratio that counts as an imbalance (3.0 = 300%)
+    pub imbalance_ratio: f64,
+}
+
+impl Default for FootprintSettings {
+    fn default() -> Self {
+        Self {
+            timeframe: FootprintTimeframe::default(),
+            imbalance_ratio: 3.0,
+        }
+    }
+}
+
+/// One footprint bar being built
+#[derive(Debug, Clone)]
+pub struct FootprintBar {
+    /// Unix ms - bucket start for time bars, first trade otherwise
+    pub start_time: u64,
+    /// Unix ms of the last trade
+    pub end_time: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub trade_count: u64,
+    /// Buy/sell volume per tick
+    pub profile: VolumeProfile,
+}
+
+impl FootprintBar {
+    pub fn volume(&self) -> u64 {
+        self.profile.total_volume()
+    }
+
+    pub fn delta(&self) -> i64 {
+        self.profile
+            .levels()
+            .map(|(_, v)| v.buy_volume as i64 - v.sell_volume as i64)
+            .sum()
+    }
+
+    /// Levels low to high with diagonal imbalances at `ratio`
+    ///
+    /// Buyers at a price are compared with sellers one tick below, sellers with
+    /// buyers one tick above. An empty tick inside the bar counts as one contract;
+    /// the bar's extremes have nothing to compare with. The imbalanced side needs
+    /// at least `min_volume`.
+    pub fn levels(&self, ratio: f64, min_volume: u64) -> Vec<FootprintLevel> {
+        let low_key = self.profile.price_key(self.low);
+        let high_key = self.profile.price_key(self.high);
+        let volume_at = |key: i64| {
+            self.profile
+                .get(self.profile.key_price(key))
+                .map(|v| (v.buy_volume, v.sell_volume))
+                .unwrap_or((0, 0))
+        };
+
+        self.profile
+            .levels()
+            .map(|(price, volume)| {
+                let key = self.profile.price_key(price);
+                let buy_imbalance = key > low_key
+                    && volume.buy_volume >= min_volume.max(1)
+                    && volume.buy_volume as f64 >= ratio * volume_at(key - 1).1.max(1) as f64;
+                let sell_imbalance = key < high_key
+                    && volume.sell_volume >= min_volume.max(1)
+                    && volume.sell_volume as f64 >= ratio * volume_at(key + 1).0.max(1) as f64;
+
+                FootprintLevel {
+                    price,
+                    buy_volume: volume.buy_volume,
+                    sell_volume: volume.sell_volume,
+                    delta: volume.buy_volume as i64 - volume.sell_volume as i64,
+                    buy_imbalance,
+                    sell_imbalance,
+                }
+            })
+            .collect()
+    }
+
+    /// Wire/export form of the bar
+    pub fn to_footprint(&self, symbol: &str, settings: &FootprintSettings, complete: bool) -> Footprint {
+        Footprint {
+            symbol: symbol.to_string(),
+            timestamp: self.start_time,
+            end_time: self.end_time,
+            timeframe: settings.timeframe.to_string(),
+            open: self.open,
+            high: self.high,
+            low: self.low,
+            close: self.close,
+            volume: self.volume(),
+            delta: self.delta(),
+            poc: self.profile.poc().unwrap_or(self.close),
+            trade_count: self.trade_count,
+            levels: self.levels(settings.imbalance_ratio, 1),
+            complete,
+        }
+    }
+}
+
+/// Cuts trades into footprint bars
+#[derive(Debug, Clone)]
+pub struct FootprintBuilder {
+    settings: FootprintSettings,
+    // One-tick buckets whatever the volume profile's bucket size
+    profile_settings: ProfileSettings,
+    tick_size: f64,
+    current: Option<FootprintBar>,
+    completed: Vec<FootprintBar>,
+    updated: bool,
+}
+
+impl FootprintBuilder {
+    pub fn new(settings: FootprintSettings, instrument: &InstrumentSpec) -> Self {
+        Self {
+            settings,
+            profile_settings: ProfileSettings {
+                bucket_points: instrument.tick_size,
+                ..instrument.profile.clone()
+            },
+            tick_size: instrument.tick_size,
+            current: None,
+            completed: Vec::new(),
+            updated: false,
+        }
+    }
+
+    pub fn settings(&self) -> &FootprintSettings {
+        &self.settings
+    }
+
+    /// Add a trade, closing the current bar first if the trade falls outside it
+    pub fn add(&mut self, price: f64, size: u64, is_buy: bool, timestamp: u64) {
+        if let Some(bar) = &self.current {
+            if self.closes_before(bar, price, timestamp) {
+                self.completed.extend(self.current.take());
+            }
+        }
+
+        let start_time = match self.settings.timeframe {
+            FootprintTimeframe::Time(ms) => timestamp - timestamp % ms,
+            _ => timestamp,
+        };
+        let bar = self.current.get_or_insert_with(|| FootprintBar {
+            start_time,
+            end_time: timestamp,
+            open: price,
+            high: price,
+            low: price,
+            close: price,
+            trade_count: 0,
+            profile: VolumeProfile::new(&self.profile_settings),
+        });
+        bar.end_time = timestamp;
+        bar.high = bar.high.max(price);
+        bar.low = bar.low.min(price);
+        bar.close = price;
+        bar.trade_count += 1;
+        bar.profile.add(price, size, is_buy, timestamp);
+        self.updated = true;
+
+        if let FootprintTimeframe::Ticks(count) = self.settings.timeframe {
+            if bar.trade_count >= count as u64 {
+                self.completed.extend(self.current.take());
+            }
+        }
+    }
+
+    fn closes_before(&self, bar: &FootprintBar, price: f64, timestamp: u64) -> bool {
+        match self.settings.timeframe {
+            FootprintTimeframe::Time(ms) => timestamp >= bar.start_time + ms,
+            FootprintTimeframe::Ticks(_) => false,
+            FootprintTimeframe::Range(ticks) => {
+                let range = bar.high.max(price) - bar.low.min(price);
+                range > ticks as f64 * self.tick_size + 1e-9
+            }
+        }
+    }
+
+    /// Closed bars not yet taken, then the open bar
+    pub fn recent(&self) -> impl Iterator<Item = &FootprintBar> + '_ {
+        self.completed.iter().chain(self.current.as_ref())
+    }
+
+    /// Bars closed since the last call
+    pub fn take_completed(&mut self) -> Vec<FootprintBar> {
+        std::mem::take(&mut self.completed)
+    }
+
+    /// The open bar, if it changed since the last call
+    pub fn take_updated(&mut self) -> Option<&FootprintBar> {
+        if std::mem::take(&mut self.updated) {
+            self.current.as_ref()
+        } else {
+            None
+        }
+    }
+
+    /// Close the open bar (end of data) and return every unsent closed bar
+    pub fn finish(&mut self) -> Vec<FootprintBar> {
+        self.completed.extend(self.current.take());
+        self.updated = false;
+        self.take_completed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn builder(timeframe: &str) -> FootprintBuilder {
+        let settings = FootprintSettings {
+            timeframe: timeframe.parse().unwrap(),
+            imbalance_ratio: 3.0,
+        };
+        FootprintBuilder::new(settings, &InstrumentSpec::default())
+    }
+
+    #[test]
+    fn test_parse_timeframes() {
+        assert_eq!("1m".parse::<FootprintTimeframe>().unwrap(), FootprintTimeframe::Time(60_000));
+        assert_eq!("30s".parse::<FootprintTimeframe>().unwrap(), FootprintTimeframe::Time(30_000));
+        assert_eq!("500t".parse::<FootprintTimeframe>().unwrap(), FootprintTimeframe::Ticks(500));
+        assert_eq!("10R".parse::<FootprintTimeframe>().unwrap(), FootprintTimeframe::Range(10));
+        assert!("0m".parse::<FootprintTimeframe>().is_err());
+        assert!("5x".parse::<FootprintTimeframe>().is_err());
+        assert_eq!(FootprintTimeframe::Time(300_000).to_string(), "5m");
+    }
+
+    #[test]
+    fn test_time_bars_and_diagonal_imbalances() {
+        let mut fp = builder("1m");
+        fp.add(100.0, 5, false, 60_000);
+        fp.add(100.25, 20, true, 61_000); // 20 vs 5 sold one tick below
+        fp.add(100.25, 2, false, 62_000);
+        fp.add(100.5, 4, true, 63_000); // 4 vs 2 - not 3x
+        assert!(fp.take_completed().is_empty());
+        assert_eq!(fp.take_updated().unwrap().trade_count, 4);
+        assert!(fp.take_updated().is_none());
+
+        fp.add(101.0, 1, true, 120_000);
+        let bars = fp.take_completed();
+        assert_eq!(bars.len(), 1);
+        let bar = &bars[0];
+        assert_eq!((bar.start_time, bar.end_time), (60_000, 63_000));
+        assert_eq!((bar.open, bar.high, bar.low, bar.close), (100.0, 100.5, 100.0, 100.5));
+        assert_eq!(bar.delta(), 17);
+
+        let footprint = bar.to_footprint("NQH6", fp.settings(), true);
+        assert_eq!(footprint.poc, 100.25);
+        let imbalances: Vec<(f64, bool, bool)> = footprint
+            .levels
+            .iter()
+            .map(|l| (l.price, l.buy_imbalance, l.sell_imbalance))
+            .collect();
+        assert_eq!(
+            imbalances,
+            vec![(100.0, false, false), (100.25, true, false), (100.5, false, false)]
+        );
+
+        assert_eq!(fp.finish()[0].start_time, 120_000);
+    }
+
+    #[test]
+    fn test_range_and_tick_bars() {
+        let mut fp = builder("4r");
+        for (i, price) in [100.0, 100.5, 101.0, 101.25].into_iter().enumerate() {
+            fp.add(price, 1, true, i as u64);
+        }
+        assert_eq!(fp.take_completed().len(), 1); // 101.25 would make a 5-tick range
+
+        let mut fp = builder("2t");
+        for i in 0..5 {
+            fp.add(100.0, 1, true, i);
+        }
+        assert_eq!(fp.take_completed().len(), 2);
+        assert_eq!(fp.finish().len(), 1);
+    }
+}
@@ -0,0 +1,165 @@
+//! Stacked Imbalances
+//!
+//! Two detectors, selected with `--stacked-imbalance`:
+//! - session: buy vs sell at the same 1-point bucket of the whole session
+//!   profile, 70% dominance over 4+ buckets with a 30 second cooldown (the original)
+//! - diagonal: the footprint definition - within one footprint bar, buyers at a
+//!   tick against sellers one tick below (sellers against buyers one tick above)
+//!   at a ratio, stacked over consecutive ticks
+//!
+//! Both emit the same `StackedImbalance` signal, so the backtester can compare them.
+
+use clap::ValueEnum;
+
+use crate::footprint::FootprintBar;
+
+/// Which stacked imbalance detector runs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum StackedImbalanceAlgorithm {
+    /// Same-price buy vs sell over the session profile
+    #[default]
+    Session,
+    /// Diagonal imbalances stacked within one footprint bar
+    Diagonal,
+}
+
+impl StackedImbalanceAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Session => "session",
+            Self::Diagonal => "diagonal",
+        }
+    }
+}
+
+/// Stacked imbalance detector settings shared by the live server and the backtester
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StackedImbalanceSettings {
+    pub algorithm: StackedImbalanceAlgorithm,
+    /// Diagonal volume ratio (3.0 = 300%) - diagonal only
+    pub ratio: f64,
+    /// Min volume on the imbalanced side of a tick - diagonal only
+    pub min_volume: u64,
+    /// Consecutive imbalanced ticks that make a stack - diagonal only
+    pub min_levels: u32,
+}
+
+impl Default for StackedImbalanceSettings {
+    fn default() -> Self {
+        Self {
+            algorithm: StackedImbalanceAlgorithm::default(),
+            ratio: 3.0,
+            min_volume: 10,
+            min_levels: 3,
+        }
+    }
+}
+
+/// A run of consecutive diagonal imbalances on one side of a bar
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagonalStack {
+    pub side: &'static str, // "buy" or "sell"
+    pub price_low: f64,
+    pub price_high: f64,
+    pub level_count: u32,
+    /// Sum of |buy - sell| over the stacked ticks
+    pub total_imbalance: i64,
+}
+
+/// Longest stack of at least `min_levels` per side in a bar (buy first)
+pub fn diagonal_stacks(bar: &FootprintBar, settings: &StackedImbalanceSettings) -> Vec<DiagonalStack> {
+    let levels = bar.levels(settings.ratio, settings.min_volume);
+    let tick = bar.profile.settings().bucket_points;
+
+    let mut stacks = Vec::new();
+    for side in ["buy", "sell"] {
+        let mut best: Option<DiagonalStack> = None;
+        let mut run: Option<DiagonalStack> = None;
+        let mut last_price = f64::NAN;
+
+        for level in &levels {
+            let imbalanced = if side == "buy" { level.buy_imbalance } else { level.sell_imbalance };
+            // Ticks with no volume break a stack too
+            let adjacent = (level.price - last_price - tick).abs() < 1e-9;
+            last_price = level.price;
+
+            if !imbalanced {
+                run = None;
+                continue;
+            }
+            match run.as_mut().filter(|_| adjacent) {
+                Some(stack) => {
+                    stack.price_high = level.price;
+                    stack.level_count += 1;
+                    stack.total_imbalance += level.delta.abs();
+                }
+                None => {
+                    run = Some(DiagonalStack {
+                        side,
+                        price_low: level.price,
+                        price_high: level.price,
+                        level_count: 1,
+                        total_imbalance: level.delta.abs(),
+                    });
+                }
+            }
+            if let Some(stack) = &run {
+                if best.as_ref().is_none_or(|best| stack.level_count > best.level_count) {
+                    best = Some(stack.clone());
+                }
+            }
+        }
+
+        stacks.extend(best.filter(|stack| stack.level_count >= settings.min_levels));
+    }
+    stacks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::footprint::{FootprintBuilder, FootprintSettings};
+    use crate::instruments::InstrumentSpec;
+
+    fn bar(trades: &[(f64, u64, bool)]) -> FootprintBar {
+        let mut builder = FootprintBuilder::new(FootprintSettings::default(), &InstrumentSpec::default());
+        for &(price, size, is_buy) in trades {
+            builder.add(price, size, is_buy, 1_000);
+        }
+        builder.finish().remove(0)
+    }
+
+    #[test]
+    fn test_diagonal_buy_stack() {
+        // Buyers lift 100.25-100.75 at 4x the sellers one tick below
+        let bar = bar(&[
+            (100.0, 5, false),
+            (100.25, 20, true),
+            (100.25, 5, false),
+            (100.5, 20, true),
+            (100.5, 5, false),
+            (100.75, 20, true),
+            (101.0, 1, true),
+        ]);
+        let settings = StackedImbalanceSettings {
+            algorithm: StackedImbalanceAlgorithm::Diagonal,
+            ..Default::default()
+        };
+
+        let stacks = diagonal_stacks(&bar, &settings);
+        assert_eq!(
+            stacks,
+            vec![DiagonalStack {
+                side: "buy",
+                price_low: 100.25,
+                price_high: 100.75,
+                level_count: 3,
+                total_imbalance: 15 + 15 + 20,
+            }]
+        );
+
+        // Every stacked tick needs the min volume on the imbalanced side
+        let settings = StackedImbalanceSettings { min_volume: 25, ..settings };
+        assert!(diagonal_stacks(&bar, &settings).is_empty());
+    }
+}
@@ -0,0 +1,489 @@
+//! Impulse Legs
+//!
+//! Fast, directional moves of 3-5 one-minute candles, scored on five questions:
+//! did it break the prior swing, was it fast, were the candles uniform, did
+//! volume increase, and was it big enough (`min_impulse_points`). Legs scoring
+//! 4+ are kept.
+//!
+//! `ImpulseDetector` confirms legs bar by bar as 1m bars close - fed whole days
+//! of bars by the pipeline, or by `ImpulseTracker`, which builds the bars from
+//! live trades and profiles each confirmed leg for its LVNs.
+
+use std::collections::VecDeque;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::instruments::{InstrumentSpec, ProfileSettings};
+use crate::lvn::{leg_lvns, LvnLevel};
+use crate::volume_profile::VolumeProfile;
+
+/// Maximum candles for a "fast" move
+const MAX_FAST_CANDLES: usize = 5;
+
+/// Minimum score for valid impulse (out of 5)
+const MIN_IMPULSE_SCORE: u8 = 4;
+
+/// Swing lookback period (bars)
+const SWING_LOOKBACK: usize = 10;
+
+/// Bar length the legs are measured in
+const BAR_MS: u64 = 60 * 1000;
+
+/// Direction of impulse move
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ImpulseDirection {
+    Up,
+    Down,
+}
+
+/// Detected impulse leg with scoring details
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpulseLeg {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub start_price: f64,
+    pub end_price: f64,
+    pub direction: ImpulseDirection,
+    pub symbol: String,
+    pub date: NaiveDate,
+
+    // Scoring breakdown (each 0 or 1)
+    pub score_total: u8,
+    pub broke_swing: bool,        // Did it break prior swing high/low?
+    pub was_fast: bool,           // 3-5 candles max
+    pub uniform_candles: bool,    // Mostly one color, little overlap
+    pub volume_increased: bool,   // Volume increased on move
+    pub sufficient_size: bool,    // Move >= min_impulse_points (30 for NQ)
+
+    // Additional metrics
+    pub num_candles: usize,
+    pub total_volume: u64,
+    pub avg_volume_per_bar: u64,
+}
+
+impl ImpulseLeg {
+    /// Close of the leg's last 1m bar - when the leg is known
+    pub fn close_time(&self) -> DateTime<Utc> {
+        self.end_time + chrono::Duration::milliseconds(BAR_MS as i64)
+    }
+}
+
+/// One-minute candle (timestamp = bar open)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candle {
+    pub timestamp: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: u64,
+}
+
+impl Candle {
+    fn is_bullish(&self) -> bool {
+        self.close > self.open
+    }
+}
+
+/// Detect impulse legs over a whole run of 1-minute candles
+pub fn detect_impulse_legs(
+    candles: &[Candle],
+    symbol: &str,
+    instrument: &InstrumentSpec,
+) -> Vec<ImpulseLeg> {
+    if candles.len() < SWING_LOOKBACK + MAX_FAST_CANDLES {
+        return Vec::new();
+    }
+
+    let mut detector = ImpulseDetector::new(symbol, instrument);
+    let mut impulse_legs: Vec<ImpulseLeg> = candles
+        .iter()
+        .flat_map(|&candle| detector.add_candle(candle))
+        .collect();
+    impulse_legs.extend(detector.finish());
+    impulse_legs
+}
+
+/// Incremental impulse leg detector
+///
+/// Each bar from the 11th on is a possible leg start. A start is decided as soon
+/// as one of its 3-5 candle moves is big enough (scored, and kept at 4+) or all
+/// five candles are in without one - the same scan as over a whole day of bars.
+#[derive(Debug, Clone)]
+pub struct ImpulseDetector {
+    symbol: String,
+    min_impulse_points: f64,
+    // Swing lookback before the next start, through the newest candle
+    candles: VecDeque<Candle>,
+    // Index of candles[0] since the first candle
+    first_index: usize,
+    // Next candle index to try as a leg start
+    next_start: usize,
+}
+
+impl ImpulseDetector {
+    pub fn new(symbol: impl Into<String>, instrument: &InstrumentSpec) -> Self {
+        Self {
+            symbol: symbol.into(),
+            min_impulse_points: instrument.thresholds.min_impulse_points,
+            candles: VecDeque::new(),
+            first_index: 0,
+            next_start: SWING_LOOKBACK,
+        }
+    }
+
+    /// Open time of the oldest candle a future leg can start at
+    pub fn pending_start(&self) -> Option<DateTime<Utc>> {
+        self.candles
+            .get(self.next_start.saturating_sub(self.first_index))
+            .map(|candle| candle.timestamp)
+    }
+
+    /// A 1m candle closed - returns the legs it confirmed
+    pub fn add_candle(&mut self, candle: Candle) -> Vec<ImpulseLeg> {
+        self.candles.push_back(candle);
+        let legs = self.scan(false);
+
+        // Keep the swing lookback for the next start
+        while self.first_index + SWING_LOOKBACK < self.next_start && !self.candles.is_empty() {
+            self.candles.pop_front();
+            self.first_index += 1;
+        }
+        legs
+    }
+
+    /// No more candles - decide the remaining starts on the candles there are
+    pub fn finish(&mut self) -> Vec<ImpulseLeg> {
+        self.scan(true)
+    }
+
+    fn scan(&mut self, finished: bool) -> Vec<ImpulseLeg> {
+        let mut legs = Vec::new();
+        let end = self.first_index + self.candles.len();
+
+        while self.next_start < end {
+            let start = self.next_start;
+            match self.try_detect_at(start - self.first_index) {
+                Some(leg) if leg.score_total >= MIN_IMPULSE_SCORE => {
+                    self.next_start = start + leg.num_candles; // Skip past this impulse
+                    legs.push(leg);
+                }
+                Some(_) => self.next_start = start + 1,
+                None if finished || end - start >= MAX_FAST_CANDLES => self.next_start = start + 1,
+                // Not enough candles yet to rule this start out
+                None => break,
+            }
+        }
+        legs
+    }
+
+    fn try_detect_at(&self, start_idx: usize) -> Option<ImpulseLeg> {
+        let bars: Vec<&Candle> = self.candles.iter().collect();
+        let start_bar = bars[start_idx];
+
+        // Look for moves of 3-5 candles
+        for num_candles in 3..=MAX_FAST_CANDLES.min(bars.len() - start_idx) {
+            let end_idx = start_idx + num_candles - 1;
+            let end_bar = bars[end_idx];
+            let move_bars = &bars[start_idx..=end_idx];
+
+            // Calculate price move
+            let price_change = end_bar.close - start_bar.open;
+            let direction = if price_change > 0.0 {
+                ImpulseDirection::Up
+            } else {
+                ImpulseDirection::Down
+            };
+
+            let move_size = price_change.abs();
+
+            // Skip if move is too small
+            if move_size < self.min_impulse_points {
+                continue;
+            }
+
+            // Score the move
+            let sufficient_size = move_size >= self.min_impulse_points;
+
+            let was_fast = num_candles <= MAX_FAST_CANDLES;
+
+            let prior_bars = &bars[start_idx - SWING_LOOKBACK..start_idx];
+            let broke_swing = check_broke_swing(direction, end_bar.close, prior_bars);
+
+            let uniform_candles = check_uniform_candles(move_bars, direction);
+
+            let volume_increased = check_volume_increase(move_bars, prior_bars);
+
+            let score_total = [
+                broke_swing,
+                was_fast,
+                uniform_candles,
+                volume_increased,
+                sufficient_size,
+            ]
+            .iter()
+            .filter(|&&x| x)
+            .count() as u8;
+
+            let total_volume: u64 = move_bars.iter().map(|b| b.volume).sum();
+
+            return Some(ImpulseLeg {
+                start_time: start_bar.timestamp,
+                end_time: end_bar.timestamp,
+                start_price: start_bar.open,
+                end_price: end_bar.close,
+                direction,
+                symbol: self.symbol.clone(),
+                date: start_bar.timestamp.date_naive(),
+                score_total,
+                broke_swing,
+                was_fast,
+                uniform_candles,
+                volume_increased,
+                sufficient_size,
+                num_candles,
+                total_volume,
+                avg_volume_per_bar: total_volume / num_candles as u64,
+            });
+        }
+
+        None
+    }
+}
+
+/// Did the move break above the prior swing high (below the swing low)?
+fn check_broke_swing(direction: ImpulseDirection, end_price: f64, prior_bars: &[&Candle]) -> bool {
+    match direction {
+        ImpulseDirection::Up => {
+            let swing_high = prior_bars.iter().map(|b| b.high).fold(f64::MIN, f64::max);
+            end_price > swing_high
+        }
+        ImpulseDirection::Down => {
+            let swing_low = prior_bars.iter().map(|b| b.low).fold(f64::MAX, f64::min);
+            end_price < swing_low
+        }
+    }
+}
+
+fn check_uniform_candles(bars: &[&Candle], direction: ImpulseDirection) -> bool {
+    if bars.is_empty() {
+        return false;
+    }
+
+    // Count candles matching the direction
+    let matching_candles = bars
+        .iter()
+        .filter(|b| match direction {
+            ImpulseDirection::Up => b.is_bullish(),
+            ImpulseDirection::Down => !b.is_bullish(),
+        })
+        .count();
+
+    // At least 70% of candles should match direction
+    let match_ratio = matching_candles as f64 / bars.len() as f64;
+    if match_ratio < 0.7 {
+        return false;
+    }
+
+    // Check for minimal overlap (bodies don't overlap much)
+    let mut overlap_count = 0;
+    for i in 1..bars.len() {
+        let prev = bars[i - 1];
+        let curr = bars[i];
+
+        let prev_body_low = prev.open.min(prev.close);
+        let prev_body_high = prev.open.max(prev.close);
+        let curr_body_low = curr.open.min(curr.close);
+        let curr_body_high = curr.open.max(curr.close);
+
+        // Check if current body overlaps with previous body
+        let overlaps = curr_body_low < prev_body_high && curr_body_high > prev_body_low;
+        if overlaps {
+            overlap_count += 1;
+        }
+    }
+
+    // Less than 50% overlap is acceptable
+    let overlap_ratio = overlap_count as f64 / (bars.len() - 1).max(1) as f64;
+    overlap_ratio < 0.5
+}
+
+fn check_volume_increase(move_bars: &[&Candle], prior_bars: &[&Candle]) -> bool {
+    // Average volume of the impulse move
+    let move_avg_volume: f64 = move_bars.iter().map(|b| b.volume as f64).sum::<f64>()
+        / move_bars.len() as f64;
+
+    // Average volume of prior bars
+    let prior_avg_volume: f64 = prior_bars.iter().map(|b| b.volume as f64).sum::<f64>()
+        / prior_bars.len() as f64;
+
+    // Volume should be at least 20% higher
+    move_avg_volume > prior_avg_volume * 1.2
+}
+
+/// Live impulse legs and their LVNs from a stream of trades
+///
+/// Trades are cut into 1m candles (a candle closes when the first trade of the
+/// next minute arrives) and kept back to the oldest possible leg start, so each
+/// confirmed leg can be profiled over its own trades.
+#[derive(Debug, Clone)]
+pub struct ImpulseTracker {
+    detector: ImpulseDetector,
+    profile: ProfileSettings,
+    candle: Option<Candle>,
+    // (timestamp, price, size, is_buy)
+    trades: VecDeque<(u64, f64, u64, bool)>,
+    confirmed: Vec<(ImpulseLeg, Vec<LvnLevel>)>,
+}
+
+impl ImpulseTracker {
+    pub fn new(instrument: &InstrumentSpec) -> Self {
+        Self {
+            detector: ImpulseDetector::new(String::new(), instrument),
+            profile: instrument.profile.clone(),
+            candle: None,
+            trades: VecDeque::new(),
+            confirmed: Vec::new(),
+        }
+    }
+
+    pub fn add_trade(&mut self, price: f64, size: u64, is_buy: bool, timestamp: u64) {
+        let minute = timestamp - timestamp % BAR_MS;
+        let open_time = DateTime::from_timestamp_millis(minute as i64).unwrap_or_default();
+
+        match &mut self.candle {
+            Some(candle) if candle.timestamp == open_time => {
+                candle.high = candle.high.max(price);
+                candle.low = candle.low.min(price);
+                candle.close = price;
+                candle.volume += size;
+            }
+            _ => {
+                if let Some(candle) = self.candle.take() {
+                    self.close_candle(candle);
+                }
+                self.candle = Some(Candle {
+                    timestamp: open_time,
+                    open: price,
+                    high: price,
+                    low: price,
+                    close: price,
+                    volume: size,
+                });
+            }
+        }
+
+        self.trades.push_back((timestamp, price, size, is_buy));
+    }
+
+    /// Legs confirmed since the last call, each with its LVNs
+    pub fn take_confirmed(&mut self) -> Vec<(ImpulseLeg, Vec<LvnLevel>)> {
+        std::mem::take(&mut self.confirmed)
+    }
+
+    fn close_candle(&mut self, candle: Candle) {
+        for leg in self.detector.add_candle(candle) {
+            // Same window as the pipeline: trades from the first candle's open to the last candle's open
+            let start = leg.start_time.timestamp_millis() as u64;
+            let end = leg.end_time.timestamp_millis() as u64;
+            let mut profile = VolumeProfile::new(&self.profile);
+            for &(timestamp, price, size, is_buy) in &self.trades {
+                if (start..=end).contains(&timestamp) {
+                    profile.add(price, size, is_buy, timestamp);
+                }
+            }
+            let lvns = leg_lvns(&profile, &leg);
+            self.confirmed.push((leg, lvns));
+        }
+
+        // Trades before the oldest possible leg start can't be profiled again
+        let keep_from = self
+            .detector
+            .pending_start()
+            .unwrap_or(candle.timestamp + chrono::Duration::milliseconds(BAR_MS as i64))
+            .timestamp_millis() as u64;
+        while self.trades.front().is_some_and(|&(timestamp, ..)| timestamp < keep_from) {
+            self.trades.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START_MS: u64 = 1_764_687_600_000; // 2025-12-02 15:00 UTC
+
+    fn candle(minute: u64, open: f64, close: f64, volume: u64) -> Candle {
+        Candle {
+            timestamp: DateTime::from_timestamp_millis((START_MS + minute * BAR_MS) as i64).unwrap(),
+            open,
+            high: open.max(close) + 0.25,
+            low: open.min(close) - 0.25,
+            close,
+            volume,
+        }
+    }
+
+    /// Ten quiet minutes around 21500, then three 12-point bull candles on double volume
+    fn candles() -> Vec<Candle> {
+        let mut candles: Vec<Candle> = (0..10)
+            .map(|m| candle(m, 21_500.0, if m % 2 == 0 { 21_501.0 } else { 21_500.0 }, 100))
+            .collect();
+        candles.push(candle(10, 21_500.0, 21_512.0, 200));
+        candles.push(candle(11, 21_512.0, 21_524.0, 200));
+        candles.push(candle(12, 21_524.0, 21_536.0, 200));
+        candles.push(candle(13, 21_536.0, 21_535.0, 100));
+        candles.push(candle(14, 21_535.0, 21_536.0, 100));
+        candles
+    }
+
+    #[test]
+    fn test_detector_confirms_leg_when_its_last_candle_closes() {
+        let instrument = InstrumentSpec::default();
+        let mut detector = ImpulseDetector::new("NQH6", &instrument);
+        let candles = candles();
+
+        for &candle in &candles[..12] {
+            assert!(detector.add_candle(candle).is_empty());
+        }
+        let legs = detector.add_candle(candles[12]);
+        assert_eq!(legs.len(), 1);
+        let leg = &legs[0];
+        assert_eq!(leg.direction, ImpulseDirection::Up);
+        assert_eq!((leg.start_price, leg.end_price), (21_500.0, 21_536.0));
+        assert_eq!(leg.num_candles, 3);
+        assert_eq!(leg.score_total, 5);
+        assert_eq!(leg.end_time, candles[12].timestamp);
+
+        // Same leg as the whole-day scan
+        let batch = detect_impulse_legs(&candles, "NQH6", &instrument);
+        assert_eq!(batch.len(), 1);
+        assert_eq!(batch[0].start_time, leg.start_time);
+        assert_eq!(batch[0].end_time, leg.end_time);
+    }
+
+    #[test]
+    fn test_tracker_profiles_leg_for_lvns() {
+        let mut tracker = ImpulseTracker::new(&InstrumentSpec::default());
+        for (minute, candle) in candles().iter().enumerate() {
+            let ts = START_MS + minute as u64 * BAR_MS;
+            tracker.add_trade(candle.open, candle.volume / 2, true, ts);
+            // One lot at 21505 during the leg - thin against the rest of its profile
+            if minute == 10 {
+                tracker.add_trade(21_505.0, 1, true, ts + 10_000);
+            }
+            tracker.add_trade(candle.close, candle.volume / 2, true, ts + 30_000);
+        }
+        // The leg's last candle closes when the next minute trades
+        tracker.add_trade(21_536.0, 1, false, START_MS + 15 * BAR_MS);
+
+        let confirmed = tracker.take_confirmed();
+        assert_eq!(confirmed.len(), 1);
+        let (leg, lvns) = &confirmed[0];
+        assert_eq!(leg.start_price, 21_500.0);
+        assert!(lvns.iter().any(|lvn| lvn.price == 21_505.0 && lvn.volume == 1));
+        assert!(tracker.take_confirmed().is_empty());
+    }
+}
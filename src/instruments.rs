@@ -0,0 +1,332 @@
+//! Instrument Specifications
+//!
+//! Contract details (tick size, tick/point value, session times) and default
+//! detection thresholds per futures root (NQ, MNQ, ES, MES), plus the volume
+//! profile bucket size and value-area percentage. Loaded from a JSON
+//! config file - `config/instruments.json` is built in and can be overridden
+//! with `--instruments <path>` on the server and the pipeline.
+
+use anyhow::{Context, Result};
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::LazyLock;
+use tracing::warn;
+
+/// Built-in instrument config
+const BUILTIN_INSTRUMENTS: &str = include_str!("../config/instruments.json");
+
+/// Root used when a symbol isn't in the registry (the app was built around NQ)
+pub const DEFAULT_ROOT: &str = "NQ";
+
+static BUILTIN: LazyLock<InstrumentRegistry> = LazyLock::new(|| {
+    InstrumentRegistry::from_json(BUILTIN_INSTRUMENTS).expect("built-in instruments.json is valid")
+});
+
+/// CME month codes (F=Jan ... Z=Dec)
+const MONTH_CODES: &str = "FGHJKMNQUVXZ";
+
+/// Session times in ET ("HH:MM")
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionTimes {
+    pub rth_start: String,
+    pub rth_end: String,
+    pub eth_start: String,
+    pub eth_end: String,
+}
+
+impl SessionTimes {
+    pub fn rth_start_time(&self) -> NaiveTime {
+        parse_hhmm(&self.rth_start).unwrap_or_default()
+    }
+
+    pub fn rth_end_time(&self) -> NaiveTime {
+        parse_hhmm(&self.rth_end).unwrap_or_default()
+    }
+
+    pub fn eth_start_time(&self) -> NaiveTime {
+        parse_hhmm(&self.eth_start).unwrap_or_default()
+    }
+
+    pub fn eth_end_time(&self) -> NaiveTime {
+        parse_hhmm(&self.eth_end).unwrap_or_default()
+    }
+
+    /// Is an ET wall-clock time inside regular trading hours?
+    pub fn is_rth(&self, et_time: NaiveTime) -> bool {
+        et_time >= self.rth_start_time() && et_time < self.rth_end_time()
+    }
+}
+
+/// Default detection thresholds (all prices in points)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstrumentThresholds {
+    /// Max price move in a window for absorption (live)
+    pub absorption_move_points: f64,
+    /// Distance from POC/VAH/VAL that counts as "at" the level (live)
+    pub key_level_tolerance_points: f64,
+    /// Min move after a signal to score it a win/loss (live outcomes)
+    pub outcome_move_points: f64,
+    /// Assumed volume/sec before any history exists (live absorption threshold)
+    pub baseline_volume_per_sec: f64,
+    /// Min size of an impulse leg
+    pub min_impulse_points: f64,
+    /// Distance from daily levels that counts as "at" the level (backtester)
+    pub level_tolerance_points: f64,
+}
+
+/// Volume profile settings shared by the live server and the pipeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProfileSettings {
+    /// Price bucket (a whole number of ticks)
+    pub bucket_points: f64,
+    /// Share of the volume in the value area (percent)
+    pub value_area_percent: f64,
+}
+
+impl Default for ProfileSettings {
+    fn default() -> Self {
+        Self {
+            bucket_points: 0.25,
+            value_area_percent: 70.0,
+        }
+    }
+}
+
+/// Contract specification for one futures root
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InstrumentSpec {
+    pub root: String,
+    pub description: String,
+    pub tick_size: f64,
+    /// Dollars per tick
+    pub tick_value: f64,
+    /// Dollars per point
+    pub point_value: f64,
+    pub session: SessionTimes,
+    pub thresholds: InstrumentThresholds,
+    #[serde(default)]
+    pub profile: ProfileSettings,
+}
+
+impl Default for InstrumentSpec {
+    fn default() -> Self {
+        BUILTIN.spec(DEFAULT_ROOT).cloned().expect("built-in config defines NQ")
+    }
+}
+
+impl InstrumentSpec {
+    /// Integer key of the tick a price falls on (volume profile / zone keys)
+    pub fn price_key(&self, price: f64) -> i64 {
+        (price / self.tick_size).round() as i64
+    }
+
+    /// Price of a tick key
+    pub fn key_price(&self, key: i64) -> f64 {
+        key as f64 * self.tick_size
+    }
+
+    /// Round a price to the nearest tick
+    pub fn round_to_tick(&self, price: f64) -> f64 {
+        self.key_price(self.price_key(price))
+    }
+
+    /// Convert points to whole ticks
+    pub fn points_to_ticks(&self, points: f64) -> i32 {
+        (points / self.tick_size).round() as i32
+    }
+
+    /// Dollar value of a move in points (one contract)
+    pub fn points_to_dollars(&self, points: f64) -> f64 {
+        points * self.point_value
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.tick_size <= 0.0 {
+            anyhow::bail!("{}: tick_size must be positive", self.root);
+        }
+        if (self.tick_value - self.tick_size * self.point_value).abs() > 1e-9 {
+            anyhow::bail!(
+                "{}: tick_value ({}) != tick_size ({}) * point_value ({})",
+                self.root, self.tick_value, self.tick_size, self.point_value
+            );
+        }
+        let bucket_ticks = self.profile.bucket_points / self.tick_size;
+        if bucket_ticks < 1.0 - 1e-9 || (bucket_ticks - bucket_ticks.round()).abs() > 1e-9 {
+            anyhow::bail!(
+                "{}: profile bucket_points ({}) must be a whole number of ticks ({})",
+                self.root, self.profile.bucket_points, self.tick_size
+            );
+        }
+        if !(self.profile.value_area_percent > 0.0 && self.profile.value_area_percent <= 100.0) {
+            anyhow::bail!(
+                "{}: profile value_area_percent ({}) must be in (0, 100]",
+                self.root, self.profile.value_area_percent
+            );
+        }
+        for time in [
+            &self.session.rth_start,
+            &self.session.rth_end,
+            &self.session.eth_start,
+            &self.session.eth_end,
+        ] {
+            parse_hhmm(time)
+                .with_context(|| format!("{}: invalid session time '{}'", self.root, time))?;
+        }
+        Ok(())
+    }
+}
+
+/// Instrument specs keyed by root symbol
+#[derive(Debug, Clone)]
+pub struct InstrumentRegistry {
+    specs: HashMap<String, InstrumentSpec>,
+}
+
+impl Default for InstrumentRegistry {
+    fn default() -> Self {
+        BUILTIN.clone()
+    }
+}
+
+impl InstrumentRegistry {
+    /// Parse a JSON array of instrument specs
+    pub fn from_json(json: &str) -> Result<Self> {
+        let specs: Vec<InstrumentSpec> =
+            serde_json::from_str(json).context("Failed to parse instrument config")?;
+
+        let mut registry = Self {
+            specs: HashMap::new(),
+        };
+        for spec in specs {
+            spec.validate()?;
+            registry.specs.insert(spec.root.to_uppercase(), spec);
+        }
+        Ok(registry)
+    }
+
+    /// Load instrument specs from a JSON config file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read instrument config: {:?}", path))?;
+        Self::from_json(&json)
+    }
+
+    /// Load from `path` if given, otherwise the built-in config
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => Self::from_file(path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Spec for an exact root symbol (e.g. "NQ")
+    pub fn spec(&self, root: &str) -> Option<&InstrumentSpec> {
+        self.specs.get(&root.to_uppercase())
+    }
+
+    /// Look up any symbol form: "NQ", "NQ.c.0", "NQZ5", "MNQH26", "NQZ5-NQH6"
+    pub fn get(&self, symbol: &str) -> Option<&InstrumentSpec> {
+        self.spec(root_symbol(symbol))
+    }
+
+    /// Look up a symbol, falling back to the default (NQ) spec
+    pub fn resolve(&self, symbol: &str) -> InstrumentSpec {
+        match self.get(symbol) {
+            Some(spec) => spec.clone(),
+            None => {
+                warn!("No instrument spec for '{}', using {}", symbol, DEFAULT_ROOT);
+                self.spec(DEFAULT_ROOT).cloned().unwrap_or_default()
+            }
+        }
+    }
+
+    /// Configured root symbols
+    pub fn roots(&self) -> Vec<&str> {
+        self.specs.keys().map(|s| s.as_str()).collect()
+    }
+}
+
+/// Strip continuous suffixes, contract month/year and spread legs from a symbol
+pub fn root_symbol(symbol: &str) -> &str {
+    // Spreads ("NQZ5-NQH6") use the front leg, continuous ("NQ.c.0") the prefix
+    let symbol = symbol.split(['-', '.', ' ']).next().unwrap_or(symbol);
+
+    // Outright contracts end in a month code followed by a 1-2 digit year
+    let without_year = symbol.trim_end_matches(|c: char| c.is_ascii_digit());
+    if without_year.len() == symbol.len() || without_year.len() < 2 {
+        return symbol;
+    }
+
+    match without_year.chars().last() {
+        Some(month) if MONTH_CODES.contains(month) => &without_year[..without_year.len() - 1],
+        _ => symbol,
+    }
+}
+
+fn parse_hhmm(time: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(time, "%H:%M").with_context(|| format!("Expected HH:MM, got '{}'", time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_symbol() {
+        assert_eq!(root_symbol("NQ"), "NQ");
+        assert_eq!(root_symbol("NQ.c.0"), "NQ");
+        assert_eq!(root_symbol("NQZ5"), "NQ");
+        assert_eq!(root_symbol("MNQH26"), "MNQ");
+        assert_eq!(root_symbol("NQZ5-NQH6"), "NQ");
+        assert_eq!(root_symbol("ESH6"), "ES");
+    }
+
+    #[test]
+    fn test_builtin_registry() {
+        let registry = InstrumentRegistry::default();
+        let nq = registry.get("NQZ5").unwrap();
+        assert_eq!(nq.tick_size, 0.25);
+        assert_eq!(nq.points_to_dollars(1.0), 20.0);
+        assert_eq!(nq.points_to_ticks(2.5), 10);
+
+        let mes = registry.get("MES.c.0").unwrap();
+        assert_eq!(mes.points_to_dollars(4.0), 20.0);
+
+        assert_eq!(registry.resolve("CLF6").root, "NQ");
+    }
+
+    #[test]
+    fn test_price_keys() {
+        let spec = InstrumentSpec::default();
+        assert_eq!(spec.price_key(21500.25), 86001);
+        assert_eq!(spec.key_price(86001), 21500.25);
+        assert_eq!(spec.round_to_tick(21500.3), 21500.25);
+    }
+
+    #[test]
+    fn test_rth_window() {
+        let spec = InstrumentSpec::default();
+        let t = |h, m| NaiveTime::from_hms_opt(h, m, 0).unwrap();
+        assert!(!spec.session.is_rth(t(9, 29)));
+        assert!(spec.session.is_rth(t(9, 30)));
+        assert!(!spec.session.is_rth(t(16, 0)));
+    }
+
+    #[test]
+    fn test_rejects_fractional_profile_bucket() {
+        let json = BUILTIN_INSTRUMENTS.replacen("\"bucket_points\": 0.25", "\"bucket_points\": 0.3", 1);
+        assert!(InstrumentRegistry::from_json(&json).is_err());
+
+        let json = BUILTIN_INSTRUMENTS.replacen("\"bucket_points\": 0.25", "\"bucket_points\": 1.0", 1);
+        assert_eq!(InstrumentRegistry::from_json(&json).unwrap().spec("NQ").unwrap().profile.bucket_points, 1.0);
+    }
+
+    #[test]
+    fn test_rejects_inconsistent_tick_value() {
+        let json = BUILTIN_INSTRUMENTS.replacen("\"tick_value\": 5.0", "\"tick_value\": 6.0", 1);
+        assert!(InstrumentRegistry::from_json(&json).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+//! Large Lots / Block Trades
+//!
+//! Works on aggressor orders rebuilt from their prints (see `aggressor`), so an
+//! order split into many fills counts once. Orders whose total size reaches a
+//! tier are flagged - tiers default to Fabio's 10 / 50 / 100 / 200 contracts -
+//! and labelled a sweep by the same rule as the `Sweep` signal
+//! (`AggressorOrder::is_sweep`), otherwise a block.
+
+use crate::aggressor::AggressorOrder;
+
+/// Large trade tiers shared by the live server and the backtester
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LargeTradeSettings {
+    /// Min contracts of each tier, ascending (tier 1 is the first)
+    tiers: Vec<u32>,
+    /// Lowest tier recorded as a `large_trade` signal (confluence, stats, backtests)
+    pub signal_tier: u8,
+}
+
+impl LargeTradeSettings {
+    pub fn new(mut tiers: Vec<u32>, signal_tier: u8) -> Self {
+        tiers.sort_unstable();
+        tiers.dedup();
+        Self { tiers, signal_tier }
+    }
+
+    pub fn tiers(&self) -> &[u32] {
+        &self.tiers
+    }
+
+    /// Highest tier (1-based) a size reaches, with that tier's min size
+    pub fn tier(&self, size: u64) -> Option<(u8, u32)> {
+        self.tiers
+            .iter()
+            .rposition(|&min| size >= min as u64)
+            .map(|i| (i as u8 + 1, self.tiers[i]))
+    }
+}
+
+impl Default for LargeTradeSettings {
+    fn default() -> Self {
+        Self::new(vec![10, 50, 100, 200], 3)
+    }
+}
+
+/// One aggressor order that reached a tier
+#[derive(Debug, Clone, PartialEq)]
+pub struct LargeTrade {
+    pub timestamp: u64,
+    pub is_buy: bool,
+    pub size: u64,
+    /// Volume weighted over the fills
+    pub price: f64,
+    pub price_low: f64,
+    pub price_high: f64,
+    pub fills: u32,
+    /// Took more price levels than the sweep threshold (`AggressorOrder::is_sweep`)
+    pub sweep: bool,
+    pub tier: u8,
+    pub tier_min: u32,
+}
+
+impl LargeTrade {
+    /// "sweep" through the book or "block" at one or a few prices
+    pub fn kind(&self) -> &'static str {
+        if self.sweep {
+            "sweep"
+        } else {
+            "block"
+        }
+    }
+}
+
+/// Keeps the aggressor orders that reach a tier
+#[derive(Debug)]
+pub struct LargeTradeDetector {
+    settings: LargeTradeSettings,
+    confirmed: Vec<LargeTrade>,
+}
+
+impl LargeTradeDetector {
+    pub fn new(settings: LargeTradeSettings) -> Self {
+        Self {
+            settings,
+            confirmed: Vec::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &LargeTradeSettings {
+        &self.settings
+    }
+
+    /// Check an order for a tier - `sweep_levels` is the threshold sweeps are signalled at
+    pub fn add_order(&mut self, order: &AggressorOrder, sweep_levels: u32) {
+        if let Some((tier, tier_min)) = self.settings.tier(order.size) {
+            self.confirmed.push(LargeTrade {
+                timestamp: order.timestamp,
+                is_buy: order.is_buy,
+                size: order.size,
+                price: order.price,
+                price_low: order.price_low,
+                price_high: order.price_high,
+                fills: order.fills,
+                sweep: order.is_sweep(sweep_levels),
+                tier,
+                tier_min,
+            });
+        }
+    }
+
+    /// Large trades found since the last call
+    pub fn take_large_trades(&mut self) -> Vec<LargeTrade> {
+        std::mem::take(&mut self.confirmed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aggressor::{OrderAggregator, DEFAULT_SWEEP_LEVELS};
+
+    #[test]
+    fn test_tiers() {
+        let settings = LargeTradeSettings::default();
+        assert_eq!(settings.tier(9), None);
+        assert_eq!(settings.tier(10), Some((1, 10)));
+        assert_eq!(settings.tier(99), Some((2, 50)));
+        assert_eq!(settings.tier(500), Some((4, 200)));
+
+        // Tiers are sorted whatever order they're given in
+        assert_eq!(LargeTradeSettings::new(vec![100, 20], 1).tier(50), Some((1, 20)));
+    }
+
+    #[test]
+    fn test_orders_reaching_a_tier() {
+        let mut orders = OrderAggregator::new();
+        // A buy order taking 100.00-100.50 in 3 fills of 20
+        orders.add(100.0, 20, true, 1_000, None);
+        orders.add(100.25, 20, true, 1_000, None);
+        orders.add(100.5, 20, true, 1_000, None);
+        // A small sell below every tier
+        orders.add(100.25, 5, false, 1_000, None);
+        // A single print
+        orders.add(100.0, 150, false, 2_000, None);
+        // A buy order filled 4 times over 2 levels - not a sweep
+        for price in [100.0, 100.0, 100.25, 100.25] {
+            orders.add(price, 15, true, 3_000, None);
+        }
+
+        let mut detector = LargeTradeDetector::new(LargeTradeSettings::default());
+        for order in orders.take_orders(3_001) {
+            detector.add_order(&order, DEFAULT_SWEEP_LEVELS);
+        }
+        let trades = detector.take_large_trades();
+        assert_eq!(trades.len(), 3);
+        assert_eq!(
+            trades[0],
+            LargeTrade {
+                timestamp: 1_000,
+                is_buy: true,
+                size: 60,
+                price: 100.25,
+                price_low: 100.0,
+                price_high: 100.5,
+                fills: 3,
+                sweep: true,
+                tier: 2,
+                tier_min: 50,
+            }
+        );
+        assert_eq!(trades[0].kind(), "sweep");
+        assert_eq!((trades[1].kind(), trades[1].tier), ("block", 3));
+        assert_eq!((trades[2].kind(), trades[2].fills, trades[2].size), ("block", 4, 60));
+    }
+}
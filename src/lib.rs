@@ -1,8 +1,26 @@
 // Library crate - exports shared types and processing logic
 
 pub mod types;
+pub mod aggressor;
+pub mod clock;
+pub mod calendar;
+pub mod instruments;
+pub mod large_trade;
+pub mod book;
+pub mod footprint;
+pub mod divergence;
+pub mod imbalance;
+pub mod impulse;
+pub mod lvn;
+pub mod market_state;
 pub mod processing;
+pub mod router;
+pub mod store;
 pub mod supabase;
+pub mod sqlite;
+pub mod trade_files;
+pub mod vwap;
+pub mod volume_profile;
 pub mod api;
 pub mod streams;
 
@@ -0,0 +1,56 @@
+//! Low Volume Nodes
+//!
+//! Prices an impulse leg moved through so fast that little traded there - under
+//! 30% of the average volume of the leg's profile levels. Price tends to move
+//! quickly through them again, which makes them trend-model entry levels.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::impulse::ImpulseLeg;
+use crate::volume_profile::VolumeProfile;
+
+/// Threshold for LVN: volume < 30% of average volume at price
+const LVN_THRESHOLD_RATIO: f64 = 0.30;
+
+/// Low Volume Node extracted from impulse leg volume profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LvnLevel {
+    pub price: f64,
+    pub volume: u64,
+    pub avg_volume: f64,
+    pub volume_ratio: f64, // Actual/Average (< 0.3 qualifies)
+    pub impulse_start_time: DateTime<Utc>,
+    pub impulse_end_time: DateTime<Utc>,
+    pub date: NaiveDate,
+    pub symbol: String,
+}
+
+/// LVNs of a leg's volume profile, by price
+pub fn leg_lvns(profile: &VolumeProfile, leg: &ImpulseLeg) -> Vec<LvnLevel> {
+    if profile.is_empty() {
+        return Vec::new();
+    }
+
+    // Average volume across all price levels
+    let avg_volume = profile.average_level_volume();
+
+    // Find LVNs: price levels with volume < 30% of average
+    profile
+        .levels()
+        .filter_map(|(price, level)| {
+            let volume = level.total();
+            let volume_ratio = volume as f64 / avg_volume;
+            (volume_ratio < LVN_THRESHOLD_RATIO).then(|| LvnLevel {
+                price,
+                volume,
+                avg_volume,
+                volume_ratio,
+                impulse_start_time: leg.start_time,
+                impulse_end_time: leg.end_time,
+                date: leg.date,
+                symbol: leg.symbol.clone(),
+            })
+        })
+        .collect()
+}
@@ -8,19 +8,27 @@ use axum::{
     routing::get,
     Router,
 };
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use futures::{SinkExt, StreamExt};
-use std::{net::SocketAddr, sync::Arc};
+use std::{collections::HashMap, net::SocketAddr, sync::Arc};
 use tokio::sync::{broadcast, RwLock};
 use tower_http::{
     cors::{Any, CorsLayer},
     services::ServeDir,
 };
-use tracing::{error, info};
-
-use orderflow_bubbles::{api, streams, supabase, types};
+use tracing::{error, info, warn};
+
+use orderflow_bubbles::{api, instruments::InstrumentRegistry, sqlite, store, streams, supabase, types};
+use orderflow_bubbles::book::BookSchema;
+use orderflow_bubbles::footprint::{FootprintSettings, FootprintTimeframe};
+use orderflow_bubbles::divergence::DivergenceSettings;
+use orderflow_bubbles::large_trade::LargeTradeSettings;
+use orderflow_bubbles::imbalance::{StackedImbalanceAlgorithm, StackedImbalanceSettings};
+use orderflow_bubbles::vwap::VwapAnchor;
+use sqlite::SqliteStore;
+use store::{SessionRecord, SharedStore, UserConfig};
 use streams::{run_databento_stream, run_db_replay, run_demo_stream, run_historical_replay, run_local_replay};
-use supabase::{SessionRecord, SupabaseClient, UserConfig};
+use supabase::SupabaseClient;
 use types::{AppState, ClientMessage, WsMessage};
 
 #[derive(Parser, Debug)]
@@ -38,15 +46,15 @@ struct Args {
     #[arg(short, long, default_value = "false")]
     replay: bool,
 
-    /// Run replay from Supabase database (data uploaded by pipeline)
+    /// Run replay from the signal store database (bars written by the pipeline)
     #[arg(long, default_value = "false")]
     db_replay: bool,
 
-    /// Run in local replay mode using downloaded .zst files (no API key needed)
+    /// Run in local replay mode using downloaded .csv.zst/.dbn.zst files (no API key needed)
     #[arg(long, default_value = "false")]
     local_replay: bool,
 
-    /// Data directory for local replay (contains .zst files)
+    /// Data directory for local replay (contains .csv.zst or .dbn.zst files)
     #[arg(long, default_value = "data/NQ_11_23_2025-12_23_2025")]
     data_dir: std::path::PathBuf,
 
@@ -77,6 +85,83 @@ struct Args {
     /// Minimum trade size to process
     #[arg(short = 'f', long, default_value = "1")]
     min_size: u32,
+
+    /// Instrument config (JSON) - defaults to the built-in config/instruments.json
+    #[arg(long, env = "INSTRUMENTS_CONFIG")]
+    instruments: Option<std::path::PathBuf>,
+
+    /// Where the session VWAP resets: rth (09:30 ET) or globex (18:00 ET)
+    #[arg(long, value_enum, default_value = "rth", env = "VWAP_ANCHOR")]
+    vwap_anchor: VwapAnchor,
+
+    /// Footprint bar timeframe: 30s, 1m, 5m (time), 500t (trades) or 10r (range in ticks)
+    #[arg(long, default_value = "1m", env = "FOOTPRINT_TIMEFRAME")]
+    footprint_timeframe: FootprintTimeframe,
+
+    /// Diagonal volume ratio that marks a footprint imbalance (3.0 = 300%)
+    #[arg(long, default_value = "3.0", env = "FOOTPRINT_IMBALANCE_RATIO")]
+    footprint_imbalance_ratio: f64,
+
+    /// Stacked imbalance detector: session (same-price, session profile) or diagonal (per footprint bar)
+    #[arg(long, value_enum, default_value = "session", env = "STACKED_IMBALANCE")]
+    stacked_imbalance: StackedImbalanceAlgorithm,
+
+    /// Diagonal stacked imbalance: volume ratio per tick (3.0 = 300%)
+    #[arg(long, default_value = "3.0", env = "STACKED_IMBALANCE_RATIO")]
+    stacked_imbalance_ratio: f64,
+
+    /// Diagonal stacked imbalance: min volume on the imbalanced side of a tick
+    #[arg(long, default_value = "10", env = "STACKED_IMBALANCE_MIN_VOLUME")]
+    stacked_imbalance_min_volume: u64,
+
+    /// Diagonal stacked imbalance: consecutive imbalanced ticks in a stack
+    #[arg(long, default_value = "3", env = "STACKED_IMBALANCE_LEVELS")]
+    stacked_imbalance_levels: u32,
+
+    /// Divergence: bar length (seconds) price and CVD swings are found on
+    #[arg(long, default_value = "15", env = "DIVERGENCE_BAR_SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    divergence_bar_secs: u64,
+
+    /// Divergence: bars on each side a swing high/low has to beat
+    #[arg(long, default_value = "3", env = "DIVERGENCE_SWING_BARS", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    divergence_swing_bars: usize,
+
+    /// Divergence: max bars between the two swings compared
+    #[arg(long, default_value = "40", env = "DIVERGENCE_LOOKBACK_BARS")]
+    divergence_lookback_bars: usize,
+
+    /// Large trade tiers: min contracts of each tier (comma-separated)
+    #[arg(long, value_delimiter = ',', default_value = "10,50,100,200", env = "LARGE_TRADE_TIERS")]
+    large_trade_tiers: Vec<u32>,
+
+    /// Lowest large trade tier (1-based) recorded as a signal
+    #[arg(long, default_value = "3", env = "LARGE_TRADE_SIGNAL_TIER")]
+    large_trade_signal_tier: u8,
+
+    /// An aggressor order (prints rebuilt into one order) is a sweep when it takes more than this many price levels
+    #[arg(long, default_value = "2", env = "SWEEP_LEVELS")]
+    sweep_levels: u32,
+
+    /// Order book feed next to trades in live mode (local replay picks up MBP-10/MBO files itself)
+    #[arg(long, value_enum, env = "BOOK_SCHEMA")]
+    book: Option<BookSchema>,
+
+    /// Where sessions, signals, config and db-replay bars are stored
+    #[arg(long, value_enum, default_value = "supabase", env = "SIGNAL_STORE")]
+    store: StoreKind,
+
+    /// SQLite database file (with --store sqlite)
+    #[arg(long, default_value = "orderflow.db", env = "SQLITE_PATH")]
+    sqlite_path: std::path::PathBuf,
+}
+
+/// Signal store backend
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum StoreKind {
+    /// Supabase/PostgREST (SUPABASE_URL + SUPABASE_ANON_KEY) - off if not configured
+    Supabase,
+    /// Embedded SQLite file - no outside service
+    Sqlite,
 }
 
 #[tokio::main]
@@ -109,7 +194,7 @@ async fn main() -> Result<()> {
     info!("Starting Orderflow Bubbles server");
     info!("Mode: {}", mode);
     if args.db_replay {
-        info!("Replaying from Supabase database");
+        info!("Replaying from {:?} store", args.store);
     } else if args.local_replay {
         info!("Data dir: {:?}", args.data_dir);
     } else {
@@ -118,6 +203,10 @@ async fn main() -> Result<()> {
     info!("Port: {}", args.port);
     info!("Min size filter: {}", args.min_size);
 
+    // Contract specs (tick size, point value, thresholds) by root symbol
+    let instruments = InstrumentRegistry::load(args.instruments.as_deref())?;
+    info!("Instruments: {:?}", instruments.roots());
+
     // Create broadcast channel for processed data
     let (tx, _rx) = broadcast::channel::<WsMessage>(1000);
 
@@ -127,13 +216,17 @@ async fn main() -> Result<()> {
         .map(|s| s.trim().to_string())
         .collect();
 
-    // Initialize Supabase client (optional - works without it)
-    let (supabase, session_id, config) = match SupabaseClient::from_env() {
-        Some(client) => {
-            info!("📊 Supabase connected - signals will be persisted");
+    // Initialize the signal store (optional - works without it)
+    let store: Option<SharedStore> = match args.store {
+        StoreKind::Supabase => SupabaseClient::from_env().map(|client| Arc::new(client) as SharedStore),
+        StoreKind::Sqlite => Some(Arc::new(SqliteStore::open(&args.sqlite_path)?)),
+    };
+    let (store, session_id, config) = match store {
+        Some(store) => {
+            info!("📊 {} connected - signals will be persisted", store.name());
 
-            // Load user config from Supabase
-            let config = match client.get_config().await {
+            // Load user config from the store
+            let config = match store.get_config().await {
                 Ok(cfg) => {
                     info!("📊 Config loaded: min_size={}, sound={}", cfg.min_size, cfg.sound_enabled);
                     cfg
@@ -152,20 +245,20 @@ async fn main() -> Result<()> {
                 session_low: None,
                 total_volume: None,
             };
-            match client.insert_session(&session).await {
+            match store.insert_session(&session).await {
                 Ok(id) => {
                     info!("📊 Session created: {}", id);
-                    (Some(client), Some(id), config)
+                    (Some(store), Some(id), config)
                 }
                 Err(e) => {
-                    error!("Failed to create session in Supabase: {}", e);
-                    (Some(client), None, config)
+                    error!("Failed to create session in {}: {}", store.name(), e);
+                    (Some(store), None, config)
                 }
             }
         }
         None => {
             info!("📊 Supabase not configured - signals will not be persisted");
-            info!("   Set SUPABASE_URL and SUPABASE_ANON_KEY to enable persistence");
+            info!("   Set SUPABASE_URL and SUPABASE_ANON_KEY, or use --store sqlite, to enable persistence");
             (None, None, UserConfig::default())
         }
     };
@@ -174,7 +267,7 @@ async fn main() -> Result<()> {
     let min_size = if args.min_size != 1 {
         args.min_size // CLI override
     } else {
-        config.min_size // From Supabase config
+        config.min_size // From stored config
     };
 
     let replay_date_clone = if args.replay || args.local_replay || args.db_replay {
@@ -188,16 +281,33 @@ async fn main() -> Result<()> {
         active_symbols: RwLock::new(symbols.iter().cloned().collect()),
         min_size: RwLock::new(min_size),
         session_id,
-        supabase,
+        store,
         config: RwLock::new(config),
-        session_stats: RwLock::new((0.0, f64::MAX, 0)),
+        session_stats: RwLock::new(HashMap::new()),
         mode: mode.to_lowercase(),
         replay_date: replay_date_clone,
-        replay_control: RwLock::new(types::ReplayControl {
-            is_paused: false,
-            speed: args.replay_speed,
-            current_timestamp: None,
-        }),
+        replay_control: RwLock::new(types::ReplayControl::new(args.replay_speed)),
+        connection_status: RwLock::new(None),
+        instruments,
+        vwap_anchor: args.vwap_anchor,
+        footprint: FootprintSettings {
+            timeframe: args.footprint_timeframe,
+            imbalance_ratio: args.footprint_imbalance_ratio,
+        },
+        stacked_imbalance: StackedImbalanceSettings {
+            algorithm: args.stacked_imbalance,
+            ratio: args.stacked_imbalance_ratio,
+            min_volume: args.stacked_imbalance_min_volume,
+            min_levels: args.stacked_imbalance_levels,
+        },
+        divergence: DivergenceSettings {
+            bar_secs: args.divergence_bar_secs,
+            swing_bars: args.divergence_swing_bars,
+            lookback_bars: args.divergence_lookback_bars,
+        },
+        large_trades: LargeTradeSettings::new(args.large_trade_tiers.clone(), args.large_trade_signal_tier),
+        sweep_levels: args.sweep_levels,
+        vwap_anchors: RwLock::new(Vec::new()),
     });
 
     // Spawn data streaming task (demo, replay, or live)
@@ -208,7 +318,7 @@ async fn main() -> Result<()> {
         let replay_speed = args.replay_speed;
 
         info!("🗄️ Starting DATABASE REPLAY mode");
-        info!("   Source: Supabase (replay_bars_1s table)");
+        info!("   Source: {:?} store (replay_bars_1s table)", args.store);
         if let Some(ref date) = replay_date {
             info!("   Date filter: {}", date);
         }
@@ -294,9 +404,13 @@ async fn main() -> Result<()> {
             .api_key
             .clone()
             .expect("API key required for live mode (use --demo or --local-replay)");
+        let book = args.book;
         info!("📡 Starting LIVE mode with Databento");
+        if let Some(book) = book {
+            info!("   Order book: {}", book.schema().as_str());
+        }
         tokio::spawn(async move {
-            if let Err(e) = run_databento_stream(api_key, symbols, state_clone).await {
+            if let Err(e) = run_databento_stream(api_key, symbols, book, state_clone).await {
                 error!("Databento stream error: {}", e);
             }
         });
@@ -359,19 +473,19 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
     // Send initial replay status
     {
         let replay_ctrl = state.replay_control.read().await;
-        let status = types::ReplayStatus {
-            mode: state.mode.clone(),
-            is_paused: replay_ctrl.is_paused,
-            speed: replay_ctrl.speed,
-            replay_date: state.replay_date.clone(),
-            replay_progress: None,
-            current_time: replay_ctrl.current_timestamp,
-        };
+        let status = replay_ctrl.status(&state.mode, state.replay_date.clone());
         if let Ok(json) = serde_json::to_string(&WsMessage::ReplayStatus(status)) {
             let _ = sender.send(Message::Text(json.into())).await;
         }
     }
 
+    // Send live feed health (if degraded, the client should know before data resumes)
+    if let Some(status) = state.connection_status.read().await.clone() {
+        if let Ok(json) = serde_json::to_string(&WsMessage::ConnectionStatus(status)) {
+            let _ = sender.send(Message::Text(json.into())).await;
+        }
+    }
+
     // Spawn task to forward messages to this client
     let send_task = tokio::spawn(async move {
         while let Ok(msg) = rx.recv().await {
@@ -395,18 +509,18 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                                 *state_clone.min_size.write().await = size;
                                 info!("Min size filter set to: {}", size);
 
-                                // Persist config change to Supabase
-                                if let Some(ref supabase) = state_clone.supabase {
+                                // Persist config change to the store
+                                if let Some(ref store) = state_clone.store {
                                     let mut config = state_clone.config.write().await;
                                     config.min_size = size;
                                     let config_clone = config.clone();
-                                    let supabase_clone = supabase.clone();
+                                    let store_clone = store.clone();
                                     // Fire and forget - don't block on persistence
                                     tokio::spawn(async move {
-                                        if let Err(e) = supabase_clone.set_config(&config_clone).await {
+                                        if let Err(e) = store_clone.set_config(&config_clone).await {
                                             error!("Failed to persist config: {}", e);
                                         } else {
-                                            info!("📊 Config persisted to Supabase");
+                                            info!("📊 Config persisted to {}", store_clone.name());
                                         }
                                     });
                                 }
@@ -417,14 +531,7 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                             ctrl.is_paused = true;
                             info!("⏸️ Replay paused");
                             // Broadcast status update
-                            let status = types::ReplayStatus {
-                                mode: state_clone.mode.clone(),
-                                is_paused: true,
-                                speed: ctrl.speed,
-                                replay_date: state_clone.replay_date.clone(),
-                                replay_progress: None,
-                                current_time: ctrl.current_timestamp,
-                            };
+                            let status = ctrl.status(&state_clone.mode, state_clone.replay_date.clone());
                             let _ = state_clone.tx.send(WsMessage::ReplayStatus(status));
                         }
                         "replay_resume" => {
@@ -432,14 +539,7 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                             ctrl.is_paused = false;
                             info!("▶️ Replay resumed");
                             // Broadcast status update
-                            let status = types::ReplayStatus {
-                                mode: state_clone.mode.clone(),
-                                is_paused: false,
-                                speed: ctrl.speed,
-                                replay_date: state_clone.replay_date.clone(),
-                                replay_progress: None,
-                                current_time: ctrl.current_timestamp,
-                            };
+                            let status = ctrl.status(&state_clone.mode, state_clone.replay_date.clone());
                             let _ = state_clone.tx.send(WsMessage::ReplayStatus(status));
                         }
                         "set_replay_speed" => {
@@ -449,17 +549,67 @@ async fn handle_socket(socket: WebSocket, state: Arc<AppState>) {
                                 ctrl.speed = speed;
                                 info!("⏩ Replay speed set to {}x", speed);
                                 // Broadcast status update
-                                let status = types::ReplayStatus {
-                                    mode: state_clone.mode.clone(),
-                                    is_paused: ctrl.is_paused,
-                                    speed,
-                                    replay_date: state_clone.replay_date.clone(),
-                                    replay_progress: None,
-                                    current_time: ctrl.current_timestamp,
-                                };
+                                let status = ctrl.status(&state_clone.mode, state_clone.replay_date.clone());
                                 let _ = state_clone.tx.send(WsMessage::ReplayStatus(status));
                             }
                         }
+                        "replay_seek" => {
+                            let mut ctrl = state_clone.replay_control.write().await;
+                            let target = match (client_msg.timestamp, client_msg.time.as_deref()) {
+                                (Some(timestamp), _) => Some(timestamp),
+                                (None, Some(time)) => match ctrl.seek_time_to_ms(time) {
+                                    Ok(timestamp) => Some(timestamp),
+                                    Err(e) => {
+                                        warn!("Invalid seek time: {}", e);
+                                        None
+                                    }
+                                },
+                                _ => None,
+                            };
+                            if let Some(target) = target {
+                                ctrl.seek_to = Some(target);
+                                info!("⏭️ Replay seek to {}", target);
+                            }
+                        }
+                        "replay_step" => {
+                            let unit = client_msg.unit.as_deref().unwrap_or("window");
+                            match types::StepUnit::parse(unit) {
+                                Some(unit) => {
+                                    // Stepping freezes the replay between steps
+                                    let mut ctrl = state_clone.replay_control.write().await;
+                                    ctrl.is_paused = true;
+                                    ctrl.step = Some(unit);
+                                    let status = ctrl.status(&state_clone.mode, state_clone.replay_date.clone());
+                                    let _ = state_clone.tx.send(WsMessage::ReplayStatus(status));
+                                }
+                                None => warn!("Unknown replay step unit: {}", unit),
+                            }
+                        }
+                        "add_vwap_anchor" | "remove_vwap_anchor" => {
+                            let Some(timestamp) = client_msg.timestamp else {
+                                warn!("{} needs a timestamp", client_msg.action);
+                                continue;
+                            };
+                            let anchor = types::ManualVwapAnchor {
+                                symbol: client_msg.symbol.clone(),
+                                timestamp,
+                            };
+                            let target = anchor.symbol.as_deref().unwrap_or("all symbols").to_string();
+                            let mut anchors = state_clone.vwap_anchors.write().await;
+                            if client_msg.action == "add_vwap_anchor" {
+                                if !anchors.contains(&anchor) {
+                                    info!("⚓ VWAP anchored at {} ({})", timestamp, target);
+                                    anchors.push(anchor);
+                                }
+                            } else {
+                                // Without a symbol, every anchor at that time is removed
+                                anchors.retain(|a| {
+                                    a.timestamp != timestamp
+                                        || (anchor.symbol.is_some() && a.symbol != anchor.symbol)
+                                });
+                                info!("⚓ VWAP anchor at {} removed ({})", timestamp, target);
+                            }
+                        }
                         _ => {}
                     }
                 }
@@ -484,15 +634,23 @@ async fn shutdown_signal(state: Arc<AppState>) {
 
     info!("🛑 Shutdown signal received, finalizing session...");
 
-    // Finalize session in Supabase with actual stats
-    if let (Some(ref supabase), Some(session_id)) = (&state.supabase, state.session_id) {
-        let (high, low, volume) = *state.session_stats.read().await;
+    // Finalize session in the store with actual stats
+    if let (Some(ref store), Some(session_id)) = (&state.store, state.session_id) {
+        // The session row holds one range - use the most active instrument
+        let (high, low, volume) = state
+            .session_stats
+            .read()
+            .await
+            .values()
+            .max_by_key(|(_, _, volume)| *volume)
+            .copied()
+            .unwrap_or((0.0, f64::MAX, 0));
         // Normalize low if it was never set (still at f64::MAX)
         let low = if low == f64::MAX { high } else { low };
 
         info!("📊 Session stats: high={:.2}, low={:.2}, volume={}", high, low, volume);
 
-        if let Err(e) = supabase.update_session(session_id, high, low, volume).await {
+        if let Err(e) = store.update_session(session_id, high, low, volume).await {
             error!("Failed to finalize session: {}", e);
         } else {
             info!("📊 Session finalized: {}", session_id);
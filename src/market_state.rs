@@ -0,0 +1,505 @@
+//! Market State
+//!
+//! Answers the first question of the strategy framework - is the market balanced
+//! (rotating around fair value) or imbalanced (pushing to find new value)? Five
+//! features each cast at most one vote:
+//! - value area overlap with the prior session (overlapping = balance, migrating = imbalance)
+//! - rotations through the developing POC over the last 30 minutes
+//! - the last 30 minutes' range against the 5-minute ATR
+//! - price against the prior session's high/low and value area (PDH/PDL/VAH/VAL)
+//! - the latest impulse leg, while price holds more than half of it
+//!
+//! A direction needs two more votes than the other and more than balance has;
+//! anything else is balance. A new state has to hold for a minute before it's reported.
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::instruments::ProfileSettings;
+use crate::volume_profile::{ValueArea, VolumeProfile};
+
+/// Bars the ATR and range expansion are measured on
+const BAR_MS: u64 = 5 * 60 * 1000;
+
+/// Completed bars averaged into the ATR
+const ATR_BARS: usize = 14;
+
+/// Bars (including the open one) in the range compared against the ATR - 30 minutes
+const EXPANSION_BARS: usize = 6;
+
+/// 30-minute range >= this many ATRs is range expansion
+const EXPANSION_RATIO: f64 = 3.5;
+
+/// 30-minute range <= this many ATRs is a contracting, rotational range
+const CONTRACTION_RATIO: f64 = 2.0;
+
+/// Window for counting rotations through the POC
+const ROTATION_WINDOW_MS: u64 = 30 * 60 * 1000;
+
+/// POC crosses in the window that mark two-sided rotation
+const BALANCE_ROTATIONS: usize = 3;
+
+/// Value area overlap (fraction of today's value area) that still counts as balance
+const BALANCE_OVERLAP: f64 = 0.5;
+
+/// Impulse legs vote for this long after they end
+const IMPULSE_LOOKBACK_MS: u64 = 60 * 60 * 1000;
+
+/// A new state must hold this long before it replaces the current one
+const MIN_STATE_MS: u64 = 60 * 1000;
+
+/// Balance or directional imbalance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketState {
+    Balance,
+    BullishImbalance,
+    BearishImbalance,
+}
+
+impl MarketState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Balance => "balance",
+            Self::BullishImbalance => "bullish_imbalance",
+            Self::BearishImbalance => "bearish_imbalance",
+        }
+    }
+
+    /// Whether `filter` ("balance", "imbalance", "bullish_imbalance", "bearish_imbalance") covers a state name
+    pub fn matches(filter: &str, state: &str) -> bool {
+        filter == state || (filter == "imbalance" && state.ends_with("_imbalance"))
+    }
+}
+
+/// The prior session's extremes and value area (PDH/PDL/VAH/VAL)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SessionReference {
+    pub high: f64,
+    pub low: f64,
+    pub value_area: Option<ValueArea>,
+}
+
+/// A completed impulse leg (Unix ms)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ImpulseReference {
+    pub start_time: u64,
+    pub end_time: u64,
+    pub start_price: f64,
+    pub end_price: f64,
+}
+
+/// A classification and the features behind it
+#[derive(Debug, Clone, PartialEq)]
+pub struct MarketStateReading {
+    pub state: MarketState,
+    pub previous: Option<MarketState>,
+    /// Share of today's value area inside the prior session's
+    pub value_area_overlap: Option<f64>,
+    /// POC crosses in the last 30 minutes
+    pub rotations: u32,
+    /// 30-minute range in 5-minute ATRs
+    pub range_expansion: Option<f64>,
+    /// Features that voted for the state
+    pub reasons: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bar {
+    start: u64,
+    open: f64,
+    high: f64,
+    low: f64,
+    close: f64,
+}
+
+/// Balance/imbalance classifier for one instrument
+#[derive(Debug, Clone)]
+pub struct MarketStateClassifier {
+    // Current session profile and extremes - the prior session's become the reference
+    profile: VolumeProfile,
+    session_start: Option<u64>,
+    session_high: f64,
+    session_low: f64,
+    prior: Option<SessionReference>,
+
+    // 5-minute bars for the ATR (completed, newest last) and the open bar
+    bars: VecDeque<Bar>,
+    bar: Option<Bar>,
+
+    // Times price crossed the POC, and which side it was last on
+    poc_crosses: VecDeque<u64>,
+    poc_side: Option<bool>,
+
+    impulse: Option<ImpulseReference>,
+
+    state: Option<MarketState>,
+    // A different state seen since, and when it was first seen
+    candidate: Option<(MarketState, u64)>,
+}
+
+impl MarketStateClassifier {
+    pub fn new(settings: &ProfileSettings) -> Self {
+        Self {
+            profile: VolumeProfile::new(settings),
+            session_start: None,
+            session_high: f64::MIN,
+            session_low: f64::MAX,
+            prior: None,
+            bars: VecDeque::new(),
+            bar: None,
+            poc_crosses: VecDeque::new(),
+            poc_side: None,
+            impulse: None,
+            state: None,
+            candidate: None,
+        }
+    }
+
+    /// Current state (None until the first classification)
+    pub fn state(&self) -> Option<MarketState> {
+        self.state
+    }
+
+    /// Reference levels to judge today against
+    pub fn set_prior_session(&mut self, reference: SessionReference) {
+        self.prior = Some(reference);
+    }
+
+    /// A newly completed impulse leg - replaces the previous one
+    pub fn add_impulse(&mut self, leg: ImpulseReference) {
+        self.impulse = Some(leg);
+    }
+
+    pub fn add_trade(&mut self, price: f64, size: u64, is_buy: bool, timestamp: u64) {
+        self.profile.add(price, size, is_buy, timestamp);
+        self.session_start.get_or_insert(timestamp);
+        self.session_high = self.session_high.max(price);
+        self.session_low = self.session_low.min(price);
+
+        let start = timestamp - timestamp % BAR_MS;
+        match &mut self.bar {
+            Some(bar) if bar.start == start => {
+                bar.high = bar.high.max(price);
+                bar.low = bar.low.min(price);
+                bar.close = price;
+            }
+            _ => {
+                if let Some(bar) = self.bar.take() {
+                    self.bars.push_back(bar);
+                    if self.bars.len() > ATR_BARS {
+                        self.bars.pop_front();
+                    }
+                }
+                self.bar = Some(Bar { start, open: price, high: price, low: price, close: price });
+            }
+        }
+    }
+
+    /// The session ended - it becomes the reference for the next one
+    pub fn roll_session(&mut self) {
+        if self.session_start.is_some() {
+            self.prior = Some(SessionReference {
+                high: self.session_high,
+                low: self.session_low,
+                value_area: self.profile.value_area(),
+            });
+        }
+        self.profile = VolumeProfile::new(self.profile.settings());
+        self.session_start = None;
+        self.session_high = f64::MIN;
+        self.session_low = f64::MAX;
+        self.poc_crosses.clear();
+        self.poc_side = None;
+    }
+
+    /// Classify at `price` - returns a reading when the state changes
+    pub fn update(&mut self, price: f64, now: u64) -> Option<MarketStateReading> {
+        let value_area = self.profile.value_area()?;
+        self.track_rotations(price, value_area.poc, now);
+
+        let mut reading = self.classify(price, value_area, now);
+        if self.state == Some(reading.state) {
+            self.candidate = None;
+            return None;
+        }
+
+        // The first classification is reported straight away, a change once it has held
+        if self.state.is_some() {
+            let since = match self.candidate {
+                Some((state, since)) if state == reading.state => since,
+                _ => {
+                    self.candidate = Some((reading.state, now));
+                    now
+                }
+            };
+            if now.saturating_sub(since) < MIN_STATE_MS {
+                return None;
+            }
+        }
+
+        self.candidate = None;
+        reading.previous = self.state;
+        self.state = Some(reading.state);
+        Some(reading)
+    }
+
+    fn track_rotations(&mut self, price: f64, poc: f64, now: u64) {
+        while self.poc_crosses.front().is_some_and(|&t| now.saturating_sub(t) > ROTATION_WINDOW_MS) {
+            self.poc_crosses.pop_front();
+        }
+        if price == poc {
+            return;
+        }
+        let above = price > poc;
+        if self.poc_side.is_some_and(|side| side != above) {
+            self.poc_crosses.push_back(now);
+        }
+        self.poc_side = Some(above);
+    }
+
+    fn classify(&self, price: f64, value_area: ValueArea, now: u64) -> MarketStateReading {
+        let mut votes = Votes::default();
+
+        // Value migration - today's value away from the prior session's
+        let prior_value = self.prior.and_then(|prior| prior.value_area);
+        let value_area_overlap = prior_value.map(|prior| {
+            let overlap = (value_area.vah.min(prior.vah) - value_area.val.max(prior.val)).max(0.0);
+            let width = value_area.vah - value_area.val;
+            if width > 0.0 {
+                overlap / width
+            } else if (prior.val..=prior.vah).contains(&value_area.poc) {
+                1.0
+            } else {
+                0.0
+            }
+        });
+        if let (Some(overlap), Some(prior)) = (value_area_overlap, prior_value) {
+            if overlap >= BALANCE_OVERLAP {
+                votes.balance("value_overlap");
+            } else {
+                votes.direction(value_area.poc > prior.poc, "value_migration");
+            }
+        }
+
+        // Rotation - two-sided trade through the POC vs one-timeframing away from it
+        let rotations = self.poc_crosses.len();
+        let session_age = self.session_start.map_or(0, |start| now.saturating_sub(start));
+        if rotations >= BALANCE_ROTATIONS {
+            votes.balance("rotation");
+        } else if rotations <= 1 && session_age >= ROTATION_WINDOW_MS && price != value_area.poc {
+            votes.direction(price > value_area.poc, "one_sided");
+        }
+
+        // Range expansion vs the 5-minute ATR
+        let range_expansion = self.range_expansion();
+        if let Some((ratio, up)) = range_expansion {
+            if ratio >= EXPANSION_RATIO {
+                votes.direction(up, "range_expansion");
+            } else if ratio <= CONTRACTION_RATIO {
+                votes.balance("range_contraction");
+            }
+        }
+
+        // Acceptance beyond the prior session's range, or back inside its value
+        if let Some(prior) = self.prior {
+            if price > prior.high {
+                votes.direction(true, "above_prior_high");
+            } else if price < prior.low {
+                votes.direction(false, "below_prior_low");
+            } else if prior.value_area.is_some_and(|va| (va.val..=va.vah).contains(&price)) {
+                votes.balance("inside_prior_value");
+            }
+        }
+
+        // A recent impulse leg that price hasn't given back half of
+        if let Some(leg) = self.impulse.filter(|leg| now.saturating_sub(leg.end_time) <= IMPULSE_LOOKBACK_MS) {
+            let midpoint = (leg.start_price + leg.end_price) / 2.0;
+            let up = leg.end_price > leg.start_price;
+            if (up && price > midpoint) || (!up && price < midpoint) {
+                votes.direction(up, "impulse");
+            }
+        }
+
+        let state = votes.state();
+        MarketStateReading {
+            state,
+            previous: None,
+            value_area_overlap,
+            rotations: rotations as u32,
+            range_expansion: range_expansion.map(|(ratio, _)| ratio),
+            reasons: votes.reasons(state),
+        }
+    }
+
+    /// 30-minute range in ATRs, and whether it moved up - None until the ATR has its bars
+    fn range_expansion(&self) -> Option<(f64, bool)> {
+        if self.bars.len() < ATR_BARS {
+            return None;
+        }
+        let bar = self.bar?;
+
+        let mut prev_close = None;
+        let mut true_range = 0.0;
+        for b in &self.bars {
+            true_range += match prev_close {
+                Some(close) => b.high.max(close) - b.low.min(close),
+                None => b.high - b.low,
+            };
+            prev_close = Some(b.close);
+        }
+        let atr = true_range / self.bars.len() as f64;
+        if atr <= 0.0 {
+            return None;
+        }
+
+        let recent: Vec<Bar> = self
+            .bars
+            .iter()
+            .skip(self.bars.len() + 1 - EXPANSION_BARS)
+            .copied()
+            .chain(std::iter::once(bar))
+            .collect();
+        let high = recent.iter().map(|b| b.high).fold(f64::MIN, f64::max);
+        let low = recent.iter().map(|b| b.low).fold(f64::MAX, f64::min);
+        Some(((high - low) / atr, bar.close > recent[0].open))
+    }
+}
+
+/// One vote per feature
+#[derive(Default)]
+struct Votes {
+    bullish: Vec<&'static str>,
+    bearish: Vec<&'static str>,
+    balance: Vec<&'static str>,
+}
+
+impl Votes {
+    fn direction(&mut self, up: bool, reason: &'static str) {
+        if up {
+            self.bullish.push(reason);
+        } else {
+            self.bearish.push(reason);
+        }
+    }
+
+    fn balance(&mut self, reason: &'static str) {
+        self.balance.push(reason);
+    }
+
+    fn state(&self) -> MarketState {
+        let (bullish, bearish, balance) = (self.bullish.len(), self.bearish.len(), self.balance.len());
+        if bullish >= bearish + 2 && bullish > balance {
+            MarketState::BullishImbalance
+        } else if bearish >= bullish + 2 && bearish > balance {
+            MarketState::BearishImbalance
+        } else {
+            MarketState::Balance
+        }
+    }
+
+    fn reasons(&self, state: MarketState) -> Vec<&'static str> {
+        match state {
+            MarketState::Balance => self.balance.clone(),
+            MarketState::BullishImbalance => self.bullish.clone(),
+            MarketState::BearishImbalance => self.bearish.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: u64 = 60 * 1000;
+
+    fn classifier() -> MarketStateClassifier {
+        let mut classifier = MarketStateClassifier::new(&ProfileSettings::default());
+        classifier.set_prior_session(SessionReference {
+            high: 110.0,
+            low: 90.0,
+            value_area: Some(ValueArea { poc: 100.0, vah: 104.0, val: 96.0 }),
+        });
+        classifier
+    }
+
+    #[test]
+    fn test_rotation_inside_prior_value_is_balance() {
+        let mut classifier = classifier();
+        let mut readings = Vec::new();
+        for minute in 0..40 {
+            let price = if minute % 2 == 0 { 99.0 } else { 101.0 };
+            classifier.add_trade(100.0, 10, true, minute * MINUTE);
+            classifier.add_trade(price, 5, minute % 2 == 0, minute * MINUTE + 1);
+            readings.extend(classifier.update(price, minute * MINUTE + 1));
+        }
+
+        // Classified once, never left balance
+        assert_eq!(readings.len(), 1);
+        assert_eq!(readings[0].state, MarketState::Balance);
+        assert_eq!(readings[0].previous, None);
+        assert_eq!(readings[0].reasons, vec!["value_overlap", "inside_prior_value"]);
+        assert_eq!(readings[0].value_area_overlap, Some(1.0));
+    }
+
+    #[test]
+    fn test_trend_above_prior_high_is_bullish_imbalance() {
+        let mut classifier = classifier();
+        let mut readings = Vec::new();
+        // Balance inside prior value for an hour and a half, then a steady trend up
+        for minute in 0..150u64 {
+            let price = if minute < 90 { 100.0 + (minute % 2) as f64 } else { 100.0 + (minute - 89) as f64 };
+            classifier.add_trade(price, 10, true, minute * MINUTE);
+            readings.extend(classifier.update(price, minute * MINUTE));
+        }
+
+        // One-timeframing away from the POC with the range expanding flips it
+        // before price even clears the prior high
+        assert_eq!(readings.len(), 2);
+        let last = &readings[1];
+        assert_eq!(last.state, MarketState::BullishImbalance);
+        assert_eq!(last.previous, Some(MarketState::Balance));
+        assert_eq!(last.reasons, vec!["one_sided", "range_expansion"]);
+        assert_eq!(classifier.state(), Some(MarketState::BullishImbalance));
+    }
+
+    #[test]
+    fn test_one_window_flip_after_long_balance_is_not_reported() {
+        let mut classifier = classifier();
+        let mut readings = Vec::new();
+        let mut rotate = |classifier: &mut MarketStateClassifier, minutes: std::ops::Range<u64>| {
+            for minute in minutes {
+                let price = if minute % 2 == 0 { 99.0 } else { 101.0 };
+                classifier.add_trade(100.0, 10, true, minute * MINUTE + 2);
+                classifier.add_trade(price, 5, minute % 2 == 0, minute * MINUTE + 3);
+                readings.extend(classifier.update(price, minute * MINUTE + 3));
+            }
+        };
+
+        // An hour and a half of balance, then one window spikes above the prior high
+        // on a fresh impulse leg before price drops straight back into the rotation
+        rotate(&mut classifier, 0..90);
+        classifier.add_impulse(ImpulseReference {
+            start_time: 89 * MINUTE,
+            end_time: 90 * MINUTE,
+            start_price: 100.0,
+            end_price: 112.0,
+        });
+        classifier.add_trade(112.0, 1, true, 90 * MINUTE);
+        let spike = classifier.update(112.0, 90 * MINUTE + 1);
+        rotate(&mut classifier, 90..120);
+
+        assert_eq!(spike, None);
+        assert_eq!(readings.len(), 1);
+        assert_eq!(classifier.state(), Some(MarketState::Balance));
+    }
+
+    #[test]
+    fn test_state_filter_matches() {
+        assert!(MarketState::matches("imbalance", "bearish_imbalance"));
+        assert!(MarketState::matches("balance", "balance"));
+        assert!(!MarketState::matches("imbalance", "balance"));
+        assert!(!MarketState::matches("bullish_imbalance", "bearish_imbalance"));
+    }
+}
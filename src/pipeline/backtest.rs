@@ -4,11 +4,15 @@
 //! Computes statistics: Win Rate, Profit, R:R, Profit Factor, etc.
 
 use crate::bars::Bar;
-use crate::levels::DailyLevels;
+use crate::levels::{DailyLevels, INITIAL_BALANCE_MS, OPENING_RANGE_MS};
+use crate::profile::CompositeLevel;
 use crate::replay::CapturedSignal;
-use chrono::{DateTime, NaiveDate, Timelike, Utc};
+use chrono::{DateTime, NaiveDate, Timelike};
+use orderflow_bubbles::calendar::SessionCalendar;
+use orderflow_bubbles::instruments::InstrumentSpec;
+use orderflow_bubbles::market_state::MarketState;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 /// Strategy configuration parameters
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -28,7 +32,8 @@ pub struct StrategyConfig {
     /// Maximum hold time in seconds before forced exit
     pub max_hold_time_secs: u64,
 
-    /// Only trade at key levels (POC, VAH, VAL)
+    /// Only trade at key levels (value area, prior day, overnight, opening range,
+    /// initial balance and composite profile nodes)
     pub require_key_level: bool,
 
     /// Only take signals with strength >= this level
@@ -36,6 +41,11 @@ pub struct StrategyConfig {
 
     /// Time-of-day filter (e.g., only RTH)
     pub rth_only: bool,
+
+    /// Only take signals fired in these market states (balance, imbalance,
+    /// bullish_imbalance, bearish_imbalance) - empty = any state
+    #[serde(default)]
+    pub market_states: Vec<String>,
 }
 
 impl Default for StrategyConfig {
@@ -49,6 +59,7 @@ impl Default for StrategyConfig {
             require_key_level: false,
             min_strength: None,
             rth_only: true,
+            market_states: vec![],
         }
     }
 }
@@ -63,7 +74,7 @@ pub struct TradeResult {
     pub direction: String,  // "long" or "short"
     pub signal_type: String,
     pub pnl_points: f64,
-    pub pnl_ticks: i32,     // NQ: 1 point = 4 ticks, 1 tick = $5
+    pub pnl_ticks: i32,     // Points / tick size (NQ: 1 point = 4 ticks)
     pub exit_reason: String, // "stop_loss", "take_profit", "timeout", "signal_exit"
     pub max_favorable_excursion: f64,  // MFE - how much it went in your favor
     pub max_adverse_excursion: f64,    // MAE - how much it went against you
@@ -81,9 +92,9 @@ impl TradeResult {
         self.max_favorable_excursion / self.max_adverse_excursion
     }
 
-    /// Dollar P&L for NQ (1 tick = $5, 4 ticks = 1 point = $20)
-    pub fn pnl_dollars(&self) -> f64 {
-        self.pnl_points * 20.0
+    /// Dollar P&L for one contract (NQ point value = $20)
+    pub fn pnl_dollars(&self, point_value: f64) -> f64 {
+        self.pnl_points * point_value
     }
 }
 
@@ -124,13 +135,22 @@ pub struct BacktestResults {
 /// Backtester engine
 pub struct Backtester {
     config: StrategyConfig,
+    instrument: InstrumentSpec,
+    calendar: SessionCalendar,
     bars: Vec<Bar>,
     daily_levels: HashMap<NaiveDate, DailyLevels>,
+    // Composite profile level prices by the trading date they apply to
+    composite_levels: HashMap<NaiveDate, Vec<f64>>,
     price_index: HashMap<u64, usize>, // timestamp -> bar index for fast lookup
 }
 
 impl Backtester {
-    pub fn new(config: StrategyConfig, bars: Vec<Bar>, levels: Vec<DailyLevels>) -> Self {
+    pub fn new(
+        config: StrategyConfig,
+        instrument: InstrumentSpec,
+        bars: Vec<Bar>,
+        levels: Vec<DailyLevels>,
+    ) -> Self {
         let daily_levels: HashMap<_, _> = levels.into_iter()
             .map(|l| (l.date, l))
             .collect();
@@ -143,12 +163,23 @@ impl Backtester {
 
         Self {
             config,
+            calendar: SessionCalendar::new(&instrument.session),
+            instrument,
             bars,
             daily_levels,
+            composite_levels: HashMap::new(),
             price_index,
         }
     }
 
+    /// Also treat composite profile POC/VAH/VAL and HVN/LVNs as key levels
+    pub fn with_composite_levels(mut self, levels: &[CompositeLevel]) -> Self {
+        for level in levels {
+            self.composite_levels.entry(level.date).or_default().push(level.price);
+        }
+        self
+    }
+
     /// Get price at a specific timestamp (or nearest bar after)
     fn get_price_at(&self, timestamp_ms: u64) -> Option<f64> {
         // Find the bar at or after this timestamp
@@ -172,26 +203,56 @@ impl Backtester {
             .collect()
     }
 
-    /// Check if timestamp is during RTH (9:30 AM - 4:00 PM ET)
+    /// Check if timestamp is during the instrument's RTH (9:30 AM - 4:00 PM ET for NQ,
+    /// shortened on early-close days, none on holidays)
     fn is_rth(&self, timestamp_ms: u64) -> bool {
-        let ts = DateTime::from_timestamp_millis(timestamp_ms as i64)
-            .unwrap_or_else(|| Utc::now());
-        let hour = ts.time().hour();
-        // Approximate RTH in UTC: 14:30 - 21:00
-        hour >= 14 && hour < 21
+        self.calendar.is_rth(timestamp_ms)
     }
 
-    /// Check if price is near a key level
-    fn is_at_key_level(&self, price: f64, date: NaiveDate) -> bool {
-        if let Some(levels) = self.daily_levels.get(&date) {
-            let tolerance = 2.0; // Within 2 points
-            return (price - levels.poc).abs() <= tolerance
-                || (price - levels.vah).abs() <= tolerance
-                || (price - levels.val).abs() <= tolerance
-                || (price - levels.pdh).abs() <= tolerance
-                || (price - levels.pdl).abs() <= tolerance;
+    /// Check if price is near a key level. Overnight, opening range and
+    /// initial balance levels only count once they're complete at `timestamp`.
+    fn is_at_key_level(&self, price: f64, timestamp: u64) -> bool {
+        let date = self.calendar.trading_date(timestamp);
+        let Some(levels) = self.daily_levels.get(&date) else {
+            return false;
+        };
+
+        let mut key_levels = vec![
+            Some(levels.poc),
+            Some(levels.vah),
+            Some(levels.val),
+            levels.pdh,
+            levels.pdl,
+        ];
+        if let Some((rth_open, _)) = self.calendar.rth_bounds(date) {
+            if timestamp >= rth_open {
+                key_levels.extend([levels.onh, levels.onl, levels.on_poc]);
+            }
+            if timestamp >= rth_open + OPENING_RANGE_MS {
+                key_levels.extend([levels.or_high, levels.or_low]);
+            }
+            if timestamp >= rth_open + INITIAL_BALANCE_MS {
+                key_levels.extend([
+                    levels.ib_high,
+                    levels.ib_low,
+                    levels.ib_ext_up_1,
+                    levels.ib_ext_down_1,
+                    levels.ib_ext_up_2,
+                    levels.ib_ext_down_2,
+                ]);
+            }
+        }
+
+        // Composites only cover sessions before this date
+        if let Some(composite) = self.composite_levels.get(&date) {
+            key_levels.extend(composite.iter().copied().map(Some));
         }
-        false
+
+        let tolerance = self.instrument.thresholds.level_tolerance_points;
+        key_levels
+            .into_iter()
+            .flatten()
+            .any(|level| (price - level).abs() <= tolerance)
     }
 
     /// Check if signal passes the strategy filter
@@ -202,8 +263,21 @@ impl Backtester {
         }
 
         // Required signals filter
-        if !self.config.required_signals.is_empty() {
-            if !self.config.required_signals.contains(&signal.signal_type) {
+        if !self.config.required_signals.is_empty()
+            && !self.config.required_signals.contains(&signal.signal_type)
+        {
+            return false;
+        }
+
+        // Market state filter - signals fired before the first classification never match
+        if !self.config.market_states.is_empty() {
+            let in_state = signal.market_state.as_deref().is_some_and(|state| {
+                self.config
+                    .market_states
+                    .iter()
+                    .any(|filter| MarketState::matches(filter, state))
+            });
+            if !in_state {
                 return false;
             }
         }
@@ -234,13 +308,11 @@ impl Backtester {
         }
 
         // Key level filter
-        if self.config.require_key_level && signal.price > 0.0 {
-            let date = DateTime::from_timestamp_millis(signal.timestamp as i64)
-                .map(|dt| dt.date_naive())
-                .unwrap_or_else(|| NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
-            if !self.is_at_key_level(signal.price, date) {
-                return false;
-            }
+        if self.config.require_key_level
+            && signal.price > 0.0
+            && !self.is_at_key_level(signal.price, signal.timestamp)
+        {
+            return false;
         }
 
         true
@@ -344,7 +416,7 @@ impl Backtester {
             direction: if is_long { "long" } else { "short" }.to_string(),
             signal_type: signal.signal_type.clone(),
             pnl_points,
-            pnl_ticks: (pnl_points * 4.0).round() as i32,
+            pnl_ticks: self.instrument.points_to_ticks(pnl_points),
             exit_reason,
             max_favorable_excursion: max_favorable,
             max_adverse_excursion: max_adverse,
@@ -445,7 +517,7 @@ impl Backtester {
         };
 
         let total_pnl_points: f64 = trades.iter().map(|t| t.pnl_points).sum();
-        let total_pnl_dollars = total_pnl_points * 20.0;
+        let total_pnl_dollars: f64 = trades.iter().map(|t| t.pnl_dollars(self.instrument.point_value)).sum();
 
         // Calculate drawdown
         let mut peak = 0.0f64;
@@ -526,7 +598,7 @@ impl Backtester {
             profit_factor,
             avg_rr,
             max_drawdown_points: max_dd,
-            max_drawdown_dollars: max_dd * 20.0,
+            max_drawdown_dollars: self.instrument.points_to_dollars(max_dd),
             sharpe_ratio: sharpe,
             max_consecutive_losses: max_consec_losses,
             max_consecutive_wins: max_consec_wins,
@@ -548,6 +620,9 @@ pub fn print_results(results: &BacktestResults) {
     println!("  Take Profit:   {:.1} pts", results.config.take_profit_points);
     println!("  Max Hold Time: {} secs", results.config.max_hold_time_secs);
     println!("  RTH Only:      {}", results.config.rth_only);
+    if !results.config.market_states.is_empty() {
+        println!("  Market States: {}", results.config.market_states.join(", "));
+    }
     println!();
 
     println!("Trade Statistics:");
@@ -584,6 +659,36 @@ pub fn print_results(results: &BacktestResults) {
     println!("\n═══════════════════════════════════════════════════════════\n");
 }
 
+/// Print backtest results for several signal variants side by side
+pub fn print_comparison(results: &BTreeMap<&str, BacktestResults>) {
+    println!("\n═══════════════════════════════════════════════════════════");
+    println!("                 BACKTEST COMPARISON                        ");
+    println!("═══════════════════════════════════════════════════════════\n");
+
+    print!("{:<16}", "");
+    for name in results.keys() {
+        print!("{:>14}", name);
+    }
+    println!();
+
+    let row = |label: &str, value: fn(&BacktestResults) -> String| {
+        print!("{:<16}", label);
+        for result in results.values() {
+            print!("{:>14}", value(result));
+        }
+        println!();
+    };
+    row("Total Trades", |r| r.total_trades.to_string());
+    row("Win Rate", |r| format!("{:.1}%", r.win_rate));
+    row("Total P&L", |r| format!("{:.1} pts", r.total_pnl_points));
+    row("Total P&L $", |r| format!("${:.2}", r.total_pnl_dollars));
+    row("Profit Factor", |r| format!("{:.2}", r.profit_factor));
+    row("Max Drawdown", |r| format!("{:.1} pts", r.max_drawdown_points));
+    row("Sharpe Ratio", |r| format!("{:.2}", r.sharpe_ratio));
+
+    println!("\n═══════════════════════════════════════════════════════════\n");
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -612,6 +717,6 @@ mod tests {
         };
 
         assert!(trade.is_winner());
-        assert_eq!(trade.pnl_dollars(), 200.0);
+        assert_eq!(trade.pnl_dollars(InstrumentSpec::default().point_value), 200.0);
     }
 }
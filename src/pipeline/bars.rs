@@ -1,5 +1,5 @@
 use crate::trades::{Side, Trade};
-use chrono::{DateTime, Duration, Timelike, Utc};
+use chrono::{DateTime, Timelike, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 
@@ -19,20 +19,6 @@ pub struct Bar {
     pub symbol: String,
 }
 
-impl Bar {
-    pub fn is_bullish(&self) -> bool {
-        self.close > self.open
-    }
-
-    pub fn body_size(&self) -> f64 {
-        (self.close - self.open).abs()
-    }
-
-    pub fn range(&self) -> f64 {
-        self.high - self.low
-    }
-}
-
 /// Aggregate trades to 1-second bars
 pub fn aggregate_to_1s_bars(trades: &[Trade]) -> Vec<Bar> {
     if trades.is_empty() {
@@ -113,7 +99,7 @@ impl BarBuilder {
     }
 
     fn add_trade(&mut self, trade: &Trade) {
-        if self.open.is_none() || self.first_ts.map_or(true, |ts| trade.ts_event < ts) {
+        if self.open.is_none() || self.first_ts.is_none_or(|ts| trade.ts_event < ts) {
             self.open = Some(trade.price);
             self.first_ts = Some(trade.ts_event);
         }
@@ -131,7 +117,7 @@ impl BarBuilder {
     }
 
     fn add_bar(&mut self, bar: &Bar) {
-        if self.open.is_none() || self.first_ts.map_or(true, |ts| bar.timestamp < ts) {
+        if self.open.is_none() || self.first_ts.is_none_or(|ts| bar.timestamp < ts) {
             self.open = Some(bar.open);
             self.first_ts = Some(bar.timestamp);
         }
@@ -168,6 +154,7 @@ impl BarBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::Duration;
 
     #[test]
     fn test_bar_aggregation() {
@@ -1,91 +1,187 @@
 use crate::bars::Bar;
-use chrono::{DateTime, NaiveDate, Timelike, Utc};
+use crate::profile::{DailyProfiles, ProfileSegment};
+use chrono::NaiveDate;
+use orderflow_bubbles::calendar::SessionCalendar;
+use orderflow_bubbles::instruments::InstrumentSpec;
+use orderflow_bubbles::vwap::{SessionVwap, VwapAnchor, VwapBands};
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::BTreeMap;
 
-/// Daily reference levels for a trading session
+/// Opening range: first 5 minutes of RTH
+pub const OPENING_RANGE_MS: u64 = 5 * 60 * 1000;
+
+/// Initial balance: first 60 minutes of RTH
+pub const INITIAL_BALANCE_MS: u64 = 60 * 60 * 1000;
+
+/// Daily reference levels for a CME trading date (Globex session rolling at 18:00 ET)
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DailyLevels {
     pub date: NaiveDate,
     pub symbol: String,
 
-    // Prior day levels (for next day reference)
-    pub pdh: f64, // Prior Day High
-    pub pdl: f64, // Prior Day Low
-    pub pdc: f64, // Prior Day Close
+    // Prior trading date's RTH levels (None when that day isn't in the data)
+    pub pdh: Option<f64>, // Prior Day High
+    pub pdl: Option<f64>, // Prior Day Low
+    pub pdc: Option<f64>, // Prior Day Close
 
-    // Volume Profile levels (computed from current day)
+    // Volume Profile levels (tick-accurate, from the current day's RTH trades)
     pub poc: f64, // Point of Control - price with highest volume
-    pub vah: f64, // Value Area High - upper bound of 70% volume
-    pub val: f64, // Value Area Low - lower bound of 70% volume
+    pub vah: f64, // Value Area High - upper bound of the value area (70% of volume by default)
+    pub val: f64, // Value Area Low - lower bound of the value area
 
-    // Session stats
+    // RTH session stats (the whole trading date if it has no RTH bars)
     pub session_high: f64,
     pub session_low: f64,
     pub session_open: f64,
     pub session_close: f64,
+    // Volume for the whole trading date (ETH + RTH)
     pub total_volume: u64,
-}
 
-/// Trading session boundaries (CME NQ futures)
-/// Regular Trading Hours: 9:30 AM - 4:00 PM ET (14:30 - 21:00 UTC)
-/// Full session: 6:00 PM - 5:00 PM ET next day
-const RTH_START_HOUR: u32 = 14; // 9:30 AM ET = 14:30 UTC
-const RTH_START_MIN: u32 = 30;
-const RTH_END_HOUR: u32 = 21; // 4:00 PM ET = 21:00 UTC
-
-/// Price bucket size for volume profile (NQ tick = 0.25)
-const PRICE_BUCKET_SIZE: f64 = 1.0; // 1 point buckets for cleaner profile
+    // Overnight (Globex session before the RTH open) - None without overnight bars
+    pub onh: Option<f64>,    // Overnight High
+    pub onl: Option<f64>,    // Overnight Low
+    pub on_poc: Option<f64>, // Overnight Point of Control
+
+    // Initial balance (first 60 minutes of RTH) and its 1x/2x range extensions
+    pub ib_high: Option<f64>,
+    pub ib_low: Option<f64>,
+    pub ib_ext_up_1: Option<f64>,
+    pub ib_ext_down_1: Option<f64>,
+    pub ib_ext_up_2: Option<f64>,
+    pub ib_ext_down_2: Option<f64>,
+
+    // Opening range (first 5 minutes of RTH)
+    pub or_high: Option<f64>,
+    pub or_low: Option<f64>,
+
+    // Session VWAP and standard-deviation bands at the RTH close
+    pub vwap: f64,
+    pub vwap_std_dev: f64,
+    pub vwap_upper_1: f64,
+    pub vwap_lower_1: f64,
+    pub vwap_upper_2: f64,
+    pub vwap_lower_2: f64,
+    pub vwap_upper_3: f64,
+    pub vwap_lower_3: f64,
+}
 
-pub fn compute_daily_levels(bars: &[Bar]) -> Vec<DailyLevels> {
+/// Compute daily levels per CME trading date - stats from the bars, POC/VAH/VAL
+/// from the trade-built `profiles`, session VWAP reset at `vwap_anchor`
+pub fn compute_daily_levels(
+    bars: &[Bar],
+    profiles: &DailyProfiles,
+    instrument: &InstrumentSpec,
+    vwap_anchor: VwapAnchor,
+) -> Vec<DailyLevels> {
     if bars.is_empty() {
         return Vec::new();
     }
 
-    // Group bars by trading date (use RTH session date)
-    let mut daily_bars: BTreeMap<NaiveDate, Vec<&Bar>> = BTreeMap::new();
+    let calendar = SessionCalendar::new(&instrument.session);
+
+    // One VWAP across all bars so a Globex session carries over the date boundary
+    let mut vwap = SessionVwap::new(vwap_anchor, &instrument.session);
 
+    // Group bars by trading date, flagging RTH bars
+    let mut daily_bars: BTreeMap<NaiveDate, Vec<(&Bar, bool)>> = BTreeMap::new();
     for bar in bars {
-        // Use the bar's date as the trading date
-        // For proper session handling, we'd need to map overnight sessions
-        let date = bar.timestamp.date_naive();
-        daily_bars.entry(date).or_default().push(bar);
+        let ts = bar.timestamp.timestamp_millis() as u64;
+        daily_bars
+            .entry(calendar.trading_date(ts))
+            .or_default()
+            .push((bar, calendar.is_rth(ts)));
     }
 
-    let mut levels_list = Vec::new();
-    let dates: Vec<_> = daily_bars.keys().cloned().collect();
+    let mut levels_list: Vec<DailyLevels> = Vec::new();
 
-    for (i, date) in dates.iter().enumerate() {
-        let bars = daily_bars.get(date).unwrap();
-        if bars.is_empty() {
-            continue;
-        }
+    for (date, day_bars) in &daily_bars {
+        let symbol = day_bars[0].0.symbol.clone();
 
-        let symbol = bars[0].symbol.clone();
+        // RTH bars drive the session stats
+        let rth_bars: Vec<&Bar> = day_bars.iter().filter(|(_, rth)| *rth).map(|(b, _)| *b).collect();
+        let session_bars: Vec<&Bar> = if rth_bars.is_empty() {
+            day_bars.iter().map(|(b, _)| *b).collect()
+        } else {
+            rth_bars
+        };
 
-        // Compute current day's session stats
-        let session_high = bars.iter().map(|b| b.high).fold(f64::MIN, f64::max);
-        let session_low = bars.iter().map(|b| b.low).fold(f64::MAX, f64::min);
-        let session_open = bars.first().map(|b| b.open).unwrap_or(0.0);
-        let session_close = bars.last().map(|b| b.close).unwrap_or(0.0);
-        let total_volume: u64 = bars.iter().map(|b| b.volume).sum();
+        let session_high = session_bars.iter().map(|b| b.high).fold(f64::MIN, f64::max);
+        let session_low = session_bars.iter().map(|b| b.low).fold(f64::MAX, f64::min);
+        let session_open = session_bars.first().map(|b| b.open).unwrap_or(0.0);
+        let session_close = session_bars.last().map(|b| b.close).unwrap_or(0.0);
+        let total_volume: u64 = day_bars.iter().map(|(b, _)| b.volume).sum();
+
+        // Prior day levels - only from the previous trading date, never a stand-in
+        let prior_date = calendar.previous_trading_date(*date);
+        let prior = levels_list.iter().rev().find(|l| l.date == prior_date);
+        let (pdh, pdl, pdc) = (
+            prior.map(|l| l.session_high),
+            prior.map(|l| l.session_low),
+            prior.map(|l| l.session_close),
+        );
+
+        // Volume profile levels from the day's trades
+        let day_profile = profiles.get(*date);
+        let (poc, vah, val) = day_profile
+            .and_then(|day| day.session_profile().value_area())
+            .map(|va| (va.poc, va.vah, va.val))
+            .unwrap_or((session_close, session_close, session_close));
+        let on_poc = day_profile
+            .and_then(|day| day.segment(ProfileSegment::Overnight))
+            .and_then(|overnight| overnight.poc());
+
+        // Overnight, opening range and initial balance are measured from the RTH open
+        let rth_open = calendar.rth_bounds(*date).map(|(open, _)| open);
+        let bars_between = |from: u64, to: u64, rth: bool| -> Vec<&Bar> {
+            day_bars
+                .iter()
+                .filter(|(b, is_rth)| {
+                    let ts = b.timestamp.timestamp_millis() as u64;
+                    *is_rth == rth && ts >= from && ts < to
+                })
+                .map(|(b, _)| *b)
+                .collect()
+        };
+        let (overnight, opening_range, initial_balance) = match rth_open {
+            Some(open) => (
+                bars_between(0, open, false),
+                bars_between(open, open + OPENING_RANGE_MS, true),
+                bars_between(open, open + INITIAL_BALANCE_MS, true),
+            ),
+            None => Default::default(),
+        };
 
-        // Get prior day levels (from previous day in our data)
-        let (pdh, pdl, pdc) = if i > 0 {
-            let prev_date = &dates[i - 1];
-            let prev_bars = daily_bars.get(prev_date).unwrap();
+        let (onh, onl) = high_low(&overnight);
+        let (or_high, or_low) = high_low(&opening_range);
+        let (ib_high, ib_low) = high_low(&initial_balance);
+        let ib_range = ib_high.zip(ib_low).map(|(high, low)| high - low);
+        let ib_ext = |n: f64| {
             (
-                prev_bars.iter().map(|b| b.high).fold(f64::MIN, f64::max),
-                prev_bars.iter().map(|b| b.low).fold(f64::MAX, f64::min),
-                prev_bars.last().map(|b| b.close).unwrap_or(0.0),
+                ib_high.zip(ib_range).map(|(high, range)| high + n * range),
+                ib_low.zip(ib_range).map(|(low, range)| low - n * range),
             )
-        } else {
-            // First day in dataset - use current day's open as reference
-            (session_high, session_low, session_open)
         };
-
-        // Compute volume profile
-        let (poc, vah, val) = compute_volume_profile(bars);
+        let (ib_ext_up_1, ib_ext_down_1) = ib_ext(1.0);
+        let (ib_ext_up_2, ib_ext_down_2) = ib_ext(2.0);
+
+        // End-of-day VWAP: bands after the last RTH bar (last bar if the day has no RTH)
+        let mut close_bands = None;
+        let mut last_bands = None;
+        for (bar, rth) in day_bars {
+            let ts = bar.timestamp.timestamp_millis() as u64;
+            let typical_price = (bar.high + bar.low + bar.close) / 3.0;
+            vwap.add(typical_price, bar.volume, ts);
+            last_bands = vwap.bands();
+            if *rth {
+                close_bands = last_bands;
+            }
+        }
+        let bands = close_bands.or(last_bands).unwrap_or(VwapBands {
+            vwap: session_close,
+            std_dev: 0.0,
+            anchor_time: 0,
+            volume: 0,
+        });
 
         levels_list.push(DailyLevels {
             date: *date,
@@ -101,119 +197,142 @@ pub fn compute_daily_levels(bars: &[Bar]) -> Vec<DailyLevels> {
             session_open,
             session_close,
             total_volume,
+            onh,
+            onl,
+            on_poc,
+            ib_high,
+            ib_low,
+            ib_ext_up_1,
+            ib_ext_down_1,
+            ib_ext_up_2,
+            ib_ext_down_2,
+            or_high,
+            or_low,
+            vwap: bands.vwap,
+            vwap_std_dev: bands.std_dev,
+            vwap_upper_1: bands.upper(1),
+            vwap_lower_1: bands.lower(1),
+            vwap_upper_2: bands.upper(2),
+            vwap_lower_2: bands.lower(2),
+            vwap_upper_3: bands.upper(3),
+            vwap_lower_3: bands.lower(3),
         });
     }
 
     levels_list
 }
 
-/// Build volume profile and compute POC, VAH, VAL
-fn compute_volume_profile(bars: &[&Bar]) -> (f64, f64, f64) {
+/// High and low of a set of bars (None if empty)
+fn high_low(bars: &[&Bar]) -> (Option<f64>, Option<f64>) {
     if bars.is_empty() {
-        return (0.0, 0.0, 0.0);
+        return (None, None);
     }
+    (
+        Some(bars.iter().map(|b| b.high).fold(f64::MIN, f64::max)),
+        Some(bars.iter().map(|b| b.low).fold(f64::MAX, f64::min)),
+    )
+}
 
-    // Build volume at price histogram
-    let mut volume_at_price: HashMap<i64, u64> = HashMap::new();
-
-    for bar in bars {
-        // Distribute bar volume across the bar's range
-        // For simplicity, put all volume at VWAP-ish price (midpoint)
-        let bar_mid = (bar.high + bar.low) / 2.0;
-        let bucket = price_to_bucket(bar_mid);
-        *volume_at_price.entry(bucket).or_insert(0) += bar.volume;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bars::aggregate_to_1s_bars;
+    use crate::trades::{Side, Trade};
+
+    /// Levels from (unix secs, price, size) buy trades, through bars and profiles like the pipeline
+    fn levels_from_trades(trades: &[(i64, f64, u64)], vwap_anchor: VwapAnchor) -> Vec<DailyLevels> {
+        let trades: Vec<Trade> = trades
+            .iter()
+            .map(|&(secs, price, size)| Trade {
+                ts_event: chrono::DateTime::from_timestamp(secs, 0).unwrap(),
+                price,
+                size,
+                side: Side::Buy,
+                symbol: "NQH6".to_string(),
+            })
+            .collect();
+
+        let instrument = InstrumentSpec::default();
+        let mut profiles = DailyProfiles::new(&instrument);
+        profiles.add_trades(&trades);
+        compute_daily_levels(&aggregate_to_1s_bars(&trades), &profiles, &instrument, vwap_anchor)
     }
 
-    if volume_at_price.is_empty() {
-        let price = bars[0].close;
-        return (price, price, price);
+    #[test]
+    fn test_end_of_day_vwap() {
+        // 2025-12-02 09:29:59 ET (pre-market), 10:00 ET and 15:59:59 ET (RTH), 17:00 ET (after close)
+        let trades = [
+            (1_764_685_799, 90.0, 10),
+            (1_764_687_600, 100.0, 10),
+            (1_764_709_199, 110.0, 10),
+            (1_764_712_800, 200.0, 10),
+        ];
+
+        let levels = levels_from_trades(&trades, VwapAnchor::Rth);
+        assert_eq!(levels.len(), 1);
+        // RTH VWAP at the close ignores the pre-market and post-close bars
+        assert_eq!(levels[0].vwap, 105.0);
+        assert_eq!(levels[0].vwap_std_dev, 5.0);
+        assert_eq!(levels[0].vwap_upper_2, 115.0);
+
+        let levels = levels_from_trades(&trades, VwapAnchor::Globex);
+        assert_eq!(levels[0].vwap, 100.0);
     }
 
-    // Find POC (bucket with max volume)
-    let (poc_bucket, _) = volume_at_price
-        .iter()
-        .max_by_key(|(_, vol)| *vol)
-        .unwrap();
-    let poc = bucket_to_price(*poc_bucket);
-
-    // Compute Value Area (70% of total volume)
-    let total_volume: u64 = volume_at_price.values().sum();
-    let target_volume = (total_volume as f64 * 0.70) as u64;
-
-    // Sort buckets by price
-    let mut sorted_buckets: Vec<_> = volume_at_price.iter().collect();
-    sorted_buckets.sort_by_key(|(bucket, _)| *bucket);
-
-    // Expand from POC to find value area
-    let poc_idx = sorted_buckets
-        .iter()
-        .position(|(b, _)| *b == poc_bucket)
-        .unwrap_or(0);
-
-    let mut val_idx = poc_idx;
-    let mut vah_idx = poc_idx;
-    let mut accumulated_volume = *volume_at_price.get(poc_bucket).unwrap_or(&0);
-
-    while accumulated_volume < target_volume {
-        let can_go_lower = val_idx > 0;
-        let can_go_higher = vah_idx < sorted_buckets.len() - 1;
-
-        if !can_go_lower && !can_go_higher {
-            break;
-        }
-
-        let lower_vol = if can_go_lower {
-            *sorted_buckets[val_idx - 1].1
-        } else {
-            0
-        };
-
-        let upper_vol = if can_go_higher {
-            *sorted_buckets[vah_idx + 1].1
-        } else {
-            0
-        };
-
-        if lower_vol >= upper_vol && can_go_lower {
-            val_idx -= 1;
-            accumulated_volume += lower_vol;
-        } else if can_go_higher {
-            vah_idx += 1;
-            accumulated_volume += upper_vol;
-        } else if can_go_lower {
-            val_idx -= 1;
-            accumulated_volume += lower_vol;
-        }
+    #[test]
+    fn test_prior_day_across_weekend() {
+        // Friday 2025-12-05 10:00 ET, Sunday 18:30 ET (Globex open) and Monday 10:00 ET
+        let trades = [(1_764_946_800, 100.0, 10), (1_765_150_200, 90.0, 10), (1_765_206_000, 95.0, 10)];
+
+        let levels = levels_from_trades(&trades, VwapAnchor::Rth);
+        assert_eq!(levels.len(), 2);
+        assert_eq!(levels[0].pdh, None);
+
+        // Sunday's open belongs to Monday: counted in volume, not in the RTH stats
+        let monday = &levels[1];
+        assert_eq!(monday.date, NaiveDate::from_ymd_opt(2025, 12, 8).unwrap());
+        assert_eq!(monday.total_volume, 20);
+        assert_eq!(monday.session_low, 95.0);
+        assert_eq!(monday.pdh, Some(100.0));
+        assert_eq!(monday.pdc, Some(100.0));
     }
 
-    let val = bucket_to_price(*sorted_buckets[val_idx].0);
-    let vah = bucket_to_price(*sorted_buckets[vah_idx].0);
-
-    (poc, vah, val)
-}
-
-fn price_to_bucket(price: f64) -> i64 {
-    (price / PRICE_BUCKET_SIZE).round() as i64
-}
-
-fn bucket_to_price(bucket: i64) -> f64 {
-    bucket as f64 * PRICE_BUCKET_SIZE
-}
-
-/// Check if a price is within a tolerance of a level
-pub fn is_near_level(price: f64, level: f64, tolerance: f64) -> bool {
-    (price - level).abs() <= tolerance
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use chrono::Duration;
+    #[test]
+    fn test_overnight_and_initial_balance() {
+        // 2025-12-02: 20:00 ET and 03:00 ET overnight, then RTH from the 09:30 ET open
+        let rth_open = 1_764_685_800;
+        let trades = [
+            (1_764_637_200, 90.0, 10),
+            (1_764_662_400, 120.0, 20),
+            (rth_open, 100.0, 10),
+            (rth_open + 4 * 60, 104.0, 10),
+            (rth_open + 30 * 60, 96.0, 10),
+            (rth_open + 61 * 60, 130.0, 10),
+        ];
+
+        let levels = levels_from_trades(&trades, VwapAnchor::Rth);
+        assert_eq!(levels.len(), 1);
+        let day = &levels[0];
+        assert_eq!((day.onh, day.onl, day.on_poc), (Some(120.0), Some(90.0), Some(120.0)));
+        assert_eq!((day.or_high, day.or_low), (Some(104.0), Some(100.0)));
+        assert_eq!((day.ib_high, day.ib_low), (Some(104.0), Some(96.0)));
+        assert_eq!((day.ib_ext_up_1, day.ib_ext_down_1), (Some(112.0), Some(88.0)));
+        assert_eq!((day.ib_ext_up_2, day.ib_ext_down_2), (Some(120.0), Some(80.0)));
+        assert_eq!(day.session_high, 130.0);
+    }
 
     #[test]
-    fn test_volume_profile() {
-        let ts = Utc::now();
-        let bars: Vec<&Bar> = vec![];
-        // Would need actual bar data for meaningful test
+    fn test_tick_accurate_value_area() {
+        // RTH trades within one point - a 1-point bucket would put POC, VAH and VAL at 21500
+        let rth_open = 1_764_685_800;
+        let trades = [
+            (rth_open, 21_500.25, 30),
+            (rth_open + 1, 21_500.5, 20),
+            (rth_open + 2, 21_500.0, 5),
+            (rth_open + 3, 21_499.75, 5),
+        ];
+
+        let levels = levels_from_trades(&trades, VwapAnchor::Rth);
+        assert_eq!((levels[0].poc, levels[0].vah, levels[0].val), (21_500.25, 21_500.5, 21_500.25));
     }
 }
@@ -1,6 +1,7 @@
 mod trades;
 mod bars;
 mod levels;
+mod profile;
 mod impulse;
 mod lvn;
 mod supabase;
@@ -9,6 +10,16 @@ mod backtest;
 
 use anyhow::Result;
 use clap::{Parser, Subcommand};
+use orderflow_bubbles::divergence::DivergenceSettings;
+use orderflow_bubbles::footprint::{FootprintBuilder, FootprintSettings, FootprintTimeframe};
+use orderflow_bubbles::imbalance::{StackedImbalanceAlgorithm, StackedImbalanceSettings};
+use orderflow_bubbles::instruments::InstrumentRegistry;
+use orderflow_bubbles::large_trade::LargeTradeSettings;
+use orderflow_bubbles::sqlite::SqliteStore;
+use orderflow_bubbles::store::SignalStore;
+use orderflow_bubbles::trade_files::MergedTrades;
+use orderflow_bubbles::vwap::VwapAnchor;
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use tracing::{info, Level};
 use tracing_subscriber::FmtSubscriber;
@@ -23,13 +34,111 @@ struct Args {
     /// Print verbose output
     #[arg(short, long, global = true)]
     verbose: bool,
+
+    /// Instrument config (JSON) - defaults to the built-in config/instruments.json
+    #[arg(long, global = true, env = "INSTRUMENTS_CONFIG")]
+    instruments: Option<PathBuf>,
+
+    /// Session anchor for the end-of-day VWAP in daily levels (rth or globex)
+    #[arg(long, global = true, value_enum, default_value = "rth")]
+    vwap_anchor: VwapAnchor,
+}
+
+/// Stacked imbalance detector used when replaying trades for signals
+#[derive(clap::Args, Debug)]
+struct StackedImbalanceArgs {
+    /// Stacked imbalance detector: session (same-price, session profile) or diagonal (per footprint bar)
+    #[arg(long, value_enum, default_value = "session")]
+    stacked_imbalance: StackedImbalanceAlgorithm,
+
+    /// Diagonal stacked imbalance: volume ratio per tick (3.0 = 300%)
+    #[arg(long, default_value = "3.0")]
+    stacked_imbalance_ratio: f64,
+
+    /// Diagonal stacked imbalance: min volume on the imbalanced side of a tick
+    #[arg(long, default_value = "10")]
+    stacked_imbalance_min_volume: u64,
+
+    /// Diagonal stacked imbalance: consecutive imbalanced ticks in a stack
+    #[arg(long, default_value = "3")]
+    stacked_imbalance_levels: u32,
+
+    /// Footprint bar timeframe the diagonal detector stacks within: 30s, 1m, 500t, 10r
+    #[arg(long, default_value = "1m")]
+    footprint_timeframe: FootprintTimeframe,
+}
+
+impl StackedImbalanceArgs {
+    fn footprint(&self) -> FootprintSettings {
+        FootprintSettings {
+            timeframe: self.footprint_timeframe,
+            imbalance_ratio: self.stacked_imbalance_ratio,
+        }
+    }
+
+    fn settings(&self, algorithm: StackedImbalanceAlgorithm) -> StackedImbalanceSettings {
+        StackedImbalanceSettings {
+            algorithm,
+            ratio: self.stacked_imbalance_ratio,
+            min_volume: self.stacked_imbalance_min_volume,
+            min_levels: self.stacked_imbalance_levels,
+        }
+    }
+}
+
+/// CVD/price divergence detector used when replaying trades for signals
+#[derive(clap::Args, Debug)]
+struct DivergenceArgs {
+    /// Divergence: bar length (seconds) price and CVD swings are found on
+    #[arg(long, default_value = "15", value_parser = clap::value_parser!(u64).range(1..))]
+    divergence_bar_secs: u64,
+
+    /// Divergence: bars on each side a swing high/low has to beat
+    #[arg(long, default_value = "3", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    divergence_swing_bars: usize,
+
+    /// Divergence: max bars between the two swings compared
+    #[arg(long, default_value = "40")]
+    divergence_lookback_bars: usize,
+}
+
+impl DivergenceArgs {
+    fn settings(&self) -> DivergenceSettings {
+        DivergenceSettings {
+            bar_secs: self.divergence_bar_secs,
+            swing_bars: self.divergence_swing_bars,
+            lookback_bars: self.divergence_lookback_bars,
+        }
+    }
+}
+
+/// Large trade tiers and the sweep threshold used when replaying trades for signals
+#[derive(clap::Args, Debug)]
+struct LargeTradeArgs {
+    /// Large trade tiers: min contracts of each tier (comma-separated)
+    #[arg(long, value_delimiter = ',', default_value = "10,50,100,200")]
+    large_trade_tiers: Vec<u32>,
+
+    /// Lowest large trade tier (1-based) recorded as a signal
+    #[arg(long, default_value = "3")]
+    large_trade_signal_tier: u8,
+
+    /// An aggressor order is a sweep when it takes more than this many price levels
+    #[arg(long, default_value = "2")]
+    sweep_levels: u32,
+}
+
+impl LargeTradeArgs {
+    fn settings(&self) -> LargeTradeSettings {
+        LargeTradeSettings::new(self.large_trade_tiers.clone(), self.large_trade_signal_tier)
+    }
 }
 
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Process trade data and export to Parquet/Supabase
     Process {
-        /// Path to data directory containing .zst files
+        /// Path to data directory containing .csv.zst or .dbn.zst files
         #[arg(short, long, default_value = "data/NQ_11_23_2025-12_23_2025")]
         data_dir: PathBuf,
 
@@ -44,11 +153,23 @@ enum Commands {
         /// Skip Supabase upload (local processing only)
         #[arg(long)]
         no_upload: bool,
+
+        /// Also write 1s bars to a SQLite store (for `--db-replay --store sqlite`)
+        #[arg(long)]
+        sqlite: Option<PathBuf>,
+
+        /// Footprint bar timeframe: 30s, 1m, 5m (time), 500t (trades) or 10r (range in ticks)
+        #[arg(long, default_value = "1m")]
+        footprint_timeframe: FootprintTimeframe,
+
+        /// Diagonal volume ratio that marks a footprint imbalance (3.0 = 300%)
+        #[arg(long, default_value = "3.0")]
+        footprint_imbalance_ratio: f64,
     },
 
     /// Replay historical trades through production ProcessingState
     Replay {
-        /// Path to data directory containing .zst files
+        /// Path to data directory containing .csv.zst or .dbn.zst files
         #[arg(short, long, default_value = "data/NQ_11_23_2025-12_23_2025")]
         data_dir: PathBuf,
 
@@ -59,11 +180,20 @@ enum Commands {
         /// Process only a specific date (YYYYMMDD format)
         #[arg(short = 'D', long)]
         date: Option<String>,
+
+        #[command(flatten)]
+        imbalance: StackedImbalanceArgs,
+
+        #[command(flatten)]
+        divergence: DivergenceArgs,
+
+        #[command(flatten)]
+        large_trades: LargeTradeArgs,
     },
 
     /// Backtest trading strategy on historical signals
     Backtest {
-        /// Path to data directory containing .zst files
+        /// Path to data directory containing .csv.zst or .dbn.zst files
         #[arg(short, long, default_value = "data/NQ_11_23_2025-12_23_2025")]
         data_dir: PathBuf,
 
@@ -95,9 +225,30 @@ enum Commands {
         #[arg(long, default_value = "2")]
         min_confluence: u8,
 
-        /// Only trade at key levels (POC, VAH, VAL, PDH, PDL)
+        /// Only trade at key levels (POC, VAH, VAL, PDH, PDL, ONH, ONL, opening range, IB, composite nodes)
         #[arg(long)]
         key_levels_only: bool,
+
+        /// Only trade signals fired in these market states (comma-separated)
+        #[arg(
+            long,
+            value_delimiter = ',',
+            value_parser = ["balance", "imbalance", "bullish_imbalance", "bearish_imbalance"]
+        )]
+        market_state: Vec<String>,
+
+        #[command(flatten)]
+        imbalance: StackedImbalanceArgs,
+
+        #[command(flatten)]
+        divergence: DivergenceArgs,
+
+        #[command(flatten)]
+        large_trades: LargeTradeArgs,
+
+        /// Backtest stacked imbalance signals from both detectors side by side
+        #[arg(long)]
+        compare_stacked_imbalance: bool,
     },
 }
 
@@ -112,22 +263,48 @@ async fn main() -> Result<()> {
         .finish();
     tracing::subscriber::set_global_default(subscriber)?;
 
+    // Contract specs (tick size, point value, thresholds) by root symbol
+    let instruments = InstrumentRegistry::load(args.instruments.as_deref())?;
+
     match args.command {
-        Commands::Process { data_dir, output_dir, date, no_upload } => {
-            run_process(data_dir, output_dir, date, no_upload).await?;
+        Commands::Process {
+            data_dir, output_dir, date, no_upload, sqlite,
+            footprint_timeframe, footprint_imbalance_ratio,
+        } => {
+            let footprint = FootprintSettings {
+                timeframe: footprint_timeframe,
+                imbalance_ratio: footprint_imbalance_ratio,
+            };
+            run_process(&instruments, args.vwap_anchor, footprint, data_dir, output_dir, date, no_upload, sqlite)
+                .await?;
         }
-        Commands::Replay { data_dir, output_dir, date } => {
-            run_replay(data_dir, output_dir, date)?;
+        Commands::Replay { data_dir, output_dir, date, imbalance, divergence, large_trades } => {
+            run_replay(
+                &instruments,
+                &imbalance,
+                divergence.settings(),
+                large_trades.settings(),
+                large_trades.sweep_levels,
+                data_dir, output_dir, date,
+            )?;
         }
         Commands::Backtest {
             data_dir, output_dir, date,
             stop_loss, take_profit, max_hold,
-            rth_only, min_confluence, key_levels_only,
+            rth_only, min_confluence, key_levels_only, market_state,
+            imbalance, divergence, large_trades, compare_stacked_imbalance,
         } => {
             run_backtest(
+                &instruments,
+                args.vwap_anchor,
+                &imbalance,
+                divergence.settings(),
+                large_trades.settings(),
+                large_trades.sweep_levels,
+                compare_stacked_imbalance,
                 data_dir, output_dir, date,
                 stop_loss, take_profit, max_hold,
-                rth_only, min_confluence, key_levels_only,
+                rth_only, min_confluence, key_levels_only, market_state,
             )?;
         }
     }
@@ -135,11 +312,16 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn run_process(
+    instruments: &InstrumentRegistry,
+    vwap_anchor: VwapAnchor,
+    footprint: FootprintSettings,
     data_dir: PathBuf,
     output_dir: PathBuf,
     date: Option<String>,
     no_upload: bool,
+    sqlite: Option<PathBuf>,
 ) -> Result<()> {
     info!("=== PROCESS MODE ===");
     info!("Data directory: {:?}", data_dir);
@@ -158,9 +340,11 @@ async fn run_process(
 
     // Collect all data
     let mut all_bars = Vec::new();
-    let mut all_daily_levels = Vec::new();
     let mut all_impulse_legs = Vec::new();
     let mut all_lvn_levels = Vec::new();
+    let mut profiles = None;
+    // Footprint bars per symbol - time bars can span the UTC-day file boundary
+    let mut footprint_builders: BTreeMap<String, FootprintBuilder> = BTreeMap::new();
 
     for zst_path in &zst_files {
         info!("Processing: {:?}", zst_path);
@@ -172,27 +356,63 @@ async fn run_process(
             continue;
         }
 
+        let instrument = instruments.resolve(&trades[0].symbol);
+        profiles
+            .get_or_insert_with(|| profile::DailyProfiles::new(&instrument))
+            .add_trades(&trades);
+
+        for trade in &trades {
+            footprint_builders
+                .entry(trade.symbol.clone())
+                .or_insert_with(|| FootprintBuilder::new(footprint, &instruments.resolve(&trade.symbol)))
+                .add(
+                    trade.price,
+                    trade.size,
+                    trade.side == trades::Side::Buy,
+                    trade.ts_event.timestamp_millis() as u64,
+                );
+        }
+
         let bars_1s = bars::aggregate_to_1s_bars(&trades);
         info!("  Created {} 1-second bars", bars_1s.len());
 
-        let daily_levels = levels::compute_daily_levels(&bars_1s);
-        info!("  Computed levels for {} trading days", daily_levels.len());
-
         let bars_1m = bars::aggregate_to_1m_bars(&bars_1s);
         info!("  Created {} 1-minute bars", bars_1m.len());
 
-        let impulse_legs = impulse::detect_impulse_legs(&bars_1m, &daily_levels);
+        let impulse_legs = impulse::detect_impulse_legs(&bars_1m, &instrument);
         info!("  Found {} valid impulse legs", impulse_legs.len());
 
-        let lvn_levels = lvn::extract_lvns(&trades, &impulse_legs);
+        let lvn_levels = lvn::extract_lvns(&trades, &impulse_legs, &instrument);
         info!("  Extracted {} LVN levels", lvn_levels.len());
 
         all_bars.extend(bars_1s);
-        all_daily_levels.extend(daily_levels);
         all_impulse_legs.extend(impulse_legs);
         all_lvn_levels.extend(lvn_levels);
     }
 
+    let all_footprints: Vec<_> = footprint_builders
+        .iter_mut()
+        .flat_map(|(symbol, builder)| {
+            builder
+                .finish()
+                .into_iter()
+                .map(|bar| bar.to_footprint(symbol, &footprint, true))
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    info!("Built {} {} footprint bars", all_footprints.len(), footprint.timeframe);
+
+    // Levels over every file at once - a CME trading date spans two UTC-day files
+    let all_daily_levels = match &profiles {
+        Some(profiles) => {
+            levels::compute_daily_levels(&all_bars, profiles, profiles.instrument(), vwap_anchor)
+        }
+        None => Vec::new(),
+    };
+    let composite_levels = profiles.as_ref().map(|p| p.composite_levels()).unwrap_or_default();
+    info!("Computed levels for {} trading days, {} composite levels",
+          all_daily_levels.len(), composite_levels.len());
+
     info!("Total: {} bars, {} daily levels, {} impulse legs, {} LVNs",
           all_bars.len(), all_daily_levels.len(),
           all_impulse_legs.len(), all_lvn_levels.len());
@@ -208,6 +428,20 @@ async fn run_process(
     supabase::write_levels_parquet(&all_daily_levels, &levels_path)?;
     info!("  Wrote {} daily levels to {:?}", all_daily_levels.len(), levels_path);
 
+    if let Some(profiles) = &profiles {
+        let profiles_path = output_dir.join("volume_profiles.parquet");
+        supabase::write_profiles_parquet(profiles, &profiles_path)?;
+        info!("  Wrote volume profiles for {} trading days to {:?}", profiles.len(), profiles_path);
+    }
+
+    let footprints_path = output_dir.join("footprints.parquet");
+    supabase::write_footprints_parquet(&all_footprints, &footprints_path)?;
+    info!("  Wrote {} footprint bars to {:?}", all_footprints.len(), footprints_path);
+
+    let composite_path = output_dir.join("composite_levels.parquet");
+    supabase::write_composite_levels_parquet(&composite_levels, &composite_path)?;
+    info!("  Wrote {} composite levels to {:?}", composite_levels.len(), composite_path);
+
     let impulse_path = output_dir.join("impulse_legs.parquet");
     supabase::write_impulse_legs_parquet(&all_impulse_legs, &impulse_path)?;
     info!("  Wrote {} impulse legs to {:?}", all_impulse_legs.len(), impulse_path);
@@ -216,6 +450,13 @@ async fn run_process(
     supabase::write_lvn_levels_parquet(&all_lvn_levels, &lvn_path)?;
     info!("  Wrote {} LVN levels to {:?}", all_lvn_levels.len(), lvn_path);
 
+    // Write bars to the local SQLite store
+    if let Some(path) = sqlite {
+        let store = SqliteStore::open(&path)?;
+        store.insert_bars(&supabase::bar_records(&all_bars)).await?;
+        info!("  Wrote {} bars to SQLite store {:?}", all_bars.len(), path);
+    }
+
     // Upload to Supabase
     if !no_upload {
         info!("Uploading to Supabase...");
@@ -225,6 +466,7 @@ async fn run_process(
                 client.upload_daily_levels(&all_daily_levels).await?;
                 client.upload_impulse_legs(&all_impulse_legs).await?;
                 client.upload_lvn_levels(&all_lvn_levels).await?;
+                client.upload_composite_levels(&composite_levels).await?;
                 info!("Upload complete!");
             }
             Err(e) => {
@@ -237,7 +479,13 @@ async fn run_process(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_replay(
+    instruments: &InstrumentRegistry,
+    imbalance: &StackedImbalanceArgs,
+    divergence: DivergenceSettings,
+    large_trades: LargeTradeSettings,
+    sweep_levels: u32,
     data_dir: PathBuf,
     output_dir: PathBuf,
     date: Option<String>,
@@ -248,21 +496,27 @@ fn run_replay(
 
     std::fs::create_dir_all(&output_dir)?;
 
-    // Parse trades
+    // Stream trades from all files in timestamp order
     let zst_files = trades::find_zst_files(&data_dir, date.as_deref())?;
     info!("Found {} trade files", zst_files.len());
 
-    let mut all_trades = Vec::new();
-    for zst_path in &zst_files {
-        let trades = trades::parse_zst_trades(zst_path)?;
-        info!("Parsed {} trades from {:?}", trades.len(), zst_path);
-        all_trades.extend(trades);
-    }
-
-    info!("Total trades: {}", all_trades.len());
+    let merged = MergedTrades::open(&zst_files)?;
+    let Some(symbol) = merged.head_symbols().into_iter().next() else {
+        info!("No trades to replay");
+        return Ok(());
+    };
+    let instrument = instruments.resolve(&symbol);
 
     // Replay through ProcessingState
-    let signals = replay::replay_trades_for_signals(&all_trades);
+    let signals = replay::replay_trades_for_signals(
+        merged,
+        &instrument,
+        imbalance.footprint(),
+        imbalance.settings(imbalance.stacked_imbalance),
+        divergence,
+        large_trades,
+        sweep_levels,
+    )?;
     info!("Generated {} signals", signals.len());
 
     // Write signals to Parquet
@@ -274,7 +528,15 @@ fn run_replay(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 fn run_backtest(
+    instruments: &InstrumentRegistry,
+    vwap_anchor: VwapAnchor,
+    imbalance: &StackedImbalanceArgs,
+    divergence: DivergenceSettings,
+    large_trades: LargeTradeSettings,
+    sweep_levels: u32,
+    compare_stacked_imbalance: bool,
     data_dir: PathBuf,
     output_dir: PathBuf,
     date: Option<String>,
@@ -284,6 +546,7 @@ fn run_backtest(
     rth_only: bool,
     min_confluence: u8,
     key_levels_only: bool,
+    market_states: Vec<String>,
 ) -> Result<()> {
     info!("=== BACKTEST MODE ===");
     info!("Running strategy backtest");
@@ -295,31 +558,36 @@ fn run_backtest(
     let zst_files = trades::find_zst_files(&data_dir, date.as_deref())?;
     info!("Found {} trade files", zst_files.len());
 
-    let mut all_trades = Vec::new();
+    // Bars and profiles are built one file at a time - trades are dropped after each file
     let mut all_bars = Vec::new();
-    let mut all_daily_levels = Vec::new();
+    let mut profiles = None;
 
     for zst_path in &zst_files {
         let trades = trades::parse_zst_trades(zst_path)?;
         info!("Parsed {} trades from {:?}", trades.len(), zst_path);
 
         if !trades.is_empty() {
-            let bars_1s = bars::aggregate_to_1s_bars(&trades);
-            let daily_levels = levels::compute_daily_levels(&bars_1s);
-            all_bars.extend(bars_1s);
-            all_daily_levels.extend(daily_levels);
+            let profiles = profiles.get_or_insert_with(|| {
+                profile::DailyProfiles::new(&instruments.resolve(&trades[0].symbol))
+            });
+            profiles.add_trades(&trades);
+            all_bars.extend(bars::aggregate_to_1s_bars(&trades));
         }
-
-        all_trades.extend(trades);
     }
 
-    info!("Total: {} trades, {} bars, {} daily levels",
-          all_trades.len(), all_bars.len(), all_daily_levels.len());
+    let Some(profiles) = profiles else {
+        info!("No trades to backtest");
+        return Ok(());
+    };
+    let instrument = profiles.instrument().clone();
 
-    // Replay through ProcessingState to get signals
-    info!("Generating signals through replay...");
-    let signals = replay::replay_trades_for_signals(&all_trades);
-    info!("Generated {} signals", signals.len());
+    // Levels over every file at once - a CME trading date spans two UTC-day files
+    let all_daily_levels =
+        levels::compute_daily_levels(&all_bars, &profiles, &instrument, vwap_anchor);
+    let composite_levels = profiles.composite_levels();
+    info!("Total: {} bars, {} daily levels, {} composite levels",
+          all_bars.len(), all_daily_levels.len(), composite_levels.len());
+    info!("Instrument: {} (tick {}, ${}/pt)", instrument.root, instrument.tick_size, instrument.point_value);
 
     // Configure backtest strategy
     let config = backtest::StrategyConfig {
@@ -331,11 +599,59 @@ fn run_backtest(
         require_key_level: key_levels_only,
         min_strength: None,
         rth_only,
+        market_states,
     };
 
+    // Stream trades from all files in timestamp order through ProcessingState to get signals
+    let replay_signals = |algorithm| -> Result<Vec<replay::CapturedSignal>> {
+        info!("Generating signals through replay...");
+        let merged = MergedTrades::open(&zst_files)?;
+        let signals = replay::replay_trades_for_signals(
+            merged,
+            &instrument,
+            imbalance.footprint(),
+            imbalance.settings(algorithm),
+            divergence,
+            large_trades.clone(),
+            sweep_levels,
+        )?;
+        info!("Generated {} signals", signals.len());
+        Ok(signals)
+    };
+
+    // Stacked imbalance signals only, once per detector, on the same strategy
+    if compare_stacked_imbalance {
+        let config = backtest::StrategyConfig {
+            required_signals: vec!["stacked_imbalance".to_string()],
+            ..config
+        };
+        let backtester = backtest::Backtester::new(config, instrument.clone(), all_bars, all_daily_levels)
+            .with_composite_levels(&composite_levels);
+
+        let mut comparison = BTreeMap::new();
+        for algorithm in [StackedImbalanceAlgorithm::Session, StackedImbalanceAlgorithm::Diagonal] {
+            info!("Running backtest ({} stacked imbalances)...", algorithm.as_str());
+            let signals = replay_signals(algorithm)?;
+            comparison.insert(algorithm.as_str(), backtester.run(&signals));
+        }
+
+        backtest::print_comparison(&comparison);
+
+        let comparison_path = output_dir.join("backtest_comparison.json");
+        let json = serde_json::to_string_pretty(&comparison)?;
+        std::fs::write(&comparison_path, json)?;
+        info!("Wrote comparison to {:?}", comparison_path);
+
+        info!("Backtest complete!");
+        return Ok(());
+    }
+
+    let signals = replay_signals(imbalance.stacked_imbalance)?;
+
     // Run backtest
     info!("Running backtest...");
-    let backtester = backtest::Backtester::new(config, all_bars, all_daily_levels);
+    let backtester = backtest::Backtester::new(config, instrument, all_bars, all_daily_levels)
+        .with_composite_levels(&composite_levels);
     let results = backtester.run(&signals);
 
     // Print results
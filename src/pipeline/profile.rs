@@ -0,0 +1,322 @@
+//! Daily Volume Profiles
+//!
+//! Tick-resolution volume at price built from raw trades, one profile per CME
+//! trading date and part of the session (overnight, RTH, post-close). Built on
+//! the library's `VolumeProfile` with the instrument's profile settings, as the
+//! live `ProcessingState` is, so historical POC/VAH/VAL match what the server
+//! showed. Trades are added one file at a time and dropped afterwards.
+//!
+//! Composite profiles merge the RTH profiles of several prior sessions (last 5
+//! trading days, prior week, prior month); their POC/VAH/VAL and high/low volume
+//! nodes become key levels for the following trading date.
+
+use crate::trades::{Side, Trade};
+use chrono::{Datelike, Days, Months, NaiveDate};
+use serde::{Deserialize, Serialize};
+use orderflow_bubbles::calendar::SessionCalendar;
+use orderflow_bubbles::instruments::InstrumentSpec;
+use orderflow_bubbles::volume_profile::VolumeProfile;
+use std::collections::BTreeMap;
+
+/// Part of a trading date a profile covers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ProfileSegment {
+    /// Globex before the RTH open
+    Overnight,
+    Rth,
+    /// After the RTH close until the daily halt
+    PostClose,
+}
+
+impl ProfileSegment {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Overnight => "overnight",
+            Self::Rth => "rth",
+            Self::PostClose => "post_close",
+        }
+    }
+}
+
+/// Profiles for one trading date
+#[derive(Debug, Clone)]
+pub struct DayProfile {
+    pub date: NaiveDate,
+    pub symbol: String,
+    pub segments: BTreeMap<ProfileSegment, VolumeProfile>,
+}
+
+impl DayProfile {
+    pub fn segment(&self, segment: ProfileSegment) -> Option<&VolumeProfile> {
+        self.segments.get(&segment)
+    }
+
+    /// RTH profile - the whole trading date if it had no RTH trades
+    pub fn session_profile(&self) -> VolumeProfile {
+        if let Some(rth) = self.segment(ProfileSegment::Rth) {
+            return rth.clone();
+        }
+
+        let mut segments = self.segments.values();
+        let mut profile = segments.next().cloned().unwrap_or_default();
+        for segment in segments {
+            profile.merge(segment);
+        }
+        profile
+    }
+}
+
+/// Daily profiles for one instrument, built incrementally from trades
+pub struct DailyProfiles {
+    instrument: InstrumentSpec,
+    calendar: SessionCalendar,
+    days: BTreeMap<NaiveDate, DayProfile>,
+}
+
+impl DailyProfiles {
+    pub fn new(instrument: &InstrumentSpec) -> Self {
+        Self {
+            instrument: instrument.clone(),
+            calendar: SessionCalendar::new(&instrument.session),
+            days: BTreeMap::new(),
+        }
+    }
+
+    /// Add trades to the profile of their trading date and segment
+    pub fn add_trades(&mut self, trades: &[Trade]) {
+        // Trading date and segment change rarely - cache them per 1-second bucket
+        let mut cached: Option<(i64, NaiveDate, ProfileSegment)> = None;
+
+        for trade in trades {
+            let second = trade.ts_event.timestamp();
+            let (date, segment) = match cached {
+                Some((cached_second, date, segment)) if cached_second == second => (date, segment),
+                _ => {
+                    let (date, segment) = self.classify(trade.ts_event.timestamp_millis() as u64);
+                    cached = Some((second, date, segment));
+                    (date, segment)
+                }
+            };
+
+            let day = self.days.entry(date).or_insert_with(|| DayProfile {
+                date,
+                symbol: trade.symbol.clone(),
+                segments: BTreeMap::new(),
+            });
+            day.segments
+                .entry(segment)
+                .or_insert_with(|| VolumeProfile::new(&self.instrument.profile))
+                .add(
+                    trade.price,
+                    trade.size,
+                    trade.side == Side::Buy,
+                    trade.ts_event.timestamp_millis() as u64,
+                );
+        }
+    }
+
+    fn classify(&self, timestamp_ms: u64) -> (NaiveDate, ProfileSegment) {
+        let date = self.calendar.trading_date(timestamp_ms);
+        let segment = if self.calendar.is_rth(timestamp_ms) {
+            ProfileSegment::Rth
+        } else {
+            match self.calendar.rth_bounds(date) {
+                Some((_, close)) if timestamp_ms >= close => ProfileSegment::PostClose,
+                _ => ProfileSegment::Overnight,
+            }
+        };
+        (date, segment)
+    }
+
+    pub fn instrument(&self) -> &InstrumentSpec {
+        &self.instrument
+    }
+
+    pub fn get(&self, date: NaiveDate) -> Option<&DayProfile> {
+        self.days.get(&date)
+    }
+
+    pub fn days(&self) -> impl Iterator<Item = &DayProfile> {
+        self.days.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.days.len()
+    }
+}
+
+/// Sessions merged into a composite profile (all before the date it applies to)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompositePeriod {
+    /// Last 5 trading dates
+    Rolling5Day,
+    /// Previous Monday-Friday week
+    PriorWeek,
+    /// Previous calendar month
+    PriorMonth,
+}
+
+impl CompositePeriod {
+    pub const ALL: [CompositePeriod; 3] = [Self::Rolling5Day, Self::PriorWeek, Self::PriorMonth];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rolling5Day => "5d",
+            Self::PriorWeek => "week",
+            Self::PriorMonth => "month",
+        }
+    }
+}
+
+/// Composite profile level, known before the trading date it applies to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompositeLevel {
+    pub date: NaiveDate,
+    pub symbol: String,
+    pub period: String, // "5d", "week" or "month"
+    pub kind: String,   // "poc", "vah", "val", "hvn" or "lvn"
+    pub price: f64,
+    pub volume: u64,
+    /// First and last trading dates merged into the composite
+    pub from_date: NaiveDate,
+    pub to_date: NaiveDate,
+}
+
+impl DailyProfiles {
+    /// Composite levels for every trading date, built from the sessions before it
+    pub fn composite_levels(&self) -> Vec<CompositeLevel> {
+        let mut levels = Vec::new();
+
+        for day in self.days.values() {
+            for period in CompositePeriod::ALL {
+                let sessions: Vec<&DayProfile> = self.composite_sessions(day.date, period).collect();
+                let (Some(first), Some(last)) = (sessions.first(), sessions.last()) else {
+                    continue;
+                };
+
+                let mut composite = VolumeProfile::new(&self.instrument.profile);
+                for session in &sessions {
+                    composite.merge(&session.session_profile());
+                }
+
+                let Some(va) = composite.value_area() else {
+                    continue;
+                };
+                let (hvns, lvns) = composite.volume_nodes();
+                let nodes = [("poc", va.poc), ("vah", va.vah), ("val", va.val)]
+                    .into_iter()
+                    .chain(hvns.into_iter().map(|price| ("hvn", price)))
+                    .chain(lvns.into_iter().map(|price| ("lvn", price)));
+
+                for (kind, price) in nodes {
+                    levels.push(CompositeLevel {
+                        date: day.date,
+                        symbol: day.symbol.clone(),
+                        period: period.as_str().to_string(),
+                        kind: kind.to_string(),
+                        price,
+                        volume: composite.get(price).map(|level| level.total()).unwrap_or(0),
+                        from_date: first.date,
+                        to_date: last.date,
+                    });
+                }
+            }
+        }
+
+        levels
+    }
+
+    /// Sessions in a composite for `date` (oldest first)
+    fn composite_sessions(
+        &self,
+        date: NaiveDate,
+        period: CompositePeriod,
+    ) -> Box<dyn Iterator<Item = &DayProfile> + '_> {
+        let before = self.days.range(..date).map(|(_, day)| day);
+        match period {
+            CompositePeriod::Rolling5Day => {
+                let count = before.clone().count();
+                Box::new(before.skip(count.saturating_sub(5)))
+            }
+            CompositePeriod::PriorWeek => {
+                let monday = date - Days::new(date.weekday().num_days_from_monday() as u64);
+                let prior_monday = monday - Days::new(7);
+                Box::new(before.filter(move |day| day.date >= prior_monday && day.date < monday))
+            }
+            CompositePeriod::PriorMonth => {
+                let month_start = date.with_day(1).expect("first of month");
+                let prior_start = month_start - Months::new(1);
+                Box::new(before.filter(move |day| day.date >= prior_start && day.date < month_start))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::DateTime;
+    use orderflow_bubbles::volume_profile::PriceVolume;
+
+    fn trade(secs: i64, price: f64, size: u64, side: Side) -> Trade {
+        Trade {
+            ts_event: DateTime::from_timestamp(secs, 0).unwrap(),
+            price,
+            size,
+            side,
+            symbol: "NQH6".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_profiles_by_segment() {
+        // 2025-12-02: 03:00 ET overnight, 10:00 ET RTH, 16:30 ET post-close
+        let trades = [
+            trade(1_764_662_400, 21_500.0, 4, Side::Buy),
+            trade(1_764_687_600, 21_510.25, 7, Side::Buy),
+            trade(1_764_687_600, 21_510.25, 3, Side::Sell),
+            trade(1_764_711_000, 21_490.0, 5, Side::Sell),
+        ];
+        let mut profiles = DailyProfiles::new(&InstrumentSpec::default());
+        profiles.add_trades(&trades);
+
+        assert_eq!(profiles.len(), 1);
+        let day = profiles.get(NaiveDate::from_ymd_opt(2025, 12, 2).unwrap()).unwrap();
+        let rth = day.segment(ProfileSegment::Rth).unwrap();
+        assert_eq!(rth.len(), 1);
+        // Tick resolution: 21510.25 keeps its own level
+        assert_eq!(rth.get(21_510.25), Some(&PriceVolume { buy_volume: 7, sell_volume: 3 }));
+        let volume = |segment| day.segment(segment).unwrap().total_volume();
+        assert_eq!(volume(ProfileSegment::Overnight), 4);
+        assert_eq!(volume(ProfileSegment::PostClose), 5);
+        assert_eq!(day.session_profile(), *rth);
+    }
+
+    #[test]
+    fn test_composite_levels() {
+        // 10:00 ET on Monday 2025-12-01 through Friday 12-05, then Monday 12-08
+        let day_secs = [0, 1, 2, 3, 4, 7].map(|d| 1_764_601_200 + d * 86_400);
+        let trades: Vec<Trade> = day_secs
+            .iter()
+            .enumerate()
+            .map(|(i, &secs)| trade(secs, 21_500.0 + i as f64, 10, Side::Buy))
+            .collect();
+        let mut profiles = DailyProfiles::new(&InstrumentSpec::default());
+        profiles.add_trades(&trades);
+
+        let levels = profiles.composite_levels();
+        let monday = NaiveDate::from_ymd_opt(2025, 12, 8).unwrap();
+        let rolling: Vec<_> = levels
+            .iter()
+            .filter(|l| l.date == monday && l.period == "5d")
+            .collect();
+        assert!(rolling.iter().all(|l| l.from_date == NaiveDate::from_ymd_opt(2025, 12, 1).unwrap()));
+        assert!(rolling.iter().all(|l| l.to_date == NaiveDate::from_ymd_opt(2025, 12, 5).unwrap()));
+        assert!(levels.iter().any(|l| l.date == monday && l.period == "week"));
+
+        // Nothing before the first session, no prior month in the data
+        assert!(levels.iter().all(|l| l.date != NaiveDate::from_ymd_opt(2025, 12, 1).unwrap()));
+        assert!(levels.iter().all(|l| l.period != "month"));
+    }
+
+}
@@ -3,7 +3,6 @@
 //! Feeds historical trades through the exact same ProcessingState as live trading.
 //! This ensures replay behavior matches production 1:1.
 
-use crate::trades::{Side, Trade as PipelineTrade};
 use anyhow::Result;
 use arrow::array::{ArrayRef, Float64Array, StringArray, UInt64Array};
 use arrow::datatypes::{DataType, Field, Schema};
@@ -18,7 +17,17 @@ use tokio::sync::broadcast;
 use tracing::info;
 
 // Import from the library crate
-use orderflow_bubbles::{ProcessingState, types::WsMessage};
+use orderflow_bubbles::{
+    clock::{EventClock, EventWindow},
+    divergence::DivergenceSettings,
+    footprint::FootprintSettings,
+    imbalance::StackedImbalanceSettings,
+    instruments::InstrumentSpec,
+    large_trade::LargeTradeSettings,
+    trade_files::TradeRecord,
+    types::WsMessage,
+    ProcessingState,
+};
 
 /// Captured signal from replay
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,34 +38,34 @@ pub struct CapturedSignal {
     pub price: f64,
     pub strength: Option<String>,
     pub extra_data: Option<String>,
-}
-
-/// Convert pipeline trades to the format expected by ProcessingState
-pub fn convert_to_processing_trade(trade: &PipelineTrade) -> orderflow_bubbles::types::Trade {
-    orderflow_bubbles::types::Trade {
-        symbol: trade.symbol.clone(),
-        price: trade.price,
-        size: trade.size as u32,
-        side: match trade.side {
-            Side::Buy => "buy".to_string(),
-            Side::Sell => "sell".to_string(),
-        },
-        timestamp: trade.ts_event.timestamp_millis() as u64,
-    }
+    /// Market state when the signal fired (balance, bullish_imbalance, bearish_imbalance)
+    pub market_state: Option<String>,
 }
 
 /// Signal collector that captures WsMessage signals for backtesting
 pub struct SignalCollector {
     pub signals: Vec<CapturedSignal>,
+    // Latest market state - signals are tagged with it
+    market_state: Option<String>,
+    // Large trades below this tier aren't signals
+    large_trade_signal_tier: u8,
 }
 
 impl SignalCollector {
     pub fn new() -> Self {
         Self {
             signals: Vec::new(),
+            market_state: None,
+            large_trade_signal_tier: LargeTradeSettings::default().signal_tier,
         }
     }
 
+    /// Capture large trades from this tier up (matching the replayed state's settings)
+    pub fn with_large_trade_signal_tier(mut self, tier: u8) -> Self {
+        self.large_trade_signal_tier = tier;
+        self
+    }
+
     /// Process a WsMessage and extract signal if applicable
     pub fn process_message(&mut self, msg: &WsMessage) {
         match msg {
@@ -68,6 +77,7 @@ impl SignalCollector {
                     price: 0.0, // Delta flips don't have a specific price
                     strength: None,
                     extra_data: Some(format!("cvd: {} -> {}", flip.cvd_before, flip.cvd_after)),
+                    market_state: self.market_state.clone(),
                 });
             }
             WsMessage::Absorption(abs) => {
@@ -78,6 +88,7 @@ impl SignalCollector {
                     price: abs.price,
                     strength: Some(abs.strength.clone()),
                     extra_data: Some(format!("delta: {}, events: {}", abs.delta, abs.event_count)),
+                    market_state: self.market_state.clone(),
                 });
             }
             WsMessage::StackedImbalance(stacked) => {
@@ -87,7 +98,49 @@ impl SignalCollector {
                     direction: if stacked.side == "buy" { "bullish" } else { "bearish" }.to_string(),
                     price: (stacked.price_high + stacked.price_low) / 2.0,
                     strength: None,
-                    extra_data: Some(format!("levels: {}, range: {:.0}-{:.0}", stacked.level_count, stacked.price_low, stacked.price_high)),
+                    extra_data: Some(format!("{}, levels: {}, range: {:.0}-{:.0}", stacked.algorithm, stacked.level_count, stacked.price_low, stacked.price_high)),
+                    market_state: self.market_state.clone(),
+                });
+            }
+            WsMessage::Divergence(divergence) => {
+                self.signals.push(CapturedSignal {
+                    timestamp: divergence.timestamp,
+                    signal_type: "divergence".to_string(),
+                    direction: divergence.direction.clone(),
+                    price: divergence.price,
+                    strength: Some(divergence.kind.clone()),
+                    extra_data: Some(format!(
+                        "price: {:.2} -> {:.2}, cvd: {} -> {}",
+                        divergence.prev_swing_price, divergence.swing_price,
+                        divergence.prev_swing_cvd, divergence.swing_cvd
+                    )),
+                    market_state: self.market_state.clone(),
+                });
+            }
+            // Only tiers at or above the signal tier count, like live
+            WsMessage::LargeTrade(large) if large.tier >= self.large_trade_signal_tier => {
+                self.signals.push(CapturedSignal {
+                    timestamp: large.timestamp,
+                    signal_type: "large_trade".to_string(),
+                    direction: if large.side == "buy" { "bullish" } else { "bearish" }.to_string(),
+                    price: large.price,
+                    strength: Some(format!("tier_{}", large.tier)),
+                    extra_data: Some(format!("{}, size: {}, fills: {}", large.kind, large.size, large.fills)),
+                    market_state: self.market_state.clone(),
+                });
+            }
+            WsMessage::Sweep(sweep) => {
+                self.signals.push(CapturedSignal {
+                    timestamp: sweep.timestamp,
+                    signal_type: "sweep".to_string(),
+                    direction: if sweep.side == "buy" { "bullish" } else { "bearish" }.to_string(),
+                    price: sweep.price,
+                    strength: Some(format!("levels_{}", sweep.levels)),
+                    extra_data: Some(format!(
+                        "size: {}, fills: {}, {:.2} -> {:.2}",
+                        sweep.size, sweep.fills, sweep.start_price, sweep.end_price
+                    )),
+                    market_state: self.market_state.clone(),
                 });
             }
             WsMessage::Confluence(conf) => {
@@ -98,60 +151,75 @@ impl SignalCollector {
                     price: conf.price,
                     strength: Some(format!("score_{}", conf.score)),
                     extra_data: Some(conf.signals.join(", ")),
+                    market_state: self.market_state.clone(),
                 });
             }
+            WsMessage::MarketState(update) => {
+                self.market_state = Some(update.state.clone());
+            }
             _ => {} // Ignore non-signal messages (bubbles, CVD, etc.)
         }
     }
 }
 
-/// Replay trades through the production ProcessingState
+/// Replay time-ordered trades (e.g. `MergedTrades`) through the production ProcessingState
+/// with the chosen stacked imbalance detector (diagonal stacks come from `footprint` bars),
+/// divergence lookbacks, large trade tiers and sweep threshold. Prints are grouped into
+/// aggressor orders from their Databento fields, like live.
+/// Impulse legs are confirmed as their bars close, like live - they feed the market state
+/// classifier and anchor VWAPs when they break structure.
 /// Returns captured signals that can be used for backtesting
-pub fn replay_trades_for_signals(trades: &[PipelineTrade]) -> Vec<CapturedSignal> {
-
-    if trades.is_empty() {
-        return Vec::new();
-    }
-
-    info!("Starting replay of {} trades through ProcessingState", trades.len());
+pub fn replay_trades_for_signals(
+    trades: impl IntoIterator<Item = Result<TradeRecord>>,
+    instrument: &InstrumentSpec,
+    footprint: FootprintSettings,
+    stacked_imbalance: StackedImbalanceSettings,
+    divergence: DivergenceSettings,
+    large_trades: LargeTradeSettings,
+    sweep_levels: u32,
+) -> Result<Vec<CapturedSignal>> {
+    info!(
+        "Starting replay through ProcessingState ({} stacked imbalances)",
+        stacked_imbalance.algorithm.as_str()
+    );
 
     // Create broadcast channel for capturing signals
     let (tx, mut rx) = broadcast::channel::<WsMessage>(10000);
 
-    // Create production ProcessingState (no Supabase, no session)
-    let mut state = ProcessingState::new(None, None, None);
+    // Create production ProcessingState (no Supabase, no session), timed by trade timestamps
+    let mut state = ProcessingState::new(None, None, None)
+        .with_instrument(instrument.clone())
+        .with_clock(EventClock::new())
+        .with_footprint(footprint)
+        .with_stacked_imbalance(stacked_imbalance)
+        .with_divergence(divergence)
+        .with_large_trades(large_trades.clone())
+        .with_sweep_levels(sweep_levels);
 
     // Signal collector
-    let mut collector = SignalCollector::new();
+    let mut collector = SignalCollector::new().with_large_trade_signal_tier(large_trades.signal_tier);
 
     // Group trades by 100ms windows (simulating real-time aggregation)
-    let mut current_window_end = 0u64;
-    let window_size_ms = 100; // 100ms windows like production
+    let mut window = EventWindow::new(100);
 
-    for trade in trades {
-        let processing_trade = convert_to_processing_trade(trade);
-        let trade_ts = processing_trade.timestamp;
+    let mut trade_count = 0u64;
+    for record in trades {
+        let record = record?;
+        trade_count += 1;
 
-        // Check if we need to process the buffer (new window)
-        if current_window_end == 0 {
-            current_window_end = trade_ts + window_size_ms;
-        }
-
-        if trade_ts >= current_window_end {
-            // Process the accumulated buffer
+        // Process the accumulated buffer when this trade opens a new window
+        if window.advance(record.trade.timestamp) {
             state.process_buffer(&tx);
 
             // Drain any signals from the channel
             while let Ok(msg) = rx.try_recv() {
                 collector.process_message(&msg);
             }
-
-            // Start new window
-            current_window_end = trade_ts + window_size_ms;
         }
 
         // Add trade to buffer
-        state.add_trade(processing_trade);
+        let print = record.print();
+        state.add_print(record.trade, Some(print));
     }
 
     // Process remaining trades in buffer
@@ -162,18 +230,21 @@ pub fn replay_trades_for_signals(trades: &[PipelineTrade]) -> Vec<CapturedSignal
         collector.process_message(&msg);
     }
 
-    info!("Replay complete. Captured {} signals", collector.signals.len());
+    info!("Replay complete. {} trades, captured {} signals", trade_count, collector.signals.len());
 
     // Log signal breakdown
     let delta_flips = collector.signals.iter().filter(|s| s.signal_type == "delta_flip").count();
     let absorptions = collector.signals.iter().filter(|s| s.signal_type == "absorption").count();
     let stacked = collector.signals.iter().filter(|s| s.signal_type == "stacked_imbalance").count();
+    let divergences = collector.signals.iter().filter(|s| s.signal_type == "divergence").count();
+    let large = collector.signals.iter().filter(|s| s.signal_type == "large_trade").count();
+    let sweeps = collector.signals.iter().filter(|s| s.signal_type == "sweep").count();
     let confluences = collector.signals.iter().filter(|s| s.signal_type == "confluence").count();
 
-    info!("Signal breakdown: {} delta_flips, {} absorptions, {} stacked_imbalances, {} confluences",
-          delta_flips, absorptions, stacked, confluences);
+    info!("Signal breakdown: {} delta_flips, {} absorptions, {} stacked_imbalances, {} divergences, {} large_trades, {} sweeps, {} confluences",
+          delta_flips, absorptions, stacked, divergences, large, sweeps, confluences);
 
-    collector.signals
+    Ok(collector.signals)
 }
 
 /// Write captured signals to Parquet file
@@ -190,6 +261,7 @@ pub fn write_signals_parquet(signals: &[CapturedSignal], path: &Path) -> Result<
         Field::new("price", DataType::Float64, false),
         Field::new("strength", DataType::Utf8, true),
         Field::new("extra_data", DataType::Utf8, true),
+        Field::new("market_state", DataType::Utf8, true),
     ]);
 
     let timestamps: Vec<u64> = signals.iter().map(|s| s.timestamp).collect();
@@ -198,6 +270,7 @@ pub fn write_signals_parquet(signals: &[CapturedSignal], path: &Path) -> Result<
     let prices: Vec<f64> = signals.iter().map(|s| s.price).collect();
     let strengths: Vec<Option<&str>> = signals.iter().map(|s| s.strength.as_deref()).collect();
     let extra_data: Vec<Option<&str>> = signals.iter().map(|s| s.extra_data.as_deref()).collect();
+    let market_states: Vec<Option<&str>> = signals.iter().map(|s| s.market_state.as_deref()).collect();
 
     let batch = RecordBatch::try_new(
         Arc::new(schema.clone()),
@@ -208,6 +281,7 @@ pub fn write_signals_parquet(signals: &[CapturedSignal], path: &Path) -> Result<
             Arc::new(Float64Array::from(prices)) as ArrayRef,
             Arc::new(StringArray::from(strengths)) as ArrayRef,
             Arc::new(StringArray::from(extra_data)) as ArrayRef,
+            Arc::new(StringArray::from(market_states)) as ArrayRef,
         ],
     )?;
 
@@ -226,7 +300,7 @@ mod tests {
 
     #[test]
     fn test_signal_collector() {
-        let mut collector = SignalCollector::new();
+        let collector = SignalCollector::new();
         assert!(collector.signals.is_empty());
     }
 }
@@ -2,6 +2,7 @@ use crate::bars::Bar;
 use crate::impulse::ImpulseLeg;
 use crate::levels::DailyLevels;
 use crate::lvn::LvnLevel;
+use crate::profile::{CompositeLevel, DailyProfiles};
 use anyhow::{Context, Result};
 use arrow::array::{
     ArrayRef, Float64Array, Int64Array, StringArray, TimestampMicrosecondArray, UInt64Array,
@@ -9,14 +10,32 @@ use arrow::array::{
 };
 use arrow::datatypes::{DataType, Field, Schema, TimeUnit};
 use arrow::record_batch::RecordBatch;
+use orderflow_bubbles::store::BarRecord;
+use orderflow_bubbles::types::Footprint;
 use parquet::arrow::ArrowWriter;
 use parquet::file::properties::WriterProperties;
 use reqwest::Client;
-use serde_json::json;
 use std::fs::File;
 use std::path::Path;
 use std::sync::Arc;
 
+/// 1s bars as replay_bars_1s rows (Supabase upload and the SQLite store)
+pub fn bar_records(bars: &[Bar]) -> Vec<BarRecord> {
+    bars.iter().map(|b| BarRecord {
+        timestamp: b.timestamp.to_rfc3339(),
+        open: b.open,
+        high: b.high,
+        low: b.low,
+        close: b.close,
+        volume: b.volume as i64,
+        buy_volume: b.buy_volume as i64,
+        sell_volume: b.sell_volume as i64,
+        delta: b.delta,
+        trade_count: b.trade_count as i64,
+        symbol: b.symbol.clone(),
+    }).collect()
+}
+
 /// Supabase client for data upload
 pub struct SupabaseClient {
     client: Client,
@@ -67,36 +86,7 @@ impl SupabaseClient {
     }
 
     pub async fn upload_bars(&self, bars: &[Bar]) -> Result<()> {
-        #[derive(serde::Serialize)]
-        struct BarRow {
-            timestamp: String,
-            open: f64,
-            high: f64,
-            low: f64,
-            close: f64,
-            volume: i64,
-            buy_volume: i64,
-            sell_volume: i64,
-            delta: i64,
-            trade_count: i64,
-            symbol: String,
-        }
-
-        let rows: Vec<_> = bars.iter().map(|b| BarRow {
-            timestamp: b.timestamp.to_rfc3339(),
-            open: b.open,
-            high: b.high,
-            low: b.low,
-            close: b.close,
-            volume: b.volume as i64,
-            buy_volume: b.buy_volume as i64,
-            sell_volume: b.sell_volume as i64,
-            delta: b.delta,
-            trade_count: b.trade_count as i64,
-            symbol: b.symbol.clone(),
-        }).collect();
-
-        self.insert_batch("replay_bars_1s", &rows).await
+        self.insert_batch("replay_bars_1s", &bar_records(bars)).await
     }
 
     pub async fn upload_daily_levels(&self, levels: &[DailyLevels]) -> Result<()> {
@@ -104,9 +94,9 @@ impl SupabaseClient {
         struct LevelRow {
             date: String,
             symbol: String,
-            pdh: f64,
-            pdl: f64,
-            pdc: f64,
+            pdh: Option<f64>,
+            pdl: Option<f64>,
+            pdc: Option<f64>,
             poc: f64,
             vah: f64,
             val: f64,
@@ -115,6 +105,25 @@ impl SupabaseClient {
             session_open: f64,
             session_close: f64,
             total_volume: i64,
+            onh: Option<f64>,
+            onl: Option<f64>,
+            on_poc: Option<f64>,
+            ib_high: Option<f64>,
+            ib_low: Option<f64>,
+            ib_ext_up_1: Option<f64>,
+            ib_ext_down_1: Option<f64>,
+            ib_ext_up_2: Option<f64>,
+            ib_ext_down_2: Option<f64>,
+            or_high: Option<f64>,
+            or_low: Option<f64>,
+            vwap: f64,
+            vwap_std_dev: f64,
+            vwap_upper_1: f64,
+            vwap_lower_1: f64,
+            vwap_upper_2: f64,
+            vwap_lower_2: f64,
+            vwap_upper_3: f64,
+            vwap_lower_3: f64,
         }
 
         let rows: Vec<_> = levels.iter().map(|l| LevelRow {
@@ -131,6 +140,25 @@ impl SupabaseClient {
             session_open: l.session_open,
             session_close: l.session_close,
             total_volume: l.total_volume as i64,
+            onh: l.onh,
+            onl: l.onl,
+            on_poc: l.on_poc,
+            ib_high: l.ib_high,
+            ib_low: l.ib_low,
+            ib_ext_up_1: l.ib_ext_up_1,
+            ib_ext_down_1: l.ib_ext_down_1,
+            ib_ext_up_2: l.ib_ext_up_2,
+            ib_ext_down_2: l.ib_ext_down_2,
+            or_high: l.or_high,
+            or_low: l.or_low,
+            vwap: l.vwap,
+            vwap_std_dev: l.vwap_std_dev,
+            vwap_upper_1: l.vwap_upper_1,
+            vwap_lower_1: l.vwap_lower_1,
+            vwap_upper_2: l.vwap_upper_2,
+            vwap_lower_2: l.vwap_lower_2,
+            vwap_upper_3: l.vwap_upper_3,
+            vwap_lower_3: l.vwap_lower_3,
         }).collect();
 
         self.insert_batch("daily_levels", &rows).await
@@ -205,6 +233,33 @@ impl SupabaseClient {
 
         self.insert_batch("lvn_levels", &rows).await
     }
+
+    pub async fn upload_composite_levels(&self, levels: &[CompositeLevel]) -> Result<()> {
+        #[derive(serde::Serialize)]
+        struct CompositeRow {
+            date: String,
+            symbol: String,
+            period: String,
+            kind: String,
+            price: f64,
+            volume: i64,
+            from_date: String,
+            to_date: String,
+        }
+
+        let rows: Vec<_> = levels.iter().map(|l| CompositeRow {
+            date: l.date.to_string(),
+            symbol: l.symbol.clone(),
+            period: l.period.clone(),
+            kind: l.kind.clone(),
+            price: l.price,
+            volume: l.volume as i64,
+            from_date: l.from_date.to_string(),
+            to_date: l.to_date.to_string(),
+        }).collect();
+
+        self.insert_batch("composite_levels", &rows).await
+    }
 }
 
 /// Write bars to Parquet file
@@ -276,9 +331,9 @@ pub fn write_levels_parquet(levels: &[DailyLevels], path: &Path) -> Result<()> {
     let schema = Schema::new(vec![
         Field::new("date", DataType::Utf8, false),
         Field::new("symbol", DataType::Utf8, false),
-        Field::new("pdh", DataType::Float64, false),
-        Field::new("pdl", DataType::Float64, false),
-        Field::new("pdc", DataType::Float64, false),
+        Field::new("pdh", DataType::Float64, true),
+        Field::new("pdl", DataType::Float64, true),
+        Field::new("pdc", DataType::Float64, true),
         Field::new("poc", DataType::Float64, false),
         Field::new("vah", DataType::Float64, false),
         Field::new("val", DataType::Float64, false),
@@ -287,13 +342,32 @@ pub fn write_levels_parquet(levels: &[DailyLevels], path: &Path) -> Result<()> {
         Field::new("session_open", DataType::Float64, false),
         Field::new("session_close", DataType::Float64, false),
         Field::new("total_volume", DataType::UInt64, false),
+        Field::new("onh", DataType::Float64, true),
+        Field::new("onl", DataType::Float64, true),
+        Field::new("on_poc", DataType::Float64, true),
+        Field::new("ib_high", DataType::Float64, true),
+        Field::new("ib_low", DataType::Float64, true),
+        Field::new("ib_ext_up_1", DataType::Float64, true),
+        Field::new("ib_ext_down_1", DataType::Float64, true),
+        Field::new("ib_ext_up_2", DataType::Float64, true),
+        Field::new("ib_ext_down_2", DataType::Float64, true),
+        Field::new("or_high", DataType::Float64, true),
+        Field::new("or_low", DataType::Float64, true),
+        Field::new("vwap", DataType::Float64, false),
+        Field::new("vwap_std_dev", DataType::Float64, false),
+        Field::new("vwap_upper_1", DataType::Float64, false),
+        Field::new("vwap_lower_1", DataType::Float64, false),
+        Field::new("vwap_upper_2", DataType::Float64, false),
+        Field::new("vwap_lower_2", DataType::Float64, false),
+        Field::new("vwap_upper_3", DataType::Float64, false),
+        Field::new("vwap_lower_3", DataType::Float64, false),
     ]);
 
     let dates: Vec<String> = levels.iter().map(|l| l.date.to_string()).collect();
     let symbols: Vec<&str> = levels.iter().map(|l| l.symbol.as_str()).collect();
-    let pdhs: Vec<f64> = levels.iter().map(|l| l.pdh).collect();
-    let pdls: Vec<f64> = levels.iter().map(|l| l.pdl).collect();
-    let pdcs: Vec<f64> = levels.iter().map(|l| l.pdc).collect();
+    let pdhs: Vec<Option<f64>> = levels.iter().map(|l| l.pdh).collect();
+    let pdls: Vec<Option<f64>> = levels.iter().map(|l| l.pdl).collect();
+    let pdcs: Vec<Option<f64>> = levels.iter().map(|l| l.pdc).collect();
     let pocs: Vec<f64> = levels.iter().map(|l| l.poc).collect();
     let vahs: Vec<f64> = levels.iter().map(|l| l.vah).collect();
     let vals: Vec<f64> = levels.iter().map(|l| l.val).collect();
@@ -302,6 +376,25 @@ pub fn write_levels_parquet(levels: &[DailyLevels], path: &Path) -> Result<()> {
     let session_opens: Vec<f64> = levels.iter().map(|l| l.session_open).collect();
     let session_closes: Vec<f64> = levels.iter().map(|l| l.session_close).collect();
     let total_volumes: Vec<u64> = levels.iter().map(|l| l.total_volume).collect();
+    let onhs: Vec<Option<f64>> = levels.iter().map(|l| l.onh).collect();
+    let onls: Vec<Option<f64>> = levels.iter().map(|l| l.onl).collect();
+    let on_pocs: Vec<Option<f64>> = levels.iter().map(|l| l.on_poc).collect();
+    let ib_highs: Vec<Option<f64>> = levels.iter().map(|l| l.ib_high).collect();
+    let ib_lows: Vec<Option<f64>> = levels.iter().map(|l| l.ib_low).collect();
+    let ib_ext_up_1s: Vec<Option<f64>> = levels.iter().map(|l| l.ib_ext_up_1).collect();
+    let ib_ext_down_1s: Vec<Option<f64>> = levels.iter().map(|l| l.ib_ext_down_1).collect();
+    let ib_ext_up_2s: Vec<Option<f64>> = levels.iter().map(|l| l.ib_ext_up_2).collect();
+    let ib_ext_down_2s: Vec<Option<f64>> = levels.iter().map(|l| l.ib_ext_down_2).collect();
+    let or_highs: Vec<Option<f64>> = levels.iter().map(|l| l.or_high).collect();
+    let or_lows: Vec<Option<f64>> = levels.iter().map(|l| l.or_low).collect();
+    let vwaps: Vec<f64> = levels.iter().map(|l| l.vwap).collect();
+    let vwap_std_devs: Vec<f64> = levels.iter().map(|l| l.vwap_std_dev).collect();
+    let vwap_upper_1s: Vec<f64> = levels.iter().map(|l| l.vwap_upper_1).collect();
+    let vwap_lower_1s: Vec<f64> = levels.iter().map(|l| l.vwap_lower_1).collect();
+    let vwap_upper_2s: Vec<f64> = levels.iter().map(|l| l.vwap_upper_2).collect();
+    let vwap_lower_2s: Vec<f64> = levels.iter().map(|l| l.vwap_lower_2).collect();
+    let vwap_upper_3s: Vec<f64> = levels.iter().map(|l| l.vwap_upper_3).collect();
+    let vwap_lower_3s: Vec<f64> = levels.iter().map(|l| l.vwap_lower_3).collect();
 
     let batch = RecordBatch::try_new(
         Arc::new(schema.clone()),
@@ -319,6 +412,25 @@ pub fn write_levels_parquet(levels: &[DailyLevels], path: &Path) -> Result<()> {
             Arc::new(Float64Array::from(session_opens)) as ArrayRef,
             Arc::new(Float64Array::from(session_closes)) as ArrayRef,
             Arc::new(UInt64Array::from(total_volumes)) as ArrayRef,
+            Arc::new(Float64Array::from(onhs)) as ArrayRef,
+            Arc::new(Float64Array::from(onls)) as ArrayRef,
+            Arc::new(Float64Array::from(on_pocs)) as ArrayRef,
+            Arc::new(Float64Array::from(ib_highs)) as ArrayRef,
+            Arc::new(Float64Array::from(ib_lows)) as ArrayRef,
+            Arc::new(Float64Array::from(ib_ext_up_1s)) as ArrayRef,
+            Arc::new(Float64Array::from(ib_ext_down_1s)) as ArrayRef,
+            Arc::new(Float64Array::from(ib_ext_up_2s)) as ArrayRef,
+            Arc::new(Float64Array::from(ib_ext_down_2s)) as ArrayRef,
+            Arc::new(Float64Array::from(or_highs)) as ArrayRef,
+            Arc::new(Float64Array::from(or_lows)) as ArrayRef,
+            Arc::new(Float64Array::from(vwaps)) as ArrayRef,
+            Arc::new(Float64Array::from(vwap_std_devs)) as ArrayRef,
+            Arc::new(Float64Array::from(vwap_upper_1s)) as ArrayRef,
+            Arc::new(Float64Array::from(vwap_lower_1s)) as ArrayRef,
+            Arc::new(Float64Array::from(vwap_upper_2s)) as ArrayRef,
+            Arc::new(Float64Array::from(vwap_lower_2s)) as ArrayRef,
+            Arc::new(Float64Array::from(vwap_upper_3s)) as ArrayRef,
+            Arc::new(Float64Array::from(vwap_lower_3s)) as ArrayRef,
         ],
     )?;
 
@@ -452,3 +564,213 @@ pub fn write_lvn_levels_parquet(lvns: &[LvnLevel], path: &Path) -> Result<()> {
 
     Ok(())
 }
+
+/// Write the per-day volume profile histograms (one row per date, segment and bucket,
+/// flagging single prints) to Parquet file
+pub fn write_profiles_parquet(profiles: &DailyProfiles, path: &Path) -> Result<()> {
+    let mut dates = Vec::new();
+    let mut symbols = Vec::new();
+    let mut segments = Vec::new();
+    let mut prices = Vec::new();
+    let mut buy_volumes = Vec::new();
+    let mut sell_volumes = Vec::new();
+    let mut total_volumes = Vec::new();
+    let mut single_prints = Vec::new();
+
+    for day in profiles.days() {
+        let date = day.date.to_string();
+        for (segment, profile) in &day.segments {
+            let ranges = profile.single_prints();
+            for (price, volume) in profile.levels() {
+                dates.push(date.clone());
+                symbols.push(day.symbol.as_str());
+                segments.push(segment.as_str());
+                prices.push(price);
+                buy_volumes.push(volume.buy_volume);
+                sell_volumes.push(volume.sell_volume);
+                total_volumes.push(volume.total());
+                single_prints.push(ranges.iter().any(|(low, high)| price >= *low && price <= *high));
+            }
+        }
+    }
+
+    if prices.is_empty() {
+        return Ok(());
+    }
+
+    let schema = Schema::new(vec![
+        Field::new("date", DataType::Utf8, false),
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("segment", DataType::Utf8, false),
+        Field::new("price", DataType::Float64, false),
+        Field::new("buy_volume", DataType::UInt64, false),
+        Field::new("sell_volume", DataType::UInt64, false),
+        Field::new("total_volume", DataType::UInt64, false),
+        Field::new("single_print", DataType::Boolean, false),
+    ]);
+
+    let batch = RecordBatch::try_new(
+        Arc::new(schema.clone()),
+        vec![
+            Arc::new(StringArray::from(dates.iter().map(|s| s.as_str()).collect::<Vec<_>>())) as ArrayRef,
+            Arc::new(StringArray::from(symbols)) as ArrayRef,
+            Arc::new(StringArray::from(segments)) as ArrayRef,
+            Arc::new(Float64Array::from(prices)) as ArrayRef,
+            Arc::new(UInt64Array::from(buy_volumes)) as ArrayRef,
+            Arc::new(UInt64Array::from(sell_volumes)) as ArrayRef,
+            Arc::new(UInt64Array::from(total_volumes)) as ArrayRef,
+            Arc::new(BooleanArray::from(single_prints)) as ArrayRef,
+        ],
+    )?;
+
+    let file = File::create(path)?;
+    let props = WriterProperties::builder().build();
+    let mut writer = ArrowWriter::try_new(file, Arc::new(schema), Some(props))?;
+    writer.write(&batch)?;
+    writer.close()?;
+
+    Ok(())
+}
+
+/// Write footprint bars to Parquet file (one row per bar and price)
+pub fn write_footprints_parquet(footprints: &[Footprint], path: &Path) -> Result<()> {
+    let mut symbols = Vec::new();
+    let mut timeframes = Vec::new();
+    let mut bar_starts = Vec::new();
+    let mut bar_ends = Vec::new();
+    let mut opens = Vec::new();
+    let mut highs = Vec::new();
+    let mut lows = Vec::new();
+    let mut closes = Vec::new();
+    let mut bar_deltas = Vec::new();
+    let mut pocs = Vec::new();
+    let mut prices = Vec::new();
+    let mut buy_volumes = Vec::new();
+    let mut sell_volumes = Vec::new();
+    let mut deltas = Vec::new();
+    let mut buy_imbalances = Vec::new();
+    let mut sell_imbalances = Vec::new();
+
+    for footprint in footprints {
+        for level in &footprint.levels {
+            symbols.push(footprint.symbol.as_str());
+            timeframes.push(footprint.timeframe.as_str());
+            bar_starts.push(footprint.timestamp as i64 * 1000);
+            bar_ends.push(footprint.end_time as i64 * 1000);
+            opens.push(footprint.open);
+            highs.push(footprint.high);
+            lows.push(footprint.low);
+            closes.push(footprint.close);
+            bar_deltas.push(footprint.delta);
+            pocs.push(footprint.poc);
+            prices.push(level.price);
+            buy_volumes.push(level.buy_volume);
+            sell_volumes.push(level.sell_volume);
+            deltas.push(level.delta);
+            buy_imbalances.push(level.buy_imbalance);
+            sell_imbalances.push(level.sell_imbalance);
+        }
+    }
+
+    if prices.is_empty() {
+        return Ok(());
+    }
+
+    let schema = Schema::new(vec![
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("timeframe", DataType::Utf8, false),
+        Field::new("bar_start", DataType::Timestamp(TimeUnit::Microsecond, None), false),
+        Field::new("bar_end", DataType::Timestamp(TimeUnit::Microsecond, None), false),
+        Field::new("open", DataType::Float64, false),
+        Field::new("high", DataType::Float64, false),
+        Field::new("low", DataType::Float64, false),
+        Field::new("close", DataType::Float64, false),
+        Field::new("bar_delta", DataType::Int64, false),
+        Field::new("bar_poc", DataType::Float64, false),
+        Field::new("price", DataType::Float64, false),
+        Field::new("buy_volume", DataType::UInt64, false),
+        Field::new("sell_volume", DataType::UInt64, false),
+        Field::new("delta", DataType::Int64, false),
+        Field::new("buy_imbalance", DataType::Boolean, false),
+        Field::new("sell_imbalance", DataType::Boolean, false),
+    ]);
+
+    let batch = RecordBatch::try_new(
+        Arc::new(schema.clone()),
+        vec![
+            Arc::new(StringArray::from(symbols)) as ArrayRef,
+            Arc::new(StringArray::from(timeframes)) as ArrayRef,
+            Arc::new(TimestampMicrosecondArray::from(bar_starts)) as ArrayRef,
+            Arc::new(TimestampMicrosecondArray::from(bar_ends)) as ArrayRef,
+            Arc::new(Float64Array::from(opens)) as ArrayRef,
+            Arc::new(Float64Array::from(highs)) as ArrayRef,
+            Arc::new(Float64Array::from(lows)) as ArrayRef,
+            Arc::new(Float64Array::from(closes)) as ArrayRef,
+            Arc::new(Int64Array::from(bar_deltas)) as ArrayRef,
+            Arc::new(Float64Array::from(pocs)) as ArrayRef,
+            Arc::new(Float64Array::from(prices)) as ArrayRef,
+            Arc::new(UInt64Array::from(buy_volumes)) as ArrayRef,
+            Arc::new(UInt64Array::from(sell_volumes)) as ArrayRef,
+            Arc::new(Int64Array::from(deltas)) as ArrayRef,
+            Arc::new(BooleanArray::from(buy_imbalances)) as ArrayRef,
+            Arc::new(BooleanArray::from(sell_imbalances)) as ArrayRef,
+        ],
+    )?;
+
+    let file = File::create(path)?;
+    let props = WriterProperties::builder().build();
+    let mut writer = ArrowWriter::try_new(file, Arc::new(schema), Some(props))?;
+    writer.write(&batch)?;
+    writer.close()?;
+
+    Ok(())
+}
+
+/// Write composite profile levels to Parquet file
+pub fn write_composite_levels_parquet(levels: &[CompositeLevel], path: &Path) -> Result<()> {
+    if levels.is_empty() {
+        return Ok(());
+    }
+
+    let schema = Schema::new(vec![
+        Field::new("date", DataType::Utf8, false),
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("period", DataType::Utf8, false),
+        Field::new("kind", DataType::Utf8, false),
+        Field::new("price", DataType::Float64, false),
+        Field::new("volume", DataType::UInt64, false),
+        Field::new("from_date", DataType::Utf8, false),
+        Field::new("to_date", DataType::Utf8, false),
+    ]);
+
+    let dates: Vec<String> = levels.iter().map(|l| l.date.to_string()).collect();
+    let symbols: Vec<&str> = levels.iter().map(|l| l.symbol.as_str()).collect();
+    let periods: Vec<&str> = levels.iter().map(|l| l.period.as_str()).collect();
+    let kinds: Vec<&str> = levels.iter().map(|l| l.kind.as_str()).collect();
+    let prices: Vec<f64> = levels.iter().map(|l| l.price).collect();
+    let volumes: Vec<u64> = levels.iter().map(|l| l.volume).collect();
+    let from_dates: Vec<String> = levels.iter().map(|l| l.from_date.to_string()).collect();
+    let to_dates: Vec<String> = levels.iter().map(|l| l.to_date.to_string()).collect();
+
+    let batch = RecordBatch::try_new(
+        Arc::new(schema.clone()),
+        vec![
+            Arc::new(StringArray::from(dates.iter().map(|s| s.as_str()).collect::<Vec<_>>())) as ArrayRef,
+            Arc::new(StringArray::from(symbols)) as ArrayRef,
+            Arc::new(StringArray::from(periods)) as ArrayRef,
+            Arc::new(StringArray::from(kinds)) as ArrayRef,
+            Arc::new(Float64Array::from(prices)) as ArrayRef,
+            Arc::new(UInt64Array::from(volumes)) as ArrayRef,
+            Arc::new(StringArray::from(from_dates.iter().map(|s| s.as_str()).collect::<Vec<_>>())) as ArrayRef,
+            Arc::new(StringArray::from(to_dates.iter().map(|s| s.as_str()).collect::<Vec<_>>())) as ArrayRef,
+        ],
+    )?;
+
+    let file = File::create(path)?;
+    let props = WriterProperties::builder().build();
+    let mut writer = ArrowWriter::try_new(file, Arc::new(schema), Some(props))?;
+    writer.write(&batch)?;
+    writer.close()?;
+
+    Ok(())
+}
@@ -1,11 +1,10 @@
-use anyhow::{Context, Result};
+use anyhow::Result;
 use chrono::{DateTime, Utc};
+use orderflow_bubbles::trade_files::{find_trade_files, TradeFileReader, TradeRecord};
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::BufReader;
 use std::path::{Path, PathBuf};
 
-/// Raw trade from Databento CSV
+/// Raw trade from a Databento CSV export or DBN file
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trade {
     pub ts_event: DateTime<Utc>,
@@ -21,92 +20,28 @@ pub enum Side {
     Sell,
 }
 
-/// CSV row structure matching Databento trades schema
-#[derive(Debug, Deserialize)]
-struct CsvRow {
-    ts_recv: String,
-    ts_event: String,
-    rtype: u8,
-    publisher_id: u32,
-    instrument_id: u64,
-    action: String,
-    side: String,
-    depth: u8,
-    price: f64,
-    size: u64,
-    flags: u32,
-    ts_in_delta: i64,
-    sequence: u64,
-    symbol: String,
-}
-
-/// Find all .zst files in directory, optionally filtered by date
-pub fn find_zst_files(data_dir: &Path, date_filter: Option<&str>) -> Result<Vec<PathBuf>> {
-    let mut files = Vec::new();
-
-    for entry in std::fs::read_dir(data_dir)
-        .with_context(|| format!("Failed to read directory: {:?}", data_dir))?
-    {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.extension().map_or(false, |ext| ext == "zst") {
-            if let Some(filter) = date_filter {
-                let filename = path.file_name().unwrap().to_string_lossy();
-                if !filename.contains(filter) {
-                    continue;
-                }
-            }
-            files.push(path);
+impl From<TradeRecord> for Trade {
+    fn from(record: TradeRecord) -> Self {
+        Self {
+            ts_event: DateTime::from_timestamp_nanos(record.ts_event_ns as i64),
+            price: record.trade.price,
+            size: record.trade.size as u64,
+            side: if record.trade.side == "buy" { Side::Buy } else { Side::Sell },
+            symbol: record.trade.symbol,
         }
     }
+}
 
-    files.sort();
-    Ok(files)
+/// Find all trade files (.zst CSV/DBN or plain .dbn) in directory, optionally filtered by date
+pub fn find_zst_files(data_dir: &Path, date_filter: Option<&str>) -> Result<Vec<PathBuf>> {
+    find_trade_files(data_dir, date_filter)
 }
 
-/// Parse trades from a zstd-compressed CSV file
+/// Parse all trades from a Databento file (zstd CSV export or DBN)
 pub fn parse_zst_trades(path: &Path) -> Result<Vec<Trade>> {
-    let file = File::open(path)
-        .with_context(|| format!("Failed to open file: {:?}", path))?;
-
-    let decoder = zstd::stream::Decoder::new(file)
-        .with_context(|| format!("Failed to create zstd decoder for: {:?}", path))?;
-
-    let reader = BufReader::new(decoder);
-    let mut csv_reader = csv::Reader::from_reader(reader);
-
-    let mut trades = Vec::new();
-
-    for result in csv_reader.deserialize() {
-        let row: CsvRow = result.with_context(|| "Failed to parse CSV row")?;
-
-        // Only process trade actions
-        if row.action != "T" {
-            continue;
-        }
-
-        let side = match row.side.as_str() {
-            "B" => Side::Buy,
-            "A" => Side::Sell,
-            _ => continue, // Skip unknown sides
-        };
-
-        // Parse timestamp
-        let ts_event = DateTime::parse_from_rfc3339(&row.ts_event)
-            .with_context(|| format!("Failed to parse timestamp: {}", row.ts_event))?
-            .with_timezone(&Utc);
-
-        trades.push(Trade {
-            ts_event,
-            price: row.price,
-            size: row.size,
-            side,
-            symbol: row.symbol,
-        });
-    }
-
-    Ok(trades)
+    TradeFileReader::open(path)?
+        .map(|record| record.map(Trade::from))
+        .collect()
 }
 
 #[cfg(test)]
@@ -1,15 +1,33 @@
+use chrono::NaiveDate;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::broadcast;
-use tracing::info;
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
-use crate::supabase::{SignalInsert, SignalOutcomeUpdate, SupabaseClient};
+use crate::aggressor::{OrderAggregator, PrintInfo, DEFAULT_SWEEP_LEVELS};
+use crate::book::{BookUpdate, OrderBook, DEPTH_LEVELS};
+use crate::calendar::SessionCalendar;
+use crate::clock::{Clock, SystemClock};
+use crate::divergence::{DivergenceDetector, DivergenceSettings};
+use crate::footprint::{FootprintBuilder, FootprintSettings};
+use crate::imbalance::{diagonal_stacks, StackedImbalanceAlgorithm, StackedImbalanceSettings};
+use crate::impulse::{ImpulseDirection, ImpulseTracker};
+use crate::instruments::InstrumentSpec;
+use crate::large_trade::{LargeTradeDetector, LargeTradeSettings};
+use crate::market_state::{ImpulseReference, MarketStateClassifier, SessionReference};
+use crate::store::{SharedStore, SignalInsert, SignalOutcomeUpdate};
 use crate::types::{
-    AbsorptionEvent, AbsorptionZone, AppState, Bubble, CVDPoint, ConfluenceEvent, DeltaFlip,
-    SessionStats, SignalRecord, SignalStats, StackedImbalance, Trade, VolumeProfileLevel,
-    WsMessage,
+    AbsorptionEvent, AbsorptionZone, AnchoredVwapSeries, AnchoredVwapUpdate, AppState, Bubble,
+    CVDPoint, ConfluenceEvent, DeltaFlip, DepthSnapshot, DevelopingValueArea, Divergence,
+    ImpulseLegEvent, LargeTrade, LvnNode, LvnUpdate, MarketStateUpdate, SessionStats, SignalRecord,
+    SignalStats, StackedImbalance, Sweep, Trade, VolumeProfileLevel, VwapUpdate, WsMessage,
 };
+use crate::volume_profile::{ValueArea, VolumeProfile};
+use crate::vwap::{AnchorSource, AnchoredVwaps, SessionVwap, VwapAnchor};
+
+/// Aggregation window for bubbles/signals (live interval and replay event windows)
+pub const AGGREGATION_WINDOW_MS: u64 = 1000;
 
 /// Volume snapshot for rolling average calculation
 #[derive(Debug, Clone)]
@@ -34,14 +52,32 @@ struct AbsorptionZoneInternal {
     peak_strength: u8, // 0=weak, 1=medium, 2=strong, 3=defended - never goes down
 }
 
-/// Processing state for trade aggregation
+/// Processing state for trade aggregation (one instance per instrument)
 pub struct ProcessingState {
+    // Instrument this state tracks - stamped on every outgoing message
+    symbol: String,
+    // Tick size and thresholds for the instrument
+    instrument: InstrumentSpec,
     trade_buffer: Vec<Trade>,
     bubble_counter: u64,
     cvd: i64,
-    volume_profile: HashMap<i64, VolumeProfileLevel>, // Key = price * 4 (for 0.25 tick size)
+    volume_profile: VolumeProfile,
     total_buy_volume: u64,
     total_sell_volume: u64,
+    // Session-anchored VWAP and bands
+    vwap: SessionVwap,
+    // VWAPs from manual, impulse and session high/low anchors
+    anchored_vwaps: AnchoredVwaps,
+    // Last streamed value area - a developing value area is sent when it moves
+    developing_value_area: Option<ValueArea>,
+    // Resting liquidity (only fed when a book schema is subscribed/replayed)
+    book: OrderBook,
+    // Footprint bars (bid x ask per tick) on the configured timeframe
+    footprint: FootprintBuilder,
+    // Impulse legs confirmed as 1m bars close, with their LVNs
+    impulses: ImpulseTracker,
+    // Balance vs imbalance, streamed when it changes
+    market_state: MarketStateClassifier,
 
     // Enhanced absorption detection
     window_first_price: Option<f64>,
@@ -50,7 +86,7 @@ pub struct ProcessingState {
     // Rolling volume for dynamic thresholds (last 60 seconds)
     volume_history: Vec<VolumeSnapshot>,
 
-    // Absorption zones by price level (key = price * 4)
+    // Absorption zones by price level (key = price in ticks)
     absorption_zones: HashMap<i64, AbsorptionZoneInternal>,
 
     // CVD trend tracking (for context)
@@ -61,16 +97,32 @@ pub struct ProcessingState {
     prev_cvd_sign: i8, // -1 = negative, 0 = zero, 1 = positive
     last_delta_flip_time: u64, // Prevent rapid-fire flip events (cooldown)
 
+    // Price vs CVD swings and the divergences between them
+    divergences: DivergenceDetector,
+
+    // Prints grouped back into aggressor orders, and the sweep threshold (levels taken)
+    orders: OrderAggregator,
+    sweep_levels: u32,
+
+    // Aggressor orders that reach a size tier
+    large_trades: LargeTradeDetector,
+
     // Stacked imbalances tracking
+    stacked_imbalance: StackedImbalanceSettings, // Detector and its diagonal thresholds
     last_stacked_imbalance_time: u64, // Cooldown to prevent spam
     last_stacked_imbalance_side: Option<String>, // Track last emitted to avoid duplicates
+    diagonal_stack_bars: HashMap<&'static str, u64>, // Side -> start of the last bar it stacked in
 
     // === CONFLUENCE & STATISTICS ===
     // Signal history for confluence detection and outcome tracking
     signal_history: Vec<SignalRecord>,
+    // Signals recorded this session (signal_history only keeps 30 minutes)
+    signal_count: u64,
     // Recent signals within confluence window (5 seconds)
     recent_signals: Vec<(u64, String, String, f64)>, // (timestamp, signal_type, direction, price)
-    // Session tracking
+    // Session tracking - resets when the CME trading date rolls (18:00 ET)
+    calendar: SessionCalendar,
+    trading_date: Option<NaiveDate>,
     session_start: u64,
     session_high: f64,
     session_low: f64,
@@ -80,31 +132,43 @@ pub struct ProcessingState {
     // Last confluence time (cooldown)
     last_confluence_time: u64,
 
-    // Supabase persistence (optional)
-    supabase: Option<SupabaseClient>,
+    // Signal store persistence (optional)
+    store: Option<SharedStore>,
     session_id: Option<Uuid>,
-    // Pending outcome updates to batch send to Supabase
+    // Off while a replay fast-forwards or re-plays a stretch it already persisted
+    persist_signals: bool,
+    // Pending outcome updates to batch send to the store
     pending_outcome_updates: Vec<SignalOutcomeUpdate>,
     // Shared app state for session stats sync
     app_state: Option<Arc<AppState>>,
+
+    // Time source: wall clock (live) or trade timestamps (replay/backtest)
+    clock: Box<dyn Clock>,
 }
 
 impl ProcessingState {
     pub fn new(
-        supabase: Option<SupabaseClient>,
+        store: Option<SharedStore>,
         session_id: Option<Uuid>,
         app_state: Option<Arc<AppState>>,
     ) -> Self {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        let clock = SystemClock;
+        let now = clock.now_ms();
+        let instrument = InstrumentSpec::default();
 
         Self {
+            symbol: String::new(),
+            vwap: SessionVwap::new(VwapAnchor::default(), &instrument.session),
+            anchored_vwaps: AnchoredVwaps::new(VwapAnchor::default(), &instrument.session),
+            developing_value_area: None,
+            book: OrderBook::new(&instrument),
+            footprint: FootprintBuilder::new(FootprintSettings::default(), &instrument),
+            impulses: ImpulseTracker::new(&instrument),
+            market_state: MarketStateClassifier::new(&instrument.profile),
             trade_buffer: Vec::new(),
             bubble_counter: 0,
             cvd: 0,
-            volume_profile: HashMap::new(),
+            volume_profile: VolumeProfile::new(&instrument.profile),
             total_buy_volume: 0,
             total_sell_volume: 0,
             window_first_price: None,
@@ -115,31 +179,135 @@ impl ProcessingState {
             cvd_history: Vec::new(),
             prev_cvd_sign: 0,
             last_delta_flip_time: 0,
+            divergences: DivergenceDetector::new(DivergenceSettings::default()),
+            orders: OrderAggregator::new(),
+            sweep_levels: DEFAULT_SWEEP_LEVELS,
+            large_trades: LargeTradeDetector::new(LargeTradeSettings::default()),
+            stacked_imbalance: StackedImbalanceSettings::default(),
             last_stacked_imbalance_time: 0,
             last_stacked_imbalance_side: None,
+            diagonal_stack_bars: HashMap::new(),
             // Confluence & stats
             signal_history: Vec::new(),
+            signal_count: 0,
             recent_signals: Vec::new(),
+            calendar: SessionCalendar::new(&instrument.session),
+            trading_date: None,
             session_start: now,
             session_high: 0.0,
             session_low: f64::MAX,
             current_price: 0.0,
             last_stats_broadcast: 0,
             last_confluence_time: 0,
-            // Supabase
-            supabase,
+            // Signal store
+            store,
             session_id,
+            persist_signals: true,
             pending_outcome_updates: Vec::new(),
             app_state,
+            clock: Box::new(clock),
+            instrument,
         }
     }
 
+    /// Set the instrument symbol (otherwise taken from the first trade)
+    pub fn with_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.symbol = symbol.into();
+        self
+    }
+
+    /// Set the contract spec (tick size, thresholds) - defaults to NQ
+    pub fn with_instrument(mut self, instrument: InstrumentSpec) -> Self {
+        self.vwap = SessionVwap::new(self.vwap.anchor(), &instrument.session);
+        self.anchored_vwaps = AnchoredVwaps::new(self.vwap.anchor(), &instrument.session);
+        self.calendar = SessionCalendar::new(&instrument.session);
+        self.volume_profile = VolumeProfile::new(&instrument.profile);
+        self.book = OrderBook::new(&instrument);
+        self.footprint = FootprintBuilder::new(*self.footprint.settings(), &instrument);
+        self.impulses = ImpulseTracker::new(&instrument);
+        self.market_state = MarketStateClassifier::new(&instrument.profile);
+        self.instrument = instrument;
+        self
+    }
+
+    /// Set where the session VWAP resets (defaults to the RTH open)
+    pub fn with_vwap_anchor(mut self, anchor: VwapAnchor) -> Self {
+        self.vwap = SessionVwap::new(anchor, &self.instrument.session);
+        self.anchored_vwaps = AnchoredVwaps::new(anchor, &self.instrument.session);
+        self
+    }
+
+    /// Set the footprint bar timeframe and imbalance ratio (defaults to 1m at 3x)
+    pub fn with_footprint(mut self, settings: FootprintSettings) -> Self {
+        self.footprint = FootprintBuilder::new(settings, &self.instrument);
+        self
+    }
+
+    /// Choose the stacked imbalance detector (defaults to the session profile one)
+    pub fn with_stacked_imbalance(mut self, settings: StackedImbalanceSettings) -> Self {
+        self.stacked_imbalance = settings;
+        self
+    }
+
+    /// Set the divergence bar length and swing lookbacks (defaults to 15s bars, 3-bar swings)
+    pub fn with_divergence(mut self, settings: DivergenceSettings) -> Self {
+        self.divergences = DivergenceDetector::new(settings);
+        self
+    }
+
+    /// Set the large trade tiers (defaults to 10/50/100/200, signals from 100)
+    pub fn with_large_trades(mut self, settings: LargeTradeSettings) -> Self {
+        self.large_trades = LargeTradeDetector::new(settings);
+        self
+    }
+
+    /// Set how many price levels an order has to take beyond to be a sweep (defaults to 2)
+    pub fn with_sweep_levels(mut self, levels: u32) -> Self {
+        self.sweep_levels = levels;
+        self
+    }
+
+    /// Anchor a VWAP at an impulse leg start (Unix ms)
+    pub fn add_impulse_anchor(&mut self, start_time: u64) -> bool {
+        self.anchored_vwaps.add_anchor(AnchorSource::Impulse, start_time)
+    }
+
+    /// Seed the market state classifier with the prior session's levels
+    /// (otherwise it learns them when the trading date rolls)
+    pub fn set_prior_session(&mut self, reference: SessionReference) {
+        self.market_state.set_prior_session(reference);
+    }
+
+    /// Make the client-placed anchored VWAPs match `anchor_times` (Unix ms)
+    pub fn sync_manual_vwap_anchors(&mut self, anchor_times: &[u64]) {
+        self.anchored_vwaps.sync_manual(anchor_times);
+    }
+
+    /// Enable/disable inserting new signals into the store (replay seeks)
+    pub fn set_persist_signals(&mut self, enabled: bool) {
+        self.persist_signals = enabled;
+    }
+
+    /// Instrument symbol this state tracks
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    /// Signals recorded so far this session
+    pub fn signal_count(&self) -> u64 {
+        self.signal_count
+    }
+
+    /// Replace the time source (e.g. `EventClock` for replays and backtests)
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.session_start = clock.now_ms();
+        self.clock = Box::new(clock);
+        self
+    }
+
     /// Calculate rolling average volume per second over last N seconds
     fn get_avg_volume_per_second(&self, seconds: u64) -> f64 {
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        let now = self.clock.now_ms();
         let cutoff = now.saturating_sub(seconds * 1000);
 
         let recent: Vec<_> = self
@@ -149,7 +317,7 @@ impl ProcessingState {
             .collect();
 
         if recent.is_empty() {
-            return 200.0; // Default baseline for NQ
+            return self.instrument.thresholds.baseline_volume_per_sec; // Default baseline
         }
 
         let total_vol: u32 = recent.iter().map(|s| s.volume).sum();
@@ -161,78 +329,16 @@ impl ProcessingState {
         self.cvd - self.cvd_5s_ago
     }
 
-    /// Find POC (Point of Control) - price with highest volume
-    fn get_poc(&self) -> Option<f64> {
-        self.volume_profile
-            .values()
-            .max_by_key(|l| l.total_volume)
-            .map(|l| l.price)
-    }
-
-    /// Get VAH/VAL (Value Area High/Low) - 70% of volume
-    fn get_value_area(&self) -> Option<(f64, f64)> {
-        if self.volume_profile.is_empty() {
-            return None;
-        }
-
-        let total_vol: u32 = self.volume_profile.values().map(|l| l.total_volume).sum();
-        let target_vol = (total_vol as f64 * 0.7) as u32;
-
-        let poc = self.get_poc()?;
-        let poc_key = (poc * 4.0).round() as i64;
-
-        let mut included_vol = self.volume_profile.get(&poc_key)?.total_volume;
-        let mut high_key = poc_key;
-        let mut low_key = poc_key;
-
-        // Expand outward from POC until we have 70% of volume
-        while included_vol < target_vol {
-            let above_key = high_key + 1;
-            let below_key = low_key - 1;
-
-            let above_vol = self
-                .volume_profile
-                .get(&above_key)
-                .map(|l| l.total_volume)
-                .unwrap_or(0);
-            let below_vol = self
-                .volume_profile
-                .get(&below_key)
-                .map(|l| l.total_volume)
-                .unwrap_or(0);
-
-            if above_vol == 0 && below_vol == 0 {
-                break;
-            }
-
-            if above_vol >= below_vol {
-                high_key = above_key;
-                included_vol += above_vol;
-            } else {
-                low_key = below_key;
-                included_vol += below_vol;
-            }
-        }
-
-        Some((high_key as f64 / 4.0, low_key as f64 / 4.0))
-    }
-
     /// Check if price is at a key level (POC, VAH, VAL)
     fn is_at_key_level(&self, price: f64) -> (bool, bool, bool) {
-        let poc = self.get_poc();
-        let va = self.get_value_area();
-
-        let tolerance = 0.5; // Within 2 ticks
+        let Some(va) = self.volume_profile.value_area() else {
+            return (false, false, false);
+        };
 
-        let at_poc = poc.map(|p| (price - p).abs() <= tolerance).unwrap_or(false);
-        let at_vah = va
-            .map(|(h, _)| (price - h).abs() <= tolerance)
-            .unwrap_or(false);
-        let at_val = va
-            .map(|(_, l)| (price - l).abs() <= tolerance)
-            .unwrap_or(false);
+        let tolerance = self.instrument.thresholds.key_level_tolerance_points;
+        let near = |level: f64| (price - level).abs() <= tolerance;
 
-        (at_poc, at_vah, at_val)
+        (near(va.poc), near(va.vah), near(va.val))
     }
 
     /// Calculate strength based on event count and context - returns (string, numeric)
@@ -308,8 +414,50 @@ impl ProcessingState {
             .unwrap_or(self.cvd);
     }
 
+    /// Start new session stats, CVD and volume profile (and so the developing
+    /// value area) when a trade lands on the next CME trading date
+    fn roll_trading_date(&mut self, timestamp: u64) {
+        let trading_date = self.calendar.trading_date(timestamp);
+        match self.trading_date {
+            Some(current) if current >= trading_date => return,
+            Some(_) => {
+                info!("📅 {} rolled to trading date {}", self.symbol, trading_date);
+                self.session_start = timestamp;
+                self.session_high = 0.0;
+                self.session_low = f64::MAX;
+                self.total_buy_volume = 0;
+                self.total_sell_volume = 0;
+                self.cvd = 0;
+                self.cvd_5s_ago = 0;
+                self.cvd_history.clear();
+                self.prev_cvd_sign = 0;
+                self.volume_profile = VolumeProfile::new(&self.instrument.profile);
+                self.developing_value_area = None;
+                self.market_state.roll_session();
+            }
+            None => {}
+        }
+        self.trading_date = Some(trading_date);
+    }
+
     /// Add a trade to the processing buffer
     pub fn add_trade(&mut self, trade: Trade) {
+        self.add_print(trade, None);
+    }
+
+    /// Add a trade with its Databento print fields, which group it into its
+    /// aggressor order (without them, same millisecond and side is one order)
+    pub fn add_print(&mut self, trade: Trade, print: Option<PrintInfo>) {
+        // Advance event time (no-op for wall clock)
+        self.clock.observe(trade.timestamp);
+        if self.session_start == 0 {
+            self.session_start = trade.timestamp;
+        }
+        self.roll_trading_date(trade.timestamp);
+        if self.symbol.is_empty() {
+            self.symbol = trade.symbol.clone();
+        }
+
         // Update CVD
         let delta = if trade.side == "buy" {
             trade.size as i64
@@ -325,47 +473,83 @@ impl ProcessingState {
             self.total_sell_volume += trade.size as u64;
         }
 
+        // Update session VWAP
+        self.vwap.add(trade.price, trade.size as u64, trade.timestamp);
+        self.anchored_vwaps.add(trade.price, trade.size as u64, trade.timestamp);
+
         // Track first and last price for absorption detection
         if self.window_first_price.is_none() {
             self.window_first_price = Some(trade.price);
         }
         self.window_last_price = Some(trade.price);
 
-        // Update volume profile (0.25 tick size)
-        let price_key = (trade.price * 4.0).round() as i64;
-        let rounded_price = price_key as f64 / 4.0;
-
+        // Update volume profile and footprint
         self.volume_profile
-            .entry(price_key)
-            .and_modify(|level| {
-                if trade.side == "buy" {
-                    level.buy_volume += trade.size;
-                } else {
-                    level.sell_volume += trade.size;
-                }
-                level.total_volume += trade.size;
-            })
-            .or_insert(VolumeProfileLevel {
-                price: rounded_price,
-                buy_volume: if trade.side == "buy" { trade.size } else { 0 },
-                sell_volume: if trade.side == "sell" { trade.size } else { 0 },
-                total_volume: trade.size,
-            });
+            .add(trade.price, trade.size as u64, trade.side == "buy", trade.timestamp);
+        self.footprint
+            .add(trade.price, trade.size as u64, trade.side == "buy", trade.timestamp);
+        self.market_state
+            .add_trade(trade.price, trade.size as u64, trade.side == "buy", trade.timestamp);
+        self.impulses
+            .add_trade(trade.price, trade.size as u64, trade.side == "buy", trade.timestamp);
+        self.divergences.add_trade(trade.price, self.cvd, trade.timestamp);
+        self.orders
+            .add(trade.price, trade.size as u64, trade.side == "buy", trade.timestamp, print);
 
         // Add to buffer for aggregation
         self.trade_buffer.push(trade);
     }
 
+    /// Send footprint bars that closed, then the open bar if it changed
+    fn send_footprints(&mut self, tx: &broadcast::Sender<WsMessage>) {
+        let settings = *self.footprint.settings();
+        for bar in self.footprint.take_completed() {
+            let footprint = bar.to_footprint(&self.symbol, &settings, true);
+            let _ = tx.send(WsMessage::Footprint(footprint));
+        }
+        if let Some(bar) = self.footprint.take_updated() {
+            let footprint = bar.to_footprint(&self.symbol, &settings, false);
+            let _ = tx.send(WsMessage::Footprint(footprint));
+        }
+    }
+
+    /// Apply an order book record
+    pub fn add_book_update(&mut self, update: BookUpdate) {
+        self.clock.observe(update.timestamp);
+        self.book.apply(&update);
+    }
+
+    /// Send the depth snapshot (if the book changed) and liquidity events
+    pub fn process_book(&mut self, tx: &broadcast::Sender<WsMessage>) {
+        let now = self.clock.now_ms();
+        if self.book.take_changed() {
+            let (bids, asks) = self.book.depth(DEPTH_LEVELS);
+            let _ = tx.send(WsMessage::Depth(DepthSnapshot {
+                symbol: self.symbol.clone(),
+                timestamp: now,
+                bids,
+                asks,
+            }));
+        }
+
+        for mut event in self.book.take_events() {
+            event.symbol = self.symbol.clone();
+            info!(
+                "💧 {} {} {} @ {:.2}: {} (resting {}, refills {})",
+                event.symbol, event.kind, event.side, event.price, event.size, event.resting_size, event.refills
+            );
+            let _ = tx.send(WsMessage::Liquidity(event));
+        }
+        self.book.prune(now);
+    }
+
     /// Process the trade buffer and emit bubbles, CVD points, and absorption events
     pub fn process_buffer(&mut self, tx: &broadcast::Sender<WsMessage>) {
         if self.trade_buffer.is_empty() {
             return;
         }
 
-        let now = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64;
+        let now = self.clock.now_ms();
 
         // Cleanup old data
         self.cleanup_old_zones(now);
@@ -430,17 +614,10 @@ impl ProcessingState {
         let imbalance_ratio = delta.abs() as f64 / total_volume as f64;
         let is_significant_imbalance = imbalance_ratio > 0.15;
 
-        // Get symbol from dominant trades (or first trade if empty)
-        let symbol = dominant_trades
-            .first()
-            .map(|t| t.symbol.clone())
-            .or_else(|| self.trade_buffer.first().map(|t| t.symbol.clone()))
-            .unwrap_or_else(|| "UNKNOWN".to_string());
-
         // Create bubble
         let bubble = Bubble {
             id: format!("bubble-{}", self.bubble_counter),
-            symbol,
+            symbol: self.symbol.clone(),
             price: avg_price,
             size: dominant_volume,
             side: dominant_side.to_string(),
@@ -457,12 +634,23 @@ impl ProcessingState {
 
         // Send CVD point
         let cvd_point = CVDPoint {
+            symbol: self.symbol.clone(),
             timestamp: now,
             value: self.cvd,
             x: 0.92,
         };
         let _ = tx.send(WsMessage::CVDPoint(cvd_point));
 
+        // Send session VWAP and bands, then the anchored VWAPs
+        self.send_vwap(tx, now);
+        self.send_anchored_vwaps(tx, now);
+        self.send_developing_value_area(tx, now);
+
+        // Impulse legs that just completed, then the market state (which leans on
+        // them) before this window's signals, so they're judged in it
+        self.send_impulse_legs(tx, now);
+        self.send_market_state(tx, now);
+
         // === DELTA FLIP DETECTION ===
         let current_cvd_sign = if self.cvd > 0 {
             1i8
@@ -487,6 +675,7 @@ impl ProcessingState {
             };
 
             let delta_flip = DeltaFlip {
+                symbol: self.symbol.clone(),
                 timestamp: now,
                 flip_type: "zero_cross".to_string(),
                 direction: direction.to_string(),
@@ -512,9 +701,20 @@ impl ProcessingState {
 
         self.prev_cvd_sign = current_cvd_sign;
 
+        // === DIVERGENCE DETECTION ===
+        // Price and CVD swings confirmed since the last window
+        self.detect_divergences(tx, now, avg_price);
+
+        // === SWEEP & LARGE TRADE DETECTION ===
+        // Individual orders hidden inside the window's bubble
+        self.detect_orders(tx, now);
+
         // === STACKED IMBALANCES DETECTION ===
-        // Look for 3+ consecutive price levels with same-direction imbalance
-        self.detect_stacked_imbalances(tx, now);
+        // Look for consecutive price levels with same-direction imbalance
+        match self.stacked_imbalance.algorithm {
+            StackedImbalanceAlgorithm::Session => self.detect_stacked_imbalances(tx, now),
+            StackedImbalanceAlgorithm::Diagonal => self.detect_diagonal_stacked_imbalances(tx, now),
+        }
 
         // === ENHANCED ABSORPTION DETECTION ===
         if let (Some(first_price), Some(last_price)) =
@@ -528,15 +728,15 @@ impl ProcessingState {
             let avg_vol = self.get_avg_volume_per_second(30);
             let min_delta_threshold = (avg_vol * 0.4).max(20.0) as i64;
 
-            // Price movement threshold - 1 tick (0.25 for NQ)
-            const PRICE_THRESHOLD: f64 = 0.25;
+            // Price movement threshold (1 tick for NQ/ES)
+            let price_threshold = self.instrument.thresholds.absorption_move_points;
 
             if abs_delta >= min_delta_threshold {
                 // Check for absorption:
                 // - Buying absorbed: delta > 0 but price didn't go up (or went down)
                 // - Selling absorbed: delta < 0 but price didn't go down (or went up)
-                let is_buying_absorbed = delta > 0 && price_change <= PRICE_THRESHOLD;
-                let is_selling_absorbed = delta < 0 && price_change >= -PRICE_THRESHOLD;
+                let is_buying_absorbed = delta > 0 && price_change <= price_threshold;
+                let is_selling_absorbed = delta < 0 && price_change >= -price_threshold;
 
                 if is_buying_absorbed || is_selling_absorbed {
                     let absorption_type = if is_buying_absorbed {
@@ -544,7 +744,7 @@ impl ProcessingState {
                     } else {
                         "selling"
                     };
-                    let price_key = (avg_price * 4.0).round() as i64;
+                    let price_key = self.instrument.price_key(avg_price);
 
                     // Get context
                     let (at_poc, at_vah, at_val) = self.is_at_key_level(avg_price);
@@ -596,6 +796,7 @@ impl ProcessingState {
 
                     if should_emit {
                         let absorption_event = AbsorptionEvent {
+                            symbol: self.symbol.clone(),
                             timestamp: now,
                             price: avg_price,
                             absorption_type: absorption_type.to_string(),
@@ -681,9 +882,15 @@ impl ProcessingState {
             .collect();
 
         if !zones.is_empty() {
-            let _ = tx.send(WsMessage::AbsorptionZones { zones });
+            let _ = tx.send(WsMessage::AbsorptionZones {
+                symbol: self.symbol.clone(),
+                zones,
+            });
         }
 
+        // Footprint bars that closed or grew this window
+        self.send_footprints(tx);
+
         // Reset window price tracking
         self.window_first_price = None;
         self.window_last_price = None;
@@ -718,9 +925,9 @@ impl ProcessingState {
         }
 
         // Aggregate into 1-point buckets (4 ticks = 1 point for NQ)
-        let mut point_buckets: HashMap<i64, (u32, u32)> = HashMap::new();
-        for level in self.volume_profile.values() {
-            let point_key = level.price.floor() as i64; // 1-point buckets
+        let mut point_buckets: HashMap<i64, (u64, u64)> = HashMap::new();
+        for (price, level) in self.volume_profile.levels() {
+            let point_key = price.floor() as i64; // 1-point buckets
             point_buckets
                 .entry(point_key)
                 .and_modify(|(buy, sell)| {
@@ -737,7 +944,7 @@ impl ProcessingState {
         // Minimum 70% dominance to count as imbalanced
         const MIN_IMBALANCE_RATIO: f64 = 0.70;
         // Minimum volume at a level to consider it (filter noise)
-        const MIN_LEVEL_VOLUME: u32 = 100;
+        const MIN_LEVEL_VOLUME: u64 = 100;
 
         let mut best_streak_side: Option<&str> = None;
         let mut best_streak: Vec<(i64, i64)> = Vec::new();
@@ -810,12 +1017,14 @@ impl ProcessingState {
                     let total_imbalance: i64 = best_streak.iter().map(|(_, delta)| delta.abs()).sum();
 
                     let stacked = StackedImbalance {
+                        symbol: self.symbol.clone(),
                         timestamp: now,
                         side: side.to_string(),
                         level_count: best_streak.len() as u32,
                         price_high,
                         price_low,
                         total_imbalance,
+                        algorithm: StackedImbalanceAlgorithm::Session.as_str().to_string(),
                         x: 0.92,
                     };
 
@@ -842,6 +1051,173 @@ impl ProcessingState {
         }
     }
 
+    /// Diagonal stacked imbalances within each footprint bar - one per side per bar
+    fn detect_diagonal_stacked_imbalances(&mut self, tx: &broadcast::Sender<WsMessage>, now: u64) {
+        let mut found = Vec::new();
+        for bar in self.footprint.recent() {
+            for stack in diagonal_stacks(bar, &self.stacked_imbalance) {
+                if self.diagonal_stack_bars.get(stack.side) != Some(&bar.start_time) {
+                    found.push((bar.start_time, stack));
+                }
+            }
+        }
+
+        for (bar_start, stack) in found {
+            self.diagonal_stack_bars.insert(stack.side, bar_start);
+
+            let _ = tx.send(WsMessage::StackedImbalance(StackedImbalance {
+                symbol: self.symbol.clone(),
+                timestamp: now,
+                side: stack.side.to_string(),
+                level_count: stack.level_count,
+                price_high: stack.price_high,
+                price_low: stack.price_low,
+                total_imbalance: stack.total_imbalance,
+                algorithm: StackedImbalanceAlgorithm::Diagonal.as_str().to_string(),
+                x: 0.92,
+            }));
+
+            info!(
+                "📊 DIAGONAL STACKED IMBALANCE [{}]: {} ticks from {:.2} to {:.2} in the {} bar | total imbalance={}",
+                stack.side.to_uppercase(),
+                stack.level_count,
+                stack.price_low,
+                stack.price_high,
+                self.footprint.settings().timeframe,
+                stack.total_imbalance
+            );
+
+            let direction = if stack.side == "buy" { "bullish" } else { "bearish" };
+            let mid_price = (stack.price_low + stack.price_high) / 2.0;
+            self.record_signal(tx, now, "stacked_imbalance", direction, mid_price);
+        }
+    }
+
+    /// Divergences between price and CVD swings confirmed since the last window,
+    /// priced at the window they're confirmed in
+    fn detect_divergences(&mut self, tx: &broadcast::Sender<WsMessage>, now: u64, price: f64) {
+        for divergence in self.divergences.take_divergences() {
+            let kind = divergence.kind.as_str();
+            let direction = divergence.direction;
+
+            let _ = tx.send(WsMessage::Divergence(Divergence {
+                symbol: self.symbol.clone(),
+                timestamp: now,
+                kind: kind.to_string(),
+                direction: direction.to_string(),
+                price,
+                swing_time: divergence.swing.time,
+                swing_price: divergence.swing.price,
+                swing_cvd: divergence.swing.cvd,
+                prev_swing_time: divergence.previous.time,
+                prev_swing_price: divergence.previous.price,
+                prev_swing_cvd: divergence.previous.cvd,
+                x: 0.92,
+            }));
+
+            info!(
+                "🔀 DIVERGENCE [{} {}]: price {:.2} → {:.2}, CVD {} → {}",
+                kind.to_uppercase(),
+                direction.to_uppercase(),
+                divergence.previous.price,
+                divergence.swing.price,
+                divergence.previous.cvd,
+                divergence.swing.cvd
+            );
+
+            // Record for confluence detection and stats
+            self.record_signal(tx, now, "divergence", direction, price);
+        }
+    }
+
+    /// Aggressor orders completed since the last window - the ones that took more
+    /// than `sweep_levels` price levels are sweeps, then each is checked for a size tier
+    fn detect_orders(&mut self, tx: &broadcast::Sender<WsMessage>, now: u64) {
+        for order in self.orders.take_orders(now) {
+            self.large_trades.add_order(&order, self.sweep_levels);
+            if !order.is_sweep(self.sweep_levels) {
+                continue;
+            }
+            let side = if order.is_buy { "buy" } else { "sell" };
+
+            let _ = tx.send(WsMessage::Sweep(Sweep {
+                symbol: self.symbol.clone(),
+                timestamp: now,
+                trade_time: order.timestamp,
+                side: side.to_string(),
+                price: order.price,
+                start_price: order.first_price,
+                end_price: order.last_price,
+                size: order.size,
+                fills: order.fills,
+                levels: order.levels,
+                x: 0.92,
+            }));
+
+            info!(
+                "🧹 SWEEP [{}]: {} {} across {} levels {:.2} → {:.2} ({} fills)",
+                side.to_uppercase(),
+                order.size,
+                self.symbol,
+                order.levels,
+                order.first_price,
+                order.last_price,
+                order.fills
+            );
+
+            // Record for confluence detection and stats
+            let direction = if order.is_buy { "bullish" } else { "bearish" };
+            self.record_signal(tx, now, "sweep", direction, order.price);
+        }
+
+        self.detect_large_trades(tx, now);
+    }
+
+    /// Large trades among the orders just completed - the ones at or above the
+    /// signal tier are also recorded as signals
+    fn detect_large_trades(&mut self, tx: &broadcast::Sender<WsMessage>, now: u64) {
+        let signal_tier = self.large_trades.settings().signal_tier;
+        for large in self.large_trades.take_large_trades() {
+            let side = if large.is_buy { "buy" } else { "sell" };
+
+            let _ = tx.send(WsMessage::LargeTrade(LargeTrade {
+                symbol: self.symbol.clone(),
+                timestamp: now,
+                trade_time: large.timestamp,
+                kind: large.kind().to_string(),
+                side: side.to_string(),
+                price: large.price,
+                price_low: large.price_low,
+                price_high: large.price_high,
+                size: large.size,
+                fills: large.fills,
+                tier: large.tier,
+                tier_min: large.tier_min,
+                x: 0.92,
+            }));
+
+            if large.tier < signal_tier {
+                continue;
+            }
+
+            info!(
+                "🐋 LARGE TRADE [{}]: {} {} @ {:.2} ({}, {} fills, tier {} {}+)",
+                side.to_uppercase(),
+                large.size,
+                self.symbol,
+                large.price,
+                large.kind(),
+                large.fills,
+                large.tier,
+                large.tier_min
+            );
+
+            // Record for confluence detection and stats
+            let direction = if large.is_buy { "bullish" } else { "bearish" };
+            self.record_signal(tx, now, "large_trade", direction, large.price);
+        }
+    }
+
     /// Record a signal for confluence detection and stats tracking
     fn record_signal(
         &mut self,
@@ -879,9 +1255,12 @@ impl ProcessingState {
             outcome: None,
         };
         self.signal_history.push(record);
+        self.signal_count += 1;
 
-        // Persist to Supabase (fire-and-forget)
-        if let (Some(client), Some(session_id)) = (&self.supabase, self.session_id) {
+        // Persist to the store (fire-and-forget)
+        if let (true, Some(store), Some(session_id)) =
+            (self.persist_signals, &self.store, self.session_id)
+        {
             let signal = SignalInsert {
                 session_id,
                 timestamp: now as i64,
@@ -893,9 +1272,11 @@ impl ProcessingState {
                 outcome: None,
                 metadata: None,
             };
-            let client = client.clone();
+            let store = store.clone();
             tokio::spawn(async move {
-                client.insert_signal(signal).await;
+                if let Err(e) = store.insert_signal(signal).await {
+                    error!("Failed to insert signal to {}: {}", store.name(), e);
+                }
             });
         }
 
@@ -968,6 +1349,7 @@ impl ProcessingState {
 
         // Create confluence event
         let confluence = ConfluenceEvent {
+            symbol: self.symbol.clone(),
             timestamp: now,
             price,
             direction: direction.to_string(),
@@ -1023,7 +1405,7 @@ impl ProcessingState {
 
                 // Determine outcome based on direction
                 let move_amount = current_price - record.price;
-                let min_move = 2.0; // Minimum 2 points for meaningful move
+                let min_move = self.instrument.thresholds.outcome_move_points; // Minimum move to score
 
                 record.outcome = Some(
                     if record.direction == "bullish" {
@@ -1049,7 +1431,7 @@ impl ProcessingState {
                 needs_update = true;
             }
 
-            // Queue Supabase update if we changed anything
+            // Queue a store update if we changed anything
             if needs_update {
                 if let Some(session_id) = self.session_id {
                     self.pending_outcome_updates.push(SignalOutcomeUpdate {
@@ -1135,7 +1517,7 @@ impl ProcessingState {
     }
 
     /// Broadcast session stats to clients and flush pending outcome updates
-    fn broadcast_stats(&mut self, tx: &broadcast::Sender<WsMessage>, _now: u64) {
+    fn broadcast_stats(&mut self, tx: &broadcast::Sender<WsMessage>, now: u64) {
         let high = if self.session_high > 0.0 {
             self.session_high
         } else {
@@ -1147,42 +1529,218 @@ impl ProcessingState {
             self.current_price
         };
         let volume = self.total_buy_volume + self.total_sell_volume;
+        let session = self.calendar.classify(now);
 
         let stats = SessionStats {
+            symbol: self.symbol.clone(),
             session_start: self.session_start,
             delta_flips: self.calculate_signal_stats("delta_flip"),
             absorptions: self.calculate_signal_stats("absorption"),
             stacked_imbalances: self.calculate_signal_stats("stacked_imbalance"),
+            divergences: self.calculate_signal_stats("divergence"),
+            large_trades: self.calculate_signal_stats("large_trade"),
+            sweeps: self.calculate_signal_stats("sweep"),
             confluences: self.calculate_signal_stats("confluence"),
             current_price: self.current_price,
             session_high: high,
             session_low: low,
             total_volume: volume,
+            trading_date: self.trading_date.unwrap_or(session.trading_date).to_string(),
+            segment: session.segment.as_str().to_string(),
+            holiday: session.holiday.map(str::to_string),
         };
 
-        let _ = tx.send(WsMessage::SessionStats(stats));
+        let _ = tx.send(WsMessage::SessionStats(Box::new(stats)));
 
         // Sync session stats to shared AppState for shutdown finalization
         if let Some(ref app_state) = self.app_state {
             let app_state = app_state.clone();
+            let symbol = self.symbol.clone();
             tokio::spawn(async move {
                 let mut stats = app_state.session_stats.write().await;
-                *stats = (high, low, volume);
+                stats.insert(symbol, (high, low, volume));
             });
         }
 
-        // Flush pending outcome updates to Supabase
-        if let Some(client) = &self.supabase {
+        // Flush pending outcome updates to the store
+        if let Some(store) = &self.store {
             let updates = std::mem::take(&mut self.pending_outcome_updates);
             if !updates.is_empty() {
-                let client = client.clone();
+                let store = store.clone();
                 tokio::spawn(async move {
-                    client.update_signal_outcomes(updates).await;
+                    if let Err(e) = store.update_signal_outcomes(updates).await {
+                        warn!("Failed to update signal outcomes: {}", e);
+                    }
                 });
             }
         }
     }
 
+    /// Send the session VWAP and its 1σ/2σ/3σ bands
+    fn send_vwap(&self, tx: &broadcast::Sender<WsMessage>, now: u64) {
+        let Some(bands) = self.vwap.bands() else {
+            return;
+        };
+        let _ = tx.send(WsMessage::Vwap(VwapUpdate {
+            symbol: self.symbol.clone(),
+            timestamp: now,
+            vwap: bands.vwap,
+            std_dev: bands.std_dev,
+            upper1: bands.upper(1),
+            lower1: bands.lower(1),
+            upper2: bands.upper(2),
+            lower2: bands.lower(2),
+            upper3: bands.upper(3),
+            lower3: bands.lower(3),
+            anchor: self.vwap.anchor().as_str().to_string(),
+            anchor_time: bands.anchor_time,
+            volume: bands.volume,
+        }));
+    }
+
+    fn send_anchored_vwaps(&self, tx: &broadcast::Sender<WsMessage>, now: u64) {
+        let anchors: Vec<AnchoredVwapSeries> = self
+            .anchored_vwaps
+            .series()
+            .map(|(anchor, bands)| AnchoredVwapSeries {
+                id: anchor.id(),
+                source: anchor.source.as_str().to_string(),
+                anchor_time: anchor.anchor_time,
+                vwap: bands.vwap,
+                std_dev: bands.std_dev,
+                volume: bands.volume,
+            })
+            .collect();
+        if anchors.is_empty() {
+            return;
+        }
+        let _ = tx.send(WsMessage::AnchoredVwaps(AnchoredVwapUpdate {
+            symbol: self.symbol.clone(),
+            timestamp: now,
+            anchors,
+        }));
+    }
+
+    /// Send the POC/VAH/VAL if they moved since the last update
+    fn send_developing_value_area(&mut self, tx: &broadcast::Sender<WsMessage>, now: u64) {
+        let Some(va) = self.volume_profile.value_area() else {
+            return;
+        };
+        if self.developing_value_area == Some(va) {
+            return;
+        }
+        self.developing_value_area = Some(va);
+
+        let _ = tx.send(WsMessage::DevelopingValueArea(DevelopingValueArea {
+            symbol: self.symbol.clone(),
+            timestamp: now,
+            poc: va.poc,
+            vah: va.vah,
+            val: va.val,
+            volume: self.volume_profile.total_volume(),
+        }));
+    }
+
+    /// Send impulse legs confirmed since the last window, each followed by its LVNs.
+    /// Legs that broke structure also anchor a VWAP at their start.
+    fn send_impulse_legs(&mut self, tx: &broadcast::Sender<WsMessage>, now: u64) {
+        for (leg, lvns) in self.impulses.take_confirmed() {
+            let start_time = leg.start_time.timestamp_millis() as u64;
+            let end_time = leg.end_time.timestamp_millis() as u64;
+            let direction = match leg.direction {
+                ImpulseDirection::Up => "up",
+                ImpulseDirection::Down => "down",
+            };
+
+            info!(
+                "🚀 IMPULSE LEG {} {}: {:.2} → {:.2} in {} bars (score {}/5, {} LVNs)",
+                self.symbol,
+                direction.to_uppercase(),
+                leg.start_price,
+                leg.end_price,
+                leg.num_candles,
+                leg.score_total,
+                lvns.len()
+            );
+
+            if leg.broke_swing {
+                self.add_impulse_anchor(start_time);
+            }
+            self.market_state.add_impulse(ImpulseReference {
+                start_time,
+                end_time: leg.close_time().timestamp_millis() as u64,
+                start_price: leg.start_price,
+                end_price: leg.end_price,
+            });
+
+            let _ = tx.send(WsMessage::ImpulseLeg(ImpulseLegEvent {
+                symbol: self.symbol.clone(),
+                timestamp: now,
+                start_time,
+                end_time,
+                start_price: leg.start_price,
+                end_price: leg.end_price,
+                direction: direction.to_string(),
+                score: leg.score_total,
+                broke_swing: leg.broke_swing,
+                was_fast: leg.was_fast,
+                uniform_candles: leg.uniform_candles,
+                volume_increased: leg.volume_increased,
+                sufficient_size: leg.sufficient_size,
+                num_candles: leg.num_candles as u32,
+                total_volume: leg.total_volume,
+            }));
+
+            if !lvns.is_empty() {
+                let _ = tx.send(WsMessage::Lvns(LvnUpdate {
+                    symbol: self.symbol.clone(),
+                    timestamp: now,
+                    impulse_start_time: start_time,
+                    impulse_end_time: end_time,
+                    levels: lvns
+                        .iter()
+                        .map(|lvn| LvnNode {
+                            price: lvn.price,
+                            volume: lvn.volume,
+                            volume_ratio: lvn.volume_ratio,
+                        })
+                        .collect(),
+                }));
+            }
+        }
+    }
+
+    /// Send the market state if the classifier changed its mind
+    fn send_market_state(&mut self, tx: &broadcast::Sender<WsMessage>, now: u64) {
+        let Some(price) = self.window_last_price else {
+            return;
+        };
+        let Some(reading) = self.market_state.update(price, now) else {
+            return;
+        };
+
+        info!(
+            "🧭 MARKET STATE {}: {} → {} @ {:.2} ({})",
+            self.symbol,
+            reading.previous.map_or("none", |state| state.as_str()),
+            reading.state.as_str(),
+            price,
+            reading.reasons.join(", ")
+        );
+
+        let _ = tx.send(WsMessage::MarketState(MarketStateUpdate {
+            symbol: self.symbol.clone(),
+            timestamp: now,
+            state: reading.state.as_str().to_string(),
+            previous: reading.previous.map(|state| state.as_str().to_string()),
+            price,
+            value_area_overlap: reading.value_area_overlap,
+            rotations: reading.rotations,
+            range_expansion: reading.range_expansion,
+            reasons: reading.reasons.iter().map(|reason| reason.to_string()).collect(),
+        }));
+    }
+
     /// Get session stats for finalization
     pub fn get_session_stats(&self) -> (f64, f64, u64) {
         (
@@ -1194,8 +1752,20 @@ impl ProcessingState {
 
     /// Send the current volume profile to clients
     pub fn send_volume_profile(&self, tx: &broadcast::Sender<WsMessage>) {
-        let levels: Vec<VolumeProfileLevel> = self.volume_profile.values().cloned().collect();
-        let _ = tx.send(WsMessage::VolumeProfile { levels });
+        let levels: Vec<VolumeProfileLevel> = self
+            .volume_profile
+            .levels()
+            .map(|(price, level)| VolumeProfileLevel {
+                price,
+                buy_volume: level.buy_volume as u32,
+                sell_volume: level.sell_volume as u32,
+                total_volume: level.total() as u32,
+            })
+            .collect();
+        let _ = tx.send(WsMessage::VolumeProfile {
+            symbol: self.symbol.clone(),
+            levels,
+        });
     }
 }
 
@@ -1337,22 +1907,15 @@ mod tests {
     #[test]
     fn test_poc_empty_profile() {
         let state = create_test_state();
-        assert!(state.get_poc().is_none());
+        assert!(state.volume_profile.poc().is_none());
     }
 
     #[test]
     fn test_poc_single_level() {
         let mut state = create_test_state();
-        state.volume_profile.insert(
-            20000,
-            VolumeProfileLevel {
-                price: 5000.0,
-                buy_volume: 100,
-                sell_volume: 50,
-                total_volume: 150,
-            },
-        );
-        let poc = state.get_poc();
+        state.volume_profile.add(5000.0, 100, true, 0);
+        state.volume_profile.add(5000.0, 50, false, 0);
+        let poc = state.volume_profile.poc();
         assert_eq!(poc, Some(5000.0));
     }
 
@@ -1360,42 +1923,18 @@ mod tests {
     fn test_poc_multiple_levels() {
         let mut state = create_test_state();
         // Add three levels with different volumes
-        state.volume_profile.insert(
-            20000,
-            VolumeProfileLevel {
-                price: 5000.0,
-                buy_volume: 100,
-                sell_volume: 50,
-                total_volume: 150,
-            },
-        );
-        state.volume_profile.insert(
-            20004,
-            VolumeProfileLevel {
-                price: 5001.0,
-                buy_volume: 200,
-                sell_volume: 150,
-                total_volume: 350, // Highest volume - this should be POC
-            },
-        );
-        state.volume_profile.insert(
-            20008,
-            VolumeProfileLevel {
-                price: 5002.0,
-                buy_volume: 80,
-                sell_volume: 70,
-                total_volume: 150,
-            },
-        );
+        state.volume_profile.add(5000.0, 150, true, 0);
+        state.volume_profile.add(5001.0, 350, true, 0); // Highest volume - this should be POC
+        state.volume_profile.add(5002.0, 150, true, 0);
 
-        let poc = state.get_poc();
+        let poc = state.volume_profile.poc();
         assert_eq!(poc, Some(5001.0)); // Highest volume level
     }
 
     #[test]
     fn test_value_area_empty_profile() {
         let state = create_test_state();
-        assert!(state.get_value_area().is_none());
+        assert!(state.volume_profile.value_area().is_none());
     }
 
     // ===========================================
@@ -1415,15 +1954,8 @@ mod tests {
     fn test_is_at_key_level_at_poc() {
         let mut state = create_test_state();
         // Create a profile with POC at 5000.0
-        state.volume_profile.insert(
-            20000,
-            VolumeProfileLevel {
-                price: 5000.0,
-                buy_volume: 500,
-                sell_volume: 500,
-                total_volume: 1000,
-            },
-        );
+        state.volume_profile.add(5000.0, 500, true, 0);
+        state.volume_profile.add(5000.0, 500, false, 0);
 
         let (at_poc, _, _) = state.is_at_key_level(5000.0);
         assert!(at_poc);
@@ -1608,13 +2140,12 @@ mod tests {
             timestamp: 1000,
         });
 
-        // Price key = 5000.0 * 4 = 20000
-        let level = state.volume_profile.get(&20000);
+        let level = state.volume_profile.get(5000.0);
         assert!(level.is_some());
         let level = level.unwrap();
         assert_eq!(level.buy_volume, 100);
         assert_eq!(level.sell_volume, 0);
-        assert_eq!(level.total_volume, 100);
+        assert_eq!(level.total(), 100);
     }
 
     #[test]
@@ -1638,10 +2169,10 @@ mod tests {
             timestamp: 1001,
         });
 
-        let level = state.volume_profile.get(&20000).unwrap();
+        let level = state.volume_profile.get(5000.0).unwrap();
         assert_eq!(level.buy_volume, 100);
         assert_eq!(level.sell_volume, 50);
-        assert_eq!(level.total_volume, 150);
+        assert_eq!(level.total(), 150);
     }
 
     #[test]
@@ -1674,6 +2205,230 @@ mod tests {
         assert_eq!(state.window_last_price, Some(5005.0));
     }
 
+    // ===========================================
+    // CLOCK TESTS
+    // ===========================================
+
+    #[test]
+    fn test_event_clock_stamps_with_trade_time() {
+        let mut state = create_test_state().with_clock(crate::clock::EventClock::new());
+        let (tx, mut rx) = broadcast::channel(100);
+
+        state.add_trade(Trade {
+            symbol: "NQ".to_string(),
+            price: 5000.0,
+            size: 10,
+            side: "buy".to_string(),
+            timestamp: 1_700_000_000_000,
+        });
+        state.add_trade(Trade {
+            symbol: "NQ".to_string(),
+            price: 5000.25,
+            size: 5,
+            side: "buy".to_string(),
+            timestamp: 1_700_000_000_400,
+        });
+        state.process_buffer(&tx);
+
+        assert_eq!(state.session_start, 1_700_000_000_000);
+        match rx.try_recv().unwrap() {
+            WsMessage::Bubble(bubble) => assert_eq!(bubble.timestamp, 1_700_000_000_400),
+            other => panic!("expected bubble, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_event_clock_avg_volume_window() {
+        let mut state = create_test_state().with_clock(crate::clock::EventClock::new());
+        state.add_trade(Trade {
+            symbol: "NQ".to_string(),
+            price: 5000.0,
+            size: 1,
+            side: "buy".to_string(),
+            timestamp: 1_000_000,
+        });
+        // Snapshot 10s before event time is inside the 30s window even though wall time is years later
+        state.volume_history.push(VolumeSnapshot {
+            timestamp: 990_000,
+            volume: 300,
+            delta: 0,
+        });
+        assert_eq!(state.get_avg_volume_per_second(30), 10.0);
+    }
+
+    // ===========================================
+    // VWAP TESTS
+    // ===========================================
+
+    #[test]
+    fn test_vwap_streamed_each_window() {
+        let mut state = create_test_state().with_clock(crate::clock::EventClock::new());
+        let (tx, mut rx) = broadcast::channel(100);
+
+        for (price, size, timestamp) in [(5000.0, 10, 1_700_000_000_000), (5010.0, 30, 1_700_000_000_500)] {
+            state.add_trade(Trade {
+                symbol: "NQ".to_string(),
+                price,
+                size,
+                side: "buy".to_string(),
+                timestamp,
+            });
+        }
+        state.process_buffer(&tx);
+
+        let vwap = std::iter::from_fn(|| rx.try_recv().ok())
+            .find_map(|msg| match msg {
+                WsMessage::Vwap(vwap) => Some(vwap),
+                _ => None,
+            })
+            .expect("vwap message");
+        assert_eq!(vwap.vwap, 5007.5);
+        assert_eq!(vwap.volume, 40);
+        assert_eq!(vwap.anchor, "rth");
+        assert!(vwap.upper1 > vwap.vwap && vwap.lower3 < vwap.lower2);
+    }
+
+    #[test]
+    fn test_impulse_leg_anchors_vwap_live() {
+        let mut state = create_test_state().with_clock(crate::clock::EventClock::new());
+        let (tx, mut rx) = broadcast::channel(10_000);
+        let start = 1_764_687_600_000; // 2025-12-02 15:00 UTC
+
+        // Ten quiet minutes, three 12-point bull minutes on double volume, then two more
+        let mut minutes: Vec<(f64, f64, u32)> = (0..10)
+            .map(|m| (21_500.0, if m % 2 == 0 { 21_501.0 } else { 21_500.0 }, 100))
+            .collect();
+        minutes.extend([(21_500.0, 21_512.0, 200), (21_512.0, 21_524.0, 200), (21_524.0, 21_536.0, 200)]);
+        minutes.extend([(21_536.0, 21_535.0, 100), (21_535.0, 21_536.0, 100)]);
+
+        let mut impulse_anchors = Vec::new();
+        for (minute, &(open, close, volume)) in minutes.iter().enumerate() {
+            let ts = start + minute as u64 * 60_000;
+            for (price, timestamp) in [(open, ts), (close, ts + 30_000)] {
+                state.add_trade(Trade {
+                    symbol: "NQ".to_string(),
+                    price,
+                    size: volume / 2,
+                    side: "buy".to_string(),
+                    timestamp,
+                });
+                state.process_buffer(&tx);
+            }
+            while let Ok(msg) = rx.try_recv() {
+                if let WsMessage::AnchoredVwaps(update) = msg {
+                    impulse_anchors = update
+                        .anchors
+                        .into_iter()
+                        .filter(|a| a.source == "impulse")
+                        .map(|a| a.anchor_time)
+                        .collect();
+                }
+            }
+            // The leg is only known once its last minute has closed
+            if minute <= 12 {
+                assert!(impulse_anchors.is_empty());
+            }
+        }
+
+        assert_eq!(impulse_anchors, vec![start + 10 * 60_000]);
+    }
+
+    #[test]
+    fn test_session_stats_roll_at_globex_open() {
+        let mut state = create_test_state().with_clock(crate::clock::EventClock::new());
+        let (tx, mut rx) = broadcast::channel(100);
+
+        // 2025-06-11 17:59 ET, then 18:00 ET - the Globex open starts Thursday's trading date
+        let before_roll = 1_749_679_140_000;
+        for (size, timestamp) in [(10, before_roll), (5, before_roll + 60_000)] {
+            state.add_trade(Trade {
+                symbol: "NQ".to_string(),
+                price: 21_500.0,
+                size,
+                side: "buy".to_string(),
+                timestamp,
+            });
+        }
+        state.broadcast_stats(&tx, before_roll + 60_000);
+
+        let stats = std::iter::from_fn(|| rx.try_recv().ok())
+            .find_map(|msg| match msg {
+                WsMessage::SessionStats(stats) => Some(stats),
+                _ => None,
+            })
+            .expect("session stats message");
+        assert_eq!(stats.trading_date, "2025-06-12");
+        assert_eq!(stats.segment, "eth");
+        assert_eq!(stats.session_start, before_roll + 60_000);
+        assert_eq!(stats.total_volume, 5);
+    }
+
+    #[test]
+    fn test_developing_value_area_sent_when_it_moves() {
+        let mut state = create_test_state().with_clock(crate::clock::EventClock::new());
+        let (tx, mut rx) = broadcast::channel(100);
+        let mut flush = |state: &mut ProcessingState, price: f64, size: u32, timestamp: u64| {
+            state.add_trade(Trade {
+                symbol: "NQ".to_string(),
+                price,
+                size,
+                side: "buy".to_string(),
+                timestamp,
+            });
+            state.process_buffer(&tx);
+            std::iter::from_fn(|| rx.try_recv().ok())
+                .filter_map(|msg| match msg {
+                    WsMessage::DevelopingValueArea(va) => Some(va),
+                    _ => None,
+                })
+                .last()
+        };
+
+        let first = flush(&mut state, 21_500.0, 10, 1_700_000_000_000).expect("initial value area");
+        assert_eq!((first.poc, first.vah, first.val), (21_500.0, 21_500.0, 21_500.0));
+
+        // More volume at the POC doesn't move the value area
+        assert!(flush(&mut state, 21_500.0, 5, 1_700_000_001_000).is_none());
+
+        // Heavier volume one tick up moves the POC
+        let moved = flush(&mut state, 21_500.25, 30, 1_700_000_002_000).expect("value area moved");
+        assert_eq!(moved.poc, 21_500.25);
+        assert_eq!(moved.volume, 45);
+    }
+
+    #[test]
+    fn test_developing_value_area_starts_over_at_globex_open() {
+        let mut state = create_test_state().with_clock(crate::clock::EventClock::new());
+        let (tx, mut rx) = broadcast::channel(100);
+        let mut flush = |state: &mut ProcessingState, price: f64, size: u32, timestamp: u64| {
+            state.add_trade(Trade {
+                symbol: "NQ".to_string(),
+                price,
+                size,
+                side: "buy".to_string(),
+                timestamp,
+            });
+            state.process_buffer(&tx);
+            std::iter::from_fn(|| rx.try_recv().ok())
+                .filter_map(|msg| match msg {
+                    WsMessage::DevelopingValueArea(va) => Some(va),
+                    _ => None,
+                })
+                .last()
+        };
+
+        // 2025-06-11 17:59 ET, then 18:00 ET - the Globex open starts Thursday's trading date
+        let before_roll = 1_749_679_140_000;
+        let previous = flush(&mut state, 21_500.0, 50, before_roll).expect("previous session value area");
+        assert_eq!((previous.poc, previous.volume), (21_500.0, 50));
+
+        // The new session's profile only holds its own trades
+        let current = flush(&mut state, 21_600.0, 5, before_roll + 60_000).expect("new session value area");
+        assert_eq!((current.poc, current.vah, current.val), (21_600.0, 21_600.0, 21_600.0));
+        assert_eq!(current.volume, 5);
+        assert_eq!(state.cvd, 5);
+    }
+
     // ===========================================
     // OUTCOME DETERMINATION TESTS
     // ===========================================
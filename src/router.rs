@@ -0,0 +1,343 @@
+//! Symbol Router
+//!
+//! Keeps one ProcessingState per instrument so CVD, volume profile, absorption
+//! zones and session stats never mix markets (e.g. NQ and ES on one feed).
+//! States are keyed by Databento instrument_id; symbols are resolved from the
+//! symbology mapping.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::info;
+use uuid::Uuid;
+
+use crate::aggressor::{PrintInfo, DEFAULT_SWEEP_LEVELS};
+use crate::book::BookUpdate;
+use crate::clock::EventClock;
+use crate::divergence::DivergenceSettings;
+use crate::footprint::FootprintSettings;
+use crate::imbalance::StackedImbalanceSettings;
+use crate::instruments::InstrumentRegistry;
+use crate::large_trade::LargeTradeSettings;
+use crate::processing::ProcessingState;
+use crate::store::SharedStore;
+use crate::types::{AppState, ManualVwapAnchor, Trade, WsMessage};
+use crate::vwap::VwapAnchor;
+
+/// Routes trades to a per-instrument ProcessingState
+pub struct SymbolRouter {
+    states: HashMap<u32, ProcessingState>,
+    // instrument_id -> symbol (from symbology mapping or the trades themselves)
+    symbols: HashMap<u32, String>,
+    // Next id handed out to sources that only know the symbol (db replay, demo)
+    next_synthetic_id: u32,
+
+    // Passed to every ProcessingState created by the router
+    store: Option<SharedStore>,
+    session_id: Option<Uuid>,
+    app_state: Option<Arc<AppState>>,
+    event_time: bool,
+    persist_signals: bool,
+    // Contract specs, VWAP anchor, footprint, imbalance, divergence, large trade and sweep settings for new states (from AppState when available)
+    instruments: InstrumentRegistry,
+    vwap_anchor: VwapAnchor,
+    footprint: FootprintSettings,
+    stacked_imbalance: StackedImbalanceSettings,
+    divergence: DivergenceSettings,
+    large_trades: LargeTradeSettings,
+    sweep_levels: u32,
+}
+
+impl SymbolRouter {
+    pub fn new(
+        store: Option<SharedStore>,
+        session_id: Option<Uuid>,
+        app_state: Option<Arc<AppState>>,
+    ) -> Self {
+        let instruments = app_state
+            .as_ref()
+            .map(|state| state.instruments.clone())
+            .unwrap_or_default();
+        let vwap_anchor = app_state
+            .as_ref()
+            .map(|state| state.vwap_anchor)
+            .unwrap_or_default();
+        let footprint = app_state
+            .as_ref()
+            .map(|state| state.footprint)
+            .unwrap_or_default();
+        let stacked_imbalance = app_state
+            .as_ref()
+            .map(|state| state.stacked_imbalance)
+            .unwrap_or_default();
+        let divergence = app_state
+            .as_ref()
+            .map(|state| state.divergence)
+            .unwrap_or_default();
+        let large_trades = app_state
+            .as_ref()
+            .map(|state| state.large_trades.clone())
+            .unwrap_or_default();
+        let sweep_levels = app_state
+            .as_ref()
+            .map(|state| state.sweep_levels)
+            .unwrap_or(DEFAULT_SWEEP_LEVELS);
+
+        Self {
+            states: HashMap::new(),
+            symbols: HashMap::new(),
+            next_synthetic_id: u32::MAX,
+            store,
+            session_id,
+            app_state,
+            event_time: false,
+            persist_signals: true,
+            instruments,
+            vwap_anchor,
+            footprint,
+            stacked_imbalance,
+            divergence,
+            large_trades,
+            sweep_levels,
+        }
+    }
+
+    /// Create states driven by trade timestamps (replays) instead of wall time
+    pub fn with_event_clock(mut self) -> Self {
+        self.event_time = true;
+        self
+    }
+
+    /// Record an instrument_id -> symbol mapping (Databento SymbolMappingMsg or metadata)
+    pub fn on_symbol_mapping(&mut self, instrument_id: u32, symbol: impl Into<String>) {
+        let symbol = symbol.into();
+        if self.symbols.get(&instrument_id) != Some(&symbol) {
+            info!("Symbol mapping: {} -> {}", instrument_id, symbol);
+            self.symbols.insert(instrument_id, symbol);
+        }
+    }
+
+    /// Resolve the symbol for an instrument
+    pub fn symbol_for(&self, instrument_id: u32) -> Option<&str> {
+        self.symbols.get(&instrument_id).map(|s| s.as_str())
+    }
+
+    /// Symbols with an active ProcessingState
+    pub fn symbols(&self) -> Vec<String> {
+        self.states.values().map(|s| s.symbol().to_string()).collect()
+    }
+
+    /// Signals recorded across all instruments
+    pub fn signal_count(&self) -> u64 {
+        self.states.values().map(|s| s.signal_count()).sum()
+    }
+
+    /// Route a trade to its instrument's ProcessingState
+    pub fn add_trade(&mut self, instrument_id: u32, trade: Trade) {
+        self.add_print(instrument_id, trade, None);
+    }
+
+    /// Route a trade with its Databento print fields (see `ProcessingState::add_print`)
+    pub fn add_print(&mut self, instrument_id: u32, mut trade: Trade, print: Option<PrintInfo>) {
+        trade.symbol = self.resolve_symbol(instrument_id, &trade.symbol);
+        self.state_for(instrument_id, &trade.symbol).add_print(trade, print);
+    }
+
+    /// Route an order book record to its instrument's ProcessingState
+    pub fn add_book_update(&mut self, instrument_id: u32, symbol: &str, update: BookUpdate) {
+        let symbol = self.resolve_symbol(instrument_id, symbol);
+        self.state_for(instrument_id, &symbol).add_book_update(update);
+    }
+
+    // Mapped symbol, else the record's own symbol (or the id) becomes the mapping
+    fn resolve_symbol(&mut self, instrument_id: u32, symbol: &str) -> String {
+        match self.symbols.get(&instrument_id) {
+            Some(symbol) => symbol.clone(),
+            None => {
+                let symbol = if symbol.is_empty() {
+                    format!("ID:{}", instrument_id)
+                } else {
+                    symbol.to_string()
+                };
+                self.on_symbol_mapping(instrument_id, symbol.clone());
+                symbol
+            }
+        }
+    }
+
+    fn state_for(&mut self, instrument_id: u32, symbol: &str) -> &mut ProcessingState {
+        if !self.states.contains_key(&instrument_id) {
+            let state = self.create_state(symbol);
+            self.states.insert(instrument_id, state);
+        }
+        self.states.get_mut(&instrument_id).expect("state inserted above")
+    }
+
+    /// Route a trade from a source without instrument ids (keyed by its symbol)
+    pub fn add_trade_by_symbol(&mut self, trade: Trade) {
+        let instrument_id = self
+            .symbols
+            .iter()
+            .find(|(_, symbol)| **symbol == trade.symbol)
+            .map(|(id, _)| *id)
+            .unwrap_or_else(|| {
+                let id = self.next_synthetic_id;
+                self.next_synthetic_id -= 1;
+                id
+            });
+        self.add_trade(instrument_id, trade);
+    }
+
+    /// Whether new signals are persisted (off while a seek replays persisted data)
+    pub fn persists_signals(&self) -> bool {
+        self.persist_signals
+    }
+
+    /// Enable/disable persisting signals for every instrument (replay seeks)
+    pub fn set_persist_signals(&mut self, enabled: bool) {
+        if self.persist_signals != enabled {
+            self.persist_signals = enabled;
+            for state in self.states.values_mut() {
+                state.set_persist_signals(enabled);
+            }
+        }
+    }
+
+    /// Apply the client-placed anchored VWAPs to every instrument
+    pub fn sync_vwap_anchors(&mut self, anchors: &[ManualVwapAnchor]) {
+        for state in self.states.values_mut() {
+            let anchor_times: Vec<u64> = anchors
+                .iter()
+                .filter(|a| a.symbol.as_deref().is_none_or(|symbol| symbol == state.symbol()))
+                .map(|a| a.timestamp)
+                .collect();
+            state.sync_manual_vwap_anchors(&anchor_times);
+        }
+    }
+
+    /// Flush every instrument's aggregation window
+    pub fn process_buffers(&mut self, tx: &broadcast::Sender<WsMessage>) {
+        for state in self.states.values_mut() {
+            state.process_buffer(tx);
+            state.process_book(tx);
+        }
+    }
+
+    /// Send every instrument's volume profile
+    pub fn send_volume_profiles(&self, tx: &broadcast::Sender<WsMessage>) {
+        for state in self.states.values() {
+            state.send_volume_profile(tx);
+        }
+    }
+
+    fn create_state(&self, symbol: &str) -> ProcessingState {
+        info!("Creating processing state for {}", symbol);
+        let mut state = ProcessingState::new(
+            self.store.clone(),
+            self.session_id,
+            self.app_state.clone(),
+        )
+        .with_symbol(symbol)
+        .with_instrument(self.instruments.resolve(symbol))
+        .with_vwap_anchor(self.vwap_anchor)
+        .with_footprint(self.footprint)
+        .with_stacked_imbalance(self.stacked_imbalance)
+        .with_divergence(self.divergence)
+        .with_large_trades(self.large_trades.clone())
+        .with_sweep_levels(self.sweep_levels);
+        state.set_persist_signals(self.persist_signals);
+
+        if self.event_time {
+            state.with_clock(EventClock::new())
+        } else {
+            state
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade(symbol: &str, price: f64) -> Trade {
+        Trade {
+            symbol: symbol.to_string(),
+            price,
+            size: 10,
+            side: "buy".to_string(),
+            timestamp: 1_000,
+        }
+    }
+
+    #[test]
+    fn test_routes_instruments_to_separate_states() {
+        let mut router = SymbolRouter::new(None, None, None).with_event_clock();
+        router.on_symbol_mapping(1, "NQ.c.0");
+        router.on_symbol_mapping(2, "ES.c.0");
+
+        router.add_trade(1, trade("", 21500.0));
+        router.add_trade(2, trade("", 6000.0));
+        router.add_trade(1, trade("", 21500.25));
+
+        let mut symbols = router.symbols();
+        symbols.sort();
+        assert_eq!(symbols, vec!["ES.c.0", "NQ.c.0"]);
+
+        let (tx, mut rx) = broadcast::channel(100);
+        router.send_volume_profiles(&tx);
+        let mut seen = 0;
+        while let Ok(msg) = rx.try_recv() {
+            if let WsMessage::VolumeProfile { symbol, levels } = msg {
+                let expected = if symbol == "NQ.c.0" { 2 } else { 1 };
+                assert_eq!(levels.len(), expected);
+                seen += 1;
+            }
+        }
+        assert_eq!(seen, 2);
+    }
+
+    #[test]
+    fn test_unmapped_instrument_uses_trade_symbol() {
+        let mut router = SymbolRouter::new(None, None, None);
+        router.add_trade(42, trade("NQH6", 21500.0));
+        assert_eq!(router.symbol_for(42), Some("NQH6"));
+
+        router.add_trade(43, trade("", 21500.0));
+        assert_eq!(router.symbol_for(43), Some("ID:43"));
+    }
+
+    #[test]
+    fn test_add_trade_by_symbol_reuses_state() {
+        let mut router = SymbolRouter::new(None, None, None);
+        router.add_trade_by_symbol(trade("NQH6", 21500.0));
+        router.add_trade_by_symbol(trade("NQH6", 21501.0));
+        router.add_trade_by_symbol(trade("NQZ5", 21400.0));
+        assert_eq!(router.symbols().len(), 2);
+    }
+
+    #[test]
+    fn test_vwap_anchors_follow_symbol() {
+        let mut router = SymbolRouter::new(None, None, None).with_event_clock();
+        router.on_symbol_mapping(1, "NQ.c.0");
+        router.on_symbol_mapping(2, "ES.c.0");
+        router.add_trade(1, trade("", 21500.0));
+        router.add_trade(2, trade("", 6000.0));
+
+        router.sync_vwap_anchors(&[
+            ManualVwapAnchor { symbol: Some("NQ.c.0".to_string()), timestamp: 1_000 },
+            ManualVwapAnchor { symbol: None, timestamp: 500 },
+        ]);
+
+        let (tx, mut rx) = broadcast::channel(100);
+        router.process_buffers(&tx);
+        let mut manual = HashMap::new();
+        while let Ok(msg) = rx.try_recv() {
+            if let WsMessage::AnchoredVwaps(update) = msg {
+                let count = update.anchors.iter().filter(|a| a.source == "manual").count();
+                manual.insert(update.symbol, count);
+            }
+        }
+        assert_eq!(manual.get("NQ.c.0"), Some(&2));
+        assert_eq!(manual.get("ES.c.0"), Some(&1));
+    }
+}
@@ -0,0 +1,593 @@
+//! SQLite Store
+//!
+//! Embedded `SignalStore` in a single database file - full history, stats and
+//! db replay with no outside service (`--store sqlite`). Mirrors the Supabase
+//! tables; queries run on the blocking pool behind one connection.
+
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
+use chrono::{SecondsFormat, Utc};
+use rusqlite::{params, params_from_iter, Connection, OptionalExtension, Row};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::store::{
+    aggregate_stats, AggregateStats, BarRecord, SessionRecord, SessionRow, SignalInsert,
+    SignalOutcomeUpdate, SignalQuery, SignalRow, SignalStore, SignalSummary, UserConfig,
+};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY,
+    started_at TEXT NOT NULL,
+    ended_at TEXT,
+    mode TEXT NOT NULL,
+    symbols TEXT NOT NULL,
+    session_high REAL,
+    session_low REAL,
+    total_volume INTEGER
+);
+CREATE TABLE IF NOT EXISTS signals (
+    id TEXT PRIMARY KEY,
+    session_id TEXT REFERENCES sessions(id),
+    created_at TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    signal_type TEXT NOT NULL,
+    direction TEXT NOT NULL,
+    price REAL NOT NULL,
+    price_after_1m REAL,
+    price_after_5m REAL,
+    outcome TEXT,
+    metadata TEXT
+);
+CREATE INDEX IF NOT EXISTS signals_session_timestamp ON signals(session_id, timestamp);
+CREATE TABLE IF NOT EXISTS config (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS replay_bars_1s (
+    timestamp TEXT NOT NULL,
+    open REAL NOT NULL,
+    high REAL NOT NULL,
+    low REAL NOT NULL,
+    close REAL NOT NULL,
+    volume INTEGER NOT NULL,
+    buy_volume INTEGER NOT NULL,
+    sell_volume INTEGER NOT NULL,
+    delta INTEGER NOT NULL,
+    trade_count INTEGER NOT NULL,
+    symbol TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS replay_bars_1s_timestamp ON replay_bars_1s(timestamp);
+";
+
+/// Key of the single config row (same as the Supabase config table)
+const CONFIG_KEY: &str = "user_settings";
+
+/// Signal store in an embedded SQLite database
+#[derive(Clone)]
+pub struct SqliteStore {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl SqliteStore {
+    /// Open (or create) the database file and its tables
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let conn = Connection::open(path)
+            .with_context(|| format!("Failed to open SQLite store {:?}", path))?;
+        info!("Opened SQLite store: {:?}", path);
+        Self::with_connection(conn)
+    }
+
+    /// In-memory database (tests, throwaway runs)
+    pub fn open_in_memory() -> Result<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)
+            .context("Failed to create SQLite tables")?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Run a query on the blocking pool
+    async fn run<T, F>(&self, f: F) -> Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut Connection) -> Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut conn = conn
+                .lock()
+                .map_err(|_| anyhow!("SQLite connection lock poisoned"))?;
+            f(&mut conn)
+        })
+        .await?
+    }
+}
+
+/// Current time as stored in `created_at`/`started_at` columns
+fn now_rfc3339() -> String {
+    Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Normalize a bar timestamp to UTC so text ordering is time ordering
+fn normalize_timestamp(timestamp: &str) -> Result<String> {
+    let parsed = chrono::DateTime::parse_from_rfc3339(timestamp)
+        .with_context(|| format!("Invalid bar timestamp '{}'", timestamp))?;
+    Ok(parsed
+        .with_timezone(&Utc)
+        .to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+/// WHERE clause and bound values for the signal filters
+fn signal_filters(query: &SignalQuery) -> (String, Vec<String>) {
+    let mut clauses = Vec::new();
+    let mut values = Vec::new();
+
+    let filters = [
+        ("signal_type = ?", &query.signal_type),
+        ("direction = ?", &query.direction),
+        ("outcome = ?", &query.outcome),
+        ("created_at >= ?", &query.start_date),
+        ("created_at <= ?", &query.end_date),
+    ];
+    for (clause, value) in filters {
+        if let Some(value) = value {
+            clauses.push(clause);
+            values.push(value.clone());
+        }
+    }
+
+    if clauses.is_empty() {
+        (String::new(), values)
+    } else {
+        (format!(" WHERE {}", clauses.join(" AND ")), values)
+    }
+}
+
+/// WHERE clause for one date (YYYY-MM-DD) of replay bars
+fn bar_filter(date_filter: Option<&str>) -> (&'static str, Vec<String>) {
+    match date_filter {
+        Some(date) => (" WHERE substr(timestamp, 1, 10) = ?", vec![date.to_string()]),
+        None => ("", Vec::new()),
+    }
+}
+
+fn parse_uuid(value: String) -> rusqlite::Result<Uuid> {
+    Uuid::parse_str(&value).map_err(|e| {
+        rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e))
+    })
+}
+
+fn signal_row(row: &Row) -> rusqlite::Result<SignalRow> {
+    let metadata: Option<String> = row.get("metadata")?;
+    Ok(SignalRow {
+        id: parse_uuid(row.get("id")?)?,
+        session_id: row
+            .get::<_, Option<String>>("session_id")?
+            .map(parse_uuid)
+            .transpose()?,
+        created_at: row.get("created_at")?,
+        timestamp: row.get("timestamp")?,
+        signal_type: row.get("signal_type")?,
+        direction: row.get("direction")?,
+        price: row.get("price")?,
+        price_after_1m: row.get("price_after_1m")?,
+        price_after_5m: row.get("price_after_5m")?,
+        outcome: row.get("outcome")?,
+        metadata: metadata.and_then(|m| serde_json::from_str(&m).ok()),
+    })
+}
+
+fn session_row(row: &Row) -> rusqlite::Result<SessionRow> {
+    let symbols: String = row.get("symbols")?;
+    Ok(SessionRow {
+        id: parse_uuid(row.get("id")?)?,
+        started_at: row.get("started_at")?,
+        ended_at: row.get("ended_at")?,
+        mode: row.get("mode")?,
+        symbols: serde_json::from_str(&symbols).unwrap_or_default(),
+        session_high: row.get("session_high")?,
+        session_low: row.get("session_low")?,
+        total_volume: row.get("total_volume")?,
+    })
+}
+
+fn bar_record(row: &Row) -> rusqlite::Result<BarRecord> {
+    Ok(BarRecord {
+        timestamp: row.get("timestamp")?,
+        open: row.get("open")?,
+        high: row.get("high")?,
+        low: row.get("low")?,
+        close: row.get("close")?,
+        volume: row.get("volume")?,
+        buy_volume: row.get("buy_volume")?,
+        sell_volume: row.get("sell_volume")?,
+        delta: row.get("delta")?,
+        trade_count: row.get("trade_count")?,
+        symbol: row.get("symbol")?,
+    })
+}
+
+#[async_trait]
+impl SignalStore for SqliteStore {
+    fn name(&self) -> &'static str {
+        "SQLite"
+    }
+
+    async fn insert_session(&self, session: &SessionRecord) -> Result<Uuid> {
+        let session = session.clone();
+        self.run(move |conn| {
+            let id = session.id.unwrap_or_else(Uuid::new_v4);
+            conn.execute(
+                "INSERT INTO sessions (id, started_at, mode, symbols, session_high, session_low, total_volume)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                params![
+                    id.to_string(),
+                    now_rfc3339(),
+                    session.mode,
+                    serde_json::to_string(&session.symbols)?,
+                    session.session_high,
+                    session.session_low,
+                    session.total_volume,
+                ],
+            )?;
+            Ok(id)
+        })
+        .await
+    }
+
+    async fn update_session(
+        &self,
+        session_id: Uuid,
+        session_high: f64,
+        session_low: f64,
+        total_volume: u64,
+    ) -> Result<()> {
+        self.run(move |conn| {
+            conn.execute(
+                "UPDATE sessions SET session_high = ?1, session_low = ?2, total_volume = ?3, ended_at = ?4
+                 WHERE id = ?5",
+                params![
+                    session_high,
+                    session_low,
+                    total_volume as i64,
+                    now_rfc3339(),
+                    session_id.to_string(),
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn insert_signal(&self, signal: SignalInsert) -> Result<()> {
+        self.run(move |conn| {
+            let metadata = signal.metadata.as_ref().map(serde_json::to_string).transpose()?;
+            conn.execute(
+                "INSERT INTO signals (id, session_id, created_at, timestamp, signal_type, direction, price,
+                                      price_after_1m, price_after_5m, outcome, metadata)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![
+                    Uuid::new_v4().to_string(),
+                    signal.session_id.to_string(),
+                    now_rfc3339(),
+                    signal.timestamp,
+                    signal.signal_type,
+                    signal.direction,
+                    signal.price,
+                    signal.price_after_1m,
+                    signal.price_after_5m,
+                    signal.outcome,
+                    metadata,
+                ],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn update_signal_outcomes(&self, updates: Vec<SignalOutcomeUpdate>) -> Result<()> {
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut stmt = tx.prepare(
+                    "UPDATE signals SET price_after_1m = ?1, price_after_5m = ?2, outcome = ?3
+                     WHERE timestamp = ?4 AND session_id = ?5",
+                )?;
+                for update in &updates {
+                    stmt.execute(params![
+                        update.price_after_1m,
+                        update.price_after_5m,
+                        update.outcome,
+                        update.timestamp,
+                        update.session_id.to_string(),
+                    ])?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn get_config(&self) -> Result<UserConfig> {
+        self.run(|conn| {
+            let value: Option<String> = conn
+                .query_row("SELECT value FROM config WHERE key = ?1", [CONFIG_KEY], |row| row.get(0))
+                .optional()?;
+            Ok(value
+                .and_then(|v| serde_json::from_str(&v).ok())
+                .unwrap_or_default())
+        })
+        .await
+    }
+
+    async fn set_config(&self, config: &UserConfig) -> Result<()> {
+        let value = serde_json::to_string(config)?;
+        self.run(move |conn| {
+            conn.execute(
+                "INSERT INTO config (key, value, updated_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(key) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+                params![CONFIG_KEY, value, now_rfc3339()],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn query_signals(&self, query: &SignalQuery) -> Result<Vec<SignalRow>> {
+        let limit = query.limit.unwrap_or(50).min(200);
+        let offset = query.offset.unwrap_or(0);
+        let (filters, values) = signal_filters(query);
+
+        self.run(move |conn| {
+            let sql = format!(
+                "SELECT * FROM signals{} ORDER BY timestamp DESC LIMIT {} OFFSET {}",
+                filters, limit, offset
+            );
+            let mut stmt = conn.prepare(&sql)?;
+            let rows = stmt
+                .query_map(params_from_iter(values), signal_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+
+    async fn count_signals(&self, query: &SignalQuery) -> Result<u32> {
+        let (filters, values) = signal_filters(query);
+
+        self.run(move |conn| {
+            let sql = format!("SELECT COUNT(*) FROM signals{}", filters);
+            let count: i64 = conn.query_row(&sql, params_from_iter(values), |row| row.get(0))?;
+            Ok(count as u32)
+        })
+        .await
+    }
+
+    async fn query_sessions(&self, limit: u32) -> Result<Vec<SessionRow>> {
+        let limit = limit.min(100);
+        self.run(move |conn| {
+            let mut stmt = conn.prepare("SELECT * FROM sessions ORDER BY started_at DESC LIMIT ?1")?;
+            let rows = stmt
+                .query_map([limit], session_row)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+
+    async fn get_aggregate_stats(&self) -> Result<AggregateStats> {
+        let signals = self
+            .run(|conn| {
+                let mut stmt = conn.prepare("SELECT signal_type, direction, outcome FROM signals")?;
+                let rows = stmt
+                    .query_map([], |row| {
+                        Ok(SignalSummary {
+                            signal_type: row.get(0)?,
+                            direction: row.get(1)?,
+                            outcome: row.get(2)?,
+                        })
+                    })?
+                    .collect::<rusqlite::Result<Vec<_>>>()?;
+                Ok(rows)
+            })
+            .await?;
+        Ok(aggregate_stats(&signals))
+    }
+
+    async fn insert_bars(&self, bars: &[BarRecord]) -> Result<()> {
+        let bars = bars.to_vec();
+        self.run(move |conn| {
+            let tx = conn.transaction()?;
+            {
+                let mut stmt = tx.prepare(
+                    "INSERT INTO replay_bars_1s (timestamp, open, high, low, close, volume, buy_volume,
+                                                 sell_volume, delta, trade_count, symbol)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                )?;
+                for bar in &bars {
+                    stmt.execute(params![
+                        normalize_timestamp(&bar.timestamp)?,
+                        bar.open,
+                        bar.high,
+                        bar.low,
+                        bar.close,
+                        bar.volume,
+                        bar.buy_volume,
+                        bar.sell_volume,
+                        bar.delta,
+                        bar.trade_count,
+                        bar.symbol,
+                    ])?;
+                }
+            }
+            tx.commit()?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn count_bars(&self, date_filter: Option<&str>) -> Result<usize> {
+        let (filter, values) = bar_filter(date_filter);
+        self.run(move |conn| {
+            let sql = format!("SELECT COUNT(*) FROM replay_bars_1s{}", filter);
+            let count: i64 = conn.query_row(&sql, params_from_iter(values), |row| row.get(0))?;
+            Ok(count as usize)
+        })
+        .await
+    }
+
+    async fn fetch_bars(
+        &self,
+        date_filter: Option<&str>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<BarRecord>> {
+        let (filter, values) = bar_filter(date_filter);
+        self.run(move |conn| {
+            let sql = format!(
+                "SELECT * FROM replay_bars_1s{} ORDER BY timestamp ASC, rowid ASC LIMIT {} OFFSET {}",
+                filter, limit, offset
+            );
+            let mut stmt = conn.prepare(&sql)?;
+            let rows = stmt
+                .query_map(params_from_iter(values), bar_record)?
+                .collect::<rusqlite::Result<Vec<_>>>()?;
+            Ok(rows)
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signal(session_id: Uuid, timestamp: i64, signal_type: &str, direction: &str) -> SignalInsert {
+        SignalInsert {
+            session_id,
+            timestamp,
+            signal_type: signal_type.to_string(),
+            direction: direction.to_string(),
+            price: 21500.0,
+            price_after_1m: None,
+            price_after_5m: None,
+            outcome: None,
+            metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_signals_outcomes_and_stats() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let session = SessionRecord {
+            id: None,
+            mode: "demo".to_string(),
+            symbols: vec!["NQ.c.0".to_string()],
+            session_high: None,
+            session_low: None,
+            total_volume: None,
+        };
+        let session_id = store.insert_session(&session).await.unwrap();
+
+        store.insert_signal(signal(session_id, 1_000, "absorption", "bullish")).await.unwrap();
+        store.insert_signal(signal(session_id, 2_000, "absorption", "bearish")).await.unwrap();
+        store.insert_signal(signal(session_id, 3_000, "delta_flip", "bullish")).await.unwrap();
+
+        store
+            .update_signal_outcomes(vec![SignalOutcomeUpdate {
+                session_id,
+                timestamp: 1_000,
+                price_after_1m: Some(21505.0),
+                price_after_5m: Some(21510.0),
+                outcome: Some("win".to_string()),
+            }])
+            .await
+            .unwrap();
+
+        let query = SignalQuery {
+            limit: None,
+            offset: None,
+            signal_type: Some("absorption".to_string()),
+            direction: None,
+            outcome: None,
+            start_date: None,
+            end_date: None,
+        };
+        let rows = store.query_signals(&query).await.unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].timestamp, 2_000); // Newest first
+        assert_eq!(rows[1].outcome.as_deref(), Some("win"));
+        assert_eq!(rows[1].session_id, Some(session_id));
+        assert_eq!(store.count_signals(&query).await.unwrap(), 2);
+
+        let stats = store.get_aggregate_stats().await.unwrap();
+        assert_eq!(stats.total_signals, 3);
+        assert_eq!(stats.by_direction.bullish, 2);
+        assert_eq!(stats.by_type["absorption"].wins, 1);
+        assert_eq!(stats.by_type["absorption"].win_rate, 100.0);
+
+        store.update_session(session_id, 21510.0, 21490.0, 500).await.unwrap();
+        let sessions = store.query_sessions(10).await.unwrap();
+        assert_eq!(sessions[0].symbols, vec!["NQ.c.0".to_string()]);
+        assert_eq!(sessions[0].total_volume, Some(500));
+        assert!(sessions[0].ended_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_config_and_bars() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        // Nothing stored yet: the same defaults as a config missing its fields
+        let config = store.get_config().await.unwrap();
+        assert_eq!((config.min_size, config.sound_enabled), (1, true));
+        assert_eq!(config.symbols, vec!["NQ.c.0", "ES.c.0"]);
+
+        let config = UserConfig {
+            min_size: 5,
+            sound_enabled: false,
+            symbols: vec!["ES.c.0".to_string()],
+        };
+        store.set_config(&config).await.unwrap();
+        store.set_config(&config).await.unwrap();
+        assert_eq!(store.get_config().await.unwrap().min_size, 5);
+
+        let bar = |timestamp: &str| BarRecord {
+            timestamp: timestamp.to_string(),
+            open: 21500.0,
+            high: 21502.0,
+            low: 21499.0,
+            close: 21501.0,
+            volume: 30,
+            buy_volume: 20,
+            sell_volume: 10,
+            delta: 10,
+            trade_count: 5,
+            symbol: "NQH6".to_string(),
+        };
+        store
+            .insert_bars(&[
+                bar("2025-12-01T14:30:01+00:00"),
+                bar("2025-12-01T14:30:00+00:00"),
+                bar("2025-12-02T14:30:00+00:00"),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(store.count_bars(None).await.unwrap(), 3);
+        assert_eq!(store.count_bars(Some("2025-12-01")).await.unwrap(), 2);
+        let bars = store.fetch_bars(Some("2025-12-01"), 10, 0).await.unwrap();
+        assert_eq!(bars[0].timestamp, "2025-12-01T14:30:00Z");
+        assert_eq!(bars[1].timestamp, "2025-12-01T14:30:01Z");
+        assert_eq!(store.fetch_bars(None, 1, 2).await.unwrap()[0].timestamp, "2025-12-02T14:30:00Z");
+    }
+}
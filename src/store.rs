@@ -0,0 +1,290 @@
+//! Signal Store
+//!
+//! Persistence for sessions, signals, outcome updates, user config, 1s replay
+//! bars and aggregate stats. `SupabaseClient` (PostgREST) and `SqliteStore`
+//! (embedded file) both implement `SignalStore`; the server picks one with
+//! `--store`, everything else only sees `SharedStore`.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// Shared handle to the configured store
+pub type SharedStore = Arc<dyn SignalStore>;
+
+/// Backend for everything the server persists
+#[async_trait]
+pub trait SignalStore: Send + Sync {
+    /// Backend name for logs ("Supabase", "SQLite")
+    fn name(&self) -> &'static str;
+
+    /// Insert a new session and return its ID
+    async fn insert_session(&self, session: &SessionRecord) -> Result<Uuid>;
+
+    /// Update session with final stats
+    async fn update_session(
+        &self,
+        session_id: Uuid,
+        session_high: f64,
+        session_low: f64,
+        total_volume: u64,
+    ) -> Result<()>;
+
+    /// Insert a signal record
+    async fn insert_signal(&self, signal: SignalInsert) -> Result<()>;
+
+    /// Update signal outcomes in batch
+    async fn update_signal_outcomes(&self, updates: Vec<SignalOutcomeUpdate>) -> Result<()>;
+
+    /// Get user configuration (defaults if none saved)
+    async fn get_config(&self) -> Result<UserConfig>;
+
+    /// Save user configuration
+    async fn set_config(&self, config: &UserConfig) -> Result<()>;
+
+    /// Get signals with optional filtering and pagination (newest first)
+    async fn query_signals(&self, query: &SignalQuery) -> Result<Vec<SignalRow>>;
+
+    /// Count signals matching the filters (for pagination)
+    async fn count_signals(&self, query: &SignalQuery) -> Result<u32>;
+
+    /// Get sessions list (newest first)
+    async fn query_sessions(&self, limit: u32) -> Result<Vec<SessionRow>>;
+
+    /// Get aggregate stats across all signals
+    async fn get_aggregate_stats(&self) -> Result<AggregateStats>;
+
+    /// Insert 1-second bars (replay_bars_1s)
+    async fn insert_bars(&self, bars: &[BarRecord]) -> Result<()>;
+
+    /// Count bars, optionally for one date (YYYY-MM-DD)
+    async fn count_bars(&self, date_filter: Option<&str>) -> Result<usize>;
+
+    /// Fetch bars ordered by timestamp, optionally for one date (YYYY-MM-DD)
+    async fn fetch_bars(
+        &self,
+        date_filter: Option<&str>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<BarRecord>>;
+}
+
+/// Session record for database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Uuid>,
+    pub mode: String,
+    pub symbols: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_high: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_low: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_volume: Option<i64>,
+}
+
+/// Signal record for database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalInsert {
+    pub session_id: Uuid,
+    pub timestamp: i64,
+    pub signal_type: String,
+    pub direction: String,
+    pub price: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price_after_1m: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub price_after_5m: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outcome: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// User configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UserConfig {
+    #[serde(default = "default_min_size")]
+    pub min_size: u32,
+    #[serde(default = "default_sound_enabled")]
+    pub sound_enabled: bool,
+    #[serde(default = "default_symbols")]
+    pub symbols: Vec<String>,
+}
+
+fn default_min_size() -> u32 {
+    1
+}
+fn default_sound_enabled() -> bool {
+    true
+}
+fn default_symbols() -> Vec<String> {
+    vec!["NQ.c.0".to_string(), "ES.c.0".to_string()]
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        Self {
+            min_size: default_min_size(),
+            sound_enabled: default_sound_enabled(),
+            symbols: default_symbols(),
+        }
+    }
+}
+
+/// Batch update for signal outcomes
+#[derive(Debug, Clone)]
+pub struct SignalOutcomeUpdate {
+    pub session_id: Uuid,
+    pub timestamp: i64,
+    pub price_after_1m: Option<f64>,
+    pub price_after_5m: Option<f64>,
+    pub outcome: Option<String>,
+}
+
+/// Signal row from database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalRow {
+    pub id: Uuid,
+    pub session_id: Option<Uuid>,
+    pub created_at: String,
+    pub timestamp: i64,
+    pub signal_type: String,
+    pub direction: String,
+    pub price: f64,
+    pub price_after_1m: Option<f64>,
+    pub price_after_5m: Option<f64>,
+    pub outcome: Option<String>,
+    pub metadata: Option<serde_json::Value>,
+}
+
+/// Session row from database
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRow {
+    pub id: Uuid,
+    pub started_at: String,
+    pub ended_at: Option<String>,
+    pub mode: String,
+    pub symbols: Vec<String>,
+    pub session_high: Option<f64>,
+    pub session_low: Option<f64>,
+    pub total_volume: Option<i64>,
+}
+
+/// 1-second bar (replay_bars_1s table) - uploaded by the pipeline, read by db replay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BarRecord {
+    /// Bar open time (RFC 3339)
+    pub timestamp: String,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: i64,
+    pub buy_volume: i64,
+    pub sell_volume: i64,
+    pub delta: i64,
+    pub trade_count: i64,
+    pub symbol: String,
+}
+
+/// Aggregate stats response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregateStats {
+    pub total_signals: u32,
+    pub by_type: HashMap<String, SignalTypeStats>,
+    pub by_direction: DirectionStats,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalTypeStats {
+    pub count: u32,
+    pub wins: u32,
+    pub losses: u32,
+    pub breakeven: u32,
+    pub win_rate: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectionStats {
+    pub bullish: u32,
+    pub bearish: u32,
+}
+
+/// Query parameters for signals
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignalQuery {
+    pub limit: Option<u32>,
+    pub offset: Option<u32>,
+    pub signal_type: Option<String>,
+    pub direction: Option<String>,
+    pub outcome: Option<String>,
+    /// Start date filter (ISO 8601 format)
+    pub start_date: Option<String>,
+    /// End date filter (ISO 8601 format)
+    pub end_date: Option<String>,
+}
+
+/// Minimal signal fields needed for aggregate stats
+#[derive(Debug, Clone, Deserialize)]
+pub struct SignalSummary {
+    pub signal_type: String,
+    pub direction: String,
+    pub outcome: Option<String>,
+}
+
+/// Aggregate signals by type and direction (shared by every backend)
+pub fn aggregate_stats(signals: &[SignalSummary]) -> AggregateStats {
+    let total_signals = signals.len() as u32;
+
+    // Aggregate by type
+    let mut by_type: HashMap<String, SignalTypeStats> = HashMap::new();
+    let mut bullish = 0u32;
+    let mut bearish = 0u32;
+
+    for signal in signals {
+        // Direction stats
+        if signal.direction == "bullish" {
+            bullish += 1;
+        } else {
+            bearish += 1;
+        }
+
+        // Type stats
+        let entry = by_type.entry(signal.signal_type.clone()).or_insert(SignalTypeStats {
+            count: 0,
+            wins: 0,
+            losses: 0,
+            breakeven: 0,
+            win_rate: 0.0,
+        });
+        entry.count += 1;
+
+        if let Some(ref outcome) = signal.outcome {
+            match outcome.as_str() {
+                "win" => entry.wins += 1,
+                "loss" => entry.losses += 1,
+                "breakeven" => entry.breakeven += 1,
+                _ => {}
+            }
+        }
+    }
+
+    // Calculate win rates
+    for stats in by_type.values_mut() {
+        let decided = stats.wins + stats.losses;
+        if decided > 0 {
+            stats.win_rate = (stats.wins as f64 / decided as f64) * 100.0;
+        }
+    }
+
+    AggregateStats {
+        total_signals,
+        by_type,
+        by_direction: DirectionStats { bullish, bearish },
+    }
+}
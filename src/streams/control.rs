@@ -0,0 +1,368 @@
+//! Replay Controls
+//!
+//! Pause, seek and step - shared by the replay loops.
+//!
+//! A `replay_seek` request sets a target in ReplayControl; the loop then
+//! fast-forwards - trades still run through ProcessingState, but without pacing
+//! and with output going to a sink - until it reaches the target. A target
+//! behind the replay position means the loop restarts from the session start
+//! and fast-forwards from there.
+//!
+//! A `replay_step` request wakes a paused replay for one window, one second or
+//! up to the next signal, broadcasting as normal (no pacing), then pauses again.
+
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::info;
+
+use crate::router::SymbolRouter;
+use crate::types::{AppState, StepUnit, WsMessage};
+
+/// What the replay loop should do with the current trade
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeekStep {
+    /// Normal playback - pace and broadcast
+    Play,
+    /// Before the seek target - process without pacing or broadcasting
+    FastForward,
+    /// Target is behind the replay - rebuild state from the session start
+    Rebuild,
+}
+
+/// Seek tracking for one replay session
+pub struct ReplaySeek {
+    target: Option<u64>,
+    // Furthest timestamp replayed before a rebuild - signals up to here were already persisted
+    persisted_until: u64,
+    // Output while fast-forwarding (no receivers, messages are dropped)
+    sink: broadcast::Sender<WsMessage>,
+}
+
+impl ReplaySeek {
+    pub fn new() -> Self {
+        let (sink, _) = broadcast::channel(1);
+        Self {
+            target: None,
+            persisted_until: 0,
+            sink,
+        }
+    }
+
+    /// Is a seek in progress?
+    pub fn is_seeking(&self) -> bool {
+        self.target.is_some()
+    }
+
+    /// Channel for the current step (the sink while fast-forwarding)
+    pub fn tx<'a>(
+        &'a self,
+        step: SeekStep,
+        tx: &'a broadcast::Sender<WsMessage>,
+    ) -> &'a broadcast::Sender<WsMessage> {
+        if step == SeekStep::FastForward {
+            &self.sink
+        } else {
+            tx
+        }
+    }
+
+    /// Called with each trade before it's processed: records the replay
+    /// position, picks up seek requests and finishes a seek at its target
+    pub async fn step(
+        &mut self,
+        state: &AppState,
+        router: &mut SymbolRouter,
+        trade_ts: u64,
+        last_trade_ts: Option<u64>,
+    ) -> SeekStep {
+        let requested = {
+            let mut ctrl = state.replay_control.write().await;
+            ctrl.current_timestamp = Some(trade_ts);
+            ctrl.seek_to.take()
+        };
+
+        if let Some(target) = requested {
+            match last_trade_ts {
+                Some(last_ts) if target <= last_ts => {
+                    info!("⏪ Seeking back to {} - rebuilding from session start", target);
+                    self.target = Some(target);
+                    self.persisted_until = self.persisted_until.max(last_ts);
+                    return SeekStep::Rebuild;
+                }
+                _ => {
+                    info!("⏩ Fast-forwarding to {}", target);
+                    self.target = Some(target);
+                }
+            }
+        }
+
+        match self.target {
+            Some(target) if trade_ts < target => {
+                // Only stretches already replayed are persisted - a forward seek
+                // into new data still records its signals
+                router.set_persist_signals(trade_ts > self.persisted_until);
+                SeekStep::FastForward
+            }
+            Some(_) => {
+                self.target = None;
+                router.set_persist_signals(trade_ts > self.persisted_until);
+                info!("🎯 Seek complete at {}", trade_ts);
+
+                // Clients get the rebuilt profile and the new position straight away
+                router.send_volume_profiles(&state.tx);
+                let status = state
+                    .replay_control
+                    .read()
+                    .await
+                    .status(&state.mode, state.replay_date.clone());
+                let _ = state.tx.send(WsMessage::ReplayStatus(status));
+                SeekStep::Play
+            }
+            None => {
+                router.set_persist_signals(trade_ts > self.persisted_until);
+                SeekStep::Play
+            }
+        }
+    }
+}
+
+/// A step in progress
+struct ActiveStep {
+    unit: StepUnit,
+    // Event time the step ends at (second)
+    until_ts: u64,
+    // Signal count when the step started (next_signal)
+    signals_before: u64,
+}
+
+/// Step tracking for one replay session
+pub struct ReplayStepper {
+    active: Option<ActiveStep>,
+}
+
+impl ReplayStepper {
+    pub fn new() -> Self {
+        Self { active: None }
+    }
+
+    /// Is a step in progress?
+    pub fn is_stepping(&self) -> bool {
+        self.active.is_some()
+    }
+
+    /// Pick up a requested step - called with each trade once the pause loop lets it through
+    pub async fn begin(&mut self, state: &AppState, router: &SymbolRouter, trade_ts: u64) {
+        if self.active.is_some() {
+            return;
+        }
+        let Some(unit) = state.replay_control.write().await.step.take() else {
+            return;
+        };
+        info!("⏯️ Replay step: {:?}", unit);
+        self.active = Some(ActiveStep {
+            unit,
+            until_ts: trade_ts + 1000,
+            signals_before: router.signal_count(),
+        });
+    }
+
+    /// Called after the trade's window flush (`flushed`) and before the trade is
+    /// added. Ends the step once it has covered its unit - remaining buffers are
+    /// flushed and clients get the new position; the replay stays paused.
+    pub async fn finish(
+        &mut self,
+        state: &AppState,
+        router: &mut SymbolRouter,
+        trade_ts: u64,
+        flushed: bool,
+    ) {
+        let Some(step) = &self.active else {
+            return;
+        };
+        let done = match step.unit {
+            StepUnit::Window => flushed,
+            StepUnit::Second => trade_ts >= step.until_ts,
+            StepUnit::NextSignal => router.signal_count() > step.signals_before,
+        };
+        if !done {
+            return;
+        }
+        self.active = None;
+
+        // A second can end mid-window - flush what's buffered so the step shows it
+        if !flushed {
+            router.process_buffers(&state.tx);
+            router.send_volume_profiles(&state.tx);
+        }
+        let status = state
+            .replay_control
+            .read()
+            .await
+            .status(&state.mode, state.replay_date.clone());
+        let _ = state.tx.send(WsMessage::ReplayStatus(status));
+    }
+}
+
+/// Block while the replay is paused (a seek or step request wakes it up)
+pub async fn wait_while_paused(state: &AppState) {
+    loop {
+        let ctrl = state.replay_control.read().await;
+        if !ctrl.is_paused || ctrl.seek_to.is_some() || ctrl.step.is_some() {
+            break;
+        }
+        drop(ctrl);
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::aggressor::DEFAULT_SWEEP_LEVELS;
+    use crate::instruments::InstrumentRegistry;
+    use crate::store::UserConfig;
+    use crate::types::{ReplayControl, Trade};
+    use std::collections::{HashMap, HashSet};
+    use tokio::sync::RwLock;
+
+    /// Replay AppState with default settings (shared with the driver tests)
+    pub(crate) fn app_state() -> AppState {
+        let (tx, _) = broadcast::channel(16);
+        AppState {
+            tx,
+            active_symbols: RwLock::new(HashSet::new()),
+            min_size: RwLock::new(1),
+            session_id: None,
+            store: None,
+            config: RwLock::new(UserConfig::default()),
+            session_stats: RwLock::new(HashMap::new()),
+            mode: "replay".to_string(),
+            replay_date: None,
+            replay_control: RwLock::new(ReplayControl::new(1)),
+            connection_status: RwLock::new(None),
+            instruments: InstrumentRegistry::default(),
+            vwap_anchor: Default::default(),
+            footprint: Default::default(),
+            stacked_imbalance: Default::default(),
+            divergence: Default::default(),
+            large_trades: Default::default(),
+            sweep_levels: DEFAULT_SWEEP_LEVELS,
+            vwap_anchors: RwLock::new(Vec::new()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_seek_forward_and_back() {
+        let state = app_state();
+        let mut router = SymbolRouter::new(None, None, None);
+        let mut seek = ReplaySeek::new();
+
+        assert_eq!(seek.step(&state, &mut router, 1_000, None).await, SeekStep::Play);
+
+        state.replay_control.write().await.seek_to = Some(5_000);
+        assert_eq!(seek.step(&state, &mut router, 2_000, Some(1_000)).await, SeekStep::FastForward);
+        // Skipped data was never replayed, so its signals are still persisted
+        assert!(router.persists_signals());
+        assert_eq!(seek.step(&state, &mut router, 4_999, Some(2_000)).await, SeekStep::FastForward);
+        assert!(router.persists_signals());
+        assert_eq!(seek.step(&state, &mut router, 5_000, Some(4_999)).await, SeekStep::Play);
+        assert!(!seek.is_seeking());
+
+        state.replay_control.write().await.seek_to = Some(3_000);
+        assert_eq!(seek.step(&state, &mut router, 6_000, Some(5_000)).await, SeekStep::Rebuild);
+        assert!(seek.is_seeking());
+        // Rebuilding up to 5_000 replays signals that are already persisted
+        assert_eq!(seek.step(&state, &mut router, 1_000, None).await, SeekStep::FastForward);
+        assert!(!router.persists_signals());
+        assert_eq!(seek.step(&state, &mut router, 3_000, Some(1_000)).await, SeekStep::Play);
+        assert!(!router.persists_signals());
+        assert_eq!(seek.step(&state, &mut router, 5_001, Some(3_000)).await, SeekStep::Play);
+        assert!(router.persists_signals());
+    }
+
+    fn trade(side: &str, size: u32, timestamp: u64) -> Trade {
+        Trade {
+            symbol: "NQH6".to_string(),
+            price: 21_500.0,
+            size,
+            side: side.to_string(),
+            timestamp,
+        }
+    }
+
+    fn replay_statuses(rx: &mut broadcast::Receiver<WsMessage>) -> usize {
+        std::iter::from_fn(|| rx.try_recv().ok())
+            .filter(|msg| matches!(msg, WsMessage::ReplayStatus(_)))
+            .count()
+    }
+
+    #[tokio::test]
+    async fn test_step_one_second() {
+        let state = app_state();
+        let mut rx = state.tx.subscribe();
+        let mut router = SymbolRouter::new(None, None, None).with_event_clock();
+        let mut stepper = ReplayStepper::new();
+
+        state.replay_control.write().await.step = Some(StepUnit::Second);
+        stepper.begin(&state, &router, 10_000).await;
+        assert!(stepper.is_stepping());
+        router.add_trade(1, trade("buy", 5, 10_000));
+
+        stepper.finish(&state, &mut router, 10_999, false).await;
+        assert!(stepper.is_stepping());
+        stepper.finish(&state, &mut router, 11_000, false).await;
+        assert!(!stepper.is_stepping());
+
+        // The second ended mid-window, so the buffered trade was flushed for the step
+        let messages: Vec<_> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert!(messages.iter().any(|msg| matches!(msg, WsMessage::Bubble(b) if b.size == 5)));
+        assert!(messages.iter().any(|msg| matches!(msg, WsMessage::ReplayStatus(_))));
+    }
+
+    #[tokio::test]
+    async fn test_step_one_window() {
+        let state = app_state();
+        let mut rx = state.tx.subscribe();
+        let mut router = SymbolRouter::new(None, None, None).with_event_clock();
+        let mut stepper = ReplayStepper::new();
+
+        state.replay_control.write().await.step = Some(StepUnit::Window);
+        stepper.begin(&state, &router, 10_000).await;
+
+        // Time alone doesn't end a window step
+        stepper.finish(&state, &mut router, 12_000, false).await;
+        assert!(stepper.is_stepping());
+        assert_eq!(replay_statuses(&mut rx), 0);
+
+        stepper.finish(&state, &mut router, 12_500, true).await;
+        assert!(!stepper.is_stepping());
+        assert_eq!(replay_statuses(&mut rx), 1);
+    }
+
+    #[tokio::test]
+    async fn test_step_to_next_signal() {
+        let state = app_state();
+        let mut rx = state.tx.subscribe();
+        let mut router = SymbolRouter::new(None, None, None).with_event_clock();
+        let mut stepper = ReplayStepper::new();
+
+        state.replay_control.write().await.step = Some(StepUnit::NextSignal);
+        stepper.begin(&state, &router, 10_000).await;
+
+        // Windows without a signal don't end it
+        router.add_trade(1, trade("buy", 5, 10_000));
+        router.process_buffers(&state.tx);
+        stepper.finish(&state, &mut router, 11_000, true).await;
+        assert!(stepper.is_stepping());
+
+        // A 150 lot closed by the next print is a tier 3 large trade (absorbed at an
+        // unchanged price, which is a signal too)
+        router.add_trade(1, trade("buy", 150, 11_000));
+        router.add_trade(1, trade("sell", 2, 11_500));
+        router.process_buffers(&state.tx);
+        assert!(router.signal_count() > 0);
+        stepper.finish(&state, &mut router, 12_000, true).await;
+        assert!(!stepper.is_stepping());
+        assert_eq!(replay_statuses(&mut rx), 1);
+    }
+}
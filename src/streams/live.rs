@@ -1,36 +1,340 @@
+//! Live Mode
+//!
+//! Streams real-time trades from the Databento live gateway. The feed runs in
+//! its own task: the first connect retries with exponential backoff, and when
+//! the connection drops it reconnects the same way, backfills the missed
+//! interval from the historical API and only then resumes live trades. Trades seen on both sides of a seam are dropped by
+//! `ts_event`/`sequence`, and clients get a `ConnectionStatus` while degraded.
+//! With `--book` an MBP-10 or MBO subscription runs next to the trades; books
+//! are cleared on reconnect and rebuilt from the new session (MBO replays a
+//! snapshot), the gap itself isn't backfilled.
+
 use anyhow::{Context, Result};
 use databento::{
-    dbn::{Record, Schema, SType, TradeMsg},
+    dbn::{decode::DbnMetadata, Dataset, MboMsg, Mbp10Msg, SType, Schema, SymbolMappingMsg, TradeMsg},
+    historical::timeseries::GetRangeParams,
     live::Subscription,
-    LiveClient,
+    HistoricalClient, LiveClient,
 };
-use std::{sync::Arc, time::Duration};
-use tokio::sync::RwLock;
+use std::collections::{HashMap, HashSet};
+use std::ffi::c_char;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
 use tracing::{info, warn};
 
-use crate::processing::ProcessingState;
-use crate::types::{AppState, Trade, WsMessage};
+use crate::aggressor::PrintInfo;
+use crate::book::{book_update_from_mbo, book_update_from_mbp10, BookEvent, BookSchema, BookUpdate};
+use crate::trade_files::trade_from_msg;
+use crate::types::{AppState, ConnectionStatus, WsMessage};
+
+use super::driver::run_source;
+use super::source::{SourceEvent, SourceTiming, TradeSource};
+
+/// First retry delay - doubled after each failed attempt
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Events buffered between the feed task and the driver
+const EVENT_BUFFER: usize = 10_000;
 
 /// Live mode: Stream real-time data from Databento
 pub async fn run_databento_stream(
     api_key: String,
     symbols: Vec<String>,
+    book: Option<BookSchema>,
     state: Arc<AppState>,
 ) -> Result<()> {
+    let (tx, events) = mpsc::channel(EVENT_BUFFER);
+    let feed = LiveFeed {
+        api_key,
+        symbols: symbols.clone(),
+        book,
+        client: None,
+        tx,
+        state: state.clone(),
+        seen: SeenTrades::default(),
+        book_instruments: HashSet::new(),
+    };
+    let feed_task = tokio::spawn(feed.run());
+
+    run_source(LiveSource { events, symbols }, state).await?;
+    feed_task.abort();
+
+    warn!("Databento stream ended");
+    Ok(())
+}
+
+/// Databento live trades and symbology, fed by the reconnecting `LiveFeed` task
+struct LiveSource {
+    events: mpsc::Receiver<SourceEvent>,
+    symbols: Vec<String>,
+}
+
+impl TradeSource for LiveSource {
+    fn timing(&self) -> SourceTiming {
+        SourceTiming::Realtime
+    }
+
+    fn symbols(&self) -> Vec<String> {
+        self.symbols.clone()
+    }
+
+    // Cancel safe: `mpsc::Receiver::recv` is
+    async fn next_event(&mut self) -> Result<Option<SourceEvent>> {
+        Ok(self.events.recv().await)
+    }
+}
+
+/// Owns the live connection - reconnects, backfills and dedupes
+struct LiveFeed {
+    api_key: String,
+    symbols: Vec<String>,
+    book: Option<BookSchema>,
+    // Set once the first connect succeeds - later sessions reconnect it
+    client: Option<LiveClient>,
+    tx: mpsc::Sender<SourceEvent>,
+    state: Arc<AppState>,
+    seen: SeenTrades,
+    // Instruments with book records (cleared on reconnect)
+    book_instruments: HashSet<u32>,
+}
+
+impl LiveFeed {
+    /// Stream forever, reconnecting whenever the gateway drops (until the driver stops)
+    async fn run(mut self) {
+        self.connect(None).await;
+        self.set_status(status("connected", 0, None, None, None)).await;
+
+        loop {
+            let reason = match self.stream().await {
+                Ok(()) => "stream ended".to_string(),
+                Err(e) => e.to_string(),
+            };
+            if self.tx.is_closed() {
+                return;
+            }
+
+            // Missed interval: from the last trade we delivered until the new session starts
+            let gap_start = self.seen.last_ts_event().unwrap_or_else(now_ns);
+            warn!("⚠️ Databento feed lost ({}), reconnecting...", reason);
+
+            self.connect(Some(gap_start)).await;
+            self.clear_books(gap_start).await;
+            let gap_end = now_ns();
+            self.backfill(gap_start, gap_end).await;
+
+            // Live trades already covered by the backfill are dropped
+            self.seen.resume();
+        }
+    }
+
+    /// Forward live records until the connection errors or closes
+    async fn stream(&mut self) -> Result<()> {
+        let Some(client) = self.client.as_mut() else {
+            return Ok(());
+        };
+        while let Some(record) = client.next_record().await? {
+            // Symbology: map instrument ids to the symbols we subscribed with
+            let event = if let Some(mapping) = record.get::<SymbolMappingMsg>() {
+                match mapping.stype_in_symbol() {
+                    Ok(symbol) => SourceEvent::SymbolMapping {
+                        instrument_id: mapping.hd.instrument_id,
+                        symbol: symbol.to_string(),
+                    },
+                    Err(e) => {
+                        warn!("Invalid symbol mapping: {}", e);
+                        continue;
+                    }
+                }
+            } else if let Some(trade) = record.get::<TradeMsg>() {
+                if !self.seen.accept(trade) {
+                    continue;
+                }
+                // Symbol is resolved by the router from the instrument id
+                match trade_from_msg(trade, String::new()) {
+                    Some(trade_msg) => SourceEvent::Trade {
+                        instrument_id: Some(trade.hd.instrument_id),
+                        trade: trade_msg,
+                        print: Some(PrintInfo::from_msg(trade)),
+                    },
+                    None => continue,
+                }
+            } else if let Some(msg) = record.get::<Mbp10Msg>() {
+                book_event(&mut self.book_instruments, msg.hd.instrument_id, book_update_from_mbp10(msg))
+            } else if let Some(msg) = record.get::<MboMsg>() {
+                book_event(&mut self.book_instruments, msg.hd.instrument_id, book_update_from_mbo(msg))
+            } else {
+                continue;
+            };
+
+            if self.tx.send(event).await.is_err() {
+                return Ok(()); // Driver stopped
+            }
+        }
+        Ok(())
+    }
+
+    /// Books missed updates while disconnected - start them over
+    async fn clear_books(&mut self, gap_start: u64) {
+        for instrument_id in &self.book_instruments {
+            let event = SourceEvent::Book {
+                instrument_id: *instrument_id,
+                symbol: String::new(),
+                update: BookUpdate {
+                    timestamp: gap_start / 1_000_000,
+                    event: BookEvent::Clear,
+                },
+            };
+            if self.tx.send(event).await.is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Connect (or reconnect after a drop at `gap_start`) and subscribe, backing off
+    /// exponentially until it works. The first attempt of a first connect isn't delayed.
+    async fn connect(&mut self, gap_start: Option<u64>) {
+        let (state, mut delay) = match gap_start {
+            None => ("connecting", Duration::ZERO),
+            Some(_) => ("reconnecting", INITIAL_BACKOFF),
+        };
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+            self.set_status(status(state, attempt, gap_start, None, None)).await;
+            tokio::time::sleep(delay).await;
+
+            match self.start_session().await {
+                Ok(()) => {
+                    info!("✅ Connected to Databento (attempt {})", attempt);
+                    return;
+                }
+                Err(e) => {
+                    delay = (delay * 2).clamp(INITIAL_BACKOFF, MAX_BACKOFF);
+                    warn!("Connect attempt {} failed: {:#} - retrying in {:?}", attempt, e, delay);
+                }
+            }
+        }
+    }
+
+    /// A new session on the existing client, or a fresh client until the first one starts
+    async fn start_session(&mut self) -> Result<()> {
+        match self.client.as_mut() {
+            Some(client) => {
+                client.reconnect().await?;
+                client.resubscribe().await?;
+                client.start().await?;
+            }
+            None => {
+                self.client = Some(new_client(&self.api_key, &self.symbols, self.book).await?);
+            }
+        }
+        Ok(())
+    }
+
+    /// Fetch the missed interval from the historical API and feed it through before live data
+    async fn backfill(&mut self, gap_start: u64, gap_end: u64) {
+        self.set_status(status("backfilling", 0, Some(gap_start), Some(gap_end), None)).await;
+        self.seen.resume();
+
+        match self.fetch_gap(gap_start, gap_end).await {
+            Ok((delivered, duplicates)) => {
+                info!(
+                    "📡 Backfilled {} trades ({} duplicates skipped) over {:.1}s gap",
+                    delivered,
+                    duplicates,
+                    (gap_end - gap_start) as f64 / 1e9
+                );
+                self.set_status(status("connected", 0, None, None, None)).await;
+            }
+            Err(e) => {
+                warn!("⚠️ Backfill failed, gap left unfilled: {}", e);
+                let message = format!("Backfill failed: {}", e);
+                self.set_status(status("degraded", 0, Some(gap_start), Some(gap_end), Some(message)))
+                    .await;
+            }
+        }
+    }
+
+    /// Returns (trades delivered, duplicates skipped)
+    async fn fetch_gap(&mut self, gap_start: u64, gap_end: u64) -> Result<(u64, u64)> {
+        let start = time::OffsetDateTime::from_unix_timestamp_nanos(gap_start as i128)?;
+        let end = time::OffsetDateTime::from_unix_timestamp_nanos(gap_end as i128)?;
+
+        let mut client = HistoricalClient::builder().key(self.api_key.clone())?.build()?;
+        let params = GetRangeParams::builder()
+            .dataset(Dataset::GlbxMdp3)
+            .date_time_range((start, end))
+            .symbols(self.symbols.clone())
+            .stype_in(SType::RawSymbol)
+            .schema(Schema::Trades)
+            .build();
+        let mut decoder = client
+            .timeseries()
+            .get_range(&params)
+            .await
+            .context("Historical request failed")?;
+
+        // Instrument ids for the raw symbols (same ids as the live feed)
+        for mapping in &decoder.metadata().mappings {
+            for interval in &mapping.intervals {
+                if let Ok(instrument_id) = interval.symbol.parse() {
+                    let event = SourceEvent::SymbolMapping {
+                        instrument_id,
+                        symbol: mapping.raw_symbol.clone(),
+                    };
+                    if self.tx.send(event).await.is_err() {
+                        return Ok((0, 0));
+                    }
+                }
+            }
+        }
+
+        let (mut delivered, mut duplicates) = (0, 0);
+        while let Some(trade) = decoder.decode_record::<TradeMsg>().await? {
+            if !self.seen.accept(trade) {
+                duplicates += 1;
+                continue;
+            }
+            let Some(trade_msg) = trade_from_msg(trade, String::new()) else {
+                continue;
+            };
+            let event = SourceEvent::Trade {
+                instrument_id: Some(trade.hd.instrument_id),
+                trade: trade_msg,
+                print: Some(PrintInfo::from_msg(trade)),
+            };
+            if self.tx.send(event).await.is_err() {
+                break;
+            }
+            delivered += 1;
+        }
+        Ok((delivered, duplicates))
+    }
+
+    /// Remember the feed health for new clients and broadcast it
+    async fn set_status(&self, status: ConnectionStatus) {
+        *self.state.connection_status.write().await = Some(status.clone());
+        let _ = self.state.tx.send(WsMessage::ConnectionStatus(status));
+    }
+}
+
+/// Build a client, subscribe to trades (and the book) and start streaming
+async fn new_client(api_key: &str, symbols: &[String], book: Option<BookSchema>) -> Result<LiveClient> {
     info!("Connecting to Databento...");
 
     let mut client = LiveClient::builder()
-        .key(api_key)?
+        .key(api_key.to_string())?
         .dataset("GLBX.MDP3")
         .build()
         .await
         .context("Failed to connect to Databento")?;
 
-    info!("Connected to Databento");
-
     // Subscribe to symbols
     let subscription = Subscription::builder()
-        .symbols(symbols.clone())
+        .symbols(symbols.to_vec())
         .schema(Schema::Trades)
         .stype_in(SType::RawSymbol)
         .build();
@@ -42,83 +346,172 @@ pub async fn run_databento_stream(
 
     info!("Subscribed to: {:?}", symbols);
 
-    // Notify clients we're connected
-    let _ = state.tx.send(WsMessage::Connected {
-        symbols: symbols.clone(),
-        mode: state.mode.clone(),
-    });
+    // Order book next to the trades (MBO starts from a snapshot of every resting order)
+    if let Some(book) = book {
+        let mut subscription = Subscription::builder()
+            .symbols(symbols.to_vec())
+            .schema(book.schema())
+            .stype_in(SType::RawSymbol)
+            .build();
+        subscription.use_snapshot = book == BookSchema::Mbo;
+
+        client
+            .subscribe(subscription)
+            .await
+            .context("Failed to subscribe to the order book")?;
+
+        info!("Subscribed to {} order book", book.schema().as_str());
+    }
 
     // Start streaming
     client.start().await.context("Failed to start stream")?;
+    Ok(client)
+}
 
-    // Create processing state with Supabase persistence and AppState for stats sync
-    let processing_state = Arc::new(RwLock::new(ProcessingState::new(
-        state.supabase.clone(),
-        state.session_id,
-        Some(state.clone()),
-    )));
-
-    // Spawn 1-second aggregation task
-    let processing_state_clone = processing_state.clone();
-    let tx_clone = state.tx.clone();
-    tokio::spawn(async move {
-        let mut interval = tokio::time::interval(Duration::from_secs(1));
-        loop {
-            interval.tick().await;
-            let mut pstate = processing_state_clone.write().await;
-            pstate.process_buffer(&tx_clone);
+fn status(
+    status: &str,
+    attempt: u32,
+    gap_start_ns: Option<u64>,
+    gap_end_ns: Option<u64>,
+    message: Option<String>,
+) -> ConnectionStatus {
+    ConnectionStatus {
+        status: status.to_string(),
+        attempt,
+        gap_start: gap_start_ns.map(|ns| ns / 1_000_000),
+        gap_end: gap_end_ns.map(|ns| ns / 1_000_000),
+        message,
+    }
+}
 
-            // Send volume profile every second
-            pstate.send_volume_profile(&tx_clone);
-        }
-    });
-
-    // Process incoming records
-    while let Some(record) = client.next_record().await? {
-        if let Some(trade) = record.get::<TradeMsg>() {
-            let min_size = *state.min_size.read().await;
-
-            if trade.size >= min_size {
-                // Determine buy/sell from aggressor side
-                // 'A' = Ask side (buyer aggressor), 'B' = Bid side (seller aggressor)
-                let side = match trade.side as u8 {
-                    b'A' | b'a' => "buy",
-                    b'B' | b'b' => "sell",
-                    _ => "buy", // Default
-                };
-
-                // Get symbol from instrument ID
-                let symbol = get_symbol_from_record(&record, &symbols);
-
-                let trade_msg = Trade {
-                    symbol,
-                    price: trade.price as f64 / 1_000_000_000.0, // Fixed-point conversion
-                    size: trade.size,
-                    side: side.to_string(),
-                    timestamp: trade.hd.ts_event / 1_000_000, // Nanos to millis
-                };
-
-                // Add trade to processing buffer
-                let mut pstate = processing_state.write().await;
-                pstate.add_trade(trade_msg);
+/// Book record for the driver, remembering the instrument so it can be cleared on reconnect
+fn book_event(book_instruments: &mut HashSet<u32>, instrument_id: u32, update: BookUpdate) -> SourceEvent {
+    book_instruments.insert(instrument_id);
+    // Symbol is resolved by the router from the instrument id
+    SourceEvent::Book {
+        instrument_id,
+        symbol: String::new(),
+        update,
+    }
+}
+
+fn now_ns() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64
+}
+
+/// Identifies a trade among those sharing a `ts_event` (one packet can carry several fills)
+type TradeKey = (u32, i64, u32, c_char);
+
+fn trade_key(trade: &TradeMsg) -> TradeKey {
+    (trade.sequence, trade.price, trade.size, trade.side)
+}
+
+/// Newest `ts_event` delivered for an instrument and the trades delivered at it
+#[derive(Debug, Clone, Default)]
+struct Frontier {
+    ts_event: u64,
+    keys: HashMap<TradeKey, u32>,
+}
+
+/// Dedupes trades where a backfill overlaps the feed before and after it
+///
+/// Within one stream every trade is new. When a stream starts (`resume`),
+/// trades at or before what was already delivered for the instrument are
+/// duplicates - except fills at the frontier `ts_event` that weren't seen yet.
+#[derive(Debug, Default)]
+struct SeenTrades {
+    delivered: HashMap<u32, Frontier>,
+    resumed: HashMap<u32, Frontier>,
+}
+
+impl SeenTrades {
+    /// A new stream (backfill or reconnected live feed) starts
+    fn resume(&mut self) {
+        self.resumed = self.delivered.clone();
+    }
+
+    /// True if the trade hasn't been delivered yet (and records it)
+    fn accept(&mut self, trade: &TradeMsg) -> bool {
+        let instrument_id = trade.hd.instrument_id;
+        let ts_event = trade.hd.ts_event;
+        let key = trade_key(trade);
+
+        if let Some(resumed) = self.resumed.get_mut(&instrument_id) {
+            if ts_event < resumed.ts_event {
+                return false;
+            }
+            if ts_event == resumed.ts_event {
+                if let Some(count) = resumed.keys.get_mut(&key).filter(|count| **count > 0) {
+                    *count -= 1;
+                    return false;
+                }
+            } else {
+                // Past the seam - nothing more to skip for this instrument
+                self.resumed.remove(&instrument_id);
             }
         }
+
+        let frontier = self.delivered.entry(instrument_id).or_default();
+        if ts_event > frontier.ts_event {
+            frontier.ts_event = ts_event;
+            frontier.keys.clear();
+        }
+        if ts_event == frontier.ts_event {
+            *frontier.keys.entry(key).or_default() += 1;
+        }
+        true
     }
 
-    warn!("Databento stream ended");
-    Ok(())
+    /// Newest `ts_event` delivered on any instrument (start of a gap)
+    fn last_ts_event(&self) -> Option<u64> {
+        self.delivered.values().map(|f| f.ts_event).max()
+    }
 }
 
-fn get_symbol_from_record(_record: &dyn Record, symbols: &[String]) -> String {
-    // For simplicity, if we only have one symbol, return it
-    // In production, you'd map instrument_id to symbol
-    if symbols.len() == 1 {
-        return symbols[0].clone();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use databento::dbn::{rtype, RecordHeader};
+
+    fn trade(ts_event: u64, sequence: u32, size: u32) -> TradeMsg {
+        TradeMsg {
+            hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 42002475, ts_event),
+            price: 21_500_250_000_000,
+            size,
+            action: b'T' as c_char,
+            side: b'B' as c_char,
+            sequence,
+            ..Default::default()
+        }
     }
 
-    // Default to first symbol - proper implementation would use symbol mapping
-    symbols
-        .first()
-        .cloned()
-        .unwrap_or_else(|| "UNKNOWN".to_string())
+    #[test]
+    fn test_seen_trades_dedupe_across_backfill() {
+        let mut seen = SeenTrades::default();
+
+        // Live feed before the drop: two fills in one packet at ts 100
+        assert!(seen.accept(&trade(90, 1, 1)));
+        assert!(seen.accept(&trade(100, 2, 1)));
+        assert!(seen.accept(&trade(100, 2, 3)));
+        assert_eq!(seen.last_ts_event(), Some(100));
+
+        // Backfill starts at the last delivered ts_event
+        seen.resume();
+        assert!(!seen.accept(&trade(90, 1, 1)));
+        assert!(!seen.accept(&trade(100, 2, 1)));
+        assert!(!seen.accept(&trade(100, 2, 3)));
+        assert!(seen.accept(&trade(100, 2, 5))); // Third fill at ts 100 was missed
+        assert!(seen.accept(&trade(150, 3, 2)));
+        assert!(seen.accept(&trade(200, 4, 1)));
+
+        // Live resumes, overlapping the end of the backfill
+        seen.resume();
+        assert!(!seen.accept(&trade(150, 3, 2)));
+        assert!(!seen.accept(&trade(200, 4, 1)));
+        assert!(seen.accept(&trade(210, 5, 1)));
+        assert!(seen.accept(&trade(200, 4, 1))); // Past the seam - no longer filtered
+    }
 }
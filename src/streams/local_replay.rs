@@ -1,111 +1,128 @@
 //! Local Replay Mode
 //!
-//! Streams trades from local .zst files through ProcessingState.
+//! Streams trades from local Databento files through ProcessingState.
+//! Reads both zstd CSV exports (`.trades.csv.zst`) and DBN files (`.dbn.zst`),
+//! merging the files in timestamp order as it goes - replay starts at once and
+//! memory stays flat however many days are selected. MBP-10/MBO files for the
+//! same dates are interleaved with the trades to drive the order book.
 //! No Databento API connection required - uses downloaded historical data.
 
-use anyhow::{Context, Result};
-use chrono::{DateTime, Utc};
-use serde::Deserialize;
-use std::fs::File;
-use std::io::BufReader;
+use anyhow::Result;
 use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::RwLock;
 use tracing::info;
 
-use crate::processing::ProcessingState;
-use crate::types::{AppState, Trade, WsMessage};
-
-/// Trade record from Databento CSV
-#[derive(Debug, Deserialize)]
-struct CsvTrade {
-    ts_recv: String,
-    ts_event: String,
-    rtype: u8,
-    publisher_id: u32,
-    instrument_id: u64,
-    action: String,
-    side: String,
-    depth: u8,
-    price: f64,
-    size: u64,
-    flags: u32,
-    ts_in_delta: i64,
-    sequence: u64,
-    symbol: String,
+use crate::trade_files::{files_end_ms, find_book_files, find_trade_files, BookRecord, MergedBook, MergedTrades, TradeRecord};
+use crate::types::AppState;
+
+use super::driver::run_source;
+use super::source::{SourceEvent, SourceTiming, TradeSource};
+
+/// Trades merged from a set of local files (also replays historical downloads)
+pub(super) struct FileSource {
+    files: Vec<PathBuf>,
+    trades: MergedTrades,
+    // Order book records, merged with the trades by receive time
+    book_files: Vec<PathBuf>,
+    book: Option<MergedBook>,
+    next_trade: Option<TradeRecord>,
+    next_book: Option<BookRecord>,
+    symbols: Vec<String>,
+    session_start: Option<u64>,
+    session_end: Option<u64>,
 }
 
-/// Find all .zst files in directory for a specific date
-pub fn find_trade_files(data_dir: &PathBuf, date_filter: Option<&str>) -> Result<Vec<PathBuf>> {
-    let mut files = Vec::new();
-
-    for entry in std::fs::read_dir(data_dir)
-        .with_context(|| format!("Failed to read directory: {:?}", data_dir))?
-    {
-        let entry = entry?;
-        let path = entry.path();
-
-        if path.extension().map_or(false, |ext| ext == "zst") {
-            if let Some(filter) = date_filter {
-                let filename = path.file_name().unwrap().to_string_lossy();
-                if !filename.contains(filter) {
-                    continue;
-                }
-            }
-            files.push(path);
-        }
-    }
+impl FileSource {
+    pub(super) fn open(files: Vec<PathBuf>) -> Result<Self> {
+        // Stream trades from all files in timestamp order (one buffered trade per file)
+        let trades = MergedTrades::open(&files)?;
+        let Some(first_ts_ns) = trades.peek_ts() else {
+            anyhow::bail!("No trades found in files");
+        };
 
-    files.sort();
-    Ok(files)
-}
+        Ok(Self {
+            symbols: trades.head_symbols(),
+            session_start: Some(first_ts_ns / 1_000_000),
+            // Files cover whole UTC days
+            session_end: files_end_ms(&files),
+            files,
+            trades,
+            book_files: Vec::new(),
+            book: None,
+            next_trade: None,
+            next_book: None,
+        })
+    }
 
-/// Parse trades from a zstd-compressed CSV file
-fn parse_zst_trades(path: &PathBuf) -> Result<Vec<Trade>> {
-    let file = File::open(path)
-        .with_context(|| format!("Failed to open file: {:?}", path))?;
+    /// Interleave order book records from MBP-10/MBO files
+    pub(super) fn with_book_files(mut self, book_files: Vec<PathBuf>) -> Result<Self> {
+        self.book = Some(MergedBook::open(&book_files)?);
+        self.book_files = book_files;
+        Ok(self)
+    }
 
-    let decoder = zstd::stream::Decoder::new(file)
-        .with_context(|| format!("Failed to create zstd decoder for: {:?}", path))?;
+    /// Override the session range used for progress (Unix ms)
+    pub(super) fn with_session_range(mut self, start: u64, end: u64) -> Self {
+        self.session_start = Some(start);
+        self.session_end = Some(end);
+        self
+    }
+}
 
-    let reader = BufReader::new(decoder);
-    let mut csv_reader = csv::Reader::from_reader(reader);
+impl TradeSource for FileSource {
+    fn timing(&self) -> SourceTiming {
+        SourceTiming::Recorded
+    }
 
-    let mut trades = Vec::new();
+    fn symbols(&self) -> Vec<String> {
+        self.symbols.clone()
+    }
 
-    for result in csv_reader.deserialize() {
-        let row: CsvTrade = result.with_context(|| "Failed to parse CSV row")?;
+    fn session_range(&self) -> (Option<u64>, Option<u64>) {
+        (self.session_start, self.session_end)
+    }
 
-        // Only process trade actions
-        if row.action != "T" {
-            continue;
+    async fn next_event(&mut self) -> Result<Option<SourceEvent>> {
+        if self.next_trade.is_none() {
+            self.next_trade = self.trades.next().transpose()?;
+        }
+        if self.next_book.is_none() {
+            if let Some(book) = &mut self.book {
+                self.next_book = book.next().transpose()?;
+            }
         }
 
-        let side = match row.side.as_str() {
-            "B" => "buy",
-            "A" => "sell",
-            _ => continue,
+        // Book records at the same time as a trade go first (the trade consumed that liquidity)
+        let book_first = match (&self.next_book, &self.next_trade) {
+            (Some(book), Some(trade)) => book.ts_recv_ns <= trade.ts_recv_ns,
+            (Some(_), None) => true,
+            (None, _) => false,
         };
+        if book_first {
+            let record = self.next_book.take().expect("checked above");
+            return Ok(Some(SourceEvent::Book {
+                instrument_id: record.instrument_id,
+                symbol: record.symbol,
+                update: record.update,
+            }));
+        }
 
-        // Parse timestamp
-        let ts_event = DateTime::parse_from_rfc3339(&row.ts_event)
-            .with_context(|| format!("Failed to parse timestamp: {}", row.ts_event))?
-            .with_timezone(&Utc);
-
-        trades.push(Trade {
-            symbol: row.symbol,
-            price: row.price,
-            size: row.size as u32,
-            side: side.to_string(),
-            timestamp: ts_event.timestamp_millis() as u64,
-        });
+        Ok(self.next_trade.take().map(|record| SourceEvent::Trade {
+            instrument_id: Some(record.instrument_id),
+            print: Some(record.print()),
+            trade: record.trade,
+        }))
     }
 
-    // Sort by timestamp
-    trades.sort_by_key(|t| t.timestamp);
-
-    Ok(trades)
+    async fn rewind(&mut self) -> Result<()> {
+        self.trades = MergedTrades::open(&self.files)?;
+        if self.book.is_some() {
+            self.book = Some(MergedBook::open(&self.book_files)?);
+        }
+        self.next_trade = None;
+        self.next_book = None;
+        Ok(())
+    }
 }
 
 /// Local replay mode: Stream trades from local .zst files through ProcessingState
@@ -122,132 +139,21 @@ pub async fn run_local_replay(
     let files = find_trade_files(&data_dir, date_filter.as_deref())?;
 
     if files.is_empty() {
-        anyhow::bail!("No .zst trade files found in {:?}", data_dir);
+        anyhow::bail!("No .zst/.dbn trade files found in {:?}", data_dir);
     }
 
     info!("Found {} trade files", files.len());
+    info!("Replay speed: {}x", replay_speed);
 
-    // Load all trades
-    let mut all_trades = Vec::new();
-    for file in &files {
-        info!("Loading trades from {:?}", file);
-        let trades = parse_zst_trades(file)?;
-        info!("  Loaded {} trades", trades.len());
-        all_trades.extend(trades);
+    let mut source = FileSource::open(files)?;
+    let book_files = find_book_files(&data_dir, date_filter.as_deref())?;
+    if !book_files.is_empty() {
+        info!("Found {} order book files", book_files.len());
+        source = source.with_book_files(book_files)?;
     }
 
-    // Sort all trades by timestamp
-    all_trades.sort_by_key(|t| t.timestamp);
-    info!("Total trades to replay: {}", all_trades.len());
-
-    if all_trades.is_empty() {
-        anyhow::bail!("No trades found in files");
-    }
-
-    // Get symbols from trades
-    let symbols: Vec<String> = all_trades
-        .iter()
-        .map(|t| t.symbol.clone())
-        .collect::<std::collections::HashSet<_>>()
-        .into_iter()
-        .collect();
-
-    // Notify clients we're connected (in replay mode)
-    let _ = state.tx.send(WsMessage::Connected {
-        symbols: symbols.clone(),
-        mode: state.mode.clone(),
-    });
-
-    // Create processing state with Supabase persistence and AppState for stats sync
-    let processing_state = Arc::new(RwLock::new(ProcessingState::new(
-        state.supabase.clone(),
-        state.session_id,
-        Some(state.clone()),
-    )));
-
-    // Spawn aggregation task (with speed multiplier)
-    let processing_state_clone = processing_state.clone();
-    let tx_clone = state.tx.clone();
-    let speed = replay_speed;
-    tokio::spawn(async move {
-        let interval_ms = 1000 / speed as u64;
-        let mut interval = tokio::time::interval(Duration::from_millis(interval_ms.max(50)));
-        loop {
-            interval.tick().await;
-            let mut pstate = processing_state_clone.write().await;
-            pstate.process_buffer(&tx_clone);
-            pstate.send_volume_profile(&tx_clone);
-        }
-    });
-
-    // Track timestamps for pacing
-    let mut last_trade_ts: Option<u64> = None;
-    let total_trades = all_trades.len();
-
-    // Process each trade
-    for (idx, trade) in all_trades.into_iter().enumerate() {
-        // Check pause state
-        loop {
-            let ctrl = state.replay_control.read().await;
-            if !ctrl.is_paused {
-                break;
-            }
-            drop(ctrl);
-            tokio::time::sleep(Duration::from_millis(100)).await;
-        }
-
-        let trade_ts = trade.timestamp;
-
-        // Update current timestamp in replay control
-        {
-            let mut ctrl = state.replay_control.write().await;
-            ctrl.current_timestamp = Some(trade_ts);
-        }
-
-        // Get current speed from shared state
-        let current_speed = state.replay_control.read().await.speed;
-
-        // Pace the trades according to their original timing (adjusted by speed)
-        if let Some(last_ts) = last_trade_ts {
-            if trade_ts > last_ts {
-                let delay_ms = (trade_ts - last_ts) / current_speed as u64;
-                if delay_ms > 0 && delay_ms < 5000 {
-                    // Cap at 5 seconds to skip gaps
-                    tokio::time::sleep(Duration::from_millis(delay_ms)).await;
-                }
-            }
-        }
-        last_trade_ts = Some(trade_ts);
-
-        // Check min size filter
-        let min_size = *state.min_size.read().await;
-        if trade.size < min_size {
-            continue;
-        }
-
-        // Add trade to processing state
-        {
-            let mut pstate = processing_state.write().await;
-            pstate.add_trade(trade);
-        }
-
-        // Log progress periodically
-        if idx % 10000 == 0 {
-            info!("Replay progress: {}/{} trades ({:.1}%)",
-                  idx, total_trades, (idx as f64 / total_trades as f64) * 100.0);
-        }
-    }
+    run_source(source, state).await?;
 
     info!("Local replay complete!");
     Ok(())
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_find_trade_files() {
-        // Would need test data
-    }
-}
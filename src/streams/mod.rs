@@ -1,11 +1,16 @@
+mod control;
 mod db_replay;
 mod demo;
+mod driver;
 mod live;
 mod local_replay;
 mod replay;
+mod source;
 
 pub use db_replay::run_db_replay;
 pub use demo::run_demo_stream;
+pub use driver::run_source;
 pub use live::run_databento_stream;
 pub use local_replay::run_local_replay;
 pub use replay::run_historical_replay;
+pub use source::{SourceEvent, SourceTiming, TradeSource};
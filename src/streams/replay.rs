@@ -1,15 +1,16 @@
 use anyhow::{Context, Result};
 use databento::{
-    dbn::{decode::DbnMetadata, Dataset, SType, Schema, TradeMsg},
-    historical::timeseries::GetRangeParams,
+    dbn::{Dataset, SType, Schema},
+    historical::timeseries::GetRangeToFileParams,
     HistoricalClient,
 };
-use std::{sync::Arc, time::Duration};
-use tokio::sync::RwLock;
+use std::sync::Arc;
 use tracing::info;
 
-use crate::processing::ProcessingState;
-use crate::types::{AppState, Trade, WsMessage};
+use crate::types::AppState;
+
+use super::driver::run_source;
+use super::local_replay::FileSource;
 
 /// Historical replay mode: fetch trades from Databento and replay at specified speed
 pub async fn run_historical_replay(
@@ -63,134 +64,50 @@ pub async fn run_historical_replay(
         replay_start, replay_end
     );
 
-    // Build historical client
-    let mut client = HistoricalClient::builder().key(api_key)?.build()?;
-
-    // Request the data
-    let params = GetRangeParams::builder()
-        .dataset(Dataset::GlbxMdp3)
-        .date_time_range((start_dt, end_dt))
-        .symbols(symbols.clone())
-        .stype_in(SType::RawSymbol)
-        .schema(Schema::Trades)
-        .build();
-
-    info!("Requesting historical trades for {:?}...", symbols);
-    let mut decoder = client
-        .timeseries()
-        .get_range(&params)
-        .await
-        .context("Failed to fetch historical data")?;
+    // Download once to a local DBN file - the replay (and any backward seek) reads it from disk
+    let symbol_key = symbols.join("_").replace(['/', '.'], "-");
+    let path = std::env::temp_dir().join(format!(
+        "orderflow-replay-{}-{}-{}-{}.dbn.zst",
+        replay_date,
+        replay_start.replace(':', ""),
+        replay_end.replace(':', ""),
+        symbol_key
+    ));
+
+    if path.exists() {
+        info!("Using cached historical data: {:?}", path);
+    } else {
+        // Build historical client
+        let mut client = HistoricalClient::builder().key(api_key)?.build()?;
+
+        // Request the data (into a partial file so a failed download isn't reused)
+        let partial = path.with_extension("part");
+        let params = GetRangeToFileParams::builder()
+            .dataset(Dataset::GlbxMdp3)
+            .date_time_range((start_dt, end_dt))
+            .symbols(symbols.clone())
+            .stype_in(SType::RawSymbol)
+            .schema(Schema::Trades)
+            .path(partial.clone())
+            .build();
+
+        info!("Requesting historical trades for {:?}...", symbols);
+        client
+            .timeseries()
+            .get_range_to_file(&params)
+            .await
+            .context("Failed to fetch historical data")?;
+        std::fs::rename(&partial, &path).context("Failed to save historical data")?;
+    }
 
     info!("Historical data received, starting replay...");
+    info!("Replay speed: {}x", replay_speed);
 
-    // Notify clients we're connected (in replay mode)
-    let _ = state.tx.send(WsMessage::Connected {
-        symbols: symbols.clone(),
-        mode: state.mode.clone(),
-    });
-
-    // Create processing state with Supabase persistence and AppState for stats sync
-    let processing_state = Arc::new(RwLock::new(ProcessingState::new(
-        state.supabase.clone(),
-        state.session_id,
-        Some(state.clone()),
-    )));
-
-    // Spawn aggregation task (but with speed multiplier)
-    let processing_state_clone = processing_state.clone();
-    let tx_clone = state.tx.clone();
-    let speed = replay_speed;
-    tokio::spawn(async move {
-        // Interval is shortened by speed multiplier
-        let interval_ms = 1000 / speed as u64;
-        let mut interval = tokio::time::interval(Duration::from_millis(interval_ms.max(50)));
-        loop {
-            interval.tick().await;
-            let mut pstate = processing_state_clone.write().await;
-            pstate.process_buffer(&tx_clone);
-            pstate.send_volume_profile(&tx_clone);
-        }
-    });
-
-    // Get symbol map for the date
-    let symbol_map = decoder.metadata().symbol_map_for_date(date)?;
-
-    // Track timestamps for pacing
-    let mut last_trade_ts: Option<u64> = None;
-
-    // Process each trade
-    while let Some(trade_msg) = decoder.decode_record::<TradeMsg>().await? {
-        // Check pause state
-        loop {
-            let ctrl = state.replay_control.read().await;
-            if !ctrl.is_paused {
-                break;
-            }
-            drop(ctrl);
-            tokio::time::sleep(Duration::from_millis(100)).await;
-        }
-
-        let trade_ts = trade_msg.hd.ts_event / 1_000_000; // nanoseconds to milliseconds
-
-        // Update current timestamp in replay control
-        {
-            let mut ctrl = state.replay_control.write().await;
-            ctrl.current_timestamp = Some(trade_ts);
-        }
-
-        // Get current speed from shared state
-        let current_speed = state.replay_control.read().await.speed;
-
-        // Pace the trades according to their original timing (adjusted by speed)
-        if let Some(last_ts) = last_trade_ts {
-            if trade_ts > last_ts {
-                let delay_ms = (trade_ts - last_ts) / current_speed as u64;
-                if delay_ms > 0 && delay_ms < 5000 {
-                    // Cap at 5 seconds to skip gaps
-                    tokio::time::sleep(Duration::from_millis(delay_ms)).await;
-                }
-            }
-        }
-        last_trade_ts = Some(trade_ts);
-
-        // Get symbol name
-        let symbol = symbol_map
-            .get(trade_msg.hd.instrument_id)
-            .cloned()
-            .unwrap_or_else(|| format!("ID:{}", trade_msg.hd.instrument_id));
-
-        // Determine side from trade action (action is i8, convert to u8 for char)
-        let action_char = trade_msg.action as u8 as char;
-        let side_char = trade_msg.side as u8 as char;
-        let side = match action_char {
-            'B' => "buy",
-            'S' | 'A' => "sell",
-            _ => {
-                if side_char == 'B' {
-                    "buy"
-                } else {
-                    "sell"
-                }
-            }
-        };
-
-        let min_size = *state.min_size.read().await;
-        let size = trade_msg.size;
-
-        if size >= min_size {
-            let trade = Trade {
-                symbol: symbol.clone(),
-                price: trade_msg.price as f64 / 1_000_000_000.0, // Fixed-point to float
-                size,
-                side: side.to_string(),
-                timestamp: trade_ts,
-            };
-
-            let mut proc_state = processing_state.write().await;
-            proc_state.add_trade(trade);
-        }
-    }
+    let source = FileSource::open(vec![path])?.with_session_range(
+        (start_dt.unix_timestamp_nanos() / 1_000_000) as u64,
+        (end_dt.unix_timestamp_nanos() / 1_000_000) as u64,
+    );
+    run_source(source, state).await?;
 
     info!("Replay complete!");
     Ok(())
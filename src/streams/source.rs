@@ -0,0 +1,66 @@
+//! Trade Sources
+//!
+//! Each mode (live, demo, local/db/historical replay) is a `TradeSource` - an
+//! async stream of trades, order book records and symbol mappings. The shared
+//! driver owns everything else: aggregation, pacing, pause/seek/step and the
+//! min-size filter.
+
+use anyhow::Result;
+use std::future::Future;
+
+use crate::aggressor::PrintInfo;
+use crate::book::BookUpdate;
+use crate::types::Trade;
+
+/// Event from a trade source
+#[derive(Debug, Clone)]
+pub enum SourceEvent {
+    /// Instrument id -> symbol (Databento symbology)
+    SymbolMapping { instrument_id: u32, symbol: String },
+    /// A trade - keyed by instrument id, or by `trade.symbol` for sources without ids
+    Trade {
+        instrument_id: Option<u32>,
+        trade: Trade,
+        /// Databento print fields (None for demo and stored bars)
+        print: Option<PrintInfo>,
+    },
+    /// An order book record (MBP-10/MBO) - `symbol` is used until a mapping arrives
+    Book {
+        instrument_id: u32,
+        symbol: String,
+        update: BookUpdate,
+    },
+}
+
+/// How a source's trades relate to wall-clock time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceTiming {
+    /// Trades arrive as they happen (live, demo): wall-clock aggregation, no pacing or seeking
+    Realtime,
+    /// Recorded trades (replays): event-time aggregation, paced by speed, seekable
+    Recorded,
+}
+
+/// An async stream of trades for the driver
+pub trait TradeSource: Send {
+    fn timing(&self) -> SourceTiming;
+
+    /// Symbols announced to clients when the stream starts
+    fn symbols(&self) -> Vec<String>;
+
+    /// First and last timestamps (Unix ms) of a recorded session - replay progress
+    fn session_range(&self) -> (Option<u64>, Option<u64>) {
+        (None, None)
+    }
+
+    /// Next event, or None when the source is exhausted
+    ///
+    /// Realtime sources are polled alongside the aggregation timer, so this
+    /// must be cancel safe for them.
+    fn next_event(&mut self) -> impl Future<Output = Result<Option<SourceEvent>>> + Send;
+
+    /// Restart from the session start (backward seeks on recorded sources)
+    fn rewind(&mut self) -> impl Future<Output = Result<()>> + Send {
+        async { anyhow::bail!("This source can't be rewound") }
+    }
+}
@@ -1,10 +1,16 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use async_trait::async_trait;
 use reqwest::Client;
-use serde::{Deserialize, Serialize};
+use serde::Deserialize;
 use serde_json::json;
-use tracing::{error, info, warn};
+use tracing::{info, warn};
 use uuid::Uuid;
 
+use crate::store::{
+    aggregate_stats, AggregateStats, BarRecord, SessionRecord, SessionRow, SignalInsert,
+    SignalOutcomeUpdate, SignalQuery, SignalRow, SignalStore, SignalSummary, UserConfig,
+};
+
 /// Supabase client for persisting signals and config
 #[derive(Clone)]
 pub struct SupabaseClient {
@@ -13,66 +19,15 @@ pub struct SupabaseClient {
     api_key: String,
 }
 
-/// Session record for database
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SessionRecord {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub id: Option<Uuid>,
-    pub mode: String,
-    pub symbols: Vec<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub session_high: Option<f64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub session_low: Option<f64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub total_volume: Option<i64>,
-}
-
-/// Signal record for database
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SignalInsert {
-    pub session_id: Uuid,
-    pub timestamp: i64,
-    pub signal_type: String,
-    pub direction: String,
-    pub price: f64,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub price_after_1m: Option<f64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub price_after_5m: Option<f64>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub outcome: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub metadata: Option<serde_json::Value>,
-}
-
-/// User configuration
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
-pub struct UserConfig {
-    #[serde(default = "default_min_size")]
-    pub min_size: u32,
-    #[serde(default = "default_sound_enabled")]
-    pub sound_enabled: bool,
-    #[serde(default = "default_symbols")]
-    pub symbols: Vec<String>,
-}
-
-fn default_min_size() -> u32 {
-    1
-}
-fn default_sound_enabled() -> bool {
-    true
-}
-fn default_symbols() -> Vec<String> {
-    vec!["NQ.c.0".to_string(), "ES.c.0".to_string()]
-}
-
 /// Response from Supabase insert with returning
 #[derive(Debug, Deserialize)]
 struct InsertResponse {
     id: Uuid,
 }
 
+/// Bars per insert request
+const BAR_INSERT_BATCH: usize = 1000;
+
 impl SupabaseClient {
     /// Create a new Supabase client from environment variables
     pub fn from_env() -> Option<Self> {
@@ -108,8 +63,82 @@ impl SupabaseClient {
             .header("Content-Type", "application/json")
     }
 
-    /// Insert a new session and return its ID
-    pub async fn insert_session(&self, session: &SessionRecord) -> Result<Uuid> {
+    async fn update_signal_outcome(&self, update: &SignalOutcomeUpdate) -> Result<()> {
+        let response = self
+            .request(
+                reqwest::Method::PATCH,
+                &format!("signals?timestamp=eq.{}&session_id=eq.{}", update.timestamp, update.session_id),
+            )
+            .json(&json!({
+                "price_after_1m": update.price_after_1m,
+                "price_after_5m": update.price_after_5m,
+                "outcome": update.outcome,
+            }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Failed to update signal: {} - {}", status, body));
+        }
+
+        Ok(())
+    }
+}
+
+/// PostgREST filters shared by the signal list and count queries
+fn signal_filters(query: &SignalQuery) -> String {
+    let mut filters = String::new();
+
+    if let Some(ref signal_type) = query.signal_type {
+        filters.push_str(&format!("&signal_type=eq.{}", signal_type));
+    }
+    if let Some(ref direction) = query.direction {
+        filters.push_str(&format!("&direction=eq.{}", direction));
+    }
+    if let Some(ref outcome) = query.outcome {
+        filters.push_str(&format!("&outcome=eq.{}", outcome));
+    }
+    if let Some(ref start_date) = query.start_date {
+        filters.push_str(&format!("&created_at=gte.{}", start_date));
+    }
+    if let Some(ref end_date) = query.end_date {
+        filters.push_str(&format!("&created_at=lte.{}", end_date));
+    }
+
+    filters
+}
+
+/// Timestamp range filter for one date (YYYY-MM-DD) of replay bars
+fn bar_date_filter(date_filter: Option<&str>) -> String {
+    match date_filter {
+        Some(date) => format!("&timestamp=gte.{}T00:00:00&timestamp=lt.{}T23:59:59", date, date),
+        None => String::new(),
+    }
+}
+
+/// Total row count from a `Prefer: count=exact` response
+fn content_range_total(response: &reqwest::Response) -> Option<u64> {
+    // Format: "0-49/1234" - we want the total after /
+    response
+        .headers()
+        .get("content-range")?
+        .to_str()
+        .ok()?
+        .split('/')
+        .nth(1)?
+        .parse()
+        .ok()
+}
+
+#[async_trait]
+impl SignalStore for SupabaseClient {
+    fn name(&self) -> &'static str {
+        "Supabase"
+    }
+
+    async fn insert_session(&self, session: &SessionRecord) -> Result<Uuid> {
         let response = self
             .request(reqwest::Method::POST, "sessions")
             .header("Prefer", "return=representation")
@@ -133,8 +162,7 @@ impl SupabaseClient {
         Ok(id)
     }
 
-    /// Update session with final stats
-    pub async fn update_session(
+    async fn update_session(
         &self,
         session_id: Uuid,
         session_high: f64,
@@ -161,15 +189,7 @@ impl SupabaseClient {
         Ok(())
     }
 
-    /// Insert a signal record (fire-and-forget style, logs errors)
-    pub async fn insert_signal(&self, signal: SignalInsert) {
-        match self.insert_signal_inner(signal).await {
-            Ok(_) => {}
-            Err(e) => error!("Failed to insert signal to Supabase: {}", e),
-        }
-    }
-
-    async fn insert_signal_inner(&self, signal: SignalInsert) -> Result<()> {
+    async fn insert_signal(&self, signal: SignalInsert) -> Result<()> {
         let response = self
             .request(reqwest::Method::POST, "signals")
             .json(&signal)
@@ -185,40 +205,17 @@ impl SupabaseClient {
         Ok(())
     }
 
-    /// Update signal outcomes in batch
-    pub async fn update_signal_outcomes(&self, updates: Vec<SignalOutcomeUpdate>) {
+    async fn update_signal_outcomes(&self, updates: Vec<SignalOutcomeUpdate>) -> Result<()> {
+        // One PATCH per signal - a failed row doesn't stop the rest
         for update in updates {
-            if let Err(e) = self.update_signal_outcome_inner(&update).await {
+            if let Err(e) = self.update_signal_outcome(&update).await {
                 warn!("Failed to update signal outcome: {}", e);
             }
         }
-    }
-
-    async fn update_signal_outcome_inner(&self, update: &SignalOutcomeUpdate) -> Result<()> {
-        let response = self
-            .request(
-                reqwest::Method::PATCH,
-                &format!("signals?timestamp=eq.{}&session_id=eq.{}", update.timestamp, update.session_id),
-            )
-            .json(&json!({
-                "price_after_1m": update.price_after_1m,
-                "price_after_5m": update.price_after_5m,
-                "outcome": update.outcome,
-            }))
-            .send()
-            .await?;
-
-        if !response.status().is_success() {
-            let status = response.status();
-            let body = response.text().await.unwrap_or_default();
-            return Err(anyhow!("Failed to update signal: {} - {}", status, body));
-        }
-
         Ok(())
     }
 
-    /// Get user configuration
-    pub async fn get_config(&self) -> Result<UserConfig> {
+    async fn get_config(&self) -> Result<UserConfig> {
         let response = self
             .request(reqwest::Method::GET, "config?key=eq.user_settings")
             .header("Accept", "application/json")
@@ -238,8 +235,7 @@ impl SupabaseClient {
         Ok(rows.into_iter().next().map(|r| r.value).unwrap_or_default())
     }
 
-    /// Save user configuration
-    pub async fn set_config(&self, config: &UserConfig) -> Result<()> {
+    async fn set_config(&self, config: &UserConfig) -> Result<()> {
         let response = self
             .request(reqwest::Method::PATCH, "config?key=eq.user_settings")
             .json(&json!({
@@ -258,107 +254,17 @@ impl SupabaseClient {
         info!("Saved config to Supabase");
         Ok(())
     }
-}
-
-/// Batch update for signal outcomes
-#[derive(Debug, Clone)]
-pub struct SignalOutcomeUpdate {
-    pub session_id: Uuid,
-    pub timestamp: i64,
-    pub price_after_1m: Option<f64>,
-    pub price_after_5m: Option<f64>,
-    pub outcome: Option<String>,
-}
-
-/// Signal row from database
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SignalRow {
-    pub id: Uuid,
-    pub session_id: Option<Uuid>,
-    pub created_at: String,
-    pub timestamp: i64,
-    pub signal_type: String,
-    pub direction: String,
-    pub price: f64,
-    pub price_after_1m: Option<f64>,
-    pub price_after_5m: Option<f64>,
-    pub outcome: Option<String>,
-    pub metadata: Option<serde_json::Value>,
-}
-
-/// Session row from database
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SessionRow {
-    pub id: Uuid,
-    pub started_at: String,
-    pub ended_at: Option<String>,
-    pub mode: String,
-    pub symbols: Vec<String>,
-    pub session_high: Option<f64>,
-    pub session_low: Option<f64>,
-    pub total_volume: Option<i64>,
-}
-
-/// Aggregate stats response
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AggregateStats {
-    pub total_signals: u32,
-    pub by_type: std::collections::HashMap<String, SignalTypeStats>,
-    pub by_direction: DirectionStats,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SignalTypeStats {
-    pub count: u32,
-    pub wins: u32,
-    pub losses: u32,
-    pub breakeven: u32,
-    pub win_rate: f64,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DirectionStats {
-    pub bullish: u32,
-    pub bearish: u32,
-}
-
-/// Query parameters for signals
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SignalQuery {
-    pub limit: Option<u32>,
-    pub offset: Option<u32>,
-    pub signal_type: Option<String>,
-    pub direction: Option<String>,
-    pub outcome: Option<String>,
-    /// Start date filter (ISO 8601 format)
-    pub start_date: Option<String>,
-    /// End date filter (ISO 8601 format)
-    pub end_date: Option<String>,
-}
 
-impl SupabaseClient {
-    /// Get signals with optional filtering and pagination
-    pub async fn query_signals(&self, query: &SignalQuery) -> Result<Vec<SignalRow>> {
+    async fn query_signals(&self, query: &SignalQuery) -> Result<Vec<SignalRow>> {
         let limit = query.limit.unwrap_or(50).min(200);
         let offset = query.offset.unwrap_or(0);
 
-        let mut url = format!("signals?select=*&order=timestamp.desc&limit={}&offset={}", limit, offset);
-
-        if let Some(ref signal_type) = query.signal_type {
-            url.push_str(&format!("&signal_type=eq.{}", signal_type));
-        }
-        if let Some(ref direction) = query.direction {
-            url.push_str(&format!("&direction=eq.{}", direction));
-        }
-        if let Some(ref outcome) = query.outcome {
-            url.push_str(&format!("&outcome=eq.{}", outcome));
-        }
-        if let Some(ref start_date) = query.start_date {
-            url.push_str(&format!("&created_at=gte.{}", start_date));
-        }
-        if let Some(ref end_date) = query.end_date {
-            url.push_str(&format!("&created_at=lte.{}", end_date));
-        }
+        let url = format!(
+            "signals?select=*&order=timestamp.desc&limit={}&offset={}{}",
+            limit,
+            offset,
+            signal_filters(query)
+        );
 
         let response = self
             .request(reqwest::Method::GET, &url)
@@ -376,8 +282,20 @@ impl SupabaseClient {
         Ok(signals)
     }
 
-    /// Get sessions list
-    pub async fn query_sessions(&self, limit: u32) -> Result<Vec<SessionRow>> {
+    async fn count_signals(&self, query: &SignalQuery) -> Result<u32> {
+        let url = format!("signals?select=count{}", signal_filters(query));
+
+        let response = self
+            .request(reqwest::Method::GET, &url)
+            .header("Accept", "application/json")
+            .header("Prefer", "count=exact")
+            .send()
+            .await?;
+
+        Ok(content_range_total(&response).unwrap_or(0) as u32)
+    }
+
+    async fn query_sessions(&self, limit: u32) -> Result<Vec<SessionRow>> {
         let url = format!("sessions?select=*&order=started_at.desc&limit={}", limit.min(100));
 
         let response = self
@@ -396,8 +314,7 @@ impl SupabaseClient {
         Ok(sessions)
     }
 
-    /// Get aggregate stats across all signals
-    pub async fn get_aggregate_stats(&self) -> Result<AggregateStats> {
+    async fn get_aggregate_stats(&self) -> Result<AggregateStats> {
         // Get all signals with outcomes
         let response = self
             .request(reqwest::Method::GET, "signals?select=signal_type,direction,outcome")
@@ -411,103 +328,71 @@ impl SupabaseClient {
             return Err(anyhow!("Failed to get aggregate stats: {} - {}", status, body));
         }
 
-        #[derive(Deserialize)]
-        struct SignalSummary {
-            signal_type: String,
-            direction: String,
-            outcome: Option<String>,
-        }
-
         let signals: Vec<SignalSummary> = response.json().await?;
-        let total_signals = signals.len() as u32;
-
-        // Aggregate by type
-        let mut by_type: std::collections::HashMap<String, SignalTypeStats> = std::collections::HashMap::new();
-        let mut bullish = 0u32;
-        let mut bearish = 0u32;
-
-        for signal in &signals {
-            // Direction stats
-            if signal.direction == "bullish" {
-                bullish += 1;
-            } else {
-                bearish += 1;
-            }
+        Ok(aggregate_stats(&signals))
+    }
 
-            // Type stats
-            let entry = by_type.entry(signal.signal_type.clone()).or_insert(SignalTypeStats {
-                count: 0,
-                wins: 0,
-                losses: 0,
-                breakeven: 0,
-                win_rate: 0.0,
-            });
-            entry.count += 1;
-
-            if let Some(ref outcome) = signal.outcome {
-                match outcome.as_str() {
-                    "win" => entry.wins += 1,
-                    "loss" => entry.losses += 1,
-                    "breakeven" => entry.breakeven += 1,
-                    _ => {}
-                }
+    async fn insert_bars(&self, bars: &[BarRecord]) -> Result<()> {
+        for chunk in bars.chunks(BAR_INSERT_BATCH) {
+            let response = self
+                .request(reqwest::Method::POST, "replay_bars_1s")
+                .header("Prefer", "return=minimal")
+                .json(chunk)
+                .send()
+                .await
+                .context("Failed to send bars to Supabase")?;
+
+            if !response.status().is_success() {
+                let status = response.status();
+                let body = response.text().await.unwrap_or_default();
+                return Err(anyhow!("Failed to insert bars: {} - {}", status, body));
             }
         }
+        Ok(())
+    }
 
-        // Calculate win rates
-        for stats in by_type.values_mut() {
-            let decided = stats.wins + stats.losses;
-            if decided > 0 {
-                stats.win_rate = (stats.wins as f64 / decided as f64) * 100.0;
-            }
-        }
+    async fn count_bars(&self, date_filter: Option<&str>) -> Result<usize> {
+        let url = format!("replay_bars_1s?select=count{}", bar_date_filter(date_filter));
 
-        Ok(AggregateStats {
-            total_signals,
-            by_type,
-            by_direction: DirectionStats { bullish, bearish },
-        })
-    }
+        let response = self
+            .request(reqwest::Method::GET, &url)
+            .header("Prefer", "count=exact")
+            .send()
+            .await
+            .context("Failed to count bars")?;
 
-    /// Count total signals (for pagination)
-    pub async fn count_signals(&self, query: &SignalQuery) -> Result<u32> {
-        let mut url = "signals?select=count".to_string();
+        Ok(content_range_total(&response).unwrap_or(0) as usize)
+    }
 
-        if let Some(ref signal_type) = query.signal_type {
-            url.push_str(&format!("&signal_type=eq.{}", signal_type));
-        }
-        if let Some(ref direction) = query.direction {
-            url.push_str(&format!("&direction=eq.{}", direction));
-        }
-        if let Some(ref outcome) = query.outcome {
-            url.push_str(&format!("&outcome=eq.{}", outcome));
-        }
-        if let Some(ref start_date) = query.start_date {
-            url.push_str(&format!("&created_at=gte.{}", start_date));
-        }
-        if let Some(ref end_date) = query.end_date {
-            url.push_str(&format!("&created_at=lte.{}", end_date));
-        }
+    async fn fetch_bars(
+        &self,
+        date_filter: Option<&str>,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<BarRecord>> {
+        let url = format!(
+            "replay_bars_1s?select=*&order=timestamp.asc&limit={}&offset={}{}",
+            limit,
+            offset,
+            bar_date_filter(date_filter)
+        );
 
         let response = self
             .request(reqwest::Method::GET, &url)
-            .header("Accept", "application/json")
-            .header("Prefer", "count=exact")
             .send()
-            .await?;
+            .await
+            .context("Failed to fetch bars from Supabase")?;
 
-        // Get count from content-range header
-        if let Some(range) = response.headers().get("content-range") {
-            if let Ok(range_str) = range.to_str() {
-                // Format: "0-49/1234" - we want the total after /
-                if let Some(total) = range_str.split('/').nth(1) {
-                    if let Ok(count) = total.parse::<u32>() {
-                        return Ok(count);
-                    }
-                }
-            }
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Supabase fetch failed ({}): {}", status, body));
         }
 
-        Ok(0)
+        let bars: Vec<BarRecord> = response
+            .json()
+            .await
+            .context("Failed to parse bars response")?;
+        Ok(bars)
     }
 }
@@ -0,0 +1,718 @@
+//! Trade File Reader
+//!
+//! Streams trades from downloaded Databento files - zstd CSV exports
+//! (`.trades.csv.zst`) and DBN files (`.dbn.zst` / `.dbn`). Each file is read
+//! lazily and files are k-way merged in receive-time order (the order Databento
+//! sorts them in, like a live feed), so a month of data replays with one
+//! buffered trade per file instead of one giant sorted Vec.
+//! Order book files (`.mbp-10.dbn.zst` / `.mbo.dbn.zst`, DBN only) are read and
+//! merged the same way. Used by `--local-replay` and the pipeline's replay/backtest.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate};
+use databento::dbn::{
+    decode::{DbnMetadata, DecodeRecordRef, DynDecoder},
+    MboMsg, Mbp10Msg, Record, SymbolIndex, TradeMsg, TsSymbolMap, VersionUpgradePolicy,
+};
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+use crate::aggressor::PrintInfo;
+use crate::book::{book_update_from_mbo, book_update_from_mbp10, BookUpdate};
+use crate::types::Trade;
+
+/// Trade record from Databento CSV (the columns used - the rest are ignored)
+#[derive(Debug, Deserialize)]
+struct CsvTrade {
+    ts_recv: String,
+    ts_event: String,
+    instrument_id: u64,
+    action: String,
+    side: String,
+    price: f64,
+    size: u64,
+    flags: u32,
+    sequence: u64,
+    symbol: String,
+}
+
+/// A trade read from a file, with the fields ProcessingState doesn't carry
+#[derive(Debug, Clone)]
+pub struct TradeRecord {
+    pub instrument_id: u32,
+    /// Event time in nanoseconds (`trade.timestamp` is milliseconds)
+    pub ts_event_ns: u64,
+    /// Receive time in nanoseconds - files are sorted by it, not by event time
+    pub ts_recv_ns: u64,
+    /// Matching engine sequence number and Databento record flags (`F_LAST`, ...)
+    pub sequence: u32,
+    pub flags: u8,
+    pub trade: Trade,
+}
+
+impl TradeRecord {
+    /// Fields that group the print into its aggressor order
+    pub fn print(&self) -> PrintInfo {
+        PrintInfo {
+            ts_event_ns: self.ts_event_ns,
+            sequence: self.sequence,
+            flags: self.flags,
+        }
+    }
+}
+
+/// An order book record read from a file
+#[derive(Debug, Clone)]
+pub struct BookRecord {
+    pub instrument_id: u32,
+    /// Event time in nanoseconds (`update.timestamp` is milliseconds)
+    pub ts_event_ns: u64,
+    /// Receive time in nanoseconds - files are sorted by it, not by event time
+    pub ts_recv_ns: u64,
+    pub symbol: String,
+    pub update: BookUpdate,
+}
+
+/// Convert a Databento trade to a Trade (None for non-trade actions and prints without an aggressor)
+///
+/// `side` is the aggressor: 'B' = buyer lifted the offer, 'A' = seller hit the bid -
+/// the same convention as the CSV export.
+pub fn trade_from_msg(msg: &TradeMsg, symbol: String) -> Option<Trade> {
+    if msg.action as u8 != b'T' {
+        return None;
+    }
+    let side = match msg.side as u8 {
+        b'B' => "buy",
+        b'A' => "sell",
+        _ => return None,
+    };
+
+    Some(Trade {
+        symbol,
+        price: msg.price_f64(),
+        size: msg.size,
+        side: side.to_string(),
+        timestamp: msg.hd.ts_event / 1_000_000, // Nanos to millis
+    })
+}
+
+/// Find all trade files (.zst CSV/DBN or plain .dbn) in directory for a specific date
+pub fn find_trade_files(data_dir: &Path, date_filter: Option<&str>) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for entry in std::fs::read_dir(data_dir)
+        .with_context(|| format!("Failed to read directory: {:?}", data_dir))?
+    {
+        let entry = entry?;
+        let path = entry.path();
+
+        if path.extension().is_some_and(|ext| ext == "zst" || ext == "dbn") && !is_book_file(&path) {
+            if let Some(filter) = date_filter {
+                let filename = path.file_name().unwrap().to_string_lossy();
+                if !filename.contains(filter) {
+                    continue;
+                }
+            }
+            files.push(path);
+        }
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Find order book (MBP-10/MBO) DBN files in directory for a specific date
+pub fn find_book_files(data_dir: &Path, date_filter: Option<&str>) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    for entry in std::fs::read_dir(data_dir)
+        .with_context(|| format!("Failed to read directory: {:?}", data_dir))?
+    {
+        let path = entry?.path();
+        if !is_book_file(&path) || !is_dbn_file(&path) {
+            continue;
+        }
+        if let Some(filter) = date_filter {
+            let filename = path.file_name().unwrap().to_string_lossy();
+            if !filename.contains(filter) {
+                continue;
+            }
+        }
+        files.push(path);
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Trading date in a Databento file name (`glbx-mdp3-20251127.trades.csv.zst`)
+pub fn file_date(path: &Path) -> Option<NaiveDate> {
+    let name = path.file_name()?.to_string_lossy();
+    name.split(|c: char| !c.is_ascii_digit())
+        .filter(|part| part.len() == 8)
+        .find_map(|part| NaiveDate::parse_from_str(part, "%Y%m%d").ok())
+}
+
+/// End of the UTC days covered by a set of daily files (Unix ms) - replay progress
+pub fn files_end_ms(files: &[PathBuf]) -> Option<u64> {
+    let last = files.iter().filter_map(|path| file_date(path)).max()?;
+    let end = last.succ_opt()?.and_hms_opt(0, 0, 0)?.and_utc();
+    Some(end.timestamp_millis() as u64)
+}
+
+/// Is this a Databento binary (DBN) file rather than a CSV export?
+pub fn is_dbn_file(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    name.ends_with(".dbn.zst") || name.ends_with(".dbn")
+}
+
+/// Is this an order book download (`glbx-mdp3-20251127.mbp-10.dbn.zst`, `.mbo.`)?
+pub fn is_book_file(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    name.contains(".mbp-10.") || name.contains(".mbo.")
+}
+
+/// Parse a CSV export timestamp (RFC 3339) to Unix nanoseconds
+fn parse_ts_ns(ts: &str) -> Result<u64> {
+    let parsed = DateTime::parse_from_rfc3339(ts)
+        .with_context(|| format!("Failed to parse timestamp: {}", ts))?;
+    Ok(parsed.timestamp_nanos_opt().unwrap_or(0) as u64)
+}
+
+type CsvRows = csv::DeserializeRecordsIntoIter<BufReader<zstd::stream::Decoder<'static, BufReader<File>>>, CsvTrade>;
+
+/// Lazily reads the trades of one file, in file order
+pub struct TradeFileReader {
+    path: PathBuf,
+    source: Source,
+}
+
+enum Source {
+    Csv(CsvRows),
+    Dbn {
+        decoder: Box<DynDecoder<'static, BufReader<File>>>,
+        // Symbology from the DBN metadata (instrument_id -> symbol per date)
+        symbol_map: TsSymbolMap,
+    },
+}
+
+impl TradeFileReader {
+    /// Open a CSV export or DBN file (detected from the file name)
+    pub fn open(path: &Path) -> Result<Self> {
+        let source = if is_dbn_file(path) {
+            let decoder = DynDecoder::from_file(path, VersionUpgradePolicy::default())
+                .with_context(|| format!("Failed to open DBN file: {:?}", path))?;
+            let symbol_map = decoder
+                .metadata()
+                .symbol_map()
+                .with_context(|| format!("Invalid symbology in DBN metadata: {:?}", path))?;
+
+            Source::Dbn {
+                decoder: Box::new(decoder),
+                symbol_map,
+            }
+        } else {
+            let file = File::open(path)
+                .with_context(|| format!("Failed to open file: {:?}", path))?;
+            let decoder = zstd::stream::Decoder::new(file)
+                .with_context(|| format!("Failed to create zstd decoder for: {:?}", path))?;
+            Source::Csv(csv::Reader::from_reader(BufReader::new(decoder)).into_deserialize())
+        };
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            source,
+        })
+    }
+
+    fn next_csv(path: &Path, rows: &mut CsvRows) -> Option<Result<TradeRecord>> {
+        for result in rows.by_ref() {
+            let row = match result {
+                Ok(row) => row,
+                Err(e) => {
+                    return Some(Err(e).with_context(|| format!("Failed to parse CSV row in {:?}", path)))
+                }
+            };
+
+            // Only process trade actions
+            if row.action != "T" {
+                continue;
+            }
+
+            let side = match row.side.as_str() {
+                "B" => "buy",
+                "A" => "sell",
+                _ => continue,
+            };
+
+            // Parse timestamps
+            let (ts_event_ns, ts_recv_ns) = match (parse_ts_ns(&row.ts_event), parse_ts_ns(&row.ts_recv)) {
+                (Ok(ts_event), Ok(ts_recv)) => (ts_event, ts_recv),
+                (Err(e), _) | (_, Err(e)) => return Some(Err(e)),
+            };
+
+            return Some(Ok(TradeRecord {
+                instrument_id: row.instrument_id as u32,
+                ts_event_ns,
+                ts_recv_ns,
+                sequence: row.sequence as u32,
+                flags: row.flags as u8,
+                trade: Trade {
+                    symbol: row.symbol,
+                    price: row.price,
+                    size: row.size as u32,
+                    side: side.to_string(),
+                    timestamp: ts_event_ns / 1_000_000,
+                },
+            }));
+        }
+        None
+    }
+
+    fn next_dbn(
+        path: &Path,
+        decoder: &mut DynDecoder<'static, BufReader<File>>,
+        symbol_map: &TsSymbolMap,
+    ) -> Option<Result<TradeRecord>> {
+        loop {
+            let record = match decoder.decode_record_ref() {
+                Ok(Some(record)) => record,
+                Ok(None) => return None,
+                Err(e) => {
+                    return Some(Err(e).with_context(|| format!("Failed to decode DBN record in: {:?}", path)))
+                }
+            };
+
+            let Some(trade) = record.get::<TradeMsg>() else {
+                continue;
+            };
+
+            let instrument_id = trade.hd.instrument_id;
+            let symbol = symbol_map
+                .get_for_rec(trade)
+                .cloned()
+                .unwrap_or_else(|| format!("ID:{}", instrument_id));
+
+            let Some(trade_msg) = trade_from_msg(trade, symbol) else {
+                continue;
+            };
+
+            return Some(Ok(TradeRecord {
+                instrument_id,
+                ts_event_ns: trade.hd.ts_event,
+                ts_recv_ns: trade.ts_recv,
+                sequence: trade.sequence,
+                flags: trade.flags.raw(),
+                trade: trade_msg,
+            }));
+        }
+    }
+}
+
+impl Iterator for TradeFileReader {
+    type Item = Result<TradeRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.source {
+            Source::Csv(rows) => Self::next_csv(&self.path, rows),
+            Source::Dbn {
+                decoder,
+                symbol_map,
+            } => Self::next_dbn(&self.path, decoder, symbol_map),
+        }
+    }
+}
+
+/// Lazily reads the MBP-10/MBO records of one DBN file, in file order
+pub struct BookFileReader {
+    path: PathBuf,
+    decoder: Box<DynDecoder<'static, BufReader<File>>>,
+    symbol_map: TsSymbolMap,
+}
+
+impl BookFileReader {
+    pub fn open(path: &Path) -> Result<Self> {
+        let decoder = DynDecoder::from_file(path, VersionUpgradePolicy::default())
+            .with_context(|| format!("Failed to open DBN file: {:?}", path))?;
+        let symbol_map = decoder
+            .metadata()
+            .symbol_map()
+            .with_context(|| format!("Invalid symbology in DBN metadata: {:?}", path))?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            decoder: Box::new(decoder),
+            symbol_map,
+        })
+    }
+}
+
+impl Iterator for BookFileReader {
+    type Item = Result<BookRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let record = match self.decoder.decode_record_ref() {
+                Ok(Some(record)) => record,
+                Ok(None) => return None,
+                Err(e) => {
+                    return Some(Err(e).with_context(|| format!("Failed to decode DBN record in: {:?}", self.path)))
+                }
+            };
+
+            let (update, symbol, ts_recv_ns) = if let Some(msg) = record.get::<Mbp10Msg>() {
+                (book_update_from_mbp10(msg), self.symbol_map.get_for_rec(msg), msg.ts_recv)
+            } else if let Some(msg) = record.get::<MboMsg>() {
+                (book_update_from_mbo(msg), self.symbol_map.get_for_rec(msg), msg.ts_recv)
+            } else {
+                continue;
+            };
+
+            let header = record.header();
+            return Some(Ok(BookRecord {
+                instrument_id: header.instrument_id,
+                ts_event_ns: header.ts_event,
+                ts_recv_ns,
+                symbol: symbol
+                    .cloned()
+                    .unwrap_or_else(|| format!("ID:{}", header.instrument_id)),
+                update,
+            }));
+        }
+    }
+}
+
+/// A per-file reader that can be k-way merged
+pub trait RecordFile: Iterator<Item = Result<Self::Record>> + Sized {
+    type Record;
+
+    fn open(path: &Path) -> Result<Self>;
+
+    /// Receive time of a record (nanoseconds) - the key files are sorted by
+    fn ts_recv_ns(record: &Self::Record) -> u64;
+}
+
+impl RecordFile for TradeFileReader {
+    type Record = TradeRecord;
+
+    fn open(path: &Path) -> Result<Self> {
+        TradeFileReader::open(path)
+    }
+
+    fn ts_recv_ns(record: &TradeRecord) -> u64 {
+        record.ts_recv_ns
+    }
+}
+
+impl RecordFile for BookFileReader {
+    type Record = BookRecord;
+
+    fn open(path: &Path) -> Result<Self> {
+        BookFileReader::open(path)
+    }
+
+    fn ts_recv_ns(record: &BookRecord) -> u64 {
+        record.ts_recv_ns
+    }
+}
+
+/// Trades from a set of files in receive-time order
+pub type MergedTrades = Merged<TradeFileReader>;
+
+/// Order book records from a set of files in receive-time order
+pub type MergedBook = Merged<BookFileReader>;
+
+/// K-way merge of per-file readers in receive-time order
+///
+/// Databento files are sorted by `ts_recv` (their `ts_event` can step back a
+/// little), so only the head record of each file is buffered. Ties go to the
+/// earlier file.
+pub struct Merged<R: RecordFile> {
+    readers: Vec<R>,
+    heads: Vec<Option<R::Record>>,
+    heap: BinaryHeap<Reverse<(u64, usize)>>,
+    // Read error hit while refilling - returned on the next call
+    pending_error: Option<anyhow::Error>,
+}
+
+impl<R: RecordFile> Merged<R> {
+    /// Open every file and buffer its first record
+    pub fn open(files: &[PathBuf]) -> Result<Self> {
+        let mut merged = Self {
+            readers: Vec::with_capacity(files.len()),
+            heads: Vec::with_capacity(files.len()),
+            heap: BinaryHeap::new(),
+            pending_error: None,
+        };
+
+        for (idx, path) in files.iter().enumerate() {
+            let mut reader = R::open(path)?;
+            let head = reader.next().transpose()?;
+            if let Some(ref record) = head {
+                merged.heap.push(Reverse((R::ts_recv_ns(record), idx)));
+            }
+            merged.readers.push(reader);
+            merged.heads.push(head);
+        }
+
+        Ok(merged)
+    }
+
+    /// Receive time of the next record (nanoseconds)
+    pub fn peek_ts(&self) -> Option<u64> {
+        self.heap.peek().map(|Reverse((ts, _))| *ts)
+    }
+}
+
+impl MergedTrades {
+    /// Symbols of the next buffered trade in each file
+    pub fn head_symbols(&self) -> Vec<String> {
+        let mut symbols: Vec<String> = self
+            .heads
+            .iter()
+            .flatten()
+            .map(|r| r.trade.symbol.clone())
+            .collect();
+        symbols.sort();
+        symbols.dedup();
+        symbols
+    }
+}
+
+impl<R: RecordFile> Iterator for Merged<R> {
+    type Item = Result<R::Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(e) = self.pending_error.take() {
+            return Some(Err(e));
+        }
+
+        let Reverse((_, idx)) = self.heap.pop()?;
+        let record = self.heads[idx].take()?;
+
+        // Refill this file's head
+        match self.readers[idx].next() {
+            Some(Ok(next)) => {
+                self.heap.push(Reverse((R::ts_recv_ns(&next), idx)));
+                self.heads[idx] = Some(next);
+            }
+            Some(Err(e)) => self.pending_error = Some(e),
+            None => {}
+        }
+
+        Some(Ok(record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::book::{BookAction, BookEvent};
+    use databento::dbn::{
+        encode::{dbn::Encoder, EncodeRecord},
+        rtype, Dataset, MappingInterval, Metadata, RecordHeader, SType, Schema, SymbolMapping,
+    };
+    use std::ffi::c_char;
+    use time::macros::date;
+
+    const TS: u64 = 1_764_599_400_000_000_000; // 2025-12-01 14:30 UTC
+
+    /// Write a DBN file with trades at the given (offset ms, side) for NQH6
+    fn write_dbn(trades: &[(u64, u8)]) -> PathBuf {
+        let trades: Vec<_> = trades.iter().map(|&(offset_ms, side)| (offset_ms, offset_ms, side)).collect();
+        write_dbn_received(&trades)
+    }
+
+    /// Write a DBN file with trades at the given (event offset ms, receive offset ms, side)
+    fn write_dbn_received(trades: &[(u64, u64, u8)]) -> PathBuf {
+        let metadata = Metadata::builder()
+            .dataset(Dataset::GlbxMdp3)
+            .schema(Some(Schema::Trades))
+            .start(TS)
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .mappings(vec![SymbolMapping {
+                raw_symbol: "NQH6".to_string(),
+                intervals: vec![MappingInterval {
+                    start_date: date!(2025 - 12 - 01),
+                    end_date: date!(2025 - 12 - 02),
+                    symbol: "42002475".to_string(),
+                }],
+            }])
+            .build();
+
+        let path = std::env::temp_dir().join(format!("{}.trades.dbn.zst", uuid::Uuid::new_v4()));
+        let file = File::create(&path).unwrap();
+        let mut encoder = Encoder::with_zstd(file, &metadata).unwrap();
+        for (event_ms, recv_ms, side) in trades {
+            encoder
+                .encode_record(&TradeMsg {
+                    hd: RecordHeader::new::<TradeMsg>(rtype::MBP_0, 1, 42002475, TS + event_ms * 1_000_000),
+                    price: 21_500_250_000_000,
+                    size: 3,
+                    action: b'T' as c_char,
+                    side: *side as c_char,
+                    ts_recv: TS + recv_ms * 1_000_000, // Symbology is indexed by ts_recv
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        path
+    }
+
+    #[test]
+    fn test_read_dbn_trades() {
+        let path = write_dbn(&[(1, b'B'), (2, b'A'), (3, b'N')]); // 'N' = no aggressor, skipped
+        assert!(is_dbn_file(&path));
+
+        let trades: Vec<_> = TradeFileReader::open(&path)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0].instrument_id, 42002475);
+        assert_eq!(trades[0].trade.symbol, "NQH6");
+        assert_eq!(trades[0].trade.side, "buy");
+        assert_eq!(trades[0].trade.price, 21500.25);
+        assert_eq!(trades[0].trade.timestamp, 1_764_599_400_001);
+        assert_eq!(trades[1].trade.side, "sell");
+    }
+
+    #[test]
+    fn test_merge_is_time_ordered() {
+        let a = write_dbn(&[(1, b'B'), (4, b'B'), (5, b'B')]);
+        let b = write_dbn(&[(2, b'A'), (3, b'A'), (6, b'A')]);
+
+        let merged = MergedTrades::open(&[a.clone(), b.clone()]).unwrap();
+        assert_eq!(merged.peek_ts(), Some(TS + 1_000_000));
+        let offsets: Vec<u64> = merged
+            .map(|r| (r.unwrap().ts_event_ns - TS) / 1_000_000)
+            .collect();
+        std::fs::remove_file(&a).ok();
+        std::fs::remove_file(&b).ok();
+
+        assert_eq!(offsets, vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn test_merge_follows_receive_order_when_events_step_back() {
+        // The trade at event 2 was received after the one at event 3
+        let a = write_dbn_received(&[(1, 1, b'B'), (3, 5, b'B'), (2, 6, b'B')]);
+        let b = write_dbn(&[(4, b'A'), (7, b'A')]);
+
+        let merged = MergedTrades::open(&[a.clone(), b.clone()]).unwrap();
+        let offsets: Vec<(u64, u64)> = merged
+            .map(|r| r.unwrap())
+            .map(|r| ((r.ts_event_ns - TS) / 1_000_000, (r.ts_recv_ns - TS) / 1_000_000))
+            .collect();
+        std::fs::remove_file(&a).ok();
+        std::fs::remove_file(&b).ok();
+
+        assert_eq!(offsets, vec![(1, 1), (4, 4), (3, 5), (2, 6), (7, 7)]);
+    }
+
+    #[test]
+    fn test_read_mbo_book_file() {
+        let metadata = Metadata::builder()
+            .dataset(Dataset::GlbxMdp3)
+            .schema(Some(Schema::Mbo))
+            .start(TS)
+            .stype_in(Some(SType::RawSymbol))
+            .stype_out(SType::InstrumentId)
+            .mappings(vec![SymbolMapping {
+                raw_symbol: "NQH6".to_string(),
+                intervals: vec![MappingInterval {
+                    start_date: date!(2025 - 12 - 01),
+                    end_date: date!(2025 - 12 - 02),
+                    symbol: "42002475".to_string(),
+                }],
+            }])
+            .build();
+
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("glbx-mdp3-20251201.mbo.dbn.zst");
+        let mut encoder = Encoder::with_zstd(File::create(&path).unwrap(), &metadata).unwrap();
+        for (offset_ms, action) in [(1, b'A'), (2, b'C')] {
+            let ts = TS + offset_ms * 1_000_000;
+            encoder
+                .encode_record(&MboMsg {
+                    hd: RecordHeader::new::<MboMsg>(rtype::MBO, 1, 42002475, ts),
+                    order_id: 7,
+                    price: 21_500_000_000_000,
+                    size: 5,
+                    action: action as c_char,
+                    side: b'B' as c_char,
+                    ts_recv: ts,
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        drop(encoder);
+
+        assert!(find_trade_files(&dir, None).unwrap().is_empty());
+        let book_files = find_book_files(&dir, Some("20251201")).unwrap();
+        assert_eq!(book_files, vec![path]);
+
+        let records: Vec<BookRecord> = MergedBook::open(&book_files)
+            .unwrap()
+            .collect::<Result<_>>()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].symbol, "NQH6");
+        assert_eq!(records[0].update.timestamp, 1_764_599_400_001);
+        assert!(matches!(
+            records[1].update.event,
+            BookEvent::Order { action: BookAction::Cancel, order_id: 7, size: 5, .. }
+        ));
+    }
+
+    #[test]
+    fn test_find_trade_files() {
+        let dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
+        std::fs::create_dir(&dir).unwrap();
+        for name in [
+            "glbx-mdp3-20251126.trades.csv.zst",
+            "glbx-mdp3-20251127.trades.csv.zst",
+            "glbx-mdp3-20251127.trades.dbn",
+            "glbx-mdp3-20251127.trades.csv",
+            "glbx-mdp3-20251127.mbo.dbn.zst",
+            "metadata.json",
+        ] {
+            File::create(dir.join(name)).unwrap();
+        }
+
+        let files = find_trade_files(&dir, Some("20251127")).unwrap();
+        let all = find_trade_files(&dir, None).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+
+        // Book files and uncompressed CSVs are not trade files
+        assert_eq!(
+            files,
+            vec![
+                dir.join("glbx-mdp3-20251127.trades.csv.zst"),
+                dir.join("glbx-mdp3-20251127.trades.dbn"),
+            ]
+        );
+        assert_eq!(all.len(), 3);
+
+        let path = Path::new("glbx-mdp3-20251127.trades.csv.zst");
+        assert_eq!(file_date(path), NaiveDate::from_ymd_opt(2025, 11, 27));
+        // 2025-11-28 00:00 UTC
+        assert_eq!(files_end_ms(&[path.to_path_buf()]), Some(1_764_288_000_000));
+    }
+}
@@ -1,9 +1,17 @@
+use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use tokio::sync::{broadcast, RwLock};
 use uuid::Uuid;
 
-use crate::supabase::{SupabaseClient, UserConfig};
+use crate::clock::et_clock_time_to_ms;
+use crate::divergence::DivergenceSettings;
+use crate::footprint::FootprintSettings;
+use crate::imbalance::StackedImbalanceSettings;
+use crate::instruments::InstrumentRegistry;
+use crate::large_trade::LargeTradeSettings;
+use crate::store::{SharedStore, UserConfig};
+use crate::vwap::VwapAnchor;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trade {
@@ -30,6 +38,7 @@ pub struct Bubble {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CVDPoint {
+    pub symbol: String,
     pub timestamp: u64,
     pub value: i64,
     pub x: f64,
@@ -73,6 +82,7 @@ pub struct AbsorptionZone {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AbsorptionEvent {
+    pub symbol: String,
     pub timestamp: u64,
     pub price: f64,
     #[serde(rename = "absorptionType")]
@@ -95,6 +105,7 @@ pub struct AbsorptionEvent {
 /// Delta Flip Event - CVD crossing zero or reversing direction
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DeltaFlip {
+    pub symbol: String,
     pub timestamp: u64,
     #[serde(rename = "flipType")]
     pub flip_type: String, // "zero_cross" or "reversal"
@@ -109,6 +120,7 @@ pub struct DeltaFlip {
 /// Stacked Imbalances - 3+ consecutive price levels with same-direction imbalance
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StackedImbalance {
+    pub symbol: String,
     pub timestamp: u64,
     pub side: String, // "buy" or "sell"
     #[serde(rename = "levelCount")]
@@ -119,12 +131,78 @@ pub struct StackedImbalance {
     pub price_low: f64,
     #[serde(rename = "totalImbalance")]
     pub total_imbalance: i64, // Sum of imbalances across levels
+    pub algorithm: String, // "session" (same-price, session profile) or "diagonal" (per footprint bar)
+    pub x: f64,
+}
+
+/// CVD/Price Divergence - price and CVD swings disagree (sent once the newer swing is confirmed)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Divergence {
+    pub symbol: String,
+    pub timestamp: u64,
+    pub kind: String,      // "regular" (reversal warning) or "hidden" (continuation)
+    pub direction: String, // "bullish" (swing lows) or "bearish" (swing highs)
+    pub price: f64,        // Price when confirmed
+    #[serde(rename = "swingTime")]
+    pub swing_time: u64,
+    #[serde(rename = "swingPrice")]
+    pub swing_price: f64,
+    #[serde(rename = "swingCvd")]
+    pub swing_cvd: i64,
+    #[serde(rename = "prevSwingTime")]
+    pub prev_swing_time: u64,
+    #[serde(rename = "prevSwingPrice")]
+    pub prev_swing_price: f64,
+    #[serde(rename = "prevSwingCvd")]
+    pub prev_swing_cvd: i64,
+    pub x: f64,
+}
+
+/// Large Trade - one aggressor order (a single print or several fills) that reached a size tier
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LargeTrade {
+    pub symbol: String,
+    pub timestamp: u64,
+    #[serde(rename = "tradeTime")]
+    pub trade_time: u64,
+    pub kind: String, // "block" or "sweep" (took more than the sweep threshold's price levels)
+    pub side: String, // "buy" or "sell"
+    pub price: f64,   // Volume weighted over the fills
+    #[serde(rename = "priceLow")]
+    pub price_low: f64,
+    #[serde(rename = "priceHigh")]
+    pub price_high: f64,
+    pub size: u64,
+    pub fills: u32,
+    pub tier: u8, // 1 = smallest tier
+    #[serde(rename = "tierMin")]
+    pub tier_min: u32, // Min contracts of the tier
+    pub x: f64,
+}
+
+/// Sweep - One aggressor order rebuilt from its prints that took more than `sweep_levels` price levels
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Sweep {
+    pub symbol: String,
+    pub timestamp: u64,
+    #[serde(rename = "tradeTime")]
+    pub trade_time: u64,
+    pub side: String, // "buy" or "sell"
+    pub price: f64,   // Volume weighted over the fills
+    #[serde(rename = "startPrice")]
+    pub start_price: f64, // First fill
+    #[serde(rename = "endPrice")]
+    pub end_price: f64, // Last fill - how far into the book it reached
+    pub size: u64,
+    pub fills: u32,
+    pub levels: u32, // Distinct prices taken
     pub x: f64,
 }
 
 /// Confluence Event - Multiple signals aligning for high-probability setup
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConfluenceEvent {
+    pub symbol: String,
     pub timestamp: u64,
     pub price: f64,
     pub direction: String, // "bullish" or "bearish"
@@ -142,7 +220,7 @@ pub struct ConfluenceEvent {
 pub struct SignalRecord {
     pub timestamp: u64,
     pub price: f64,
-    pub signal_type: String, // "delta_flip", "absorption", "stacked_imbalance", "confluence"
+    pub signal_type: String, // "delta_flip", "absorption", "stacked_imbalance", "divergence", "large_trade", "sweep", "confluence"
     pub direction: String,   // "bullish" or "bearish"
     #[serde(rename = "priceAfter1m")]
     pub price_after_1m: Option<f64>,
@@ -154,6 +232,7 @@ pub struct SignalRecord {
 /// Session Statistics - aggregated stats for all signals
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionStats {
+    pub symbol: String,
     #[serde(rename = "sessionStart")]
     pub session_start: u64,
     #[serde(rename = "deltaFlips")]
@@ -161,6 +240,10 @@ pub struct SessionStats {
     pub absorptions: SignalStats,
     #[serde(rename = "stackedImbalances")]
     pub stacked_imbalances: SignalStats,
+    pub divergences: SignalStats,
+    #[serde(rename = "largeTrades")]
+    pub large_trades: SignalStats,
+    pub sweeps: SignalStats,
     pub confluences: SignalStats,
     #[serde(rename = "currentPrice")]
     pub current_price: f64,
@@ -170,6 +253,220 @@ pub struct SessionStats {
     pub session_low: f64,
     #[serde(rename = "totalVolume")]
     pub total_volume: u64,
+    /// CME trading date (YYYY-MM-DD) the stats cover
+    #[serde(rename = "tradingDate")]
+    pub trading_date: String,
+    pub segment: String, // "rth", "eth" or "closed"
+    /// Exchange holiday in effect (abbreviated Globex session)
+    pub holiday: Option<String>,
+}
+
+/// Developing value area - sent whenever the profile's POC, VAH or VAL moves
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DevelopingValueArea {
+    pub symbol: String,
+    pub timestamp: u64,
+    pub poc: f64,
+    pub vah: f64,
+    pub val: f64,
+    /// Profile volume when the value area moved
+    pub volume: u64,
+}
+
+/// Impulse leg - sent when its last 1m bar closes and it scores 4+ of 5
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImpulseLegEvent {
+    pub symbol: String,
+    pub timestamp: u64,
+    /// Open of the first and last 1m bars (Unix ms)
+    #[serde(rename = "startTime")]
+    pub start_time: u64,
+    #[serde(rename = "endTime")]
+    pub end_time: u64,
+    #[serde(rename = "startPrice")]
+    pub start_price: f64,
+    #[serde(rename = "endPrice")]
+    pub end_price: f64,
+    pub direction: String, // "up" or "down"
+    pub score: u8,
+    #[serde(rename = "brokeSwing")]
+    pub broke_swing: bool,
+    #[serde(rename = "wasFast")]
+    pub was_fast: bool,
+    #[serde(rename = "uniformCandles")]
+    pub uniform_candles: bool,
+    #[serde(rename = "volumeIncreased")]
+    pub volume_increased: bool,
+    #[serde(rename = "sufficientSize")]
+    pub sufficient_size: bool,
+    #[serde(rename = "numCandles")]
+    pub num_candles: u32,
+    #[serde(rename = "totalVolume")]
+    pub total_volume: u64,
+}
+
+/// Low volume node inside an impulse leg
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LvnNode {
+    pub price: f64,
+    pub volume: u64,
+    #[serde(rename = "volumeRatio")]
+    pub volume_ratio: f64, // Volume / the leg's average level volume
+}
+
+/// LVNs of an impulse leg - sent right after the leg
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LvnUpdate {
+    pub symbol: String,
+    pub timestamp: u64,
+    #[serde(rename = "impulseStartTime")]
+    pub impulse_start_time: u64,
+    #[serde(rename = "impulseEndTime")]
+    pub impulse_end_time: u64,
+    pub levels: Vec<LvnNode>,
+}
+
+/// Balance/imbalance classification - sent whenever the state changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketStateUpdate {
+    pub symbol: String,
+    pub timestamp: u64,
+    pub state: String, // "balance", "bullish_imbalance" or "bearish_imbalance"
+    pub previous: Option<String>,
+    pub price: f64,
+    /// Share of today's value area inside the prior session's (None without a prior session)
+    #[serde(rename = "valueAreaOverlap")]
+    pub value_area_overlap: Option<f64>,
+    /// POC crosses in the last 30 minutes
+    pub rotations: u32,
+    /// 30-minute range in 5-minute ATRs (None until there are enough bars)
+    #[serde(rename = "rangeExpansion")]
+    pub range_expansion: Option<f64>,
+    /// Features that voted for the state
+    pub reasons: Vec<String>,
+}
+
+/// Session-anchored VWAP with standard-deviation bands
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VwapUpdate {
+    pub symbol: String,
+    pub timestamp: u64,
+    pub vwap: f64,
+    #[serde(rename = "stdDev")]
+    pub std_dev: f64,
+    pub upper1: f64,
+    pub lower1: f64,
+    pub upper2: f64,
+    pub lower2: f64,
+    pub upper3: f64,
+    pub lower3: f64,
+    pub anchor: String, // "rth" or "globex"
+    #[serde(rename = "anchorTime")]
+    pub anchor_time: u64,
+    pub volume: u64,
+}
+
+/// One anchored VWAP series
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnchoredVwapSeries {
+    pub id: String,
+    pub source: String, // "manual", "impulse", "session_high" or "session_low"
+    #[serde(rename = "anchorTime")]
+    pub anchor_time: u64,
+    pub vwap: f64,
+    #[serde(rename = "stdDev")]
+    pub std_dev: f64,
+    pub volume: u64,
+}
+
+/// Every anchored VWAP for a symbol (anchors missing from the list were removed)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnchoredVwapUpdate {
+    pub symbol: String,
+    pub timestamp: u64,
+    pub anchors: Vec<AnchoredVwapSeries>,
+}
+
+/// Buy/sell volume at one price of a footprint bar
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FootprintLevel {
+    pub price: f64,
+    #[serde(rename = "buyVolume")]
+    pub buy_volume: u64,
+    #[serde(rename = "sellVolume")]
+    pub sell_volume: u64,
+    pub delta: i64,
+    /// Buyers outnumber sellers one tick below by the imbalance ratio
+    #[serde(rename = "buyImbalance")]
+    pub buy_imbalance: bool,
+    /// Sellers outnumber buyers one tick above by the imbalance ratio
+    #[serde(rename = "sellImbalance")]
+    pub sell_imbalance: bool,
+}
+
+/// Footprint bar - sent while it builds and once more when it closes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Footprint {
+    pub symbol: String,
+    /// Bar start (Unix ms)
+    pub timestamp: u64,
+    #[serde(rename = "endTime")]
+    pub end_time: u64,
+    pub timeframe: String, // e.g. "1m", "500t" or "10r"
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: u64,
+    pub delta: i64,
+    pub poc: f64,
+    #[serde(rename = "tradeCount")]
+    pub trade_count: u64,
+    /// Low to high, one per traded tick
+    pub levels: Vec<FootprintLevel>,
+    pub complete: bool,
+}
+
+/// Resting size at one price
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DepthLevel {
+    pub price: f64,
+    pub size: u32,
+    /// Orders at the price
+    pub count: u32,
+}
+
+/// Top of book (best first on both sides) - sent when the book changes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DepthSnapshot {
+    pub symbol: String,
+    pub timestamp: u64,
+    pub bids: Vec<DepthLevel>,
+    pub asks: Vec<DepthLevel>,
+}
+
+/// Passive liquidity event from the order book
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LiquidityEvent {
+    pub symbol: String,
+    pub timestamp: u64,
+    pub kind: String, // "absorption", "iceberg", "pulled" or "stacked"
+    pub side: String, // resting side: "bid" or "ask"
+    pub price: f64,
+    /// Traded into the price (absorption/iceberg), cancelled (pulled) or resting (stacked)
+    pub size: u64,
+    #[serde(rename = "restingSize")]
+    pub resting_size: u32,
+    /// Times the price was refilled after being traded into
+    pub refills: u32,
+}
+
+/// Anchored VWAP placed by a client (add_vwap_anchor)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManualVwapAnchor {
+    /// None anchors every symbol
+    pub symbol: Option<String>,
+    pub timestamp: u64,
 }
 
 /// Stats for a specific signal type
@@ -205,19 +502,46 @@ pub struct ReplayStatus {
     pub current_time: Option<u64>, // Current replay timestamp
 }
 
+/// Live feed health - anything but "connected" means data may be missing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionStatus {
+    pub status: String, // "connecting", "connected", "reconnecting", "backfilling", "degraded"
+    /// Reconnect attempt (0 while connected)
+    pub attempt: u32,
+    /// Missed interval being (or that failed to be) backfilled, Unix ms
+    #[serde(rename = "gapStart")]
+    pub gap_start: Option<u64>,
+    #[serde(rename = "gapEnd")]
+    pub gap_end: Option<u64>,
+    pub message: Option<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum WsMessage {
     Bubble(Bubble),
     CVDPoint(CVDPoint),
-    VolumeProfile { levels: Vec<VolumeProfileLevel> },
+    VolumeProfile { symbol: String, levels: Vec<VolumeProfileLevel> },
     Absorption(AbsorptionEvent),
-    AbsorptionZones { zones: Vec<AbsorptionZone> },
+    AbsorptionZones { symbol: String, zones: Vec<AbsorptionZone> },
     DeltaFlip(DeltaFlip),
     StackedImbalance(StackedImbalance),
+    Divergence(Divergence),
+    LargeTrade(LargeTrade),
+    Sweep(Sweep),
     Confluence(ConfluenceEvent),
-    SessionStats(SessionStats),
+    SessionStats(Box<SessionStats>),
+    Vwap(VwapUpdate),
+    AnchoredVwaps(AnchoredVwapUpdate),
+    DevelopingValueArea(DevelopingValueArea),
+    MarketState(MarketStateUpdate),
+    ImpulseLeg(ImpulseLegEvent),
+    Lvns(LvnUpdate),
+    Footprint(Footprint),
+    Depth(DepthSnapshot),
+    Liquidity(LiquidityEvent),
     ReplayStatus(ReplayStatus),
+    ConnectionStatus(ConnectionStatus),
     Connected { symbols: Vec<String>, mode: String },
     Error { message: String },
 }
@@ -228,6 +552,34 @@ pub struct ClientMessage {
     pub symbol: Option<String>,
     pub min_size: Option<u32>,
     pub speed: Option<u32>,
+    /// Seek target (replay_seek) or anchor (add/remove_vwap_anchor) in Unix milliseconds
+    pub timestamp: Option<u64>,
+    /// Seek target as an ET clock time, e.g. "10:15" (replay_seek)
+    pub time: Option<String>,
+    /// "window", "second" or "next_signal" (replay_step)
+    pub unit: Option<String>,
+}
+
+/// How far a `replay_step` advances before pausing again
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepUnit {
+    /// One aggregation window
+    Window,
+    /// One second of event time
+    Second,
+    /// Until the next signal is recorded
+    NextSignal,
+}
+
+impl StepUnit {
+    pub fn parse(unit: &str) -> Option<Self> {
+        match unit {
+            "window" => Some(Self::Window),
+            "second" => Some(Self::Second),
+            "next_signal" => Some(Self::NextSignal),
+            _ => None,
+        }
+    }
 }
 
 /// Shared replay control state
@@ -235,6 +587,65 @@ pub struct ReplayControl {
     pub is_paused: bool,
     pub speed: u32,
     pub current_timestamp: Option<u64>,
+    /// Pending seek target (Unix ms) - taken by the replay loop
+    pub seek_to: Option<u64>,
+    /// Pending step while paused - taken by the replay loop
+    pub step: Option<StepUnit>,
+    /// First and last timestamps of the replay session (for progress)
+    pub session_start: Option<u64>,
+    pub session_end: Option<u64>,
+}
+
+impl ReplayControl {
+    pub fn new(speed: u32) -> Self {
+        Self {
+            is_paused: false,
+            speed,
+            current_timestamp: None,
+            seek_to: None,
+            step: None,
+            session_start: None,
+            session_end: None,
+        }
+    }
+
+    /// Fraction of the session replayed so far (0.0 to 1.0)
+    pub fn progress(&self) -> Option<f64> {
+        let (start, end, current) = (self.session_start?, self.session_end?, self.current_timestamp?);
+        if end <= start {
+            return None;
+        }
+        Some((current.saturating_sub(start) as f64 / (end - start) as f64).clamp(0.0, 1.0))
+    }
+
+    /// Resolve an ET clock time ("10:15") on the day being replayed
+    ///
+    /// Uses the ET date of the current position; a time that falls before the
+    /// session start (e.g. a Globex session opening the evening before) rolls
+    /// forward a day.
+    pub fn seek_time_to_ms(&self, time: &str) -> Result<u64> {
+        let reference = self
+            .current_timestamp
+            .or(self.session_start)
+            .ok_or_else(|| anyhow::anyhow!("Replay hasn't started"))?;
+        let target = et_clock_time_to_ms(reference, time)?;
+        match self.session_start {
+            Some(start) if target < start => Ok(target + 24 * 60 * 60 * 1000),
+            _ => Ok(target),
+        }
+    }
+
+    /// Status message for clients
+    pub fn status(&self, mode: &str, replay_date: Option<String>) -> ReplayStatus {
+        ReplayStatus {
+            mode: mode.to_string(),
+            is_paused: self.is_paused,
+            speed: self.speed,
+            replay_date,
+            replay_progress: self.progress(),
+            current_time: self.current_timestamp,
+        }
+    }
 }
 
 /// Shared application state
@@ -243,15 +654,34 @@ pub struct AppState {
     pub active_symbols: RwLock<HashSet<String>>,
     pub min_size: RwLock<u32>,
     pub session_id: Option<Uuid>,
-    pub supabase: Option<SupabaseClient>,
-    /// User configuration (persisted to Supabase)
+    /// Signal store (Supabase or SQLite) - None when persistence is off
+    pub store: Option<SharedStore>,
+    /// User configuration (persisted to the signal store)
     pub config: RwLock<UserConfig>,
-    /// Session stats: (high, low, volume) - updated by ProcessingState
-    pub session_stats: RwLock<(f64, f64, u64)>,
+    /// Session stats per symbol: (high, low, volume) - updated by each ProcessingState
+    pub session_stats: RwLock<HashMap<String, (f64, f64, u64)>>,
     /// Current mode: "live", "demo", or "replay"
     pub mode: String,
     /// Replay date (for replay mode)
     pub replay_date: Option<String>,
     /// Replay control state (pause, speed)
     pub replay_control: RwLock<ReplayControl>,
+    /// Live feed health (None outside live mode)
+    pub connection_status: RwLock<Option<ConnectionStatus>>,
+    /// Contract specs by root symbol (tick size, thresholds)
+    pub instruments: InstrumentRegistry,
+    /// Where session VWAPs reset
+    pub vwap_anchor: VwapAnchor,
+    /// Footprint bar timeframe and imbalance ratio
+    pub footprint: FootprintSettings,
+    /// Stacked imbalance detector (session or diagonal) and its thresholds
+    pub stacked_imbalance: StackedImbalanceSettings,
+    /// CVD/price divergence bar length and swing lookbacks
+    pub divergence: DivergenceSettings,
+    /// Large trade size tiers and the tier that counts as a signal
+    pub large_trades: LargeTradeSettings,
+    /// An aggressor order is a sweep when it takes more than this many price levels
+    pub sweep_levels: u32,
+    /// Client-placed anchored VWAPs - applied by the stream driver each window
+    pub vwap_anchors: RwLock<Vec<ManualVwapAnchor>>,
 }
@@ -0,0 +1,389 @@
+//! Volume Profile
+//!
+//! Volume at price in buckets of the instrument's `profile.bucket_points` (one
+//! tick by default), with POC, value area (`profile.value_area_percent` of the
+//! volume), high/low volume nodes and single prints. The live ProcessingState
+//! and every pipeline stage (daily, composite and impulse leg profiles) use this
+//! one type so their levels always agree.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::instruments::ProfileSettings;
+
+/// TPO period for single-print detection (30 minutes, as in Market Profile)
+pub const TPO_PERIOD_MS: u64 = 30 * 60 * 1000;
+
+/// Smoothing half-width for node detection (points either side)
+const NODE_SMOOTHING_POINTS: f64 = 0.5;
+
+/// A node must be the extreme within this many points either side
+const NODE_SPACING_POINTS: f64 = 5.0;
+
+/// HVN: smoothed volume >= 150% of the average level
+const HVN_THRESHOLD_RATIO: f64 = 1.5;
+
+/// LVN: smoothed volume < 30% of the average level (same ratio as impulse leg LVNs)
+const LVN_THRESHOLD_RATIO: f64 = 0.30;
+
+/// Shortest run of single-period buckets reported as single prints
+const MIN_SINGLE_PRINT_POINTS: f64 = 1.0;
+
+/// Buy/sell volume traded at one price
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PriceVolume {
+    pub buy_volume: u64,
+    pub sell_volume: u64,
+}
+
+impl PriceVolume {
+    pub fn total(&self) -> u64 {
+        self.buy_volume + self.sell_volume
+    }
+
+    fn add(&mut self, other: &PriceVolume) {
+        self.buy_volume += other.buy_volume;
+        self.sell_volume += other.sell_volume;
+    }
+}
+
+/// Point of control and value area prices
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValueArea {
+    pub poc: f64,
+    pub vah: f64,
+    pub val: f64,
+}
+
+/// Volume at price keyed by bucket
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct VolumeProfile {
+    settings: ProfileSettings,
+    levels: BTreeMap<i64, PriceVolume>,
+    // TPO periods each bucket traded in: (count, last period)
+    periods: HashMap<i64, (u32, u64)>,
+}
+
+impl VolumeProfile {
+    pub fn new(settings: &ProfileSettings) -> Self {
+        Self {
+            settings: settings.clone(),
+            levels: BTreeMap::new(),
+            periods: HashMap::new(),
+        }
+    }
+
+    pub fn settings(&self) -> &ProfileSettings {
+        &self.settings
+    }
+
+    /// Bucket key of a price
+    pub fn price_key(&self, price: f64) -> i64 {
+        (price / self.settings.bucket_points).round() as i64
+    }
+
+    /// Price of a bucket key
+    pub fn key_price(&self, key: i64) -> f64 {
+        key as f64 * self.settings.bucket_points
+    }
+
+    /// Whole buckets in a distance in points (at least one)
+    fn points_to_buckets(&self, points: f64) -> i64 {
+        ((points / self.settings.bucket_points).round() as i64).max(1)
+    }
+
+    /// Add a trade (timestamp in Unix ms, used for single prints)
+    pub fn add(&mut self, price: f64, size: u64, is_buy: bool, timestamp: u64) {
+        let key = self.price_key(price);
+        let level = self.levels.entry(key).or_default();
+        if is_buy {
+            level.buy_volume += size;
+        } else {
+            level.sell_volume += size;
+        }
+
+        let period = timestamp / TPO_PERIOD_MS;
+        self.periods
+            .entry(key)
+            .and_modify(|(count, last)| {
+                if *last != period {
+                    *count += 1;
+                    *last = period;
+                }
+            })
+            .or_insert((1, period));
+    }
+
+    /// Add another profile's volume (composites, whole-day profiles)
+    pub fn merge(&mut self, other: &VolumeProfile) {
+        for (&key, volume) in &other.levels {
+            self.levels.entry(self.price_key(other.key_price(key))).or_default().add(volume);
+        }
+        for (&key, &(count, last)) in &other.periods {
+            let entry = self.periods.entry(self.price_key(other.key_price(key))).or_insert((0, last));
+            entry.0 += count;
+            entry.1 = entry.1.max(last);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    /// Number of buckets with volume
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    /// Buckets with volume, lowest price first
+    pub fn levels(&self) -> impl Iterator<Item = (f64, &PriceVolume)> + '_ {
+        self.levels.iter().map(|(&key, volume)| (self.key_price(key), volume))
+    }
+
+    /// Volume in the bucket a price falls in
+    pub fn get(&self, price: f64) -> Option<&PriceVolume> {
+        self.levels.get(&self.price_key(price))
+    }
+
+    pub fn total_volume(&self) -> u64 {
+        self.levels.values().map(PriceVolume::total).sum()
+    }
+
+    /// Average volume of the buckets with volume
+    pub fn average_level_volume(&self) -> f64 {
+        if self.levels.is_empty() {
+            return 0.0;
+        }
+        self.total_volume() as f64 / self.levels.len() as f64
+    }
+
+    fn volume_at(&self, key: i64) -> u64 {
+        self.levels.get(&key).map(PriceVolume::total).unwrap_or(0)
+    }
+
+    /// Bucket with the highest volume (lowest price on a tie, so a developing
+    /// POC doesn't flicker between equal levels)
+    fn poc_key(&self) -> Option<i64> {
+        self.levels
+            .iter()
+            .max_by_key(|(key, level)| (level.total(), std::cmp::Reverse(**key)))
+            .map(|(key, _)| *key)
+    }
+
+    /// Point of Control - price with the highest volume
+    pub fn poc(&self) -> Option<f64> {
+        self.poc_key().map(|key| self.key_price(key))
+    }
+
+    /// POC, VAH and VAL - expands from the POC toward the heavier neighbouring
+    /// level until `value_area_percent` of the volume is included (empty
+    /// buckets in between are skipped)
+    pub fn value_area(&self) -> Option<ValueArea> {
+        let poc = self.poc_key()?;
+        let target_volume = (self.total_volume() as f64 * self.settings.value_area_percent / 100.0) as u64;
+
+        let mut included_volume = self.volume_at(poc);
+        let mut high = poc;
+        let mut low = poc;
+
+        while included_volume < target_volume {
+            let above = self.levels.range(high + 1..).next();
+            let below = self.levels.range(..low).next_back();
+
+            match (above, below) {
+                (None, None) => break,
+                (Some((&key, level)), None) => {
+                    high = key;
+                    included_volume += level.total();
+                }
+                (None, Some((&key, level))) => {
+                    low = key;
+                    included_volume += level.total();
+                }
+                (Some((&above_key, above)), Some((&below_key, below))) => {
+                    if above.total() >= below.total() {
+                        high = above_key;
+                        included_volume += above.total();
+                    } else {
+                        low = below_key;
+                        included_volume += below.total();
+                    }
+                }
+            }
+        }
+
+        Some(ValueArea {
+            poc: self.key_price(poc),
+            vah: self.key_price(high),
+            val: self.key_price(low),
+        })
+    }
+
+    /// High and low volume node prices - local extremes of the smoothed volume,
+    /// LVNs only between the outermost HVNs (not in the thin tails)
+    pub fn volume_nodes(&self) -> (Vec<f64>, Vec<f64>) {
+        let (Some((&low, _)), Some((&high, _))) = (self.levels.first_key_value(), self.levels.last_key_value()) else {
+            return (Vec::new(), Vec::new());
+        };
+
+        let smoothing = self.points_to_buckets(NODE_SMOOTHING_POINTS);
+        let smoothed: Vec<(i64, f64)> = (low..=high)
+            .map(|key| {
+                let sum: u64 = ((key - smoothing)..=(key + smoothing)).map(|k| self.volume_at(k)).sum();
+                (key, sum as f64 / (2 * smoothing + 1) as f64)
+            })
+            .collect();
+        let average = smoothed.iter().map(|(_, v)| v).sum::<f64>() / smoothed.len() as f64;
+
+        let spacing = self.points_to_buckets(NODE_SPACING_POINTS) as usize;
+        let neighbours = |i: usize| {
+            smoothed[i.saturating_sub(spacing)..(i + spacing + 1).min(smoothed.len())]
+                .iter()
+                .map(|(_, v)| *v)
+        };
+
+        // Plateaus keep their first key only
+        let mut hvns = Vec::new();
+        let mut lvns = Vec::new();
+        for (i, &(key, volume)) in smoothed.iter().enumerate() {
+            let first_of_plateau = i == 0 || smoothed[i - 1].1 != volume;
+            if !first_of_plateau {
+                continue;
+            }
+            if volume >= average * HVN_THRESHOLD_RATIO && neighbours(i).all(|v| v <= volume) {
+                hvns.push(key);
+            } else if volume < average * LVN_THRESHOLD_RATIO && neighbours(i).all(|v| v >= volume) {
+                lvns.push(key);
+            }
+        }
+
+        if let (Some(&first_hvn), Some(&last_hvn)) = (hvns.first(), hvns.last()) {
+            lvns.retain(|key| *key > first_hvn && *key < last_hvn);
+        } else {
+            lvns.clear();
+        }
+
+        let prices = |keys: Vec<i64>| keys.into_iter().map(|key| self.key_price(key)).collect();
+        (prices(hvns), prices(lvns))
+    }
+
+    /// Single prints as (low, high) price ranges - runs of buckets that traded
+    /// in only one TPO period (or were skipped), inside the profile's range.
+    /// Runs reaching the profile high or low are tails and left out.
+    pub fn single_prints(&self) -> Vec<(f64, f64)> {
+        let (Some((&low, _)), Some((&high, _))) = (self.levels.first_key_value(), self.levels.last_key_value()) else {
+            return Vec::new();
+        };
+
+        let min_buckets = self.points_to_buckets(MIN_SINGLE_PRINT_POINTS);
+        let is_single = |key: i64| self.periods.get(&key).is_none_or(|(count, _)| *count <= 1);
+
+        let mut runs = Vec::new();
+        let mut run_start = None;
+        for key in low..=high {
+            if is_single(key) {
+                run_start.get_or_insert(key);
+            } else if let Some(start) = run_start.take() {
+                if start > low && key - start >= min_buckets {
+                    runs.push((self.key_price(start), self.key_price(key - 1)));
+                }
+            }
+        }
+        runs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(levels: &[(f64, u64)]) -> VolumeProfile {
+        let mut profile = VolumeProfile::new(&ProfileSettings::default());
+        for &(price, volume) in levels {
+            profile.add(price, volume, true, 0);
+        }
+        profile
+    }
+
+    #[test]
+    fn test_value_area() {
+        // Empty buckets between levels are skipped until 70% is included:
+        // 101 + 102 hold 70 of 81 contracts (86%), 101 alone only 62%
+        let va = profile(&[(100.0, 10), (101.0, 50), (102.0, 20), (110.0, 1)]).value_area().unwrap();
+        assert_eq!((va.poc, va.vah, va.val), (101.0, 102.0, 101.0));
+
+        // The expansion crosses gaps on both sides to reach the target
+        let va = profile(&[(95.0, 31), (100.0, 38), (105.0, 31)]).value_area().unwrap();
+        assert_eq!((va.poc, va.vah, va.val), (100.0, 105.0, 95.0));
+
+        let va = profile(&[(100.0, 10), (100.25, 50), (100.5, 20), (100.75, 1)]).value_area().unwrap();
+        assert_eq!((va.poc, va.vah, va.val), (100.25, 100.5, 100.25));
+
+        // POC ties go to the lower price
+        assert_eq!(profile(&[(100.0, 10), (100.25, 10)]).poc(), Some(100.0));
+        assert!(VolumeProfile::default().value_area().is_none());
+    }
+
+    #[test]
+    fn test_configurable_value_area_and_buckets() {
+        let levels = [(100.0, 10), (100.25, 50), (100.5, 20), (100.75, 15)];
+        let settings = ProfileSettings {
+            value_area_percent: 90.0,
+            ..ProfileSettings::default()
+        };
+        let mut wide = VolumeProfile::new(&settings);
+        for (price, volume) in levels {
+            wide.add(price, volume, false, 0);
+        }
+        let va = wide.value_area().unwrap();
+        assert_eq!((va.vah, va.val), (100.75, 100.25));
+
+        // 1-point buckets put all four ticks in one level
+        let settings = ProfileSettings {
+            bucket_points: 1.0,
+            ..ProfileSettings::default()
+        };
+        let mut coarse = VolumeProfile::new(&settings);
+        coarse.merge(&wide);
+        assert_eq!(coarse.len(), 2);
+        assert_eq!(coarse.get(100.0).unwrap().total(), 60);
+        assert_eq!(coarse.total_volume(), 95);
+    }
+
+    #[test]
+    fn test_volume_nodes() {
+        // Two high-volume humps with an empty gap between them
+        let levels: Vec<(f64, u64)> = (0..=100)
+            .filter(|key| !(48..=52).contains(key))
+            .map(|key| {
+                let volume = if (15..=25).contains(&key) || (75..=85).contains(&key) { 100 } else { 10 };
+                (key as f64 * 0.25, volume)
+            })
+            .collect();
+
+        let (hvns, lvns) = profile(&levels).volume_nodes();
+        assert_eq!(hvns, vec![4.25, 19.25]);
+        // The thin tails at either end aren't LVNs
+        assert_eq!(lvns, vec![12.5]);
+    }
+
+    #[test]
+    fn test_single_prints() {
+        let mut profile = VolumeProfile::new(&ProfileSettings::default());
+        let second_period = TPO_PERIOD_MS;
+        // 100 and 101 trade in both periods (the skipped ticks between are too short
+        // to count), 101.25-102.5 once on the way up, 102.75-103 in both again
+        for (price, timestamp) in [(100.0, 0), (100.0, second_period), (101.0, 0), (101.0, second_period)] {
+            profile.add(price, 5, true, timestamp);
+        }
+        for tick in 5..=10 {
+            profile.add(101.0 + tick as f64 * 0.25, 1, true, 0);
+        }
+        for (price, timestamp) in [(102.75, 0), (102.75, second_period), (103.0, 0), (103.0, second_period)] {
+            profile.add(price, 5, true, timestamp);
+        }
+        // A single-period tail at the high isn't a single print
+        profile.add(104.0, 1, true, second_period);
+
+        assert_eq!(profile.single_prints(), vec![(101.25, 102.5)]);
+    }
+}
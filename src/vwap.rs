@@ -0,0 +1,431 @@
+//! Session and Anchored VWAP
+//!
+//! Volume-weighted average price with 1σ/2σ/3σ standard-deviation bands,
+//! updated trade by trade and reset at a session anchor (RTH open or Globex
+//! open, from the instrument's session times). Used by ProcessingState for the
+//! streamed `Vwap` message and by the pipeline for end-of-day levels.
+//!
+//! `AnchoredVwaps` tracks VWAPs from arbitrary anchors - manual (client
+//! timestamps), impulse leg starts and the session high/low. A per-second
+//! history lets an anchor placed in the past start with the volume since then.
+//! Impulse anchors last one session, like the session extremes.
+
+use chrono::NaiveTime;
+use clap::ValueEnum;
+use std::collections::VecDeque;
+
+use crate::clock::et_anchor_ms;
+use crate::instruments::SessionTimes;
+
+/// Where the session VWAP resets
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum VwapAnchor {
+    /// Regular trading hours open (09:30 ET for equity index futures)
+    #[default]
+    Rth,
+    /// Globex open (18:00 ET)
+    Globex,
+}
+
+impl VwapAnchor {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rth => "rth",
+            Self::Globex => "globex",
+        }
+    }
+
+    /// ET clock time of the anchor for an instrument
+    pub fn time(&self, session: &SessionTimes) -> NaiveTime {
+        match self {
+            Self::Rth => session.rth_start_time(),
+            Self::Globex => session.eth_start_time(),
+        }
+    }
+}
+
+/// VWAP and its volume-weighted standard deviation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct VwapBands {
+    pub vwap: f64,
+    pub std_dev: f64,
+    /// Session anchor the VWAP accumulates from (Unix ms)
+    pub anchor_time: u64,
+    pub volume: u64,
+}
+
+impl VwapBands {
+    /// Upper band at `n` standard deviations
+    pub fn upper(&self, n: u8) -> f64 {
+        self.vwap + n as f64 * self.std_dev
+    }
+
+    /// Lower band at `n` standard deviations
+    pub fn lower(&self, n: u8) -> f64 {
+        self.vwap - n as f64 * self.std_dev
+    }
+}
+
+/// Volume-weighted sums of price deviations from a reference price
+#[derive(Debug, Clone, Copy, Default)]
+struct VwapSums {
+    volume: u64,
+    sum_dev_volume: f64,
+    sum_dev_sq_volume: f64,
+}
+
+impl VwapSums {
+    fn add(&mut self, dev: f64, size: u64) {
+        let volume = size as f64;
+        self.volume += size;
+        self.sum_dev_volume += dev * volume;
+        self.sum_dev_sq_volume += dev * dev * volume;
+    }
+
+    fn merge(&mut self, other: &VwapSums) {
+        self.volume += other.volume;
+        self.sum_dev_volume += other.sum_dev_volume;
+        self.sum_dev_sq_volume += other.sum_dev_sq_volume;
+    }
+
+    fn bands(&self, reference_price: f64, anchor_time: u64) -> Option<VwapBands> {
+        if self.volume == 0 {
+            return None;
+        }
+
+        let volume = self.volume as f64;
+        let mean_dev = self.sum_dev_volume / volume;
+        let variance = (self.sum_dev_sq_volume / volume - mean_dev * mean_dev).max(0.0);
+
+        Some(VwapBands {
+            vwap: reference_price + mean_dev,
+            std_dev: variance.sqrt(),
+            anchor_time,
+            volume: self.volume,
+        })
+    }
+}
+
+/// Running VWAP for the current session
+#[derive(Debug, Clone)]
+pub struct SessionVwap {
+    anchor: VwapAnchor,
+    anchor_clock: NaiveTime,
+    // Current session anchor (Unix ms) - a trade past the next one resets
+    session_anchor: Option<u64>,
+    // Sums are taken relative to the session's first price to keep the variance precise
+    reference_price: f64,
+    sums: VwapSums,
+}
+
+impl SessionVwap {
+    pub fn new(anchor: VwapAnchor, session: &SessionTimes) -> Self {
+        Self {
+            anchor,
+            anchor_clock: anchor.time(session),
+            session_anchor: None,
+            reference_price: 0.0,
+            sums: VwapSums::default(),
+        }
+    }
+
+    pub fn anchor(&self) -> VwapAnchor {
+        self.anchor
+    }
+
+    /// Add a trade (or a bar at its typical price), resetting at a new session anchor
+    pub fn add(&mut self, price: f64, size: u64, timestamp: u64) {
+        let anchor = et_anchor_ms(timestamp, self.anchor_clock);
+        if self.session_anchor.is_none_or(|current| anchor > current) {
+            self.session_anchor = Some(anchor);
+            self.reference_price = price;
+            self.sums = VwapSums::default();
+        }
+
+        self.sums.add(price - self.reference_price, size);
+    }
+
+    /// Current VWAP and bands (None before the first trade)
+    pub fn bands(&self) -> Option<VwapBands> {
+        self.sums.bands(self.reference_price, self.session_anchor?)
+    }
+}
+
+/// What placed an anchored VWAP
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnchorSource {
+    /// Client request (add_vwap_anchor)
+    Manual,
+    /// Start of an impulse leg - expires at the next session anchor
+    Impulse,
+    /// Session high - moves with each new high
+    SessionHigh,
+    /// Session low - moves with each new low
+    SessionLow,
+}
+
+impl AnchorSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Manual => "manual",
+            Self::Impulse => "impulse",
+            Self::SessionHigh => "session_high",
+            Self::SessionLow => "session_low",
+        }
+    }
+}
+
+/// One anchored VWAP
+#[derive(Debug, Clone)]
+pub struct AnchoredVwap {
+    pub source: AnchorSource,
+    /// Unix ms the VWAP accumulates from
+    pub anchor_time: u64,
+    sums: VwapSums,
+}
+
+impl AnchoredVwap {
+    /// Series id - session extremes keep theirs as the anchor moves
+    pub fn id(&self) -> String {
+        match self.source {
+            AnchorSource::SessionHigh | AnchorSource::SessionLow => self.source.as_str().to_string(),
+            _ => format!("{}-{}", self.source.as_str(), self.anchor_time),
+        }
+    }
+}
+
+/// Per-second history kept for anchors placed in the past (5 days)
+const MAX_HISTORY_SECS: usize = 5 * 24 * 60 * 60;
+
+/// Anchored VWAPs for one instrument
+#[derive(Debug, Clone)]
+pub struct AnchoredVwaps {
+    session_clock: NaiveTime,
+    // Current session anchor (Unix ms) - session extremes restart at the next one
+    session_anchor: Option<u64>,
+    // Every sum (history and anchors) is relative to the first price seen
+    reference_price: Option<f64>,
+    // (second start in Unix ms, sums for that second)
+    history: VecDeque<(u64, VwapSums)>,
+    anchors: Vec<AnchoredVwap>,
+    session_high: Option<f64>,
+    session_low: Option<f64>,
+}
+
+impl AnchoredVwaps {
+    /// Session extremes restart at the same anchor as the session VWAP
+    pub fn new(anchor: VwapAnchor, session: &SessionTimes) -> Self {
+        Self {
+            session_clock: anchor.time(session),
+            session_anchor: None,
+            reference_price: None,
+            history: VecDeque::new(),
+            anchors: Vec::new(),
+            session_high: None,
+            session_low: None,
+        }
+    }
+
+    /// Add a trade to the history and every anchor it falls after
+    pub fn add(&mut self, price: f64, size: u64, timestamp: u64) {
+        let reference_price = *self.reference_price.get_or_insert(price);
+        let dev = price - reference_price;
+
+        // New session: extremes start over from this trade and impulse anchors expire
+        let session_anchor = et_anchor_ms(timestamp, self.session_clock);
+        if self.session_anchor.is_none_or(|current| session_anchor > current) {
+            self.session_anchor = Some(session_anchor);
+            self.session_high = None;
+            self.session_low = None;
+            self.anchors.retain(|a| a.source != AnchorSource::Impulse);
+        }
+
+        let second = timestamp / 1000 * 1000;
+        match self.history.back_mut() {
+            Some((last, sums)) if *last >= second => sums.add(dev, size),
+            _ => {
+                let mut sums = VwapSums::default();
+                sums.add(dev, size);
+                self.history.push_back((second, sums));
+                if self.history.len() > MAX_HISTORY_SECS {
+                    self.history.pop_front();
+                }
+            }
+        }
+
+        for anchor in self.anchors.iter_mut().filter(|a| a.anchor_time <= timestamp) {
+            anchor.sums.add(dev, size);
+        }
+
+        if self.session_high.is_none_or(|high| price > high) {
+            self.session_high = Some(price);
+            self.reanchor(AnchorSource::SessionHigh, timestamp, dev, size);
+        }
+        if self.session_low.is_none_or(|low| price < low) {
+            self.session_low = Some(price);
+            self.reanchor(AnchorSource::SessionLow, timestamp, dev, size);
+        }
+    }
+
+    /// Move a session extreme anchor to this trade
+    fn reanchor(&mut self, source: AnchorSource, timestamp: u64, dev: f64, size: u64) {
+        let mut sums = VwapSums::default();
+        sums.add(dev, size);
+        let anchor = AnchoredVwap { source, anchor_time: timestamp, sums };
+        match self.anchors.iter_mut().find(|a| a.source == source) {
+            Some(existing) => *existing = anchor,
+            None => self.anchors.push(anchor),
+        }
+    }
+
+    /// Place an anchor - one in the past starts with the history from the
+    /// first second that begins at or after it (trades earlier in the anchor's
+    /// own second aren't counted), one in the future accumulates once trades
+    /// reach it. Returns false if the anchor already exists.
+    pub fn add_anchor(&mut self, source: AnchorSource, anchor_time: u64) -> bool {
+        if self.has_anchor(source, anchor_time) {
+            return false;
+        }
+
+        let start = self.history.partition_point(|(ts, _)| *ts < anchor_time);
+        let mut sums = VwapSums::default();
+        for (_, bucket) in self.history.range(start..) {
+            sums.merge(bucket);
+        }
+
+        self.anchors.push(AnchoredVwap { source, anchor_time, sums });
+        true
+    }
+
+    /// Remove an anchor - returns false if it didn't exist
+    pub fn remove_anchor(&mut self, source: AnchorSource, anchor_time: u64) -> bool {
+        let before = self.anchors.len();
+        self.anchors
+            .retain(|a| !(a.source == source && a.anchor_time == anchor_time));
+        self.anchors.len() != before
+    }
+
+    fn has_anchor(&self, source: AnchorSource, anchor_time: u64) -> bool {
+        self.anchors
+            .iter()
+            .any(|a| a.source == source && a.anchor_time == anchor_time)
+    }
+
+    /// Make the manual anchors match the requested timestamps
+    pub fn sync_manual(&mut self, anchor_times: &[u64]) {
+        self.anchors
+            .retain(|a| a.source != AnchorSource::Manual || anchor_times.contains(&a.anchor_time));
+        for &anchor_time in anchor_times {
+            self.add_anchor(AnchorSource::Manual, anchor_time);
+        }
+    }
+
+    /// Anchors with their current VWAP (anchors no trade has reached yet are skipped)
+    pub fn series(&self) -> impl Iterator<Item = (&AnchoredVwap, VwapBands)> {
+        let reference_price = self.reference_price.unwrap_or_default();
+        self.anchors.iter().filter_map(move |anchor| {
+            anchor
+                .sums
+                .bands(reference_price, anchor.anchor_time)
+                .map(|bands| (anchor, bands))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruments::InstrumentSpec;
+
+    // 2025-11-27 14:30:00 UTC = 09:30 ET
+    const RTH_OPEN: u64 = 1_764_253_800_000;
+
+    #[test]
+    fn test_vwap_and_bands() {
+        let spec = InstrumentSpec::default();
+        let mut vwap = SessionVwap::new(VwapAnchor::Rth, &spec.session);
+        assert!(vwap.bands().is_none());
+
+        vwap.add(100.0, 1, RTH_OPEN);
+        vwap.add(102.0, 1, RTH_OPEN + 1_000);
+        vwap.add(104.0, 2, RTH_OPEN + 2_000);
+
+        let bands = vwap.bands().unwrap();
+        assert_eq!(bands.vwap, 102.5);
+        assert_eq!(bands.volume, 4);
+        assert_eq!(bands.anchor_time, RTH_OPEN);
+        // Weighted variance: (6.25 + 0.25 + 2 * 2.25) / 4 = 2.75
+        assert!((bands.std_dev - 2.75f64.sqrt()).abs() < 1e-9);
+        assert!((bands.upper(2) - (102.5 + 2.0 * 2.75f64.sqrt())).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_resets_at_anchor() {
+        let spec = InstrumentSpec::default();
+        let mut rth = SessionVwap::new(VwapAnchor::Rth, &spec.session);
+        let mut globex = SessionVwap::new(VwapAnchor::Globex, &spec.session);
+
+        // 09:29 ET, then 09:30 ET
+        for vwap in [&mut rth, &mut globex] {
+            vwap.add(100.0, 10, RTH_OPEN - 60_000);
+            vwap.add(200.0, 10, RTH_OPEN);
+        }
+
+        // RTH anchor reset at the open; Globex (18:00 ET) kept both trades
+        assert_eq!(rth.bands().unwrap().vwap, 200.0);
+        assert_eq!(globex.bands().unwrap().vwap, 150.0);
+        assert_eq!(globex.bands().unwrap().anchor_time, RTH_OPEN - 15 * 3_600_000 - 30 * 60_000);
+    }
+
+    #[test]
+    fn test_anchored_vwaps() {
+        let spec = InstrumentSpec::default();
+        let mut anchored = AnchoredVwaps::new(VwapAnchor::Rth, &spec.session);
+
+        anchored.add(100.0, 10, RTH_OPEN);
+        anchored.add(104.0, 10, RTH_OPEN + 1_000);
+        anchored.add(102.0, 10, RTH_OPEN + 2_000);
+
+        // Placed in the past: picks up the history from its second on
+        assert!(anchored.add_anchor(AnchorSource::Manual, RTH_OPEN + 2_000));
+        assert!(!anchored.add_anchor(AnchorSource::Manual, RTH_OPEN + 2_000));
+        // Mid-second: the trade at +2000 came before it
+        assert!(anchored.add_anchor(AnchorSource::Manual, RTH_OPEN + 2_500));
+        // Placed in the future: nothing until trades reach it
+        assert!(anchored.add_anchor(AnchorSource::Impulse, RTH_OPEN + 10_000));
+
+        anchored.add(99.0, 20, RTH_OPEN + 3_000);
+
+        let series: Vec<_> = anchored.series().map(|(a, b)| (a.id(), b.vwap, b.volume)).collect();
+        assert!(series.contains(&("session_high".to_string(), 101.0, 40)));
+        assert!(series.contains(&("session_low".to_string(), 99.0, 20)));
+        assert!(series.contains(&(format!("manual-{}", RTH_OPEN + 2_000), 100.0, 30)));
+        assert!(series.contains(&(format!("manual-{}", RTH_OPEN + 2_500), 99.0, 20)));
+        assert_eq!(series.len(), 4);
+
+        anchored.sync_manual(&[]);
+        assert!(anchored.remove_anchor(AnchorSource::Impulse, RTH_OPEN + 10_000));
+        assert_eq!(anchored.series().count(), 2);
+    }
+
+    #[test]
+    fn test_impulse_anchors_expire_at_next_session() {
+        let spec = InstrumentSpec::default();
+        let mut anchored = AnchoredVwaps::new(VwapAnchor::Rth, &spec.session);
+
+        anchored.add(100.0, 10, RTH_OPEN);
+        for minute in 1..=3 {
+            assert!(anchored.add_anchor(AnchorSource::Impulse, RTH_OPEN + minute * 60_000));
+        }
+        assert!(anchored.add_anchor(AnchorSource::Manual, RTH_OPEN + 60_000));
+        anchored.add(101.0, 10, RTH_OPEN + 4 * 60_000);
+        assert_eq!(anchored.anchors.len(), 6);
+
+        // Next day's RTH open: only the manual anchor and the new extremes remain
+        anchored.add(102.0, 10, RTH_OPEN + 24 * 3_600_000);
+        let sources: Vec<_> = anchored.anchors.iter().map(|a| a.source).collect();
+        assert_eq!(anchored.anchors.len(), 3);
+        assert!(!sources.contains(&AnchorSource::Impulse));
+        assert!(sources.contains(&AnchorSource::Manual));
+    }
+}
@@ -13,6 +13,7 @@ export interface Bubble {
 }
 
 export interface CVDPoint {
+  symbol: string;
   timestamp: number;
   value: number;
   x: number;
@@ -40,6 +41,7 @@ export interface AbsorptionZone {
 }
 
 export interface AbsorptionEvent {
+  symbol: string;
   timestamp: number;
   price: number;
   absorptionType: 'buying' | 'selling';
@@ -54,6 +56,7 @@ export interface AbsorptionEvent {
 }
 
 export interface DeltaFlip {
+  symbol: string;
   timestamp: number;
   flipType: 'zero_cross' | 'reversal';
   direction: 'bullish' | 'bearish';
@@ -63,16 +66,64 @@ export interface DeltaFlip {
 }
 
 export interface StackedImbalance {
+  symbol: string;
   timestamp: number;
   side: 'buy' | 'sell';
   levelCount: number;
   priceHigh: number;
   priceLow: number;
   totalImbalance: number;
+  algorithm: 'session' | 'diagonal';
+  x: number;
+}
+
+export interface Divergence {
+  symbol: string;
+  timestamp: number;
+  kind: 'regular' | 'hidden';
+  direction: 'bullish' | 'bearish';
+  price: number;
+  swingTime: number;
+  swingPrice: number;
+  swingCvd: number;
+  prevSwingTime: number;
+  prevSwingPrice: number;
+  prevSwingCvd: number;
+  x: number;
+}
+
+export interface LargeTrade {
+  symbol: string;
+  timestamp: number;
+  tradeTime: number;
+  kind: 'block' | 'sweep';
+  side: 'buy' | 'sell';
+  price: number;
+  priceLow: number;
+  priceHigh: number;
+  size: number;
+  fills: number;
+  tier: number;
+  tierMin: number;
+  x: number;
+}
+
+export interface Sweep {
+  symbol: string;
+  timestamp: number;
+  tradeTime: number;
+  side: 'buy' | 'sell';
+  price: number; // Volume weighted over the fills
+  startPrice: number; // First fill
+  endPrice: number; // Last fill
+  size: number;
+  fills: number;
+  levels: number;
   x: number;
 }
 
 export interface ConfluenceEvent {
+  symbol: string;
   timestamp: number;
   price: number;
   direction: 'bullish' | 'bearish';
@@ -95,15 +146,156 @@ export interface SignalStats {
 }
 
 export interface SessionStats {
+  symbol: string;
   sessionStart: number;
   deltaFlips: SignalStats;
   absorptions: SignalStats;
   stackedImbalances: SignalStats;
+  divergences: SignalStats;
+  largeTrades: SignalStats;
+  sweeps: SignalStats;
   confluences: SignalStats;
   currentPrice: number;
   sessionHigh: number;
   sessionLow: number;
   totalVolume: number;
+  tradingDate: string;
+  segment: 'rth' | 'eth' | 'closed';
+  holiday: string | null;
+}
+
+export interface VwapUpdate {
+  symbol: string;
+  timestamp: number;
+  vwap: number;
+  stdDev: number;
+  upper1: number;
+  lower1: number;
+  upper2: number;
+  lower2: number;
+  upper3: number;
+  lower3: number;
+  anchor: 'rth' | 'globex';
+  anchorTime: number;
+  volume: number;
+}
+
+export interface AnchoredVwapSeries {
+  id: string;
+  source: 'manual' | 'impulse' | 'session_high' | 'session_low';
+  anchorTime: number;
+  vwap: number;
+  stdDev: number;
+  volume: number;
+}
+
+export interface AnchoredVwapUpdate {
+  symbol: string;
+  timestamp: number;
+  anchors: AnchoredVwapSeries[];
+}
+
+export interface DevelopingValueArea {
+  symbol: string;
+  timestamp: number;
+  poc: number;
+  vah: number;
+  val: number;
+  volume: number;
+}
+
+export interface MarketStateUpdate {
+  symbol: string;
+  timestamp: number;
+  state: 'balance' | 'bullish_imbalance' | 'bearish_imbalance';
+  previous: string | null;
+  price: number;
+  valueAreaOverlap: number | null;
+  rotations: number;
+  rangeExpansion: number | null;
+  reasons: string[];
+}
+
+export interface ImpulseLegEvent {
+  symbol: string;
+  timestamp: number;
+  startTime: number;
+  endTime: number;
+  startPrice: number;
+  endPrice: number;
+  direction: 'up' | 'down';
+  score: number;
+  brokeSwing: boolean;
+  wasFast: boolean;
+  uniformCandles: boolean;
+  volumeIncreased: boolean;
+  sufficientSize: boolean;
+  numCandles: number;
+  totalVolume: number;
+}
+
+export interface LvnNode {
+  price: number;
+  volume: number;
+  volumeRatio: number;
+}
+
+export interface LvnUpdate {
+  symbol: string;
+  timestamp: number;
+  impulseStartTime: number;
+  impulseEndTime: number;
+  levels: LvnNode[];
+}
+
+export interface FootprintLevel {
+  price: number;
+  buyVolume: number;
+  sellVolume: number;
+  delta: number;
+  buyImbalance: boolean;
+  sellImbalance: boolean;
+}
+
+export interface Footprint {
+  symbol: string;
+  timestamp: number;
+  endTime: number;
+  timeframe: string;
+  open: number;
+  high: number;
+  low: number;
+  close: number;
+  volume: number;
+  delta: number;
+  poc: number;
+  tradeCount: number;
+  levels: FootprintLevel[];
+  complete: boolean;
+}
+
+export interface DepthLevel {
+  price: number;
+  size: number;
+  count: number;
+}
+
+export interface DepthSnapshot {
+  symbol: string;
+  timestamp: number;
+  bids: DepthLevel[];
+  asks: DepthLevel[];
+}
+
+export interface LiquidityEvent {
+  symbol: string;
+  timestamp: number;
+  kind: 'absorption' | 'iceberg' | 'pulled' | 'stacked';
+  side: 'bid' | 'ask';
+  price: number;
+  size: number;
+  restingSize: number;
+  refills: number;
 }
 
 export interface ReplayStatus {
@@ -115,17 +307,38 @@ export interface ReplayStatus {
   currentTime: number | null;
 }
 
+export interface ConnectionStatus {
+  status: 'connecting' | 'connected' | 'reconnecting' | 'backfilling' | 'degraded';
+  attempt: number;
+  gapStart: number | null;
+  gapEnd: number | null;
+  message: string | null;
+}
+
 export type WsMessage =
   | { type: 'Bubble' } & Bubble
-  | { type: 'CVDPoint'; timestamp: number; value: number; x: number }
-  | { type: 'VolumeProfile'; levels: VolumeProfileLevel[] }
+  | { type: 'CVDPoint' } & CVDPoint
+  | { type: 'VolumeProfile'; symbol: string; levels: VolumeProfileLevel[] }
   | { type: 'Absorption' } & AbsorptionEvent
-  | { type: 'AbsorptionZones'; zones: AbsorptionZone[] }
+  | { type: 'AbsorptionZones'; symbol: string; zones: AbsorptionZone[] }
   | { type: 'DeltaFlip' } & DeltaFlip
   | { type: 'StackedImbalance' } & StackedImbalance
+  | { type: 'Divergence' } & Divergence
+  | { type: 'LargeTrade' } & LargeTrade
+  | { type: 'Sweep' } & Sweep
   | { type: 'Confluence' } & ConfluenceEvent
   | { type: 'SessionStats' } & SessionStats
+  | { type: 'Vwap' } & VwapUpdate
+  | { type: 'AnchoredVwaps' } & AnchoredVwapUpdate
+  | { type: 'DevelopingValueArea' } & DevelopingValueArea
+  | { type: 'MarketState' } & MarketStateUpdate
+  | { type: 'ImpulseLeg' } & ImpulseLegEvent
+  | { type: 'Lvns' } & LvnUpdate
+  | { type: 'Footprint' } & Footprint
+  | { type: 'Depth' } & DepthSnapshot
+  | { type: 'Liquidity' } & LiquidityEvent
   | { type: 'ReplayStatus' } & ReplayStatus
+  | { type: 'ConnectionStatus' } & ConnectionStatus
   | { type: 'Connected'; symbols: string[]; mode: string }
   | { type: 'Error'; message: string };
 
@@ -268,6 +481,29 @@ export class RustWebSocket {
     this.send({ action: 'set_replay_speed', speed });
   }
 
+  // Jump to a Unix ms timestamp or an ET clock time ("10:15")
+  replaySeek(target: number | string) {
+    if (typeof target === 'number') {
+      this.send({ action: 'replay_seek', timestamp: target });
+    } else {
+      this.send({ action: 'replay_seek', time: target });
+    }
+  }
+
+  // Advance a paused replay by one window, one second or up to the next signal
+  replayStep(unit: 'window' | 'second' | 'next_signal') {
+    this.send({ action: 'replay_step', unit });
+  }
+
+  // Anchor a VWAP at a Unix ms timestamp (all symbols unless one is given)
+  addVwapAnchor(timestamp: number, symbol?: string) {
+    this.send({ action: 'add_vwap_anchor', timestamp, symbol });
+  }
+
+  removeVwapAnchor(timestamp: number, symbol?: string) {
+    this.send({ action: 'remove_vwap_anchor', timestamp, symbol });
+  }
+
   setMinSize(minSize: number) {
     this.send({ action: 'set_min_size', min_size: minSize });
   }